@@ -0,0 +1,16 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto");
+        let file_descriptors = protox::compile(["proto/ethparser.proto"], ["proto"]).unwrap();
+        tonic_prost_build::configure().build_client(false).compile_fds(file_descriptors).unwrap();
+    }
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let config = cbindgen::Config::from_file("cbindgen.toml").unwrap();
+        cbindgen::generate_with_config(&crate_dir, config).unwrap().write_to_file("include/ethparser.h");
+    }
+}
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use ethparser::abi_decoding::{decode_handle_ops, decode_input, implied_transfer};
+
+fuzz_target!(|input: &str| {
+    if let Some(call) = decode_input(input) {
+        let _ = implied_transfer(&call, "0xsender", "0xcontract");
+    }
+    let _ = decode_handle_ops(input);
+});
@@ -0,0 +1,68 @@
+#ifndef ETHPARSER_H
+#define ETHPARSER_H
+
+/* Generated by cbindgen from src/ffi.rs with `cargo build --features ffi`. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct EthGraph EthGraph;
+
+typedef struct EthGraphStats {
+  size_t node_count;
+  size_t edge_count;
+  double total_usd_volume;
+  double mean_usd_value;
+} EthGraphStats;
+
+/**
+ * Message of the last failed call on this thread, or NULL. Valid until the next failing call.
+ */
+const char *ethparser_last_error(void);
+
+/**
+ * Loads a graph saved by ethparser. The path is used as given, not resolved under the json folder.
+ *
+ * # Safety
+ * path must be NULL or a NUL-terminated string.
+ */
+struct EthGraph *ethparser_graph_load(const char *path);
+
+/**
+ * # Safety
+ * graph must be NULL or a handle returned by this library that was not freed yet.
+ */
+void ethparser_graph_free(struct EthGraph *graph);
+
+/**
+ * New graph with the stablecoin transfers valued within [min_usd, max_usd]. payload is NULL for any
+ * stablecoin, or a name such as "USDC". Nodes left without edges are dropped.
+ *
+ * # Safety
+ * graph must be NULL or a live handle, payload NULL or a NUL-terminated string.
+ */
+struct EthGraph *ethparser_graph_filter(const struct EthGraph *graph,
+                                        double min_usd,
+                                        double max_usd,
+                                        const char *payload);
+
+/**
+ * Fills stats for the graph. Returns 0 on success and -1 on failure.
+ *
+ * # Safety
+ * graph must be NULL or a live handle, stats NULL or writable memory for one EthGraphStats.
+ */
+int ethparser_graph_stats(const struct EthGraph *graph, struct EthGraphStats *stats);
+
+/**
+ * Writes one CSV row per edge to path. Returns 0 on success and -1 on failure.
+ *
+ * # Safety
+ * graph must be NULL or a live handle, path NULL or a NUL-terminated string.
+ */
+int ethparser_graph_export_csv(const struct EthGraph *graph, const char *path);
+
+#endif  /* ETHPARSER_H */
@@ -0,0 +1,256 @@
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+// Calls of common ERC-20 and Uniswap V2 router methods, decoded from transaction input. Addresses are
+// lowercase 0x-prefixed hex and amounts decimal strings in the token's base units.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum DecodedCall {
+    Transfer { to: String, amount: String },
+    TransferFrom { from: String, to: String, amount: String },
+    Approve { spender: String, amount: String },
+    IncreaseAllowance { spender: String, added_amount: String },
+    Swap { method: RouterMethod, amount_in: Option<String>, amount_out: Option<String>, path: Vec<String>, to: String },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum RouterMethod {
+    SwapExactTokensForTokens,
+    SwapTokensForExactTokens,
+    SwapExactETHForTokens,
+    SwapETHForExactTokens,
+    SwapExactTokensForETH,
+    SwapTokensForExactETH,
+}
+
+// A token movement implied by a decoded call. For exact-input swaps only the minimum output is known.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DecodedTransfer {
+    pub from: String,
+    pub to: String,
+    pub token: String,
+    pub amount: String,
+    pub amount_is_minimum: bool,
+    // Of the token contract, from the token metadata cache when the crawl could resolve them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+}
+
+impl DecodedTransfer {
+    // The amount in whole tokens, when the decimals are known.
+    pub fn normalized_amount(&self) -> Option<f64> {
+        let amount: f64 = self.amount.parse().ok()?;
+        Some(amount / 10f64.powi(self.decimals? as i32))
+    }
+}
+
+// One UserOperation of an ERC-4337 EntryPoint handleOps bundle, with the calls its account makes. Calls are
+// only known for the common account execute methods and are empty for any other account calldata.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserOperation {
+    pub sender: String,
+    pub nonce: String,
+    pub calls: Vec<AccountCall>,
+}
+
+// A call made by a smart account: value in wei as a decimal string and calldata as 0x-prefixed hex.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AccountCall {
+    pub target: String,
+    pub value: String,
+    pub data: String,
+}
+
+// Calldata without the 0x prefix and selector, split into 32-byte words.
+struct Arguments<'a> {
+    words: Vec<&'a str>,
+}
+
+impl<'a> Arguments<'a> {
+    fn new(body: &'a str) -> Option<Arguments<'a>> {
+        (body.len().is_multiple_of(64) && body.is_ascii()).then(|| Arguments { words: (0..body.len() / 64).map(|i| &body[i * 64..(i + 1) * 64]).collect() })
+    }
+
+    fn word(&self, index: usize) -> Option<&'a str> {
+        self.words.get(index).copied()
+    }
+
+    fn uint(&self, index: usize) -> Option<U256> {
+        U256::from_str_radix(self.word(index)?, 16).ok()
+    }
+
+    fn amount(&self, index: usize) -> Option<String> {
+        Some(self.uint(index)?.to_string())
+    }
+
+    fn address(&self, index: usize) -> Option<String> {
+        let word = self.word(index)?;
+        word[..24].chars().all(|c| c == '0').then(|| format!("0x{}", word[24..].to_lowercase()))
+    }
+
+    // Dynamic address[] argument whose head word holds the byte offset of its length word.
+    fn address_array(&self, index: usize) -> Option<Vec<String>> {
+        let offset = self.uint(index)?;
+        if offset % 32 != U256::zero() || offset > U256::from(self.words.len() * 32) {
+            return None;
+        }
+        let length_index = offset.as_usize() / 32;
+        let length = self.uint(length_index)?;
+        if length > U256::from(self.words.len()) {
+            return None;
+        }
+        (0..length.as_usize()).map(|i| self.address(length_index + 1 + i)).collect()
+    }
+
+    // The words from the byte offset held by a head word on, e.g. the encoding of a dynamic tuple. Offsets
+    // count from the start of the enclosing data, which is where these words start.
+    fn tail(&self, index: usize) -> Option<Arguments<'a>> {
+        let offset = self.uint(index)?;
+        if offset % 32 != U256::zero() || offset >= U256::from(self.words.len() * 32) {
+            return None;
+        }
+        Some(Arguments { words: self.words[offset.as_usize() / 32..].to_vec() })
+    }
+
+    // The length of a dynamic array and the words after its length word.
+    fn array_body(&self, index: usize) -> Option<(usize, Arguments<'a>)> {
+        let tail = self.tail(index)?;
+        let length = tail.uint(0)?;
+        if length >= U256::from(tail.words.len()) {
+            return None;
+        }
+        Some((length.as_usize(), Arguments { words: tail.words[1..].to_vec() }))
+    }
+
+    // Dynamic bytes argument as 0x-prefixed hex.
+    fn bytes(&self, index: usize) -> Option<String> {
+        let tail = self.tail(index)?;
+        let length = tail.uint(0)?;
+        if length > U256::from((tail.words.len() - 1) * 32) {
+            return None;
+        }
+        Some(format!("0x{}", &tail.words[1..].concat()[..length.as_usize() * 2]))
+    }
+
+    // Array of dynamic elements, such as tuples with bytes fields or bytes[], as a view of every element.
+    fn dynamic_array(&self, index: usize) -> Option<Vec<Arguments<'a>>> {
+        let (length, elements) = self.array_body(index)?;
+        (0..length).map(|i| elements.tail(i)).collect()
+    }
+
+    fn static_array<T>(&self, index: usize, element: impl Fn(&Arguments<'a>, usize) -> Option<T>) -> Option<Vec<T>> {
+        let (length, elements) = self.array_body(index)?;
+        (0..length).map(|i| element(&elements, i)).collect()
+    }
+}
+
+// Selector and arguments of ABI-encoded calldata.
+fn split_calldata(input: &str) -> Option<(&str, Arguments<'_>)> {
+    let calldata = input.strip_prefix("0x")?;
+    if calldata.len() < 8 || !calldata.is_ascii() {
+        return None;
+    }
+    let (selector, body) = calldata.split_at(8);
+    Some((selector, Arguments::new(body)?))
+}
+
+pub fn decode_input(input: &str) -> Option<DecodedCall> {
+    let (selector, args) = split_calldata(input)?;
+    let swap = |method, amount_in, amount_out, path_index: usize| {
+        Some(DecodedCall::Swap { method, amount_in, amount_out, path: args.address_array(path_index)?, to: args.address(path_index + 1)? })
+    };
+    match selector {
+        "a9059cbb" => Some(DecodedCall::Transfer { to: args.address(0)?, amount: args.amount(1)? }),
+        "23b872dd" => Some(DecodedCall::TransferFrom { from: args.address(0)?, to: args.address(1)?, amount: args.amount(2)? }),
+        "095ea7b3" => Some(DecodedCall::Approve { spender: args.address(0)?, amount: args.amount(1)? }),
+        "39509351" => Some(DecodedCall::IncreaseAllowance { spender: args.address(0)?, added_amount: args.amount(1)? }),
+        "38ed1739" => swap(RouterMethod::SwapExactTokensForTokens, args.amount(0), args.amount(1), 2),
+        "8803dbee" => swap(RouterMethod::SwapTokensForExactTokens, args.amount(1), args.amount(0), 2),
+        "7ff36ab5" => swap(RouterMethod::SwapExactETHForTokens, None, args.amount(0), 1),
+        "fb3bdb41" => swap(RouterMethod::SwapETHForExactTokens, None, args.amount(0), 1),
+        "18cbafe5" => swap(RouterMethod::SwapExactTokensForETH, args.amount(0), args.amount(1), 2),
+        "4a25d94a" => swap(RouterMethod::SwapTokensForExactETH, args.amount(1), args.amount(0), 2),
+        _ => None,
+    }
+}
+
+// `sender` sent the transaction and `contract` is the address it was sent to.
+pub fn implied_transfer(call: &DecodedCall, sender: &str, contract: &str) -> Option<DecodedTransfer> {
+    match call {
+        DecodedCall::Transfer { to, amount } => Some(DecodedTransfer {
+            from: sender.to_string(),
+            to: to.clone(),
+            token: contract.to_string(),
+            amount: amount.clone(),
+            amount_is_minimum: false,
+            symbol: None,
+            decimals: None,
+        }),
+        DecodedCall::TransferFrom { from, to, amount } => Some(DecodedTransfer {
+            from: from.clone(),
+            to: to.clone(),
+            token: contract.to_string(),
+            amount: amount.clone(),
+            amount_is_minimum: false,
+            symbol: None,
+            decimals: None,
+        }),
+        // The recipient gets the last token of the path; for exact-input swaps amount_out is amountOutMin.
+        DecodedCall::Swap { method, amount_out: Some(amount_out), path, to, .. } => Some(DecodedTransfer {
+            from: sender.to_string(),
+            to: to.clone(),
+            token: path.last()?.clone(),
+            amount: amount_out.clone(),
+            amount_is_minimum: matches!(
+                method,
+                RouterMethod::SwapExactTokensForTokens | RouterMethod::SwapExactETHForTokens | RouterMethod::SwapExactTokensForETH
+            ),
+            symbol: None,
+            decimals: None,
+        }),
+        _ => None,
+    }
+}
+
+// EntryPoint v0.6 and v0.7, which are deployed at the same addresses on every EVM chain.
+pub const ENTRY_POINTS: [&str; 2] = ["0x5ff137d4b0fdcd49dca30c7cf57e578a026d2789", "0x0000000071727de22e5e9d8baf0edac6f37da032"];
+
+// Calls requested by the calldata of a UserOperation: SimpleAccount-style execute and executeBatch, and the
+// Safe 4337 module's executeUserOp.
+fn decode_account_calls(call_data: &str) -> Option<Vec<AccountCall>> {
+    let (selector, args) = split_calldata(call_data)?;
+    match selector {
+        "b61d27f6" | "7bb37428" => Some(vec![AccountCall { target: args.address(0)?, value: args.amount(1)?, data: args.bytes(2)? }]),
+        // executeBatch(address[],bytes[]) sends no value.
+        "18dfb3c7" => {
+            let targets = args.static_array(0, |elements, i| elements.address(i))?;
+            let data = args.static_array(1, |elements, i| elements.bytes(i))?;
+            (targets.len() == data.len()).then(|| targets.into_iter().zip(data).map(|(target, data)| AccountCall { target, value: "0".to_string(), data }).collect())
+        }
+        // executeBatch(address[],uint256[],bytes[])
+        "47e1da2a" => {
+            let targets = args.static_array(0, |elements, i| elements.address(i))?;
+            let values = args.static_array(1, |elements, i| elements.amount(i))?;
+            let data = args.static_array(2, |elements, i| elements.bytes(i))?;
+            (targets.len() == values.len() && targets.len() == data.len())
+                .then(|| targets.into_iter().zip(values).zip(data).map(|((target, value), data)| AccountCall { target, value, data }).collect())
+        }
+        _ => None,
+    }
+}
+
+// handleOps of EntryPoint v0.6 and v0.7, whose operation tuples both start with sender, nonce, initCode and callData.
+pub fn decode_handle_ops(input: &str) -> Option<Vec<UserOperation>> {
+    let (selector, args) = split_calldata(input)?;
+    if selector != "1fad948c" && selector != "765e827f" {
+        return None;
+    }
+    args.dynamic_array(0)?
+        .iter()
+        .map(|operation| {
+            let calls = operation.bytes(3).and_then(|call_data| decode_account_calls(&call_data)).unwrap_or_default();
+            Some(UserOperation { sender: operation.address(0)?, nonce: operation.amount(1)?, calls })
+        })
+        .collect()
+}
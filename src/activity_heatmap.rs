@@ -0,0 +1,138 @@
+use chrono::{Datelike, TimeZone, Timelike};
+use chrono_tz::Tz;
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+#[cfg(not(target_arch = "wasm32"))]
+use plotters::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::ActivityHeatmapArgs;
+use crate::time_buckets::parse_timezone;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::workspace;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+pub const ALL_ADDRESSES: &str = "all";
+
+// Sent transactions by local day of week (Monday first) and hour of day.
+pub type ActivityMatrix = [[usize; 24]; 7];
+
+#[derive(Serialize)]
+struct HeatmapRow<'a> {
+    address: &'a str,
+    day_of_week: &'static str,
+    hour: usize,
+    transactions: usize,
+}
+
+// Activity is attributed to the sender, whose local clock or scheduler triggered the transaction.
+pub fn activity_matrices(graph: &G, tz: &Tz) -> (ActivityMatrix, HashMap<String, ActivityMatrix>) {
+    let mut total: ActivityMatrix = [[0; 24]; 7];
+    let mut per_address: HashMap<String, ActivityMatrix> = HashMap::new();
+    for edge in graph.edge_references().filter(|e| e.weight().timestamp != 0) {
+        let local = tz.timestamp_opt(edge.weight().timestamp as i64, 0).unwrap();
+        let (day, hour) = (local.weekday().num_days_from_monday() as usize, local.hour() as usize);
+        total[day][hour] += 1;
+        per_address.entry(graph[edge.source()].clone()).or_insert([[0; 24]; 7])[day][hour] += 1;
+    }
+    (total, per_address)
+}
+
+fn transaction_count(matrix: &ActivityMatrix) -> usize {
+    matrix.iter().flatten().sum()
+}
+
+// Share of activity in the busiest 8 consecutive hours, wrapping around midnight. Close to 1/3 for
+// around-the-clock automation, higher when a human working day dominates.
+pub fn busiest_window_share(matrix: &ActivityMatrix) -> (usize, f64) {
+    let by_hour: Vec<usize> = (0..24).map(|hour| matrix.iter().map(|day| day[hour]).sum()).collect();
+    let total: usize = by_hour.iter().sum();
+    let (start, in_window) = (0..24)
+        .map(|start| (start, (start..start + 8).map(|hour| by_hour[hour % 24]).sum::<usize>()))
+        .max_by_key(|&(start, in_window)| (in_window, std::cmp::Reverse(start)))
+        .unwrap();
+    (start, if total == 0 { 0.0 } else { in_window as f64 / total as f64 })
+}
+
+// Path-based bitmaps need a filesystem, which wasm32 builds of the library do not have.
+#[cfg(not(target_arch = "wasm32"))]
+fn plot_heatmap(matrix: &ActivityMatrix, plot_pathname: &str, description: &str) -> Result<()> {
+    let root = BitMapBackend::new(plot_pathname, (720, 300)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let max_count = *matrix.iter().flatten().max().unwrap_or(&0);
+    let mut chart = ChartBuilder::on(&root)
+        .margin(5)
+        .caption(description, ("sans-serif", 20))
+        .x_label_area_size(40)
+        .y_label_area_size(40)
+        .build_cartesian_2d(0u32..24u32, 0u32..7u32)?;
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .x_labels(24)
+        .y_labels(7)
+        .y_label_formatter(&|day| WEEKDAYS.get(*day as usize).copied().unwrap_or("").to_string())
+        .x_desc("Hour of day")
+        .draw()?;
+    chart.draw_series(matrix.iter().enumerate().flat_map(|(day, hours)| {
+        hours.iter().enumerate().map(move |(hour, &count)| {
+            let intensity = if max_count == 0 { 0.0 } else { count as f64 / max_count as f64 };
+            Rectangle::new([(hour as u32, day as u32), (hour as u32 + 1, day as u32 + 1)], BLUE.mix(intensity).filled())
+        })
+    }))?;
+    root.present()?;
+    workspace::record_artifact(plot_pathname)
+}
+
+pub fn run_activity_heatmap_command(args: &ActivityHeatmapArgs) -> Result<()> {
+    let tz = parse_timezone(&args.timezone)?;
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let (total, per_address) = activity_matrices(&graph, &tz);
+
+    let mut addresses: Vec<(&String, &ActivityMatrix)> =
+        per_address.iter().filter(|(_, matrix)| transaction_count(matrix) >= args.min_transactions).collect();
+    addresses.sort_by_key(|(address, matrix)| (std::cmp::Reverse(transaction_count(matrix)), address.to_string()));
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for (address, matrix) in std::iter::once((ALL_ADDRESSES, &total)).chain(addresses.iter().map(|(a, m)| (a.as_str(), *m))) {
+        for (day, hours) in matrix.iter().enumerate() {
+            for (hour, &transactions) in hours.iter().enumerate().filter(|(_, &count)| count != 0) {
+                writer.serialize(HeatmapRow { address, day_of_week: WEEKDAYS[day], hour, transactions })?;
+            }
+        }
+    }
+    commit_csv(writer)?;
+    println!("Saved activity of {} senders with at least {} transactions as {}", addresses.len(), args.min_transactions, &file_pathname);
+
+    let (plotted, label) = match &args.address {
+        Some(address) => (
+            per_address.get(&address.to_lowercase()).ok_or_else(|| eyre::eyre!("Address {} sent no transactions", address))?,
+            address.to_lowercase(),
+        ),
+        None => (&total, "all senders".to_string()),
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let plot_pathname = format!("{}/{}.png", DATA_STORAGE_FOLDER, args.output.trim_end_matches(".csv"));
+        plot_heatmap(plotted, &plot_pathname, &format!("Activity of {} ({})", label, args.timezone))?;
+        println!("Saved plot as {}", &plot_pathname);
+    }
+
+    let (start, share) = busiest_window_share(plotted);
+    println!(
+        "Busiest 8 hours for {}: {:02}:00-{:02}:00 {} with {:.1}% of {} transactions",
+        label,
+        start,
+        (start + 8) % 24,
+        args.timezone,
+        share * 100.0,
+        transaction_count(plotted)
+    );
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use crate::analysis_pass::{AnalysisPass, Report};
+use crate::pricing::PriceProvider;
+use crate::time_buckets::time_label;
+use crate::{Transaction, G};
+
+// Poisoners generate vanity addresses agreeing with a real counterparty on the hex digits wallets show,
+// the first and last few, and send dust from them so the lookalike turns up in the victim's history to be
+// copied from. Lookalikes are matched within an edit distance on those digits, and must differ in most of
+// the digits in between, which tells them from sequential or vanity addresses of one owner.
+const SHOWN_DIGITS: usize = 4;
+const MAX_SHOWN_EDITS: usize = 1;
+const MIN_HIDDEN_DIFFERENCES: usize = 16;
+// Transfers below this are dust. Calls without a priced transfer count as dust when they moved no coin.
+const DUST_USD: f64 = 1.0;
+// Non-dust transfers with a counterparty, either way, that make it one the victim is likely to copy from.
+const MIN_COUNTERPARTY_TRANSFERS: usize = 3;
+const MAX_REPORTED: usize = 50;
+
+fn is_dust(transaction: &Transaction) -> bool {
+    match &transaction.data {
+        Some(data) => data.usd_value < DUST_USD,
+        None => transaction.value_wei == 0,
+    }
+}
+
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, &x) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, &y) in b.iter().enumerate() {
+            current[j + 1] = (previous[j] + (x != y) as usize).min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Whether `candidate` imitates `original`: both 0x-prefixed 40-digit hex addresses, close on the shown
+// digits and unlike in the hidden ones.
+pub fn is_lookalike(candidate: &str, original: &str) -> bool {
+    let (Some(a), Some(b)) = (candidate.strip_prefix("0x"), original.strip_prefix("0x")) else { return false };
+    let (a, b) = (a.to_lowercase(), b.to_lowercase());
+    if a.len() != 40 || b.len() != 40 || a == b {
+        return false;
+    }
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let shown_edits = edit_distance(&a[..SHOWN_DIGITS], &b[..SHOWN_DIGITS]) + edit_distance(&a[40 - SHOWN_DIGITS..], &b[40 - SHOWN_DIGITS..]);
+    let hidden_differences = a[SHOWN_DIGITS..40 - SHOWN_DIGITS].iter().zip(&b[SHOWN_DIGITS..40 - SHOWN_DIGITS]).filter(|(x, y)| x != y).count();
+    shown_edits <= MAX_SHOWN_EDITS && hidden_differences >= MIN_HIDDEN_DIFFERENCES
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoisoningCandidate {
+    pub victim: NodeIndex,
+    pub lookalike: NodeIndex,
+    pub imitated: NodeIndex,
+    pub dust_transfers: usize,
+    pub first_timestamp: u64,
+}
+
+pub fn poisoning_candidates(graph: &G) -> Vec<PoisoningCandidate> {
+    let mut candidates = vec![];
+    for victim in graph.node_indices() {
+        // Non-dust and dust transfers with every counterparty, with the first dust timestamp.
+        let mut counterparties: HashMap<NodeIndex, (usize, usize, u64)> = HashMap::new();
+        for edge in graph.edges_directed(victim, petgraph::Direction::Outgoing).chain(graph.edges_directed(victim, petgraph::Direction::Incoming)) {
+            let counterparty = if edge.source() == victim { edge.target() } else { edge.source() };
+            if counterparty == victim {
+                continue;
+            }
+            let entry = counterparties.entry(counterparty).or_insert((0, 0, u64::MAX));
+            if is_dust(edge.weight()) {
+                entry.1 += 1;
+                entry.2 = entry.2.min(edge.weight().timestamp);
+            } else {
+                entry.0 += 1;
+            }
+        }
+        let frequent: Vec<NodeIndex> = counterparties.iter().filter(|(_, &(transfers, _, _))| transfers >= MIN_COUNTERPARTY_TRANSFERS).map(|(&n, _)| n).collect();
+        for (&lookalike, &(transfers, dust_transfers, first_timestamp)) in counterparties.iter() {
+            if dust_transfers == 0 || transfers >= MIN_COUNTERPARTY_TRANSFERS {
+                continue;
+            }
+            if let Some(&imitated) = frequent.iter().filter(|&&c| is_lookalike(&graph[lookalike], &graph[c])).min_by_key(|&&c| &graph[c]) {
+                candidates.push(PoisoningCandidate { victim, lookalike, imitated, dust_transfers, first_timestamp });
+            }
+        }
+    }
+    candidates.sort_by(|x, y| (&graph[x.victim], &graph[x.lookalike]).cmp(&(&graph[y.victim], &graph[y.lookalike])));
+    candidates
+}
+
+pub fn poisoning_report(graph: &G, candidates: &[PoisoningCandidate]) -> Result<String> {
+    let mut report = format!(
+        "{} lookalike addresses sent or received dust (below {} USD) next to a counterparty they imitate; counterparty statistics counting them are polluted\n",
+        candidates.len(),
+        DUST_USD
+    );
+    for candidate in candidates.iter().take(MAX_REPORTED) {
+        writeln!(
+            report,
+            "  {} imitates {} for {}: {} dust transfers from {}",
+            graph[candidate.lookalike], graph[candidate.imitated], graph[candidate.victim], candidate.dust_transfers, time_label(candidate.first_timestamp)?
+        )
+        .unwrap();
+    }
+    if candidates.len() > MAX_REPORTED {
+        writeln!(report, "  ... and {} more", candidates.len() - MAX_REPORTED).unwrap();
+    }
+    Ok(report)
+}
+
+pub struct AddressPoisoningPass;
+
+impl AnalysisPass for AddressPoisoningPass {
+    fn name(&self) -> &str {
+        "address_poisoning"
+    }
+
+    fn parameters(&self) -> String {
+        format!(
+            "shown_digits={} max_shown_edits={} min_hidden_differences={} dust_usd={} min_counterparty_transfers={}",
+            SHOWN_DIGITS, MAX_SHOWN_EDITS, MIN_HIDDEN_DIFFERENCES, DUST_USD, MIN_COUNTERPARTY_TRANSFERS
+        )
+    }
+
+    fn run(&self, graph: &G, _prices: &dyn PriceProvider) -> Result<Report> {
+        let candidates = poisoning_candidates(graph);
+        let mut victims: Vec<NodeIndex> = candidates.iter().map(|c| c.victim).collect();
+        victims.dedup();
+        let metrics = BTreeMap::from([
+            ("lookalike_addresses".to_string(), candidates.len() as f64),
+            ("poisoned_addresses".to_string(), victims.len() as f64),
+        ]);
+        Ok(Report { text: poisoning_report(graph, &candidates)?, metrics })
+    }
+}
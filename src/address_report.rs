@@ -0,0 +1,216 @@
+use chrono_tz::Tz;
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+
+use crate::annotations::{Annotation, Annotations};
+use crate::atomic_file::{commit_csv, csv_writer, write_atomically};
+use crate::cli::ReportArgs;
+use crate::fees::charges_fee;
+use crate::graph_query::load_labels;
+use crate::money::Amount;
+use crate::time_buckets::{local_label, parse_timezone};
+use crate::{deserialize_graph, Transaction, DATA_STORAGE_FOLDER, G};
+
+const TOP_COUNTERPARTIES_SHOWN: usize = 10;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReportRow {
+    pub time: String,
+    pub timestamp: u64,
+    pub block_number: u64,
+    pub direction: &'static str,
+    pub counterparty: String,
+    pub counterparty_label: Option<String>,
+    pub counterparty_tags: Option<String>,
+    pub hash: String,
+    pub kind: String,
+    pub payload: Option<String>,
+    pub function: Option<String>,
+    pub usd_value: Option<f64>,
+    // Only for transactions the address sent, since the sender pays the fee.
+    pub fee_usd: Option<f64>,
+    // USD received minus USD sent up to and including this transfer.
+    pub running_balance_usd: f64,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ReportSummary {
+    pub incoming_transfers: usize,
+    pub outgoing_transfers: usize,
+    pub self_transfers: usize,
+    pub received_usd: f64,
+    pub sent_usd: f64,
+    pub net_usd: f64,
+    pub fees_usd: f64,
+    pub counterparties: usize,
+    pub first_timestamp: Option<u64>,
+    pub last_timestamp: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AddressReport {
+    pub address: String,
+    pub label: Option<String>,
+    pub annotation: Option<Annotation>,
+    pub summary: ReportSummary,
+    // Counterparties by USD volume exchanged in either direction, largest first.
+    pub top_counterparties: Vec<(String, f64)>,
+    pub transactions: Vec<ReportRow>,
+}
+
+pub fn address_report(graph: &G, address: &str, labels: &HashMap<String, String>, annotations: &Annotations, tz: &Tz) -> Result<AddressReport> {
+    let address = address.to_lowercase();
+    let node = graph.node_indices().find(|&n| graph[n] == address).ok_or_else(|| eyre::eyre!("Address {} is not present in the graph", address))?;
+
+    let mut edges: Vec<(&'static str, String, &Transaction)> = graph
+        .edges_directed(node, Direction::Outgoing)
+        .map(|e| (if e.target() == node { "self" } else { "out" }, graph[e.target()].clone(), e.weight()))
+        .chain(
+            graph
+                .edges_directed(node, Direction::Incoming)
+                .filter(|e| e.source() != node)
+                .map(|e| ("in", graph[e.source()].clone(), e.weight())),
+        )
+        .collect();
+    edges.sort_by_key(|(_, _, t)| (t.timestamp, t.block_number, t.transaction_index, t.log_index, t.trace_index));
+
+    let mut summary = ReportSummary::default();
+    let mut volume_by_counterparty: BTreeMap<String, f64> = BTreeMap::new();
+    let mut running_balance_usd = 0.0;
+    let mut transactions = Vec::new();
+    // A sent transaction's fee shows on the first of its edges, its token transfers copy the hash and gas.
+    let mut charged_hashes: HashSet<&str> = HashSet::new();
+    for (direction, counterparty, transaction) in edges {
+        let usd = transaction.usd_value();
+        let fee_usd = if direction == "in" || !charges_fee(&mut charged_hashes, transaction) { None } else { transaction.fee_usd };
+        match direction {
+            "in" => {
+                summary.incoming_transfers += 1;
+                summary.received_usd += usd.unwrap_or(0.0);
+                running_balance_usd += usd.unwrap_or(0.0);
+            }
+            "out" => {
+                summary.outgoing_transfers += 1;
+                summary.sent_usd += usd.unwrap_or(0.0);
+                running_balance_usd -= usd.unwrap_or(0.0);
+            }
+            _ => summary.self_transfers += 1,
+        }
+        summary.fees_usd += fee_usd.unwrap_or(0.0);
+        if direction != "self" {
+            *volume_by_counterparty.entry(counterparty.clone()).or_default() += usd.unwrap_or(0.0);
+        }
+        summary.first_timestamp = Some(summary.first_timestamp.map_or(transaction.timestamp, |t| t.min(transaction.timestamp)));
+        summary.last_timestamp = Some(summary.last_timestamp.map_or(transaction.timestamp, |t| t.max(transaction.timestamp)));
+        transactions.push(ReportRow {
+            time: local_label(transaction.timestamp, tz)?,
+            timestamp: transaction.timestamp,
+            block_number: transaction.block_number,
+            direction,
+            counterparty_label: labels.get(&counterparty).cloned(),
+            counterparty_tags: annotations.get(&counterparty).filter(|a| !a.tags.is_empty()).map(Annotation::tag_list),
+            counterparty,
+            hash: transaction.hash.clone(),
+            kind: format!("{:?}", transaction.kind),
+            payload: transaction.data.as_ref().map(|d| format!("{:?}", d.payload)),
+            function: transaction.data.as_ref().map(|d| format!("{:?}", d.used_onchain_function)),
+            usd_value: usd,
+            fee_usd,
+            running_balance_usd,
+        });
+    }
+    summary.net_usd = summary.received_usd - summary.sent_usd;
+    summary.counterparties = volume_by_counterparty.len();
+
+    let mut top_counterparties: Vec<(String, f64)> = volume_by_counterparty.into_iter().collect();
+    top_counterparties.sort_by(|x, y| y.1.partial_cmp(&x.1).unwrap().then(x.0.cmp(&y.0)));
+    top_counterparties.truncate(TOP_COUNTERPARTIES_SHOWN);
+    Ok(AddressReport { label: labels.get(&address).cloned(), annotation: annotations.get(&address).cloned(), address, summary, top_counterparties, transactions })
+}
+
+fn labelled(address: &str, labels: &HashMap<String, String>, annotations: &Annotations) -> String {
+    match labels.get(address) {
+        Some(label) => format!("{} ({})", annotations.mention(address), label),
+        None => annotations.mention(address),
+    }
+}
+
+// The report without its transaction list.
+pub fn summary_text(report: &AddressReport, labels: &HashMap<String, String>, annotations: &Annotations, tz: &Tz) -> Result<String> {
+    let summary = &report.summary;
+    let labelled = |address: &str| labelled(address, labels, annotations);
+    let mut text = format!("Transaction report for {}\n", labelled(&report.address));
+    for note in report.annotation.iter().flat_map(|annotation| annotation.notes.iter()) {
+        text.push_str(&format!("Note: {}\n", note.text));
+    }
+    if let (Some(first), Some(last)) = (summary.first_timestamp, summary.last_timestamp) {
+        text.push_str(&format!("Active from {} to {}\n", local_label(first, tz)?, local_label(last, tz)?));
+    }
+    text.push_str(&format!(
+        "Incoming: {} transfers, {}\nOutgoing: {} transfers, {}\nSelf-transfers: {}\nNet flow: {}, fees paid: {}\nCounterparties: {}\n",
+        summary.incoming_transfers,
+        Amount::Usd(summary.received_usd),
+        summary.outgoing_transfers,
+        Amount::Usd(summary.sent_usd),
+        summary.self_transfers,
+        Amount::Usd(summary.net_usd),
+        Amount::Usd(summary.fees_usd),
+        summary.counterparties
+    ));
+    text.push_str("\nTop counterparties by volume:\n");
+    for (counterparty, volume) in report.top_counterparties.iter() {
+        text.push_str(&format!("  {} {}\n", labelled(counterparty), Amount::Usd(*volume)));
+    }
+    Ok(text)
+}
+
+pub fn report_text(report: &AddressReport, labels: &HashMap<String, String>, annotations: &Annotations, tz: &Tz) -> Result<String> {
+    let labelled = |address: &str| labelled(address, labels, annotations);
+    let mut text = summary_text(report, labels, annotations, tz)?;
+    text.push_str("\nTransactions:\n");
+    for row in report.transactions.iter() {
+        let arrow = match row.direction {
+            "in" => "<-",
+            "out" => "->",
+            _ => "<>",
+        };
+        text.push_str(&format!(
+            "{} {} {} {} {} {}, balance {}\n",
+            row.time,
+            arrow,
+            labelled(&row.counterparty),
+            row.kind,
+            row.payload.as_deref().unwrap_or("-"),
+            row.usd_value.map_or("unknown value".to_string(), |usd| Amount::Usd(usd).to_string()),
+            Amount::Usd(row.running_balance_usd)
+        ));
+        text.push_str(&format!("    {}{}\n", row.hash, row.fee_usd.map_or(String::new(), |fee| format!(", fee {}", Amount::Usd(fee)))));
+    }
+    Ok(text)
+}
+
+pub fn run_report_command(args: &ReportArgs) -> Result<()> {
+    let tz = parse_timezone(&args.timezone)?;
+    let labels = args.labels.as_ref().map(|name| load_labels(name)).transpose()?.unwrap_or_default();
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let annotations = Annotations::load()?;
+    let report = address_report(&graph, &args.address, &labels, &annotations, &tz)?;
+    let text = report_text(&report, &labels, &annotations, &tz)?;
+    print!("{}", text);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let prefix = format!("{}/{}", DATA_STORAGE_FOLDER, args.output.clone().unwrap_or(format!("report_{}", report.address)));
+    write_atomically(&format!("{}.txt", prefix), text.as_bytes())?;
+    let mut writer = csv_writer(&format!("{}.csv", prefix))?;
+    for row in report.transactions.iter() {
+        writer.serialize(row)?;
+    }
+    commit_csv(writer)?;
+    write_atomically(&format!("{}.json", prefix), serde_json::to_string_pretty(&report)?.as_bytes())?;
+    println!("\nSaved report for {} transactions as {}.txt, .csv and .json", report.transactions.len(), prefix);
+    Ok(())
+}
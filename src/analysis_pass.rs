@@ -0,0 +1,287 @@
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::address_poisoning::AddressPoisoningPass;
+use crate::approval_risk::{approval_risk_summary, find_approvals};
+use crate::atomic_file::write_atomically;
+use crate::cli::AnalyzeArgs;
+use crate::entity_clustering::EntitiesPass;
+use crate::exit_status::{self, FailureKind};
+use crate::fees::{fee_percentiles_over_time, fee_spike_summary};
+use crate::layers::select_layers;
+use crate::mixers::MixersPass;
+use crate::near_duplicates::NearDuplicatesPass;
+use crate::pass_cache::PassCache;
+use crate::pipeline::run_pipeline;
+use crate::pricing::{PriceAudit, PriceCache, PriceChecks, PriceProvider, PriceSeries};
+use crate::risk_scoring::{RiskScorer, RiskScorerRegistry};
+use crate::stable_graph::remove_isolated_nodes;
+use crate::stats::graph_stats_report;
+use crate::task_control::TaskControl;
+use crate::time_buckets::CalendarPeriod;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+const FEE_SYMBOL: &str = "ETH";
+const FEE_SPIKE_FACTOR: f64 = 2.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Report {
+    pub text: String,
+    // Headline numbers copied into the manifest, so runs can be compared without parsing report text.
+    pub metrics: BTreeMap<String, f64>,
+}
+
+// A named analysis over a loaded graph. Passes registered in a PassRegistry run in the analyze command and
+// after every crawl, and their reports land in the same manifest as the built-in ones.
+pub trait AnalysisPass {
+    fn name(&self) -> &str;
+    fn run(&self, graph: &G, prices: &dyn PriceProvider) -> Result<Report>;
+    // Settings the report depends on besides the graph and prices; cached reports are only reused when
+    // these match, so a pass with tunable settings must list them here.
+    fn parameters(&self) -> String {
+        String::new()
+    }
+}
+
+// Used when no price CSV is given; passes needing prices report their USD figures as unknown.
+pub struct NoPrices;
+
+impl PriceProvider for NoPrices {
+    fn hourly_usd_price(&self, _symbol: &str, _hour_start: u64) -> Option<f64> {
+        None
+    }
+}
+
+struct StatsPass;
+
+impl AnalysisPass for StatsPass {
+    fn name(&self) -> &str {
+        "stats"
+    }
+
+    fn run(&self, graph: &G, _prices: &dyn PriceProvider) -> Result<Report> {
+        let metrics = BTreeMap::from([
+            ("nodes".to_string(), graph.node_count() as f64),
+            ("edges".to_string(), graph.edge_count() as f64),
+        ]);
+        Ok(Report { text: graph_stats_report(graph), metrics })
+    }
+}
+
+struct FeesPass;
+
+impl AnalysisPass for FeesPass {
+    fn name(&self) -> &str {
+        "fees"
+    }
+
+    fn parameters(&self) -> String {
+        format!("symbol={} spike_factor={}", FEE_SYMBOL, FEE_SPIKE_FACTOR)
+    }
+
+    fn run(&self, graph: &G, prices: &dyn PriceProvider) -> Result<Report> {
+        let tz = chrono_tz::UTC;
+        let mut cache = PriceCache::new(prices);
+        let periods = fee_percentiles_over_time(graph, CalendarPeriod::Day, &tz, Some((&mut cache, FEE_SYMBOL)))?;
+        let mut metrics = BTreeMap::from([
+            ("periods".to_string(), periods.len() as f64),
+            ("total_fee_coin".to_string(), periods.iter().fold(0.0, |acc, p| acc + p.total_fee_coin)),
+        ]);
+        if let Some(total_fee_usd) = periods.iter().map(|p| p.total_fee_usd).sum::<Option<f64>>() {
+            metrics.insert("total_fee_usd".to_string(), total_fee_usd);
+        }
+        Ok(Report { text: fee_spike_summary(&periods, FEE_SPIKE_FACTOR, &tz)?, metrics })
+    }
+}
+
+struct ApprovalsPass;
+
+impl AnalysisPass for ApprovalsPass {
+    fn name(&self) -> &str {
+        "approvals"
+    }
+
+    fn run(&self, graph: &G, _prices: &dyn PriceProvider) -> Result<Report> {
+        let approvals = find_approvals(graph);
+        let unlimited = approvals.iter().filter(|a| a.latest && a.amount != "0" && a.unlimited).count();
+        let metrics = BTreeMap::from([
+            ("approvals".to_string(), approvals.len() as f64),
+            ("active_unlimited".to_string(), unlimited as f64),
+        ]);
+        Ok(Report { text: approval_risk_summary(&approvals)?, metrics })
+    }
+}
+
+#[derive(Default)]
+pub struct PassRegistry {
+    passes: Vec<Box<dyn AnalysisPass>>,
+    // Combined by the risk command.
+    scorers: RiskScorerRegistry,
+}
+
+impl PassRegistry {
+    pub fn with_builtin_passes() -> PassRegistry {
+        PassRegistry {
+            passes: vec![Box::new(StatsPass), Box::new(FeesPass), Box::new(ApprovalsPass), Box::new(EntitiesPass), Box::new(NearDuplicatesPass), Box::new(AddressPoisoningPass), Box::new(MixersPass)],
+            scorers: RiskScorerRegistry::with_builtin_scorers(),
+        }
+    }
+
+    pub fn register(&mut self, pass: Box<dyn AnalysisPass>) -> Result<()> {
+        if self.passes.iter().any(|p| p.name() == pass.name()) {
+            return Err(eyre::eyre!("An analysis pass named {} is already registered", pass.name()));
+        }
+        self.passes.push(pass);
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.passes.iter().map(|p| p.name()).collect()
+    }
+
+    pub fn register_scorer(&mut self, scorer: Box<dyn RiskScorer>) -> Result<()> {
+        self.scorers.register(scorer)
+    }
+
+    pub fn scorers(&self) -> &RiskScorerRegistry {
+        &self.scorers
+    }
+
+    // Runs the selected passes in registration order, all of them when none are selected. A failing pass
+    // is recorded and does not stop the others.
+    pub fn run(&self, graph: &G, prices: &dyn PriceProvider, selected: &[String]) -> Result<Vec<(String, Result<Report>)>> {
+        self.run_with_control(graph, prices, selected, None, &TaskControl::default())
+    }
+
+    // As run, but reports found in the cache are reused and fresh ones are added to it. Failed passes are
+    // not cached, so they run again next time. Cancellation is checked before each pass.
+    pub fn run_with_control(
+        &self,
+        graph: &G,
+        prices: &dyn PriceProvider,
+        selected: &[String],
+        cache: Option<&PassCache>,
+        control: &TaskControl,
+    ) -> Result<Vec<(String, Result<Report>)>> {
+        if let Some(unknown) = selected.iter().find(|name| !self.names().contains(&name.as_str())) {
+            return Err(eyre::eyre!("Unknown analysis pass {}, registered passes are {}", unknown, self.names().join(", ")));
+        }
+        let chosen: Vec<&dyn AnalysisPass> =
+            self.passes.iter().map(|p| p.as_ref()).filter(|p| selected.is_empty() || selected.iter().any(|name| name == p.name())).collect();
+        chosen
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                control.check("analysis")?;
+                control.report("analysis", i, Some(chosen.len()));
+                if let Some(report) = cache.and_then(|cache| cache.get(p.name(), &p.parameters())) {
+                    println!("Reusing cached report of analysis pass {}", p.name());
+                    return Ok((p.name().to_string(), Ok(report)));
+                }
+                println!("Running analysis pass {}", p.name());
+                let report = p.run(graph, prices);
+                if let (Some(cache), Ok(report)) = (cache, &report) {
+                    cache.put(p.name(), &p.parameters(), report)?;
+                }
+                Ok((p.name().to_string(), report))
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+struct ManifestEntry {
+    pass: String,
+    report_pathname: Option<String>,
+    metrics: BTreeMap<String, f64>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AnalysisManifest {
+    input: String,
+    prices: Option<String>,
+    // Checks of the price series and the points the passes of this run looked up. Reports reused from
+    // the cache read their prices in the run that computed them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price_audit: Option<PriceAudit>,
+    passes: Vec<ManifestEntry>,
+}
+
+pub fn price_checks(args: &AnalyzeArgs) -> PriceChecks {
+    PriceChecks { max_gap_hours: args.max_price_gap_hours, max_jump_factor: args.max_price_jump, smooth_outliers: args.smooth_price_outliers }
+}
+
+// Each report goes to analysis_<pass>.txt next to the manifest. Returns the combined report text.
+pub fn save_reports(
+    reports: Vec<(String, Result<Report>)>,
+    input: &str,
+    prices: Option<(&str, &PriceSeries)>,
+    manifest_name: &str,
+) -> Result<String> {
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let mut combined = String::new();
+    let mut entries = Vec::new();
+    for (pass, report) in reports {
+        let entry = match report {
+            Ok(report) => {
+                let report_pathname = format!("{}/analysis_{}.txt", DATA_STORAGE_FOLDER, pass);
+                write_atomically(&report_pathname, report.text.as_bytes())?;
+                combined.push_str(&format!("Analysis pass {}:\n{}\n", pass, report.text));
+                ManifestEntry { pass, report_pathname: Some(report_pathname), metrics: report.metrics, error: None }
+            }
+            Err(e) => {
+                println!("Analysis pass {} failed:\n{}", pass, e);
+                exit_status::record(FailureKind::Partial, format!("Analysis pass {} failed: {}", pass, e));
+                combined.push_str(&format!("Analysis pass {} failed: {}\n\n", pass, e));
+                ManifestEntry { pass, report_pathname: None, metrics: BTreeMap::new(), error: Some(e.to_string()) }
+            }
+        };
+        entries.push(entry);
+    }
+    let manifest = AnalysisManifest {
+        input: input.to_string(),
+        prices: prices.map(|(name, _)| name.to_string()),
+        price_audit: prices.map(|(_, series)| series.audit()),
+        passes: entries,
+    };
+    let manifest_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, manifest_name);
+    write_atomically(&manifest_pathname, serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    println!("Saved analysis manifest for {} passes as {}", manifest.passes.len(), &manifest_pathname);
+    Ok(combined)
+}
+
+pub fn load_analysis_input(input: &str, args: &AnalyzeArgs) -> Result<G> {
+    let (graph, _) = deserialize_graph(input)?;
+    let mut graph = select_layers(graph, &args.layer)?;
+    if args.remove_isolated_nodes {
+        println!("Removed {} isolated addresses", remove_isolated_nodes(&mut graph));
+    }
+    Ok(graph)
+}
+
+pub fn run_analyze_command(args: &AnalyzeArgs, passes: &PassRegistry) -> Result<()> {
+    if args.list {
+        for name in passes.names() {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+    if let Some(pipeline) = &args.pipeline {
+        return run_pipeline(pipeline, args, passes);
+    }
+    let graph = load_analysis_input(&args.input, args)?;
+    let checks = price_checks(args);
+    let price_series =
+        args.prices.as_ref().map(|name| PriceSeries::from_csv_checked(&format!("{}/{}", DATA_STORAGE_FOLDER, name), &checks)).transpose()?;
+    let prices: &dyn PriceProvider = match &price_series {
+        Some(series) => series,
+        None => &NoPrices,
+    };
+    let cache = (!args.no_cache).then(|| PassCache::new(&graph, args.prices.as_deref(), &checks)).transpose()?;
+    let reports = passes.run_with_control(&graph, prices, &args.passes, cache.as_ref(), &TaskControl::default())?;
+    print!("{}", save_reports(reports, &args.input, args.prices.as_deref().zip(price_series.as_ref()), &args.manifest)?);
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::atomic_file::write_atomically;
+use crate::cli::{AnnotateArgs, AnnotationsArgs};
+use crate::time_buckets::time_label;
+use crate::workspace;
+use crate::DATA_STORAGE_FOLDER;
+
+// Analyst notes and tags on addresses, kept in the workspace so that they outlive the graphs they were made on.
+const ANNOTATIONS_FILENAME: &str = "annotations.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Note {
+    pub text: String,
+    pub added_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Annotation {
+    pub tags: BTreeSet<String>,
+    pub notes: Vec<Note>,
+}
+
+impl Annotation {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.notes.is_empty()
+    }
+
+    // Tags joined for a CSV cell or an inline mention, e.g. "phishing;suspect".
+    pub fn tag_list(&self) -> String {
+        self.tags.iter().cloned().collect::<Vec<String>>().join(";")
+    }
+
+    pub fn note_list(&self) -> String {
+        self.notes.iter().map(|note| note.text.as_str()).collect::<Vec<&str>>().join("; ")
+    }
+}
+
+// Keyed by lowercase address.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Annotations {
+    pub addresses: BTreeMap<String, Annotation>,
+}
+
+fn annotations_pathname() -> String {
+    format!("{}/{}", DATA_STORAGE_FOLDER, ANNOTATIONS_FILENAME)
+}
+
+impl Annotations {
+    // A workspace without annotations has an empty set.
+    pub fn load() -> Result<Annotations> {
+        match File::open(annotations_pathname()) {
+            Ok(file) => Ok(serde_json::from_reader(std::io::BufReader::new(file))?),
+            Err(_) => Ok(Annotations::default()),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+        write_atomically(&annotations_pathname(), serde_json::to_string_pretty(self)?.as_bytes())?;
+        workspace::record_artifact(&annotations_pathname())
+    }
+
+    pub fn get(&self, address: &str) -> Option<&Annotation> {
+        self.addresses.get(&address.to_lowercase())
+    }
+
+    pub fn add_tag(&mut self, address: &str, tag: &str) {
+        self.addresses.entry(address.to_lowercase()).or_default().tags.insert(tag.to_string());
+    }
+
+    pub fn add_note(&mut self, address: &str, text: &str) {
+        let added_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        self.addresses.entry(address.to_lowercase()).or_default().notes.push(Note { text: text.to_string(), added_at });
+    }
+
+    // Addresses left without tags and notes are dropped. Returns whether the tag was there.
+    pub fn remove_tag(&mut self, address: &str, tag: &str) -> bool {
+        let address = address.to_lowercase();
+        let Some(annotation) = self.addresses.get_mut(&address) else { return false };
+        let removed = annotation.tags.remove(tag);
+        if annotation.is_empty() {
+            self.addresses.remove(&address);
+        }
+        removed
+    }
+
+    // Index as shown by the annotate command, counting from 1.
+    pub fn remove_note(&mut self, address: &str, index: usize) -> Option<Note> {
+        let address = address.to_lowercase();
+        let annotation = self.addresses.get_mut(&address)?;
+        let note = (1..=annotation.notes.len()).contains(&index).then(|| annotation.notes.remove(index - 1));
+        if annotation.is_empty() {
+            self.addresses.remove(&address);
+        }
+        note
+    }
+
+    pub fn clear(&mut self, address: &str) -> Option<Annotation> {
+        self.addresses.remove(&address.to_lowercase())
+    }
+
+    pub fn with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = (&'a String, &'a Annotation)> + 'a {
+        self.addresses.iter().filter(move |(_, annotation)| annotation.tags.contains(tag))
+    }
+
+    // The address followed by its tags in brackets, for report lines.
+    pub fn mention(&self, address: &str) -> String {
+        match self.get(address).filter(|annotation| !annotation.tags.is_empty()) {
+            Some(annotation) => format!("{} [{}]", address, annotation.tag_list()),
+            None => address.to_string(),
+        }
+    }
+}
+
+pub fn annotation_text(address: &str, annotation: &Annotation) -> Result<String> {
+    let mut text = format!("{}\n", address);
+    if !annotation.tags.is_empty() {
+        text.push_str(&format!("  Tags: {}\n", annotation.tags.iter().cloned().collect::<Vec<String>>().join(", ")));
+    }
+    for (i, note) in annotation.notes.iter().enumerate() {
+        text.push_str(&format!("  Note {} (added at {}): {}\n", i + 1, time_label(note.added_at)?, note.text));
+    }
+    Ok(text)
+}
+
+pub fn run_annotate_command(args: &AnnotateArgs) -> Result<()> {
+    let mut annotations = Annotations::load()?;
+    let address = args.address.to_lowercase();
+    let changes = !args.tag.is_empty() || !args.note.is_empty() || !args.untag.is_empty() || args.remove_note.is_some() || args.clear;
+    if args.clear && annotations.clear(&address).is_none() {
+        println!("{} has no annotation", address);
+    }
+    for tag in args.untag.iter() {
+        if !annotations.remove_tag(&address, tag) {
+            println!("{} is not tagged {}", address, tag);
+        }
+    }
+    if let Some(index) = args.remove_note {
+        if annotations.remove_note(&address, index).is_none() {
+            println!("{} has no note {}", address, index);
+        }
+    }
+    for tag in args.tag.iter() {
+        annotations.add_tag(&address, tag);
+    }
+    for note in args.note.iter() {
+        annotations.add_note(&address, note);
+    }
+    if changes {
+        annotations.save()?;
+    }
+    match annotations.get(&address) {
+        Some(annotation) => print!("{}", annotation_text(&address, annotation)?),
+        None => println!("{} has no annotation", address),
+    }
+    Ok(())
+}
+
+pub fn run_annotations_command(args: &AnnotationsArgs) -> Result<()> {
+    let annotations = Annotations::load()?;
+    let listed: Vec<(&String, &Annotation)> = match &args.tag {
+        Some(tag) => annotations.with_tag(tag).collect(),
+        None => annotations.addresses.iter().collect(),
+    };
+    for (address, annotation) in listed.iter() {
+        print!("{}", annotation_text(address, annotation)?);
+    }
+    println!("{} annotated addresses", listed.len());
+    Ok(())
+}
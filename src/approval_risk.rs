@@ -0,0 +1,171 @@
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+use primitive_types::U256;
+#[cfg(feature = "crawl")]
+use reqwest::Client;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+#[cfg(feature = "crawl")]
+use std::time::Duration;
+#[cfg(feature = "crawl")]
+use tokio::runtime::Runtime;
+
+use crate::abi_decoding::DecodedCall;
+use crate::atomic_file::{commit_csv, csv_writer, write_atomically};
+use crate::cli::ApprovalsArgs;
+use crate::time_buckets::time_label;
+#[cfg(feature = "crawl")]
+use crate::{etherscan::Etherscan, http_client, read_api_key, API_REQUESTS_PER_SECOND};
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Approval {
+    pub owner: String,
+    pub token: String,
+    pub spender: String,
+    pub method: &'static str,
+    pub amount: String,
+    pub unlimited: bool,
+    // False when a later approve() of the same owner, token and spender replaced this allowance.
+    pub latest: bool,
+    pub spender_verified: Option<bool>,
+    pub timestamp: u64,
+    pub hash: String,
+}
+
+// Wallets and dapps ask for the maximum uint256; anything in the top half of the range is as good as unlimited.
+fn is_unlimited(amount: &str) -> bool {
+    U256::from_dec_str(amount).is_ok_and(|amount| amount >= U256::one() << 255)
+}
+
+pub fn find_approvals(graph: &G) -> Vec<Approval> {
+    let mut approvals: Vec<Approval> = graph
+        .edge_references()
+        .filter_map(|edge| {
+            let (method, spender, amount) = match edge.weight().decoded_call.as_ref()? {
+                DecodedCall::Approve { spender, amount } => ("approve", spender, amount),
+                DecodedCall::IncreaseAllowance { spender, added_amount } => ("increaseAllowance", spender, added_amount),
+                _ => return None,
+            };
+            Some(Approval {
+                owner: graph[edge.source()].clone(),
+                token: graph[edge.target()].clone(),
+                spender: spender.clone(),
+                method,
+                amount: amount.clone(),
+                unlimited: is_unlimited(amount),
+                latest: true,
+                spender_verified: None,
+                timestamp: edge.weight().timestamp,
+                hash: edge.weight().hash.clone(),
+            })
+        })
+        .collect();
+    approvals.sort_by(|a, b| (a.timestamp, &a.hash).cmp(&(b.timestamp, &b.hash)));
+
+    let mut latest_approve: HashMap<(String, String, String), usize> = HashMap::new();
+    for (i, approval) in approvals.iter().enumerate().filter(|(_, a)| a.method == "approve") {
+        latest_approve.insert((approval.owner.clone(), approval.token.clone(), approval.spender.clone()), i);
+    }
+    for (i, approval) in approvals.iter_mut().enumerate() {
+        if let Some(&latest_index) = latest_approve.get(&(approval.owner.clone(), approval.token.clone(), approval.spender.clone())) {
+            approval.latest = i >= latest_index;
+        }
+    }
+    approvals
+}
+
+// Etherscan returns an empty SourceCode for contracts without verified source.
+#[cfg(feature = "crawl")]
+async fn is_verified(address: &str, client: &Client, api_key: &str) -> Result<bool> {
+    let (response, _): (serde_json::Value, usize) = Etherscan::new(client, api_key).call("contract", "getsourcecode", &[("address", address.to_string())]).await?;
+    let source_code = response["result"][0]["SourceCode"]
+        .as_str()
+        .ok_or_else(|| eyre::eyre!("Unexpected getsourcecode response for {}: {}", address, response))?;
+    Ok(!source_code.is_empty())
+}
+
+#[cfg(feature = "crawl")]
+async fn spender_verification(spenders: &[String], api_key: &str) -> HashMap<String, bool> {
+    let client = http_client::client();
+    let mut verified = HashMap::new();
+    for (i, spender) in spenders.iter().enumerate() {
+        match is_verified(spender, &client, api_key).await {
+            Ok(is_verified) => {
+                verified.insert(spender.clone(), is_verified);
+            }
+            Err(e) => println!("Could not check verification of {}:\n{}", spender, e),
+        }
+        println!("Verification check progress is {} / {} spenders", i + 1, spenders.len());
+        tokio::time::sleep(Duration::from_secs_f64(1.0 / API_REQUESTS_PER_SECOND)).await;
+    }
+    verified
+}
+
+#[cfg(feature = "crawl")]
+fn check_spender_verification(approvals: &mut [Approval]) -> Result<()> {
+    let mut spenders: Vec<String> = approvals.iter().filter(|a| a.unlimited).map(|a| a.spender.clone()).collect();
+    spenders.sort();
+    spenders.dedup();
+    let api_key = read_api_key()?;
+    let verified = Runtime::new()?.block_on(spender_verification(&spenders, &api_key));
+    for approval in approvals.iter_mut() {
+        approval.spender_verified = verified.get(&approval.spender).copied();
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "crawl"))]
+fn check_spender_verification(_approvals: &mut [Approval]) -> Result<()> {
+    Err(eyre::eyre!("--check-verification queries Etherscan and needs a build with the crawl feature"))
+}
+
+pub fn approval_risk_summary(approvals: &[Approval]) -> Result<String> {
+    let active: Vec<&Approval> = approvals.iter().filter(|a| a.latest && a.amount != "0").collect();
+    let unlimited: Vec<&Approval> = active.iter().copied().filter(|a| a.unlimited).collect();
+    let owners: HashSet<&str> = approvals.iter().map(|a| a.owner.as_str()).collect();
+    let mut summary = format!(
+        "Approval calls: {} by {} owners\nActive allowances: {}, of them unlimited: {}\n",
+        approvals.len(),
+        owners.len(),
+        active.len(),
+        unlimited.len()
+    );
+    let risky: Vec<&Approval> = unlimited.iter().copied().filter(|a| a.spender_verified == Some(false)).collect();
+    if unlimited.iter().all(|a| a.spender_verified.is_none()) {
+        summary.push_str("Spender verification was not checked, run with --check-verification\n");
+    } else {
+        summary.push_str(&format!("Active unlimited allowances to unverified contracts: {}\n", risky.len()));
+        for approval in risky.iter() {
+            summary.push_str(&format!(
+                "  {} allows {} to spend all {} since {} ({})\n",
+                approval.owner, approval.spender, approval.token, time_label(approval.timestamp)?, approval.hash
+            ));
+        }
+    }
+    Ok(summary)
+}
+
+pub fn run_approvals_command(args: &ApprovalsArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let mut approvals = find_approvals(&graph);
+
+    if args.check_verification {
+        check_spender_verification(&mut approvals)?;
+    }
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for approval in approvals.iter() {
+        writer.serialize(approval)?;
+    }
+    commit_csv(writer)?;
+    println!("Saved {} approval calls as {}\n", approvals.len(), &file_pathname);
+
+    let summary = approval_risk_summary(&approvals)?;
+    print!("{}", &summary);
+    write_atomically(&format!("{}/{}", DATA_STORAGE_FOLDER, args.report), summary.as_bytes())?;
+    Ok(())
+}
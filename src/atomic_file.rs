@@ -0,0 +1,58 @@
+use eyre::Result;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+// A file written under a temporary name and renamed over the target on commit, so readers never see
+// a partially written file. Dropping it without committing removes the temporary file.
+pub struct AtomicFile {
+    temp_pathname: String,
+    final_pathname: String,
+    writer: Option<BufWriter<File>>,
+}
+
+impl AtomicFile {
+    pub fn create(pathname: &str) -> Result<AtomicFile> {
+        let temp_pathname = format!("{}.tmp", pathname);
+        let writer = BufWriter::new(File::create(&temp_pathname)?);
+        Ok(AtomicFile { temp_pathname, final_pathname: pathname.to_string(), writer: Some(writer) })
+    }
+
+    pub fn commit(mut self) -> Result<()> {
+        let file = self.writer.take().unwrap().into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        fs::rename(&self.temp_pathname, &self.final_pathname)?;
+        crate::workspace::record_artifact(&self.final_pathname)
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.as_mut().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.as_mut().unwrap().flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.writer.take().is_some() {
+            let _ = fs::remove_file(&self.temp_pathname);
+        }
+    }
+}
+
+pub fn write_atomically(pathname: &str, contents: &[u8]) -> Result<()> {
+    let mut file = AtomicFile::create(pathname)?;
+    file.write_all(contents)?;
+    file.commit()
+}
+
+pub fn csv_writer(pathname: &str) -> Result<csv::Writer<AtomicFile>> {
+    Ok(csv::Writer::from_writer(AtomicFile::create(pathname)?))
+}
+
+pub fn commit_csv(writer: csv::Writer<AtomicFile>) -> Result<()> {
+    writer.into_inner().map_err(|e| eyre::eyre!("Failed to flush CSV output: {}", e.error()))?.commit()
+}
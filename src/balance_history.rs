@@ -0,0 +1,201 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use plotters::prelude::*;
+#[cfg(feature = "crawl")]
+use {crate::etherscan::Etherscan, reqwest::Client, tokio::runtime::Runtime};
+
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::BalanceArgs;
+use crate::fees::effective_fee_wei;
+use crate::money::{Amount, WEI_PER_COIN};
+use crate::time_buckets::{local_label, parse_timezone};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::workspace;
+use crate::{deserialize_graph, EdgeKind, DATA_STORAGE_FOLDER, G};
+#[cfg(feature = "crawl")]
+use crate::{http_client, read_api_key};
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BalancePoint {
+    pub time: String,
+    pub timestamp: u64,
+    pub hash: String,
+    pub change_coin: f64,
+    pub fee_coin: f64,
+    pub balance_coin: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct BalanceHistory {
+    pub points: Vec<BalancePoint>,
+    pub received_wei: u128,
+    pub sent_wei: u128,
+    pub fees_wei: u128,
+}
+
+impl BalanceHistory {
+    pub fn final_balance_wei(&self) -> i128 {
+        self.received_wei as i128 - self.sent_wei as i128 - self.fees_wei as i128
+    }
+}
+
+fn signed_wei_to_coin(wei: i128) -> f64 {
+    wei as f64 / WEI_PER_COIN
+}
+
+// Native value moves only on call and block reward edges. The fee of every transaction the address sent is charged once,
+// including token transfers, whose calls carry no native value of their own.
+pub fn balance_history(graph: &G, node: NodeIndex, tz: &chrono_tz::Tz) -> Result<BalanceHistory> {
+    let mut edges: Vec<_> = graph
+        .edges_directed(node, Direction::Outgoing)
+        .chain(graph.edges_directed(node, Direction::Incoming).filter(|e| e.source() != node))
+        .filter(|e| matches!(e.weight().kind, EdgeKind::Call | EdgeKind::TokenTransfer | EdgeKind::BlockReward))
+        .collect();
+    edges.sort_by_key(|e| (e.weight().timestamp, e.weight().block_number, e.weight().transaction_index));
+
+    let mut history = BalanceHistory::default();
+    let mut charged_hashes: HashSet<&str> = HashSet::new();
+    let mut balance_wei: i128 = 0;
+    for edge in edges {
+        let transaction = edge.weight();
+        let (mut change_wei, mut fee_wei): (i128, u128) = (0, 0);
+        if matches!(transaction.kind, EdgeKind::Call | EdgeKind::BlockReward) && edge.target() == node && edge.source() != node {
+            change_wei = change_wei.saturating_add_unsigned(transaction.value_wei);
+            history.received_wei = history.received_wei.saturating_add(transaction.value_wei);
+        }
+        if edge.source() == node {
+            if transaction.kind == EdgeKind::Call && edge.target() != node {
+                change_wei = change_wei.saturating_sub_unsigned(transaction.value_wei);
+                history.sent_wei = history.sent_wei.saturating_add(transaction.value_wei);
+            }
+            if charged_hashes.insert(transaction.hash.as_str()) {
+                fee_wei = effective_fee_wei(transaction).unwrap_or(0);
+                history.fees_wei = history.fees_wei.saturating_add(fee_wei);
+            }
+        }
+        if change_wei == 0 && fee_wei == 0 {
+            continue;
+        }
+        balance_wei = balance_wei.saturating_add(change_wei).saturating_sub_unsigned(fee_wei);
+        history.points.push(BalancePoint {
+            time: local_label(transaction.timestamp, tz)?,
+            timestamp: transaction.timestamp,
+            hash: transaction.hash.clone(),
+            change_coin: signed_wei_to_coin(change_wei),
+            fee_coin: signed_wei_to_coin(fee_wei as i128),
+            balance_coin: signed_wei_to_coin(balance_wei),
+        });
+    }
+    Ok(history)
+}
+
+// The balance the parsed edges explain against the actual one. Flows missing from the graph amount to at
+// least the unexplained difference, so coverage is the observed flow over observed plus unexplained flow.
+pub fn coverage_summary(history: &BalanceHistory, actual_balance_wei: u128) -> String {
+    let unexplained = actual_balance_wei as i128 - history.final_balance_wei();
+    let observed = history.received_wei.saturating_add(history.sent_wei).saturating_add(history.fees_wei) as f64;
+    let coverage = if observed + unexplained.unsigned_abs() as f64 == 0.0 {
+        100.0
+    } else {
+        100.0 * observed / (observed + unexplained.unsigned_abs() as f64)
+    };
+    format!(
+        "Actual balance: {}, unexplained by parsed edges: {}\nEstimated coverage of native flows: {:.1}%\n",
+        Amount::Coin(signed_wei_to_coin(actual_balance_wei as i128)),
+        Amount::Coin(signed_wei_to_coin(unexplained)),
+        coverage
+    )
+}
+
+#[cfg(feature = "crawl")]
+async fn get_balance(address: &str, client: &Client, api_key: &str) -> Result<u128> {
+    let (response, _) = Etherscan::new(client, api_key).balancemulti(&[address.to_string()]).await?;
+    let balance = response
+        .result
+        .into_iter()
+        .find(|balance| balance.account.eq_ignore_ascii_case(address))
+        .ok_or_else(|| eyre::eyre!("Balance request for {} failed: {}", address, response.message))?;
+    Ok(balance.balance.parse()?)
+}
+
+#[cfg(feature = "crawl")]
+fn onchain_balance(address: &str) -> Result<u128> {
+    let api_key = read_api_key()?;
+    Runtime::new()?.block_on(get_balance(address, &http_client::client(), &api_key))
+}
+
+#[cfg(not(feature = "crawl"))]
+fn onchain_balance(_address: &str) -> Result<u128> {
+    Err(eyre::eyre!("ethparser was built without the crawl feature, pass --current-balance instead of --onchain"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn plot_balance(points: &[BalancePoint], plot_pathname: &str, description: &str) -> Result<()> {
+    let root = BitMapBackend::new(plot_pathname, (720, 480)).into_drawing_area();
+    root.fill(&WHITE)?;
+    // Days since the first balance change, computed in f64 since integer timestamps overflow the axis key points.
+    let first = points.first().map_or(0, |p| p.timestamp);
+    let days = |timestamp: u64| (timestamp - first) as f64 / 86400.0;
+    let last = points.last().map_or(0.0, |p| days(p.timestamp)).max(1.0);
+    let lowest = points.iter().fold(0.0f64, |acc, p| acc.min(p.balance_coin));
+    let highest = points.iter().fold(0.0f64, |acc, p| acc.max(p.balance_coin)).max(lowest + 1E-9);
+    let mut chart = ChartBuilder::on(&root)
+        .margin(5)
+        .caption(description, ("sans-serif", 20))
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0.0..last, lowest..highest)?;
+    chart.configure_mesh().x_desc("Days since the first balance change").y_desc("Balance, ETH").draw()?;
+    chart.draw_series(LineSeries::new(points.iter().map(|p| (days(p.timestamp), p.balance_coin)), &BLUE))?;
+    root.present()?;
+    workspace::record_artifact(plot_pathname)
+}
+
+pub fn run_balance_command(args: &BalanceArgs) -> Result<()> {
+    let tz = parse_timezone(&args.timezone)?;
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let address = args.address.to_lowercase();
+    let node = graph.node_indices().find(|&n| graph[n] == address).ok_or_else(|| eyre::eyre!("Address {} is not present in the graph", address))?;
+    let history = balance_history(&graph, node, &tz)?;
+
+    println!(
+        "Reconstructed balance of {}: {} from {} received, {} sent and {} in fees over {} balance changes",
+        address,
+        Amount::Coin(signed_wei_to_coin(history.final_balance_wei())),
+        Amount::Coin(signed_wei_to_coin(history.received_wei as i128)),
+        Amount::Coin(signed_wei_to_coin(history.sent_wei as i128)),
+        Amount::Coin(signed_wei_to_coin(history.fees_wei as i128)),
+        history.points.len()
+    );
+    let actual_balance_wei = match (args.onchain, args.current_balance) {
+        (true, _) => Some(onchain_balance(&address)?),
+        (false, Some(coin)) => Some((coin * WEI_PER_COIN) as u128),
+        (false, None) => None,
+    };
+    if let Some(actual_balance_wei) = actual_balance_wei {
+        print!("{}", coverage_summary(&history, actual_balance_wei));
+    }
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let output = args.output.clone().unwrap_or(format!("balance_{}", address));
+    let file_pathname = format!("{}/{}.csv", DATA_STORAGE_FOLDER, output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for point in history.points.iter() {
+        writer.serialize(point)?;
+    }
+    commit_csv(writer)?;
+    println!("Saved balance series as {}", &file_pathname);
+    #[cfg(not(target_arch = "wasm32"))]
+    if !history.points.is_empty() {
+        let plot_pathname = format!("{}/{}.png", DATA_STORAGE_FOLDER, output);
+        plot_balance(&history.points, &plot_pathname, &format!("Reconstructed balance of {}", address))?;
+        println!("Saved plot as {}", &plot_pathname);
+    }
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+use crate::cli::BlockRewardsArgs;
+use crate::etherscan::{ApiResponse, Etherscan};
+use crate::fees::effective_fee_wei;
+use crate::http_client;
+use crate::{deserialize_graph, read_api_key, serialize_graph, EdgeKind, FilteringResultInfo, Transaction, API_REQUESTS_PER_SECOND, G};
+
+// Source node of every block reward edge. Rewards are minted or paid by the protocol, not by an address.
+pub const BLOCK_REWARD_NODE: &str = "BLOCK_REWARD";
+
+// Blocks per getminedblocks page. Etherscan serves at most 10000 records per query window.
+const MINED_BLOCKS_PAGE_SIZE: usize = 1000;
+const MAX_MINED_BLOCKS: usize = 10000;
+
+#[allow(non_snake_case)]
+#[derive(Deserialize, Debug, Clone)]
+pub struct MinedBlock {
+    pub blockNumber: String,
+    pub timeStamp: String,
+    // Static reward plus the fees of the block before the merge, the priority fees paid to the fee
+    // recipient after it.
+    pub blockReward: String,
+}
+
+// Native value in and out of an address as seen by the graph, counting the fees of the transactions it sent.
+fn native_flows(graph: &G, node: NodeIndex) -> (u128, u128) {
+    let received = graph.edges_directed(node, Direction::Incoming).filter(|e| e.source() != node).filter(|e| e.weight().kind == EdgeKind::Call).map(|e| e.weight().value_wei).fold(0, u128::saturating_add);
+    let mut charged_hashes: HashSet<&str> = HashSet::new();
+    let sent = graph
+        .edges_directed(node, Direction::Outgoing)
+        .map(|e| {
+            let transaction = e.weight();
+            let value = if transaction.kind == EdgeKind::Call && e.target() != node { transaction.value_wei } else { 0 };
+            let fee = if charged_hashes.insert(transaction.hash.as_str()) { effective_fee_wei(transaction).unwrap_or(0) } else { 0 };
+            value.saturating_add(fee)
+        })
+        .fold(0, u128::saturating_add);
+    (received, sent)
+}
+
+// Addresses that send more native value than the graph shows them receiving, largest deficit first. Miners,
+// validators and block builders look like this, since their income never appears as a transaction.
+pub fn reward_candidates(graph: &G, limit: usize) -> Vec<String> {
+    let mut deficits: Vec<(u128, String)> = graph
+        .node_indices()
+        .filter(|&n| graph[n] != BLOCK_REWARD_NODE)
+        .filter(|&n| !graph.edges_directed(n, Direction::Incoming).any(|e| e.weight().kind == EdgeKind::BlockReward))
+        .filter_map(|n| {
+            let (received, sent) = native_flows(graph, n);
+            (sent > received).then(|| (sent - received, graph[n].clone()))
+        })
+        .collect();
+    deficits.sort_by(|x, y| y.0.cmp(&x.0).then(x.1.cmp(&y.1)));
+    deficits.into_iter().take(limit).map(|(_, address)| address).collect()
+}
+
+async fn get_mined_blocks_page(address: &str, page: usize, client: &Client, api_key: &str) -> Result<Vec<MinedBlock>> {
+    let parameters = [
+        ("address", address.to_string()),
+        ("blocktype", "blocks".to_string()),
+        ("page", page.to_string()),
+        ("offset", MINED_BLOCKS_PAGE_SIZE.to_string()),
+    ];
+    let (response, _): (ApiResponse<serde_json::Value>, usize) = Etherscan::new(client, api_key).call("account", "getminedblocks", &parameters).await?;
+    match (response.status.as_str(), response.result) {
+        ("1", result) => Ok(serde_json::from_value(result)?),
+        // Addresses that never produced a block get status 0 with an empty result.
+        (_, serde_json::Value::Array(result)) if result.is_empty() => Ok(vec![]),
+        (_, result) => Err(eyre::eyre!("Mined blocks request for {} failed: {} {}", address, response.message, result)),
+    }
+}
+
+pub async fn get_mined_blocks(address: &str, client: &Client, api_key: &str) -> Result<Vec<MinedBlock>> {
+    let mut blocks = vec![];
+    for page in 1..=MAX_MINED_BLOCKS / MINED_BLOCKS_PAGE_SIZE {
+        let page_blocks = get_mined_blocks_page(address, page, client, api_key).await?;
+        tokio::time::sleep(Duration::from_secs_f64(1.0 / API_REQUESTS_PER_SECOND)).await;
+        let last_page = page_blocks.len() < MINED_BLOCKS_PAGE_SIZE;
+        blocks.extend(page_blocks);
+        if last_page {
+            break;
+        }
+    }
+    Ok(blocks)
+}
+
+// Adds a BlockReward edge from the block reward node to the fee recipient for every block it produced,
+// skipping blocks that already have one. Returns the number of edges added.
+pub fn add_block_reward_edges(graph: &mut G, rewards: &HashMap<String, Vec<MinedBlock>>) -> Result<usize> {
+    let node_indices: HashMap<String, NodeIndex> = graph.node_indices().map(|n| (graph[n].clone(), n)).collect();
+    let existing: HashSet<(NodeIndex, u64)> =
+        graph.raw_edges().iter().filter(|e| e.weight.kind == EdgeKind::BlockReward).map(|e| (e.target(), e.weight.block_number)).collect();
+    let mut recipients: Vec<(&String, &Vec<MinedBlock>)> = rewards.iter().filter(|(address, _)| node_indices.contains_key(*address)).collect();
+    recipients.sort_by_key(|(address, _)| *address);
+
+    let mut origin = node_indices.get(BLOCK_REWARD_NODE).copied();
+    let mut added = 0;
+    for (address, blocks) in recipients {
+        let target = node_indices[address];
+        for block in blocks {
+            let block_number: u64 = block.blockNumber.parse()?;
+            if existing.contains(&(target, block_number)) {
+                continue;
+            }
+            let source = *origin.get_or_insert_with(|| graph.add_node(BLOCK_REWARD_NODE.to_string()));
+            let edge = Transaction {
+                hash: format!("block:{}", block_number),
+                timestamp: block.timeStamp.parse()?,
+                block_number,
+                value_wei: block.blockReward.parse()?,
+                kind: EdgeKind::BlockReward,
+                ..Default::default()
+            };
+            graph.add_edge(source, target, edge);
+            added += 1;
+        }
+    }
+    Ok(added)
+}
+
+pub fn run_block_rewards_command(args: &BlockRewardsArgs) -> Result<()> {
+    let (mut graph, _) = deserialize_graph(&args.input)?;
+    let mut addresses: Vec<String> = args.address.iter().map(|address| address.to_lowercase()).collect();
+    addresses.extend(reward_candidates(&graph, args.max_addresses));
+    addresses.sort();
+    addresses.dedup();
+
+    let api_key = read_api_key()?;
+    let client = http_client::client();
+    let runtime = Runtime::new()?;
+    let mut rewards: HashMap<String, Vec<MinedBlock>> = HashMap::new();
+    for (i, address) in addresses.iter().enumerate() {
+        match runtime.block_on(get_mined_blocks(address, &client, &api_key)) {
+            Ok(blocks) if !blocks.is_empty() => {
+                println!("{} produced {} blocks", address, blocks.len());
+                rewards.insert(address.clone(), blocks);
+            }
+            Ok(_) => {}
+            Err(e) => println!("Could not fetch the mined blocks of {}:\n{}", address, e),
+        }
+        println!("Block reward lookup progress is {} / {} addresses", i + 1, addresses.len());
+    }
+
+    let (node_count_before, edge_count_before) = (graph.node_count(), graph.edge_count());
+    let added = add_block_reward_edges(&mut graph, &rewards)?;
+    println!("Added {} block reward edges to {} fee recipients", added, rewards.len());
+    let info = FilteringResultInfo {
+        node_count_before_filtering: node_count_before,
+        edge_count_before_filtering: edge_count_before,
+        node_count: graph.node_count(),
+        edge_count: graph.edge_count(),
+    };
+    serialize_graph(&graph, &info, &args.output)
+}
@@ -0,0 +1,157 @@
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+
+use crate::money::Amount;
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::BridgeFlowsArgs;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+struct BridgeTransfer {
+    address: String,
+    hash: String,
+    usd_value: f64,
+    timestamp: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CrossChainFlow {
+    pub source_sender: String,
+    pub source_hash: String,
+    pub source_usd_value: f64,
+    pub source_timestamp: u64,
+    pub destination_recipient: String,
+    pub destination_hash: String,
+    pub destination_usd_value: f64,
+    pub destination_timestamp: u64,
+    pub delay_seconds: u64,
+    pub amount_difference_fraction: f64,
+    pub confidence: f64,
+}
+
+fn lowercase_set(addresses: &[String]) -> HashSet<String> {
+    addresses.iter().map(|a| a.to_lowercase()).collect()
+}
+
+// Deposits into a bridge on the source chain: the sender is the party whose funds leave.
+fn bridge_outflows(graph: &G, bridges: &HashSet<String>) -> Vec<BridgeTransfer> {
+    graph
+        .edge_references()
+        .filter(|e| bridges.contains(&graph[e.target()].to_lowercase()))
+        .filter_map(|e| {
+            let data = e.weight().data.as_ref()?;
+            Some(BridgeTransfer {
+                address: graph[e.source()].clone(),
+                hash: e.weight().hash.clone(),
+                usd_value: data.usd_value,
+                timestamp: e.weight().timestamp,
+            })
+        })
+        .collect()
+}
+
+// Releases from a bridge on the destination chain: the recipient is the party receiving funds.
+fn bridge_inflows(graph: &G, bridges: &HashSet<String>) -> Vec<BridgeTransfer> {
+    graph
+        .edge_references()
+        .filter(|e| bridges.contains(&graph[e.source()].to_lowercase()))
+        .filter_map(|e| {
+            let data = e.weight().data.as_ref()?;
+            Some(BridgeTransfer {
+                address: graph[e.target()].clone(),
+                hash: e.weight().hash.clone(),
+                usd_value: data.usd_value,
+                timestamp: e.weight().timestamp,
+            })
+        })
+        .collect()
+}
+
+pub fn match_bridge_flows(
+    source_graph: &G,
+    destination_graph: &G,
+    source_bridges: &HashSet<String>,
+    destination_bridges: &HashSet<String>,
+    amount_tolerance: f64,
+    max_delay_seconds: u64,
+) -> Vec<CrossChainFlow> {
+    let outflows = bridge_outflows(source_graph, source_bridges);
+    let inflows = bridge_inflows(destination_graph, destination_bridges);
+
+    let mut hypotheses = vec![];
+    for (out_index, outflow) in outflows.iter().enumerate() {
+        for (in_index, inflow) in inflows.iter().enumerate() {
+            if inflow.timestamp < outflow.timestamp || inflow.timestamp - outflow.timestamp > max_delay_seconds {
+                continue;
+            }
+            let amount_difference_fraction = (outflow.usd_value - inflow.usd_value).abs() / outflow.usd_value.max(f64::MIN_POSITIVE);
+            if amount_difference_fraction > amount_tolerance {
+                continue;
+            }
+            let delay_seconds = inflow.timestamp - outflow.timestamp;
+            // Closer amounts and shorter delays make a pairing more plausible, equally weighted.
+            let amount_score = if amount_tolerance > 0.0 { 1.0 - amount_difference_fraction / amount_tolerance } else { 1.0 };
+            let delay_score = if max_delay_seconds > 0 { 1.0 - delay_seconds as f64 / max_delay_seconds as f64 } else { 1.0 };
+            hypotheses.push((out_index, in_index, (amount_score + delay_score) / 2.0, delay_seconds, amount_difference_fraction));
+        }
+    }
+
+    // Greedy one-to-one assignment, best scoring hypotheses first.
+    hypotheses.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap());
+    let mut used_outflows = HashSet::new();
+    let mut used_inflows = HashSet::new();
+    let mut flows = vec![];
+    for (out_index, in_index, confidence, delay_seconds, amount_difference_fraction) in hypotheses {
+        if used_outflows.contains(&out_index) || used_inflows.contains(&in_index) {
+            continue;
+        }
+        used_outflows.insert(out_index);
+        used_inflows.insert(in_index);
+        let (outflow, inflow) = (&outflows[out_index], &inflows[in_index]);
+        flows.push(CrossChainFlow {
+            source_sender: outflow.address.clone(),
+            source_hash: outflow.hash.clone(),
+            source_usd_value: outflow.usd_value,
+            source_timestamp: outflow.timestamp,
+            destination_recipient: inflow.address.clone(),
+            destination_hash: inflow.hash.clone(),
+            destination_usd_value: inflow.usd_value,
+            destination_timestamp: inflow.timestamp,
+            delay_seconds,
+            amount_difference_fraction,
+            confidence,
+        });
+    }
+    flows
+}
+
+pub fn run_bridge_flows_command(args: &BridgeFlowsArgs) -> Result<()> {
+    let (source_graph, _) = deserialize_graph(&args.source_graph)?;
+    let (destination_graph, _) = deserialize_graph(&args.destination_graph)?;
+    let flows = match_bridge_flows(
+        &source_graph,
+        &destination_graph,
+        &lowercase_set(&args.source_bridges),
+        &lowercase_set(&args.destination_bridges),
+        args.amount_tolerance,
+        args.max_delay_hours * 3_600,
+    );
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for flow in flows.iter() {
+        writer.serialize(flow)?;
+    }
+    commit_csv(writer)?;
+    let matched_volume = flows.iter().fold(0.0, |acc, f| acc + f.source_usd_value);
+    println!(
+        "Matched {} cross-chain flow hypotheses totaling {}, saved as {}",
+        flows.len(),
+        Amount::Usd(matched_volume),
+        &file_pathname
+    );
+    Ok(())
+}
@@ -0,0 +1,230 @@
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+
+use crate::address_report::{address_report, report_text, AddressReport};
+use crate::analysis_pass::{NoPrices, PassRegistry};
+use crate::annotations::Annotations;
+use crate::atomic_file::AtomicFile;
+use crate::cli::ExportCaseArgs;
+use crate::compare::neighborhood;
+use crate::graph_query::load_labels;
+use crate::money::Amount;
+use crate::pipeline::{escape_xml, graphml};
+use crate::pricing::{PriceProvider, PriceSeries};
+use crate::task_control::TaskControl;
+use crate::time_buckets::{local_label, parse_timezone};
+use crate::zip_archive::ZipArchive;
+use crate::{deserialize_graph, serialize_graph, FilteringResultInfo, DATA_STORAGE_FOLDER, G};
+
+#[derive(Serialize)]
+struct TransactionRow<'a> {
+    time: String,
+    timestamp: u64,
+    block_number: u64,
+    from: &'a str,
+    to: &'a str,
+    hash: &'a str,
+    kind: String,
+    payload: Option<String>,
+    usd_value: Option<f64>,
+    value_wei: u128,
+}
+
+#[derive(Serialize)]
+struct AnalysisEntry {
+    pass: String,
+    metrics: BTreeMap<String, f64>,
+    error: Option<String>,
+}
+
+// What the case was built from, with a SHA-256 per bundled file so the recipient can check nothing changed.
+#[derive(Serialize)]
+struct CaseManifest {
+    ethparser_version: &'static str,
+    created_at: String,
+    input: String,
+    addresses: Vec<String>,
+    hops: usize,
+    node_count: usize,
+    edge_count: usize,
+    prices: Option<String>,
+    labels: Option<String>,
+    analyses: Vec<AnalysisEntry>,
+    files: BTreeMap<String, String>,
+}
+
+// The case addresses and everything within the hop limit of any of them, with the edges between those.
+pub fn ego_subgraph(graph: &G, addresses: &[String], hops: usize) -> Result<G> {
+    let mut kept = HashSet::new();
+    for address in addresses {
+        let seed = graph.node_indices().find(|&n| graph[n] == *address).ok_or_else(|| eyre::eyre!("Address {} is not present in the graph", address))?;
+        kept.extend(neighborhood(graph, seed, hops));
+    }
+    Ok(graph.filter_map(|n, address| kept.contains(&n).then(|| address.clone()), |_, transaction| Some(transaction.clone())))
+}
+
+fn transactions_csv(graph: &G, tz: &chrono_tz::Tz) -> Result<Vec<u8>> {
+    let mut edges: Vec<_> = graph.edge_references().collect();
+    edges.sort_by_key(|e| (e.weight().timestamp, e.weight().block_number, e.weight().transaction_index, e.weight().log_index, e.weight().trace_index));
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for edge in edges {
+        let transaction = edge.weight();
+        writer.serialize(TransactionRow {
+            time: local_label(transaction.timestamp, tz)?,
+            timestamp: transaction.timestamp,
+            block_number: transaction.block_number,
+            from: &graph[edge.source()],
+            to: &graph[edge.target()],
+            hash: &transaction.hash,
+            kind: format!("{:?}", transaction.kind),
+            payload: transaction.data.as_ref().map(|d| format!("{:?}", d.payload)),
+            usd_value: transaction.usd_value(),
+            value_wei: transaction.value_wei,
+        })?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+fn index_html(manifest: &CaseManifest, reports: &[AddressReport], annotations: &Annotations) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Case file</title></head>\n<body>\n<h1>Case file</h1>\n");
+    writeln!(
+        html,
+        "<p>Built with ethparser {} at {} from {}: {} addresses within {} hops of the case addresses, {} transactions.</p>",
+        manifest.ethparser_version,
+        escape_xml(&manifest.created_at),
+        escape_xml(&manifest.input),
+        manifest.node_count,
+        manifest.hops,
+        manifest.edge_count
+    )
+    .unwrap();
+    html.push_str("<h2>Case addresses</h2>\n<table border=\"1\">\n<tr><th>Address</th><th>Label</th><th>Tags</th><th>Notes</th><th>Received</th><th>Sent</th><th>Net</th><th>Transfers</th><th>Report</th></tr>\n");
+    for report in reports {
+        let annotation = annotations.get(&report.address);
+        let summary = &report.summary;
+        writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td><a href=\"reports/{}.txt\">text</a> <a href=\"reports/{}.csv\">csv</a></td></tr>",
+            report.address,
+            escape_xml(report.label.as_deref().unwrap_or("")),
+            escape_xml(&annotation.map(|a| a.tag_list()).unwrap_or_default()),
+            escape_xml(&annotation.map(|a| a.note_list()).unwrap_or_default()),
+            Amount::Usd(summary.received_usd),
+            Amount::Usd(summary.sent_usd),
+            Amount::Usd(summary.net_usd),
+            summary.incoming_transfers + summary.outgoing_transfers + summary.self_transfers,
+            report.address,
+            report.address
+        )
+        .unwrap();
+    }
+    html.push_str("</table>\n<h2>Analyses of the subgraph</h2>\n<ul>\n");
+    for analysis in manifest.analyses.iter() {
+        match &analysis.error {
+            Some(error) => writeln!(html, "<li>{}: failed, {}</li>", escape_xml(&analysis.pass), escape_xml(error)).unwrap(),
+            None => writeln!(html, "<li><a href=\"analysis/{}.txt\">{}</a></li>", escape_xml(&analysis.pass), escape_xml(&analysis.pass)).unwrap(),
+        }
+    }
+    html.push_str("</ul>\n<h2>Files</h2>\n<table border=\"1\">\n<tr><th>File</th><th>SHA-256</th></tr>\n");
+    for (name, digest) in manifest.files.iter() {
+        writeln!(html, "<tr><td><a href=\"{}\">{}</a></td><td><code>{}</code></td></tr>", escape_xml(name), escape_xml(name), digest).unwrap();
+    }
+    html.push_str("<tr><td><a href=\"manifest.json\">manifest.json</a></td><td>this list</td></tr>\n</table>\n</body>\n</html>\n");
+    html
+}
+
+fn sha256_hex(contents: &[u8]) -> String {
+    Sha256::digest(contents).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Bundles the ego subgraph of the case addresses with a report per address, every transaction of the
+// subgraph, the analysis passes run on it, the price data used and a manifest into one zip with an index.html.
+pub fn run_export_case_command(args: &ExportCaseArgs, passes: &PassRegistry) -> Result<()> {
+    let tz = parse_timezone(&args.timezone)?;
+    let labels = args.labels.as_ref().map(|name| load_labels(name)).transpose()?.unwrap_or_default();
+    let annotations = Annotations::load()?;
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let mut addresses: Vec<String> = args.addresses.iter().map(|address| address.to_lowercase()).collect();
+    addresses.sort();
+    addresses.dedup();
+    let subgraph = ego_subgraph(&graph, &addresses, args.hops)?;
+    println!("Case subgraph has {} nodes and {} edges", subgraph.node_count(), subgraph.edge_count());
+
+    // The subgraph is also kept in the workspace, where later commands can load it.
+    let subgraph_name = format!("{}_subgraph.json", args.output);
+    let info = FilteringResultInfo {
+        node_count_before_filtering: graph.node_count(),
+        edge_count_before_filtering: graph.edge_count(),
+        node_count: subgraph.node_count(),
+        edge_count: subgraph.edge_count(),
+    };
+    serialize_graph(&subgraph, &info, &subgraph_name)?;
+
+    let mut files: Vec<(String, Vec<u8>)> = vec![
+        ("graph.json".to_string(), fs::read(format!("{}/{}", DATA_STORAGE_FOLDER, subgraph_name))?),
+        ("graph.graphml".to_string(), graphml(&subgraph, &annotations).into_bytes()),
+        ("transactions.csv".to_string(), transactions_csv(&subgraph, &tz)?),
+    ];
+    let mut reports = vec![];
+    for address in addresses.iter() {
+        let report = address_report(&subgraph, address, &labels, &annotations, &tz)?;
+        files.push((format!("reports/{}.txt", address), report_text(&report, &labels, &annotations, &tz)?.into_bytes()));
+        let mut writer = csv::Writer::from_writer(vec![]);
+        for row in report.transactions.iter() {
+            writer.serialize(row)?;
+        }
+        files.push((format!("reports/{}.csv", address), writer.into_inner()?));
+        files.push((format!("reports/{}.json", address), serde_json::to_vec_pretty(&report)?));
+        reports.push(report);
+    }
+
+    let price_series = args.prices.as_ref().map(|name| PriceSeries::from_csv(&format!("{}/{}", DATA_STORAGE_FOLDER, name))).transpose()?;
+    let prices: &dyn PriceProvider = match &price_series {
+        Some(series) => series,
+        None => &NoPrices,
+    };
+    if let Some(name) = &args.prices {
+        files.push((format!("prices/{}", name), fs::read(format!("{}/{}", DATA_STORAGE_FOLDER, name))?));
+    }
+    let mut analyses = vec![];
+    for (pass, report) in passes.run_with_control(&subgraph, prices, &[], None, &TaskControl::default())? {
+        match report {
+            Ok(report) => {
+                files.push((format!("analysis/{}.txt", pass), report.text.into_bytes()));
+                analyses.push(AnalysisEntry { pass, metrics: report.metrics, error: None });
+            }
+            Err(e) => analyses.push(AnalysisEntry { pass, metrics: BTreeMap::new(), error: Some(e.to_string()) }),
+        }
+    }
+
+    let manifest = CaseManifest {
+        ethparser_version: env!("CARGO_PKG_VERSION"),
+        created_at: local_label(chrono::Utc::now().timestamp() as u64, &tz)?,
+        input: args.input.clone(),
+        addresses,
+        hops: args.hops,
+        node_count: subgraph.node_count(),
+        edge_count: subgraph.edge_count(),
+        prices: args.prices.clone(),
+        labels: args.labels.clone(),
+        analyses,
+        files: files.iter().map(|(name, contents)| (name.clone(), sha256_hex(contents))).collect(),
+    };
+    files.push(("manifest.json".to_string(), serde_json::to_vec_pretty(&manifest)?));
+    files.push(("index.html".to_string(), index_html(&manifest, &reports, &annotations).into_bytes()));
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}.zip", DATA_STORAGE_FOLDER, args.output);
+    let mut archive = ZipArchive::new(AtomicFile::create(&file_pathname)?);
+    for (name, contents) in files.iter() {
+        archive.add(&format!("{}/{}", args.output, name), contents)?;
+    }
+    archive.finish()?.commit()?;
+    println!("Saved case file with {} files as {}", files.len(), &file_pathname);
+    Ok(())
+}
@@ -0,0 +1,1292 @@
+use chrono_tz::Tz;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::edge_payload::EdgeSchema;
+use crate::history_window::SortDirection;
+use crate::profiles::Profile;
+use crate::special_addresses::SpecialAddressPolicy;
+use crate::time_buckets::{parse_timezone, CalendarPeriod};
+use crate::tx_count_precheck::OversizedAction;
+
+#[derive(Parser)]
+#[command(
+    name = "ethparser",
+    about = "Blockchain transaction graph parser and analyzer",
+    after_help = "Exit codes: 0 success, 1 error, 2 usage error, 3 invalid or missing API key, 4 daily API quota exhausted, \
+                  5 network failure, 6 partial completion. On failure json/errors.json lists what failed and the outputs written."
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Decimal places for USD amounts in reports and logs
+    #[arg(long, global = true, default_value_t = 0)]
+    pub usd_precision: usize,
+    /// Decimal places for native coin amounts
+    #[arg(long, global = true, default_value_t = 6)]
+    pub coin_precision: usize,
+    /// Decimal places for gas prices in gwei
+    #[arg(long, global = true, default_value_t = 3)]
+    pub gwei_precision: usize,
+    /// Group thousands in amounts with this character, e.g. ','
+    #[arg(long, global = true)]
+    pub thousands_separator: Option<char>,
+    /// IANA timezone of the times in logs and reports, which commands with a --timezone of their own leave
+    /// to it; CSV and JSON outputs keep unix seconds
+    #[arg(long, global = true, default_value = "UTC", value_parser = parse_timezone)]
+    pub display_timezone: Tz,
+    /// Built-in limits, strategies and report sets for a common scenario; options given on the command line
+    /// override the profile's
+    #[arg(long, global = true, value_enum)]
+    pub profile: Option<Profile>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Crawl the chain from the traversal starting address and run the default analysis (default)
+    Crawl(CrawlArgs),
+    /// Produce a representative subgraph of a saved graph
+    Sample(SampleArgs),
+    /// Export a saved graph with addresses, also those in decoded calls, replaced by pseudonyms, and transaction
+    /// hashes, raw API fields, block positions and nonces stripped
+    Pseudonymize(PseudonymizeArgs),
+    /// Learn node2vec-style address embeddings from random walks over a saved graph
+    Embeddings(EmbeddingsArgs),
+    /// Extract a per-address feature table for model training
+    Features(FeaturesArgs),
+    /// Report gas price percentiles over time and fee spike periods
+    Fees(FeesArgs),
+    /// Flag same-block sandwich and backrun candidates
+    Mev(MevArgs),
+    /// Match bridge deposits on one chain graph with bridge releases on another
+    BridgeFlows(BridgeFlowsArgs),
+    /// Project token transfers into a bipartite holder-token graph with overlap metrics
+    TokenHolders(TokenHoldersArgs),
+    /// Project transactions into a caller -> contract interaction graph
+    ContractInteractions(ContractInteractionsArgs),
+    /// Net flow into an address set over a rolling window, as a time series
+    RollingFlow(RollingFlowArgs),
+    /// Report addresses that reactivated after a dormant period, with the reactivating transaction
+    Dormancy(DormancyArgs),
+    /// Flag sender-recipient pairs with repeated identical or round amounts in short windows
+    Structuring(StructuringArgs),
+    /// Export the aggregated weighted adjacency matrix in Matrix Market format
+    ExportMatrix(ExportMatrixArgs),
+    /// Top adjacency eigenvalues and algebraic connectivity of the aggregated undirected graph
+    #[cfg(feature = "spectral")]
+    Spectral(SpectralArgs),
+    /// Summary statistics of a saved graph, including degree assortativity and rich-club coefficients
+    Stats(StatsArgs),
+    /// Count chain, ping-pong, triangle and fan-in/fan-out burst motifs within a time window
+    TemporalMotifs(TemporalMotifsArgs),
+    /// Combine saved graphs and drop edges that describe the same transfer twice
+    Dedup(DedupArgs),
+    /// Round-trip ratio and opposing transfer lag for every two-way pair
+    Reciprocity(ReciprocityArgs),
+    /// Lagged cross-correlation of hourly parsed volume with a price series
+    PriceCorrelation(PriceCorrelationArgs),
+    /// Stamp each edge with its USD value and fee at transfer time, so later analyses need no price CSV
+    EnrichUsd(EnrichUsdArgs),
+    /// Export hour-of-day by day-of-week activity per sender and for the whole graph as CSV and PNG
+    ActivityHeatmap(ActivityHeatmapArgs),
+    /// Report decoded approve() and increaseAllowance() calls, flagging unlimited allowances to unverified contracts
+    Approvals(ApprovalsArgs),
+    /// Classify graph addresses as contracts or externally owned accounts with batched eth_getCode calls
+    #[cfg(feature = "crawl")]
+    DetectContracts(DetectContractsArgs),
+    /// Keep the edges matching a Rhai filter expression over from, to, value_usd, timestamp and function
+    #[cfg(feature = "scripting")]
+    FilterScript(FilterScriptArgs),
+    /// List the graphs in the workspace catalog with their derived artifacts and any untracked files
+    List,
+    /// Show how a cataloged graph or artifact was produced and what was derived from it
+    Describe(DescribeArgs),
+    /// Drop catalog entries of deleted files and delete artifacts whose source graphs are gone
+    Gc(GcArgs),
+    /// Run the registered analysis passes over a saved graph and record their reports in a manifest
+    Analyze(AnalyzeArgs),
+    /// Serve crawl control, progress streaming and graph queries over gRPC
+    #[cfg(feature = "grpc")]
+    ServeGrpc(ServeGrpcArgs),
+    /// Run a MATCH ... WHERE ... RETURN query over a saved graph and print a table or save CSV
+    Query(QueryArgs),
+    /// Compare saved graphs or seed neighborhoods side by side: volume, shared addresses and Jaccard similarity
+    Compare(CompareArgs),
+    /// Score pairs of addresses by the counterparties they share, to surface likely same-owner pairs
+    Similarity(SimilarityArgs),
+    /// Group addresses into probable entities from shared counterparties, deposit addresses, funders, gas payers and co-activity
+    Entities(EntitiesArgs),
+    /// Add funded-by edges from the first funders recorded by the last crawl to a saved graph
+    FundedBy(FundedByArgs),
+    /// Detailed transaction breakdown of one address with USD values, counterparty labels and running balance, as text, CSV and JSON
+    Report(ReportArgs),
+    /// Reconstruct an address's native balance over time and estimate the coverage of its history
+    Balance(BalanceArgs),
+    /// Show which graph addresses the crawl expanded, fetched partially or never queried
+    Coverage(CoverageArgs),
+    /// Add block reward edges into the fee recipients among graph addresses that send more native value than they receive
+    #[cfg(feature = "crawl")]
+    BlockRewards(BlockRewardsArgs),
+    /// Add, remove or show the tags and notes of an address kept in the workspace and shown in reports and exports
+    Annotate(AnnotateArgs),
+    /// List the annotated addresses of the workspace
+    Annotations(AnnotationsArgs),
+    /// Bundle the ego subgraph of a set of addresses with per-address reports, transactions, analyses, prices and a manifest into one zip with an index.html
+    ExportCase(ExportCaseArgs),
+    /// Generate a small deterministic graph from transfer patterns, with a matching price CSV
+    GenFixture(GenFixtureArgs),
+    /// Colour a crawled graph by which of its seeds reached each address, as CSV, DOT, HTML and GEXF
+    SeedMap(SeedMapArgs),
+    /// Compare a graph with an earlier snapshot of it: new addresses, volume, new alerts and top new counterparties
+    PeriodSummary(PeriodSummaryArgs),
+    /// Crawl since the last run at an interval and send a summary of every finished week or month to notifiers
+    #[cfg(feature = "crawl")]
+    Daemon(DaemonArgs),
+    /// Rewrite a saved graph with renumbered nodes, merged duplicate addresses and optionally no isolated addresses
+    Compact(CompactArgs),
+    /// Total every address pair's transfers per asset layer, native coin, each stablecoin and decoded tokens, and in a combined USD layer
+    Layers(LayersArgs),
+    /// Replay a recorded crawl under other traversal strategies and budgets and compare what each would have collected
+    BenchmarkStrategies(BenchmarkStrategiesArgs),
+    /// Score every address by random-walk exposure to flagged addresses such as sanctioned wallets and mixers
+    Exposure(ExposureArgs),
+    /// Report deposits to and withdrawals from mixer contracts per address
+    Mixers(MixersArgs),
+    /// Combine label hits, mixer exposure and anomaly flags into an explained risk score per address
+    Risk(RiskArgs),
+    /// Fetch the newest transactions of one address and summarize its counterparties in seconds, without a crawl
+    #[cfg(feature = "crawl")]
+    Quick(QuickArgs),
+    /// Fetch the addresses listed in failed_addresses.json again and merge their histories into the stored crawl
+    #[cfg(feature = "crawl")]
+    RetryFailed(RetryFailedArgs),
+    /// Report p50, p90 and max transfer sizes and counts per direction of every active address pair
+    PairDistribution(PairDistributionArgs),
+    /// Record the graph's volume, size and reciprocity and report their drift since the previous run
+    Drift(DriftArgs),
+    /// Export a saved graph as node and edge Parquet files through Polars DataFrames
+    #[cfg(feature = "polars")]
+    ToParquet(ToParquetArgs),
+    /// Build a saved graph from node and edge Parquet files
+    #[cfg(feature = "polars")]
+    FromParquet(FromParquetArgs),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum EdgeHandling {
+    /// Keep the edges in the analysed graph
+    #[default]
+    Keep,
+    /// Remove the edges without reporting them
+    Drop,
+    /// Remove the edges and report their count and volume separately
+    Report,
+}
+
+#[derive(Args, Clone, Copy, Debug)]
+pub struct SeedArgs {
+    /// Random number generator seed, to reproduce an earlier run; a fresh seed is drawn and recorded when omitted
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Args, Default)]
+pub struct EdgeHandlingArgs {
+    /// How edges from an address to itself are treated
+    #[arg(long, value_enum, default_value_t = EdgeHandling::Keep)]
+    pub self_transfers: EdgeHandling,
+    /// How edges without a USD value are treated
+    #[arg(long, value_enum, default_value_t = EdgeHandling::Keep)]
+    pub zero_value: EdgeHandling,
+    /// Remove transfers below this USD value that are the only transaction between their addresses and come
+    /// from an unannotated sender, listing them in removed_dust.csv
+    #[arg(long, value_name = "USD")]
+    pub drop_dust_below: Option<f64>,
+}
+
+#[derive(Args, Default)]
+pub struct LayerArgs {
+    /// Asset layer to keep: native, a stablecoin (bscusd, usdc) or token:<contract address>; repeatable, all
+    /// layers when none is given
+    #[arg(long = "layer")]
+    pub layers: Vec<String>,
+}
+
+#[derive(Args, Default)]
+pub struct CrawlArgs {
+    /// Only fetch blocks newer than the last run for already known addresses and merge them into the stored graph
+    #[arg(long)]
+    pub since_last_run: bool,
+    /// Continue the traversal of the previous run from its saved frontier for another transaction budget
+    #[arg(long, conflicts_with = "since_last_run")]
+    pub resume: bool,
+    /// Estimate API requests, wall-clock time and output size for the crawl without sending any request
+    #[arg(long)]
+    pub dry_run: bool,
+    /// History length per address assumed by --dry-run when there is no previous run to learn it from
+    #[arg(long, default_value_t = 200)]
+    pub assumed_transactions_per_address: usize,
+    /// Stream crawl events to stdout, jsonl:<file>, sqlite:<file> or nats:<host:port>/<subject> (the last two
+    /// with the sqlite and nats features); repeatable
+    #[arg(long = "sink")]
+    pub sinks: Vec<String>,
+    /// Add a funded-by edge from each address's first funder to the address in the saved graph
+    #[arg(long)]
+    pub funded_by_edges: bool,
+    /// Fields kept on every edge: lean (hash, value, timestamp), standard (+ block, nonce, gas, decoded call)
+    /// or full (+ block hash, native value, gas limit, input data). Resumed and delta runs keep the first run's
+    #[arg(long, value_enum, default_value_t = EdgeSchema::Standard, conflicts_with_all = ["since_last_run", "resume"])]
+    pub edge_schema: EdgeSchema,
+    /// Split ERC-4337 EntryPoint handleOps bundles into an edge per account call, from the smart account to its
+    /// target with the value it sent; resumed and delta runs keep the first run's choice
+    #[arg(long, conflicts_with_all = ["since_last_run", "resume"])]
+    pub user_operations: bool,
+    /// What to do with genesis allocations, drop by default; resumed and delta runs keep the first run's choice
+    #[arg(long, value_enum, conflicts_with_all = ["since_last_run", "resume"])]
+    pub genesis: Option<SpecialAddressPolicy>,
+    /// What to do with transfers to the zero and dead addresses, keep by default; resumed and delta runs keep
+    /// the first run's choice
+    #[arg(long, value_enum, conflicts_with_all = ["since_last_run", "resume"])]
+    pub burns: Option<SpecialAddressPolicy>,
+    /// Order histories are fetched in, which decides the end of a history beyond the per-address cap that is kept
+    #[arg(long, value_enum, default_value_t = SortDirection::Descending, conflicts_with_all = ["since_last_run", "resume"])]
+    pub sort: SortDirection,
+    /// First block of every fetched history
+    #[arg(long, conflicts_with_all = ["since_last_run", "resume", "anchor"])]
+    pub from_block: Option<u64>,
+    /// Last block of every fetched history
+    #[arg(long, conflicts_with_all = ["since_last_run", "resume", "anchor"])]
+    pub to_block: Option<u64>,
+    /// Only fetch transactions around this time, in unix seconds or as a UTC date like 2024-03-01T12:00
+    #[arg(long, conflicts_with_all = ["since_last_run", "resume"])]
+    pub anchor: Option<String>,
+    /// Hours on either side of --anchor
+    #[arg(long, default_value_t = 24.0, requires = "anchor")]
+    pub anchor_window_hours: f64,
+    /// Start from this address instead of the built-in one, repeatable; seed-map then shows which seeds
+    /// reached each address
+    #[arg(long = "seed", conflicts_with_all = ["since_last_run", "resume", "seed_transaction"])]
+    pub seeds: Vec<String>,
+    /// Start from both endpoints of this transaction instead of the built-in address and only fetch activity
+    /// within --seed-window-days of it
+    #[arg(long, conflicts_with_all = ["since_last_run", "resume", "anchor", "from_block", "to_block"])]
+    pub seed_transaction: Option<String>,
+    /// Days on either side of the seed transaction
+    #[arg(long, default_value_t = 7.0, requires = "seed_transaction")]
+    pub seed_window_days: f64,
+    /// Ask for each address's sent transaction count before fetching its history, and apply
+    /// --oversized-action to addresses above this count
+    #[arg(long, conflicts_with_all = ["since_last_run", "resume", "two_pass"])]
+    pub max_sent_transactions: Option<u64>,
+    #[arg(long, value_enum, default_value_t = OversizedAction::Skip, requires = "max_sent_transactions")]
+    pub oversized_action: OversizedAction,
+    /// Discover the address set with small samples first, then fetch the full histories of the most relevant
+    /// addresses concurrently
+    #[arg(long, conflicts_with_all = ["since_last_run", "resume"])]
+    pub two_pass: bool,
+    /// Hops from the starting address sampled during --two-pass discovery
+    #[arg(long, default_value_t = 2)]
+    pub discovery_depth: usize,
+    /// Newest transactions requested per address during --two-pass discovery
+    #[arg(long, default_value_t = 100)]
+    pub discovery_offset: usize,
+    /// Discovered addresses whose full history --two-pass fetches, the rest become the resumable frontier
+    #[arg(long, default_value_t = 500)]
+    pub backfill_addresses: usize,
+    /// Most requests in flight at once during --two-pass; fewer are sent while the API is slow or rate limiting
+    #[arg(long, default_value_t = 4)]
+    pub concurrency: usize,
+    /// Analysis pass to run on the crawled graph, repeatable; all registered passes run when none is given
+    #[arg(long = "pass")]
+    pub passes: Vec<String>,
+    /// Serve the crawl's status and commands over HTTP on this loopback address, e.g. 127.0.0.1:7070:
+    /// GET /status, POST /checkpoint, POST /concurrency/<n> and POST /stop
+    #[arg(long, value_name = "ADDRESS")]
+    pub control: Option<String>,
+    /// Edges per section of the console preview printed after the crawl, 0 for none
+    #[arg(long, default_value_t = 5)]
+    pub preview_edges: usize,
+    #[command(flatten)]
+    pub edge_handling: EdgeHandlingArgs,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SamplingMethod {
+    UniformEdge,
+    RandomWalk,
+    ForestFire,
+}
+
+#[derive(Args)]
+pub struct SampleArgs {
+    /// Serialized graph to sample, relative to the data storage folder
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Where to save the sampled graph, relative to the data storage folder
+    #[arg(long, default_value = "sampled_transactions.json")]
+    pub output: String,
+    #[arg(long, value_enum, default_value_t = SamplingMethod::ForestFire)]
+    pub method: SamplingMethod,
+    /// Target edge count for uniform-edge, target node count for random-walk and forest-fire
+    #[arg(long, default_value_t = 1000)]
+    pub size: usize,
+    /// Starting address for random-walk (defaults to the highest degree node)
+    #[arg(long)]
+    pub start: Option<String>,
+    #[arg(long, default_value_t = 0.15)]
+    pub restart_probability: f64,
+    #[arg(long, default_value_t = 0.7)]
+    pub forward_burning_probability: f64,
+    #[command(flatten)]
+    pub seed: SeedArgs,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum PseudonymScheme {
+    SaltedHash,
+    Sequential,
+}
+
+#[derive(Args)]
+pub struct PseudonymizeArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    #[arg(long, default_value = "pseudonymized_transactions.json")]
+    pub output: String,
+    #[arg(long, value_enum, default_value_t = PseudonymScheme::SaltedHash)]
+    pub scheme: PseudonymScheme,
+    /// Salt for the salted-hash scheme; reuse it to get the same pseudonyms across exports (random if omitted)
+    #[arg(long)]
+    pub salt: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum EmbeddingFormat {
+    Csv,
+    Npy,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum EdgeWeight {
+    Count,
+    UsdVolume,
+}
+
+#[derive(Args)]
+pub struct EmbeddingsArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Output file relative to the data storage folder (defaults to embeddings.csv or embeddings.npy)
+    #[arg(long)]
+    pub output: Option<String>,
+    #[arg(long, value_enum, default_value_t = EmbeddingFormat::Csv)]
+    pub format: EmbeddingFormat,
+    /// What the walk transition probabilities are proportional to
+    #[arg(long, value_enum, default_value_t = EdgeWeight::Count)]
+    pub weight: EdgeWeight,
+    #[arg(long, default_value_t = 64)]
+    pub dimensions: usize,
+    #[arg(long, default_value_t = 10)]
+    pub walks_per_node: usize,
+    #[arg(long, default_value_t = 40)]
+    pub walk_length: usize,
+    #[arg(long, default_value_t = 5)]
+    pub window: usize,
+    /// Node2vec return parameter p
+    #[arg(long, default_value_t = 1.0)]
+    pub return_parameter: f64,
+    /// Node2vec in-out parameter q
+    #[arg(long, default_value_t = 1.0)]
+    pub in_out_parameter: f64,
+    #[arg(long, default_value_t = 5)]
+    pub negative_samples: usize,
+    #[arg(long, default_value_t = 0.025)]
+    pub learning_rate: f32,
+    #[command(flatten)]
+    pub seed: SeedArgs,
+    #[command(flatten)]
+    pub layer: LayerArgs,
+}
+
+#[derive(Args)]
+pub struct FeaturesArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    #[arg(long, default_value = "node_features.csv")]
+    pub output: String,
+    /// Also write the features as Parquet to this file in the data storage folder
+    #[cfg(feature = "polars")]
+    #[arg(long)]
+    pub parquet: Option<String>,
+}
+
+#[derive(Args)]
+pub struct FeesArgs {
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    #[arg(long, default_value = "fee_percentiles.csv")]
+    pub output: String,
+    /// Hourly price CSV (timestamp,symbol,usd_price) in the data storage folder, used to report fees in USD
+    /// instead of the fees stamped by enrich-usd
+    #[arg(long)]
+    pub prices: Option<String>,
+    /// Symbol of the coin fees are paid in, as it appears in the price CSV
+    #[arg(long, default_value = "ETH")]
+    pub fee_symbol: String,
+    /// Calendar period each percentile bucket covers
+    #[arg(long, value_enum, default_value_t = CalendarPeriod::Day)]
+    pub period: CalendarPeriod,
+    /// IANA timezone whose calendar boundaries define the buckets, e.g. Europe/Berlin
+    #[arg(long, default_value = "UTC")]
+    pub timezone: String,
+    /// A period is a spike when its median gas price exceeds this multiple of the overall median
+    #[arg(long, default_value_t = 2.0)]
+    pub spike_factor: f64,
+}
+
+#[derive(Args)]
+pub struct MevArgs {
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    #[arg(long, default_value = "mev_report.txt")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct BridgeFlowsArgs {
+    /// Graph of the chain funds leave from
+    #[arg(long)]
+    pub source_graph: String,
+    /// Graph of the chain funds arrive on
+    #[arg(long)]
+    pub destination_graph: String,
+    /// Bridge contract addresses on the source chain (comma separated)
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub source_bridges: Vec<String>,
+    /// Bridge contract addresses on the destination chain (comma separated)
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub destination_bridges: Vec<String>,
+    /// Maximum relative amount difference, covering bridge fees
+    #[arg(long, default_value_t = 0.01)]
+    pub amount_tolerance: f64,
+    #[arg(long, default_value_t = 6)]
+    pub max_delay_hours: u64,
+    #[arg(long, default_value = "bridge_flows.csv")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct TokenHoldersArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    #[arg(long, default_value = "token_holder_graph.json")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct ContractInteractionsArgs {
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    #[arg(long, default_value = "contract_interactions.csv")]
+    pub output: String,
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+}
+
+#[derive(Args)]
+pub struct RollingFlowArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Addresses treated as one entity (comma separated)
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub addresses: Vec<String>,
+    #[arg(long, default_value_t = 24)]
+    pub step_hours: u64,
+    #[arg(long, default_value_t = 168)]
+    pub window_hours: u64,
+    #[arg(long, default_value = "rolling_flow.csv")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct DormancyArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Minimum number of days without any activity before a transaction counts as a reactivation
+    #[arg(long, default_value_t = 30)]
+    pub min_dormant_days: u64,
+    #[arg(long, default_value = "dormancy.csv")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct StructuringArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    #[arg(long, default_value_t = 24)]
+    pub window_hours: u64,
+    /// Minimum number of identical or round transfers within one window for a pair to be flagged
+    #[arg(long, default_value_t = 3)]
+    pub min_repeats: usize,
+    /// Amounts close to a multiple of this value count as round
+    #[arg(long, default_value_t = 1000.0)]
+    pub round_unit_usd: f64,
+    /// Allowed relative deviation from the nearest multiple of the round unit
+    #[arg(long, default_value_t = 0.001)]
+    pub round_tolerance: f64,
+    #[arg(long, default_value = "structuring.csv")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct ExportMatrixArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// What each matrix entry sums over the parallel edges between two addresses
+    #[arg(long, value_enum, default_value_t = EdgeWeight::UsdVolume)]
+    pub weight: EdgeWeight,
+    /// Add every edge in both directions, giving an undirected adjacency matrix
+    #[arg(long)]
+    pub symmetric: bool,
+    /// Row addresses are written next to it as <name>.addresses.txt
+    #[arg(long, default_value = "adjacency.mtx")]
+    pub output: String,
+    #[command(flatten)]
+    pub layer: LayerArgs,
+}
+
+#[cfg(feature = "spectral")]
+#[derive(Args)]
+pub struct SpectralArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    #[arg(long, value_enum, default_value_t = EdgeWeight::Count)]
+    pub weight: EdgeWeight,
+    /// Number of largest adjacency eigenvalues to report
+    #[arg(long, default_value_t = 5)]
+    pub top: usize,
+    /// Lanczos iterations; more iterations resolve more eigenvalues on large graphs
+    #[arg(long, default_value_t = 150)]
+    pub iterations: usize,
+    #[arg(long, default_value = "spectral_report.txt")]
+    pub output: String,
+    #[command(flatten)]
+    pub seed: SeedArgs,
+}
+
+#[derive(Args)]
+pub struct StatsArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    #[command(flatten)]
+    pub edge_handling: EdgeHandlingArgs,
+    #[arg(long, default_value = "stats_report.txt")]
+    pub output: String,
+    #[command(flatten)]
+    pub layer: LayerArgs,
+}
+
+#[derive(Args)]
+pub struct TemporalMotifsArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Time window in which all edges of a motif must occur
+    #[arg(long, default_value_t = 60)]
+    pub delta_minutes: u64,
+    /// Distinct counterparties within the window that make a fan-in or fan-out burst
+    #[arg(long, default_value_t = 5)]
+    pub min_burst_counterparties: usize,
+    /// Participating addresses listed per motif
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+    #[arg(long, default_value = "temporal_motifs.csv")]
+    pub output: String,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum DedupPolicy {
+    /// Drop edges whose hash, kind, trace index and log index repeat
+    Exact,
+    /// Additionally drop the outer call of transactions that also have token or internal transfer edges
+    PreferValueTransfers,
+}
+
+#[derive(Args)]
+pub struct DedupArgs {
+    /// Serialized graphs to combine (comma separated)
+    #[arg(long, value_delimiter = ',', default_value = "parsed_transactions.json")]
+    pub inputs: Vec<String>,
+    #[arg(long, value_enum, default_value_t = DedupPolicy::Exact)]
+    pub policy: DedupPolicy,
+    #[arg(long, default_value = "dedup_transactions.json")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct ReciprocityArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    #[arg(long, default_value = "reciprocity.csv")]
+    pub output: String,
+    /// Net flows between entities instead of addresses, resolving addresses through --labels and probable
+    /// entity clustering
+    #[arg(long)]
+    pub by_entity: bool,
+    /// Address labels as an address,label CSV in the data storage folder; addresses with one label form one entity
+    #[arg(long, requires = "by_entity")]
+    pub labels: Option<String>,
+    /// Pairs of addresses with a combined evidence confidence below this are not clustered into one entity
+    #[arg(long, default_value_t = 0.5, requires = "by_entity")]
+    pub min_confidence: f64,
+    /// Also write every priced transfer of every pair as a text log in the data storage folder
+    #[arg(long)]
+    pub transfer_log: Option<String>,
+    /// Transfers below this USD value are summed per pair and direction in the transfer log instead of listed
+    #[arg(long, default_value_t = 0.0, requires = "transfer_log")]
+    pub dust_floor_usd: f64,
+}
+
+#[derive(Args)]
+pub struct PriceCorrelationArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Hourly price CSV (timestamp,symbol,usd_price) in the data storage folder
+    #[arg(long, default_value = "prices.csv")]
+    pub prices: String,
+    #[arg(long, default_value = "ETH")]
+    pub symbol: String,
+    #[arg(long, default_value_t = 48)]
+    pub max_lag_hours: i64,
+    #[arg(long, default_value = "price_correlation.csv")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct EnrichUsdArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Hourly price CSV (timestamp,symbol,usd_price) in the data storage folder
+    #[arg(long, default_value = "prices.csv")]
+    pub prices: String,
+    /// Symbol of the native coin, which fees and native transfers are paid in, as it appears in the price CSV
+    #[arg(long, default_value = "ETH")]
+    pub fee_symbol: String,
+    /// Enriched graph, relative to the data storage folder; defaults to overwriting the input
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct ActivityHeatmapArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Long-format CSV with one row per non-empty cell; the plot is saved next to it as PNG
+    #[arg(long, default_value = "activity_heatmap.csv")]
+    pub output: String,
+    /// IANA timezone the hours and weekdays are taken in, e.g. Asia/Shanghai
+    #[arg(long, default_value = "UTC")]
+    pub timezone: String,
+    /// Senders with fewer transactions are left out of the per-address rows
+    #[arg(long, default_value_t = 10)]
+    pub min_transactions: usize,
+    /// Plot this sender instead of the whole graph
+    #[arg(long)]
+    pub address: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ApprovalsArgs {
+    /// Decoded calls are only kept in the full crawl output
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    #[arg(long, default_value = "approvals.csv")]
+    pub output: String,
+    #[arg(long, default_value = "approval_risk.txt")]
+    pub report: String,
+    /// Query the explorer for source verification of each unlimited spender (needs api_key.txt)
+    #[arg(long)]
+    pub check_verification: bool,
+}
+
+#[cfg(feature = "crawl")]
+#[derive(Args)]
+pub struct DetectContractsArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    #[arg(long, default_value = "address_kinds.csv")]
+    pub output: String,
+    /// JSON-RPC endpoint that accepts batch requests
+    #[arg(long, default_value = "https://bsc-dataseed.binance.org")]
+    pub rpc_url: String,
+    /// eth_getCode calls per batch request
+    #[arg(long, default_value_t = 500)]
+    pub batch_size: usize,
+}
+
+#[cfg(feature = "scripting")]
+#[derive(Args)]
+pub struct FilterScriptArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    #[arg(long, default_value = "script_filtered_transactions.json")]
+    pub output: String,
+    /// Filter expression, e.g. "value_usd > 1e6 && hour(timestamp) < 6"
+    #[arg(long, conflicts_with = "script")]
+    pub expr: Option<String>,
+    /// File holding a Rhai script whose last expression is the filter result
+    #[arg(long)]
+    pub script: Option<String>,
+    /// IANA timezone used by the hour, weekday and day helpers
+    #[arg(long, default_value = "UTC")]
+    pub timezone: String,
+}
+
+#[derive(Args)]
+pub struct DescribeArgs {
+    /// Name relative to the data storage folder, the .json extension of graphs may be left out
+    pub name: String,
+}
+
+#[derive(Args)]
+pub struct GcArgs {
+    /// Delete files and rewrite the catalog instead of printing what would be done
+    #[arg(long)]
+    pub apply: bool,
+}
+
+#[derive(Args)]
+pub struct AnalyzeArgs {
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    /// Hourly price CSV (timestamp,symbol,usd_price) in the data storage folder, handed to every pass
+    #[arg(long)]
+    pub prices: Option<String>,
+    /// Pass to run, repeatable; all registered passes run when none is given
+    #[arg(long = "pass")]
+    pub passes: Vec<String>,
+    #[arg(long, default_value = "analysis_manifest.json")]
+    pub manifest: String,
+    /// Print the registered pass names and exit
+    #[arg(long)]
+    pub list: bool,
+    /// TOML file listing filter, analysis and export steps to run in order, in place of --pass
+    #[arg(long, conflicts_with_all = ["passes", "list"])]
+    pub pipeline: Option<String>,
+    /// Recompute every pass instead of reusing reports cached in the workspace for the same graph and settings
+    #[arg(long)]
+    pub no_cache: bool,
+    /// Price points of a symbol further apart than this are reported as a gap
+    #[arg(long, default_value_t = 24)]
+    pub max_price_gap_hours: u64,
+    /// Consecutive price points differing by more than this factor either way are reported as an implausible jump
+    #[arg(long, default_value_t = 3.0)]
+    pub max_price_jump: f64,
+    /// Replace a single price point that jumps away and straight back by the mean of its neighbours
+    #[arg(long)]
+    pub smooth_price_outliers: bool,
+    /// Drop addresses without any transaction from the input graph too, so they do not count towards node
+    /// totals; every filter already drops the addresses it leaves without transactions
+    #[arg(long)]
+    pub remove_isolated_nodes: bool,
+    #[command(flatten)]
+    pub layer: LayerArgs,
+}
+
+#[cfg(feature = "grpc")]
+#[derive(Args)]
+pub struct ServeGrpcArgs {
+    #[arg(long, default_value = "127.0.0.1:50051")]
+    pub listen: String,
+    /// Graph answering queries until a crawl started over gRPC completes
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+}
+
+#[derive(Args)]
+pub struct QueryArgs {
+    /// e.g. "MATCH a-[t]->b WHERE t.usd > 10000 AND b.label = 'Binance' RETURN a.address, sum(t.usd) AS volume ORDER BY volume DESC LIMIT 10"
+    pub query: String,
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// CSV with address,label columns in the data storage folder, read by the label node property
+    #[arg(long)]
+    pub labels: Option<String>,
+    /// Save the result as CSV in the data storage folder instead of printing a table
+    #[arg(long)]
+    pub output: Option<String>,
+}
+
+#[derive(Args)]
+pub struct CompareArgs {
+    /// Graph to compare, repeatable. With a single input, pass two or more --seed addresses instead
+    #[arg(long = "input", required = true)]
+    pub inputs: Vec<String>,
+    /// Seed address, repeatable: one per input, or several within a single input
+    #[arg(long = "seed")]
+    pub seeds: Vec<String>,
+    /// Hops around each seed that make up its ecosystem
+    #[arg(long, default_value_t = 1)]
+    pub hops: usize,
+    #[arg(long, default_value = "compare_report.txt")]
+    pub output: String,
+    /// Save the shared addresses of every pair as CSV in the data storage folder
+    #[arg(long)]
+    pub shared_output: Option<String>,
+}
+
+#[derive(Args)]
+pub struct SimilarityArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Target address, repeatable. Without targets the most connected addresses are scored
+    #[arg(long = "target")]
+    pub targets: Vec<String>,
+    /// Number of most connected addresses to score when no --target is given
+    #[arg(long, default_value_t = 50)]
+    pub top: usize,
+    #[arg(long, default_value = "counterparty_similarity.csv")]
+    pub output: String,
+    /// Number of pairs without direct transactions to print
+    #[arg(long, default_value_t = 10)]
+    pub show: usize,
+}
+
+#[derive(Args)]
+pub struct EntitiesArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Pairs of addresses with a combined evidence confidence below this are not linked
+    #[arg(long, default_value_t = 0.5)]
+    pub min_confidence: f64,
+    #[arg(long, default_value = "probable_entities.txt")]
+    pub report: String,
+    /// Entity memberships as CSV in the data storage folder
+    #[arg(long, default_value = "probable_entities.csv")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct FundedByArgs {
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    #[arg(long, default_value = "funded_transactions.json")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct ReportArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    #[arg(long)]
+    pub address: String,
+    /// Address labels as an address,label CSV in the data storage folder
+    #[arg(long)]
+    pub labels: Option<String>,
+    /// IANA timezone the transaction times are shown in
+    #[arg(long, default_value = "UTC")]
+    pub timezone: String,
+    /// Writes <output>.txt, <output>.csv and <output>.json; defaults to report_<address>
+    #[arg(long)]
+    pub output: Option<String>,
+}
+
+#[derive(Args)]
+pub struct BalanceArgs {
+    /// The unfiltered graph, filtering drops the native transfers the balance is built from
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    #[arg(long)]
+    pub address: String,
+    /// Current balance in coins to compare the reconstruction against
+    #[arg(long, conflicts_with = "onchain")]
+    pub current_balance: Option<f64>,
+    /// Fetch the current balance from the block explorer API
+    #[arg(long)]
+    pub onchain: bool,
+    /// IANA timezone the series times are given in
+    #[arg(long, default_value = "UTC")]
+    pub timezone: String,
+    /// Writes <output>.csv and <output>.png; defaults to balance_<address>
+    #[arg(long)]
+    pub output: Option<String>,
+}
+
+#[derive(Args)]
+pub struct CoverageArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Writes <output>.csv, <output>.dot and <output>.html coloured by coverage
+    #[arg(long, default_value = "coverage")]
+    pub output: String,
+}
+
+#[cfg(feature = "crawl")]
+#[derive(Args)]
+pub struct BlockRewardsArgs {
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    #[arg(long, default_value = "rewarded_transactions.json")]
+    pub output: String,
+    /// Looks up the blocks produced by this many addresses with the largest unexplained native outflow
+    #[arg(long, default_value_t = 20)]
+    pub max_addresses: usize,
+    /// Known fee recipients to look up in addition, repeatable
+    #[arg(long)]
+    pub address: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct AnnotateArgs {
+    pub address: String,
+    /// Tag to add, repeatable
+    #[arg(long)]
+    pub tag: Vec<String>,
+    /// Note to add, repeatable
+    #[arg(long)]
+    pub note: Vec<String>,
+    /// Tag to remove, repeatable
+    #[arg(long)]
+    pub untag: Vec<String>,
+    /// Number of the note to remove, as shown by annotate
+    #[arg(long)]
+    pub remove_note: Option<usize>,
+    /// Remove all tags and notes of the address before adding any given ones
+    #[arg(long)]
+    pub clear: bool,
+}
+
+#[derive(Args)]
+pub struct AnnotationsArgs {
+    /// Only list addresses with this tag
+    #[arg(long)]
+    pub tag: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ExportCaseArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Address of the case, repeatable
+    #[arg(long = "address", required = true)]
+    pub addresses: Vec<String>,
+    /// Hops around the case addresses included in the subgraph
+    #[arg(long, default_value_t = 1)]
+    pub hops: usize,
+    /// Hourly price CSV (timestamp,symbol,usd_price) in the data storage folder, bundled and handed to every pass
+    #[arg(long)]
+    pub prices: Option<String>,
+    /// Address labels as an address,label CSV in the data storage folder
+    #[arg(long)]
+    pub labels: Option<String>,
+    /// IANA timezone the transaction times are shown in
+    #[arg(long, default_value = "UTC")]
+    pub timezone: String,
+    /// Writes <output>.zip and the subgraph as <output>_subgraph.json
+    #[arg(long, default_value = "case")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct GenFixtureArgs {
+    /// Transfer pattern, repeatable: pair:N, two-way:N, chain:N, cycle:N, star:N, fan-in:N or
+    /// random:NODES:EDGES, with an optional @amount for its transfers, e.g. chain:5@2500
+    #[arg(long = "pattern", required = true)]
+    pub patterns: Vec<String>,
+    /// Token amount of every transfer without an @amount
+    #[arg(long, default_value_t = 100.0)]
+    pub usd: f64,
+    /// Transfer amounts vary uniformly by up to this fraction of the amount
+    #[arg(long, default_value_t = 0.0)]
+    pub value_spread: f64,
+    /// Alternate transfers between BSCUSD and USDC instead of using BSCUSD only
+    #[arg(long)]
+    pub mixed_tokens: bool,
+    /// Timestamp of the first transfer
+    #[arg(long, default_value_t = 1_700_000_000)]
+    pub start_timestamp: u64,
+    /// Seconds between consecutive transfers
+    #[arg(long, default_value_t = 3_600)]
+    pub interval: u64,
+    #[arg(long, default_value_t = 30_000_000)]
+    pub start_block: u64,
+    /// Gas price of every legacy transfer; transfers carry no fees at 0
+    #[arg(long, default_value_t = 0)]
+    pub gas_price_gwei: u64,
+    /// Block base fee; above 0 every other transfer is an EIP-1559 transaction paying it plus the priority fee,
+    /// with every second of those capped below that by its max fee. Without a gas price the other transfers used
+    /// gas at an unknown price
+    #[arg(long, default_value_t = 0)]
+    pub base_fee_gwei: u64,
+    #[arg(long, default_value_t = 1)]
+    pub priority_fee_gwei: u64,
+    /// Symbol of the fee coin in the price CSV
+    #[arg(long, default_value = "ETH")]
+    pub fee_symbol: String,
+    /// Fee coin price at the first hour
+    #[arg(long, default_value_t = 2_000.0)]
+    pub coin_price: f64,
+    /// Largest hourly relative move of the fee coin price
+    #[arg(long, default_value_t = 0.01)]
+    pub coin_volatility: f64,
+    /// Seeds the random patterns, amounts and prices; the same arguments always give the same fixture
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+    /// Where to save the graph, relative to the data storage folder
+    #[arg(long, default_value = "fixture_transactions.json")]
+    pub output: String,
+    /// Where to save the prices, relative to the data storage folder
+    #[arg(long, default_value = "fixture_prices.csv")]
+    pub prices_output: String,
+}
+
+#[derive(Args)]
+pub struct SeedMapArgs {
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    /// Writes <output>.csv, <output>.dot, <output>.html and <output>.gexf coloured by seed
+    #[arg(long, default_value = "seed_map")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct PeriodSummaryArgs {
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    /// The earlier graph to compare against, in the data storage folder
+    #[arg(long)]
+    pub previous: String,
+    /// New counterparties listed, by USD volume
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+    #[arg(long, default_value = "period_summary.txt")]
+    pub output: String,
+}
+
+#[cfg(feature = "crawl")]
+#[derive(Args)]
+pub struct DaemonArgs {
+    /// Minutes to wait after one crawl before the next
+    #[arg(long, default_value_t = 60)]
+    pub interval_minutes: u64,
+    /// Period each summary covers, compared against the graph as it was when the period started
+    #[arg(long, value_enum, default_value_t = CalendarPeriod::Week)]
+    pub period: CalendarPeriod,
+    /// IANA timezone the periods start in
+    #[arg(long, default_value = "UTC")]
+    pub timezone: String,
+    /// Where summaries and crawl failures go: stdout, file:<file> appended to in the data storage folder, or
+    /// webhook:<url> posted as {"text": ...}; repeatable
+    #[arg(long = "notify")]
+    pub notifiers: Vec<String>,
+    /// Also send crawl events to stdout, jsonl:<file>, sqlite:<file> or nats:<host:port>/<subject>; repeatable
+    #[arg(long = "sink")]
+    pub sinks: Vec<String>,
+    /// New counterparties listed in each summary, by USD volume
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+    /// Stop after this many crawls instead of running until interrupted
+    #[arg(long)]
+    pub runs: Option<usize>,
+    #[command(flatten)]
+    pub drift: DriftThresholds,
+}
+
+#[derive(Args)]
+pub struct CompactArgs {
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    /// Where to save the compacted graph, relative to the data storage folder (defaults to rewriting the input)
+    #[arg(long)]
+    pub output: Option<String>,
+    /// Also drop addresses without any transaction
+    #[arg(long)]
+    pub drop_isolated: bool,
+}
+
+#[derive(Args)]
+pub struct LayersArgs {
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    #[command(flatten)]
+    pub layer: LayerArgs,
+    /// Hourly price CSV (timestamp,symbol,usd_price) in the data storage folder, pricing native transfers
+    /// for the combined USD layer
+    #[arg(long)]
+    pub prices: Option<String>,
+    /// Symbol of the native coin, as it appears in the price CSV
+    #[arg(long, default_value = "ETH")]
+    pub coin_symbol: String,
+    #[arg(long, default_value = "layer_pairs.csv")]
+    pub output: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TraversalStrategy {
+    /// Most transactions seen with the address first, as the crawl does
+    Relevance,
+    /// Fewest hops from the seeds first
+    BreadthFirst,
+    /// Most USD volume seen with the address first
+    Volume,
+}
+
+#[derive(Args)]
+pub struct BenchmarkStrategiesArgs {
+    /// Graph of the recorded crawl, whose crawl state lists the addresses it fetched
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    /// Strategy to replay, repeatable; all of them by default
+    #[arg(long = "strategy", value_enum)]
+    pub strategies: Vec<TraversalStrategy>,
+    /// Transaction budget to replay every strategy with, repeatable; the recorded edge count by default
+    #[arg(long = "budget")]
+    pub budgets: Vec<usize>,
+    /// Address whose coverage is compared, repeatable; the annotated addresses by default
+    #[arg(long = "target")]
+    pub targets: Vec<String>,
+    #[arg(long, default_value = "strategy_benchmark.csv")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct ExposureArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Annotation tag of the flagged addresses, repeatable
+    #[arg(long = "tag", default_values_t = ["sanctioned".to_string(), "mixer".to_string()])]
+    pub tags: Vec<String>,
+    /// Flagged address in addition to the tagged ones, repeatable
+    #[arg(long)]
+    pub flagged: Vec<String>,
+    /// What the walk transition probabilities are proportional to
+    #[arg(long, value_enum, default_value_t = EdgeWeight::UsdVolume)]
+    pub weight: EdgeWeight,
+    /// Walk edges in both directions instead of only along the money, exposing senders to flagged recipients too
+    #[arg(long)]
+    pub undirected: bool,
+    /// Chance per step that a walker jumps back to a flagged address; higher keeps exposure closer to them
+    #[arg(long, default_value_t = 0.15)]
+    pub restart_probability: f64,
+    #[arg(long, default_value_t = 100)]
+    pub iterations: usize,
+    /// Stop iterating once the scores change by less than this in total
+    #[arg(long, default_value_t = 1e-10)]
+    pub tolerance: f64,
+    /// Number of most exposed addresses to print
+    #[arg(long, default_value_t = 10)]
+    pub show: usize,
+    #[arg(long, default_value = "exposure.csv")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct MixersArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Mixer contract in addition to the built-in Tornado Cash pools and addresses tagged mixer, repeatable
+    #[arg(long = "mixer")]
+    pub mixers: Vec<String>,
+    /// Tag the mixers found in the graph as mixer and the addresses using them as mixer-user, for exposure
+    /// scoring and reports
+    #[arg(long)]
+    pub annotate: bool,
+    #[arg(long, default_value = "mixer_interactions.csv")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct RiskArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Risk scorer to combine, repeatable; all registered scorers by default
+    #[arg(long = "scorer")]
+    pub scorers: Vec<String>,
+    /// Number of riskiest addresses to report with their components
+    #[arg(long, default_value_t = 10)]
+    pub show: usize,
+    #[arg(long, default_value = "risk_scores.csv")]
+    pub output: String,
+    #[arg(long, default_value = "risk_report.txt")]
+    pub report: String,
+}
+
+#[cfg(feature = "crawl")]
+#[derive(Args)]
+pub struct QuickArgs {
+    pub address: String,
+    /// Newest transactions to fetch, all in one request
+    #[arg(long, default_value_t = 1000)]
+    pub recent: usize,
+    /// Address labels as an address,label CSV in the data storage folder
+    #[arg(long)]
+    pub labels: Option<String>,
+    /// IANA timezone the transaction times are shown in
+    #[arg(long, default_value = "UTC")]
+    pub timezone: String,
+    /// Save the one-hop graph under this name in the data storage folder
+    #[arg(long)]
+    pub output: Option<String>,
+}
+
+#[cfg(feature = "crawl")]
+#[derive(Args)]
+pub struct RetryFailedArgs {
+    /// Retry only this failed address, repeatable; all of them when omitted
+    #[arg(long = "address")]
+    pub addresses: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct PairDistributionArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Transfers in both directions together a pair needs to be reported
+    #[arg(long, default_value_t = 2)]
+    pub min_transfers: usize,
+    /// Pairs to print, most transfers first
+    #[arg(long, default_value_t = 10)]
+    pub show: usize,
+    #[arg(long, default_value = "pair_distribution.csv")]
+    pub output: String,
+}
+
+// Drift from one incremental run to the next that is worth an alert.
+#[derive(Args, Clone, Copy, Debug)]
+pub struct DriftThresholds {
+    /// Alert when the USD volume grows by more than this percent from one run to the next
+    #[arg(long, default_value_t = 50.0)]
+    pub max_volume_growth_percent: f64,
+    /// Alert when the addresses added since the previous run exceed this percent of the addresses it had
+    #[arg(long, default_value_t = 20.0)]
+    pub max_new_node_percent: f64,
+    /// Alert when the share of address pairs with value flowing both ways moves by more than this many percentage points
+    #[arg(long, default_value_t = 5.0)]
+    pub max_reciprocity_change: f64,
+}
+
+#[derive(Args)]
+pub struct DriftArgs {
+    #[arg(long, default_value = "parsed_transactions.json")]
+    pub input: String,
+    #[command(flatten)]
+    pub thresholds: DriftThresholds,
+}
+
+#[cfg(feature = "polars")]
+#[derive(Args)]
+pub struct ToParquetArgs {
+    #[arg(long, default_value = "filtered_transactions_polygon.json")]
+    pub input: String,
+    /// Writes <prefix>_nodes.parquet and <prefix>_edges.parquet into the data storage folder
+    #[arg(long, default_value = "graph")]
+    pub prefix: String,
+}
+
+#[cfg(feature = "polars")]
+#[derive(Args)]
+pub struct FromParquetArgs {
+    /// Reads <prefix>_nodes.parquet and <prefix>_edges.parquet from the data storage folder
+    #[arg(long, default_value = "graph")]
+    pub prefix: String,
+    #[arg(long, default_value = "parquet_transactions.json")]
+    pub output: String,
+}
@@ -0,0 +1,69 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::cli::CompactArgs;
+use crate::{deserialize_graph, serialize_graph, FilteringResultInfo, DATA_STORAGE_FOLDER, G};
+
+#[derive(Debug, Default)]
+pub struct Compaction {
+    pub merged_duplicates: usize,
+    pub dropped_isolated: usize,
+}
+
+// Rebuilds the graph with one node per address, numbered in the order edges first reach them, so the
+// nodes an edge list touches together sit together. Addresses listed more than once, e.g. after graphs
+// were concatenated, are merged with their edges. Isolated addresses, which filtering leaves behind when
+// it drops every edge of an address, come last or are dropped.
+pub fn compact_graph(graph: &G, drop_isolated: bool) -> (G, Compaction) {
+    let mut compacted = G::with_capacity(graph.node_count(), graph.edge_count());
+    let mut node_indices: HashMap<&str, NodeIndex> = HashMap::new();
+    let mut node = |compacted: &mut G, n: NodeIndex| *node_indices.entry(graph[n].as_str()).or_insert_with(|| compacted.add_node(graph[n].clone()));
+    for edge in graph.edge_references() {
+        let (source, target) = (node(&mut compacted, edge.source()), node(&mut compacted, edge.target()));
+        compacted.add_edge(source, target, edge.weight().clone());
+    }
+    let mut compaction = Compaction::default();
+    for n in graph.node_indices() {
+        if graph.neighbors_undirected(n).next().is_some() {
+            continue;
+        }
+        if drop_isolated {
+            compaction.dropped_isolated += 1;
+        } else {
+            node(&mut compacted, n);
+        }
+    }
+    compaction.merged_duplicates = graph.node_count() - compaction.dropped_isolated - compacted.node_count();
+    (compacted, compaction)
+}
+
+pub fn run_compact_command(args: &CompactArgs) -> Result<()> {
+    let output = args.output.as_deref().unwrap_or(&args.input);
+    let size_before = fs::metadata(format!("{}/{}", DATA_STORAGE_FOLDER, args.input))?.len();
+    let (graph, info) = deserialize_graph(&args.input)?;
+    let (compacted, compaction) = compact_graph(&graph, args.drop_isolated);
+    let compacted_info = FilteringResultInfo {
+        node_count: compacted.node_count(),
+        edge_count: compacted.edge_count(),
+        ..info
+    };
+    serialize_graph(&compacted, &compacted_info, output)?;
+    let size_after = fs::metadata(format!("{}/{}", DATA_STORAGE_FOLDER, output))?.len();
+    println!(
+        "Merged {} duplicate address entries and dropped {} isolated addresses, {} -> {} nodes",
+        compaction.merged_duplicates,
+        compaction.dropped_isolated,
+        graph.node_count(),
+        compacted.node_count()
+    );
+    let change = if size_after <= size_before {
+        format!("saved {} bytes ({:.1}%)", size_before - size_after, 100.0 * (size_before - size_after) as f64 / size_before.max(1) as f64)
+    } else {
+        format!("grew by {} bytes, the input was written more compactly than pretty-printed JSON", size_after - size_before)
+    };
+    println!("{} bytes -> {} bytes, {}", size_before, size_after, change);
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+
+use crate::atomic_file::{commit_csv, csv_writer, write_atomically};
+use crate::cli::CompareArgs;
+use crate::money::Amount;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+// One side of a comparison: a whole saved graph, or the neighborhood of a seed address within one.
+pub struct Side<'a> {
+    pub label: String,
+    pub graph: &'a G,
+    pub seed: Option<NodeIndex>,
+    // Addresses within the hop limit of the seed, or all addresses without a seed. Seeds themselves are
+    // left out so two seeds that transact with each other do not count as a shared ecosystem.
+    pub ecosystem: HashSet<String>,
+    // Direct neighbors of the seed, or all addresses without a seed.
+    pub counterparties: HashSet<String>,
+}
+
+fn find_node(graph: &G, address: &str) -> Result<NodeIndex> {
+    let address = address.to_lowercase();
+    graph.node_indices().find(|&n| graph[n] == address).ok_or_else(|| eyre::eyre!("Address {} is not present in the graph", address))
+}
+
+pub fn neighborhood(graph: &G, seed: NodeIndex, hops: usize) -> HashSet<NodeIndex> {
+    let mut reached = HashSet::from([seed]);
+    let mut queue = VecDeque::from([(seed, 0)]);
+    while let Some((node, depth)) = queue.pop_front() {
+        if depth == hops {
+            continue;
+        }
+        for neighbor in graph.neighbors_undirected(node) {
+            if reached.insert(neighbor) {
+                queue.push_back((neighbor, depth + 1));
+            }
+        }
+    }
+    reached
+}
+
+impl<'a> Side<'a> {
+    pub fn new(label: String, graph: &'a G, seed: Option<&str>, hops: usize, all_seeds: &[String]) -> Result<Side<'a>> {
+        let seed = seed.map(|address| find_node(graph, address)).transpose()?;
+        let addresses = |nodes: &mut dyn Iterator<Item = NodeIndex>| -> HashSet<String> {
+            nodes.map(|n| graph[n].clone()).filter(|address| !all_seeds.contains(address)).collect()
+        };
+        let (ecosystem, counterparties) = match seed {
+            Some(seed) => (
+                addresses(&mut neighborhood(graph, seed, hops).into_iter()),
+                addresses(&mut graph.neighbors_undirected(seed)),
+            ),
+            None => {
+                let all = addresses(&mut graph.node_indices());
+                (all.clone(), all)
+            }
+        };
+        Ok(Side { label, graph, seed, ecosystem, counterparties })
+    }
+
+    pub fn usd_volume(&self) -> f64 {
+        self.graph
+            .raw_edges()
+            .iter()
+            .filter(|e| self.seed.is_none() || self.ecosystem_edge(e.source(), e.target()))
+            .filter_map(|e| e.weight.data.as_ref())
+            .fold(0.0, |acc, d| acc + d.usd_value)
+    }
+
+    fn contains(&self, node: NodeIndex) -> bool {
+        Some(node) == self.seed || self.ecosystem.contains(&self.graph[node])
+    }
+
+    fn ecosystem_edge(&self, source: NodeIndex, target: NodeIndex) -> bool {
+        self.contains(source) && self.contains(target)
+    }
+
+    // USD volume on edges between addresses both sides share.
+    pub fn shared_volume(&self, shared: &HashSet<String>) -> f64 {
+        self.graph
+            .raw_edges()
+            .iter()
+            .filter(|e| shared.contains(&self.graph[e.source()]) && shared.contains(&self.graph[e.target()]))
+            .filter_map(|e| e.weight.data.as_ref())
+            .fold(0.0, |acc, d| acc + d.usd_value)
+    }
+}
+
+pub fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        0.0
+    } else {
+        a.intersection(b).count() as f64 / union as f64
+    }
+}
+
+// Shared share of the smaller set, so a small seed neighborhood inside a large one scores 1.
+pub fn overlap_coefficient(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let smaller = a.len().min(b.len());
+    if smaller == 0 {
+        0.0
+    } else {
+        a.intersection(b).count() as f64 / smaller as f64
+    }
+}
+
+fn side_by_side(sides: &[Side]) -> String {
+    let rows: Vec<(&str, Vec<String>)> = vec![
+        ("Nodes / edges", sides.iter().map(|s| format!("{} / {}", s.graph.node_count(), s.graph.edge_count())).collect()),
+        ("Seed", sides.iter().map(|s| s.seed.map_or("none".to_string(), |n| s.graph[n].clone())).collect()),
+        ("USD volume", sides.iter().map(|s| Amount::Usd(s.usd_volume()).to_string()).collect()),
+        ("Ecosystem addresses", sides.iter().map(|s| s.ecosystem.len().to_string()).collect()),
+        ("Direct counterparties", sides.iter().map(|s| s.counterparties.len().to_string()).collect()),
+    ];
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap();
+    let widths: Vec<usize> =
+        (0..sides.len()).map(|i| rows.iter().map(|(_, cells)| cells[i].len()).chain([sides[i].label.len()]).max().unwrap()).collect();
+    let line = |label: &str, cells: Vec<&str>| {
+        let cells: Vec<String> = cells.iter().zip(widths.iter()).map(|(cell, &width)| format!("{:<width$}", cell, width = width)).collect();
+        format!("{:<label_width$} | {}\n", label, cells.join(" | ").trim_end(), label_width = label_width)
+    };
+    let mut table = line("", sides.iter().map(|s| s.label.as_str()).collect());
+    for (label, cells) in rows.iter() {
+        table.push_str(&line(label, cells.iter().map(String::as_str).collect()));
+    }
+    table
+}
+
+pub fn comparison_report(sides: &[Side]) -> (String, Vec<(usize, usize, Vec<String>)>) {
+    let mut report = side_by_side(sides);
+    let mut shared_by_pair = Vec::new();
+    for i in 0..sides.len() {
+        for j in i + 1..sides.len() {
+            let (a, b) = (&sides[i], &sides[j]);
+            let shared: HashSet<String> = a.ecosystem.intersection(&b.ecosystem).cloned().collect();
+            let shared_counterparties = a.counterparties.intersection(&b.counterparties).count();
+            report.push_str(&format!(
+                "\n{} vs {}:\n  Shared addresses: {}, Jaccard similarity of address sets: {:.4}, overlap coefficient: {:.4}\n",
+                a.label,
+                b.label,
+                shared.len(),
+                jaccard(&a.ecosystem, &b.ecosystem),
+                overlap_coefficient(&a.ecosystem, &b.ecosystem)
+            ));
+            report.push_str(&format!(
+                "  Shared direct counterparties: {}, overlap coefficient: {:.4}\n",
+                shared_counterparties,
+                overlap_coefficient(&a.counterparties, &b.counterparties)
+            ));
+            report.push_str(&format!(
+                "  Volume among shared addresses: {} in {}, {} in {}\n",
+                Amount::Usd(a.shared_volume(&shared)),
+                a.label,
+                Amount::Usd(b.shared_volume(&shared)),
+                b.label
+            ));
+            let mut shared: Vec<String> = shared.into_iter().collect();
+            shared.sort();
+            shared_by_pair.push((i, j, shared));
+        }
+    }
+    (report, shared_by_pair)
+}
+
+pub fn run_compare_command(args: &CompareArgs) -> Result<()> {
+    let comparing_seeds_in_one_graph = args.inputs.len() == 1 && args.seeds.len() >= 2;
+    if args.inputs.len() < 2 && !comparing_seeds_in_one_graph {
+        return Err(eyre::eyre!("Compare needs at least two --input graphs, or one graph and at least two --seed addresses"));
+    }
+    if !args.seeds.is_empty() && !comparing_seeds_in_one_graph && args.seeds.len() != args.inputs.len() {
+        return Err(eyre::eyre!("Pass one --seed per --input, {} seeds for {} inputs", args.seeds.len(), args.inputs.len()));
+    }
+    let graphs = args.inputs.iter().map(|input| deserialize_graph(input).map(|(graph, _)| graph)).collect::<Result<Vec<G>>>()?;
+    let all_seeds: Vec<String> = args.seeds.iter().map(|seed| seed.to_lowercase()).collect();
+
+    let sides = if comparing_seeds_in_one_graph {
+        all_seeds.iter().map(|seed| Side::new(seed.clone(), &graphs[0], Some(seed), args.hops, &all_seeds)).collect::<Result<Vec<Side>>>()?
+    } else {
+        args.inputs
+            .iter()
+            .zip(graphs.iter())
+            .enumerate()
+            .map(|(i, (input, graph))| Side::new(input.clone(), graph, all_seeds.get(i).map(String::as_str), args.hops, &all_seeds))
+            .collect::<Result<Vec<Side>>>()?
+    };
+
+    let (report, shared_by_pair) = comparison_report(&sides);
+    print!("{}", report);
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    write_atomically(&file_pathname, report.as_bytes())?;
+    println!("\nSaved comparison report as {}", &file_pathname);
+
+    if let Some(shared_output) = &args.shared_output {
+        let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, shared_output);
+        let mut writer = csv_writer(&file_pathname)?;
+        writer.write_record(["left", "right", "address"])?;
+        for (i, j, shared) in shared_by_pair.iter() {
+            for address in shared.iter() {
+                writer.write_record([sides[*i].label.as_str(), sides[*j].label.as_str(), address.as_str()])?;
+            }
+        }
+        commit_csv(writer)?;
+        println!("Saved shared addresses as {}", &file_pathname);
+    }
+    Ok(())
+}
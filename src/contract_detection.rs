@@ -0,0 +1,103 @@
+use eyre::Result;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use tokio::runtime::Runtime;
+
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::DetectContractsArgs;
+use crate::http_client;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER};
+
+#[derive(Serialize)]
+struct GetCodeCall<'a> {
+    jsonrpc: &'static str,
+    id: usize,
+    method: &'static str,
+    params: (&'a str, &'static str),
+}
+
+#[derive(Deserialize)]
+struct GetCodeReply {
+    id: usize,
+    result: Option<String>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct AddressKind<'a> {
+    address: &'a str,
+    is_contract: Option<bool>,
+}
+
+// One JSON-RPC batch of eth_getCode calls. Addresses whose call failed are left out of the result.
+async fn classify_batch(addresses: &[String], client: &Client, rpc_url: &str) -> Result<HashMap<String, bool>> {
+    let calls: Vec<GetCodeCall> = addresses
+        .iter()
+        .enumerate()
+        .map(|(id, address)| GetCodeCall { jsonrpc: "2.0", id, method: "eth_getCode", params: (address, "latest") })
+        .collect();
+    let response = client
+        .post(rpc_url)
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_vec(&calls)?)
+        .send()
+        .await?;
+    let body_bytes = http_client::body_bytes(response).await?;
+    let replies: Vec<GetCodeReply> = serde_json::from_slice(&body_bytes)
+        .map_err(|_| eyre::eyre!("Failed to decode batch response: {}", String::from_utf8_lossy(&body_bytes)))?;
+
+    let mut kinds = HashMap::new();
+    for reply in replies {
+        match (reply.result, addresses.get(reply.id)) {
+            (Some(code), Some(address)) => {
+                kinds.insert(address.clone(), code != "0x");
+            }
+            (None, Some(address)) => println!("eth_getCode failed for {}: {:?}", address, reply.error),
+            _ => {}
+        }
+    }
+    Ok(kinds)
+}
+
+pub async fn classify_addresses(addresses: &[String], rpc_url: &str, batch_size: usize) -> (HashMap<String, bool>, usize) {
+    let client = http_client::client();
+    let mut kinds = HashMap::new();
+    let mut requests = 0;
+    for batch in addresses.chunks(batch_size.max(1)) {
+        requests += 1;
+        match classify_batch(batch, &client, rpc_url).await {
+            Ok(batch_kinds) => kinds.extend(batch_kinds),
+            Err(e) => println!("Batch request {} failed:\n{}", requests, e),
+        }
+        println!("Contract detection progress is {} / {} addresses", (requests * batch_size).min(addresses.len()), addresses.len());
+    }
+    (kinds, requests)
+}
+
+pub fn run_detect_contracts_command(args: &DetectContractsArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let mut addresses: Vec<String> = graph.node_weights().cloned().collect();
+    addresses.sort();
+    addresses.dedup();
+
+    let (kinds, requests) = Runtime::new()?.block_on(classify_addresses(&addresses, &args.rpc_url, args.batch_size));
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for address in addresses.iter() {
+        writer.serialize(AddressKind { address, is_contract: kinds.get(address).copied() })?;
+    }
+    commit_csv(writer)?;
+    println!(
+        "Classified {} / {} addresses in {} requests: {} contracts, saved as {}",
+        kinds.len(),
+        addresses.len(),
+        requests,
+        kinds.values().filter(|&&is_contract| is_contract).count(),
+        &file_pathname
+    );
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::{Directed, Graph};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+use crate::money::Amount;
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::ContractInteractionsArgs;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ContractInteraction {
+    pub call_count: usize,
+    pub usd_value_sent: f64,
+    pub first_call: u64,
+    pub last_call: u64,
+}
+
+pub type InteractionGraph = Graph<String, ContractInteraction, Directed>;
+
+// Transaction-level edges (sender -> called address, with calldata) aggregated per sender/contract pair.
+// Token amounts moved by a call are attributed through the digested edge carrying the same hash.
+pub fn contract_interaction_projection(graph: &G) -> InteractionGraph {
+    let mut usd_by_hash: HashMap<&str, f64> = HashMap::new();
+    for transaction in graph.edge_weights() {
+        if let Some(data) = &transaction.data {
+            *usd_by_hash.entry(transaction.hash.as_str()).or_insert(0.0) += data.usd_value;
+        }
+    }
+
+    let mut interactions: BTreeMap<(NodeIndex, NodeIndex), ContractInteraction> = BTreeMap::new();
+    for edge in graph.edge_references() {
+        let transaction = edge.weight();
+        if transaction.data.is_some() || !transaction.contract_call {
+            continue;
+        }
+        let interaction = interactions.entry((edge.source(), edge.target())).or_insert(ContractInteraction {
+            first_call: transaction.timestamp,
+            last_call: transaction.timestamp,
+            ..Default::default()
+        });
+        interaction.call_count += 1;
+        interaction.usd_value_sent += usd_by_hash.get(transaction.hash.as_str()).copied().unwrap_or_default();
+        interaction.first_call = interaction.first_call.min(transaction.timestamp);
+        interaction.last_call = interaction.last_call.max(transaction.timestamp);
+    }
+
+    let mut projection = InteractionGraph::new();
+    let mut node_indices: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+    for ((eoa, contract), interaction) in interactions {
+        let eoa_node = *node_indices.entry(eoa).or_insert_with(|| projection.add_node(graph[eoa].clone()));
+        let contract_node = *node_indices.entry(contract).or_insert_with(|| projection.add_node(graph[contract].clone()));
+        projection.add_edge(eoa_node, contract_node, interaction);
+    }
+    projection
+}
+
+pub fn contract_usage_summary(projection: &InteractionGraph, top: usize) -> String {
+    let mut contracts: Vec<(NodeIndex, usize, usize, f64)> = projection
+        .node_indices()
+        .filter(|&n| projection.edges_directed(n, petgraph::Direction::Incoming).next().is_some())
+        .map(|n| {
+            let incoming: Vec<_> = projection.edges_directed(n, petgraph::Direction::Incoming).collect();
+            let calls = incoming.iter().map(|e| e.weight().call_count).sum();
+            let value = incoming.iter().fold(0.0, |acc, e| acc + e.weight().usd_value_sent);
+            (n, incoming.len(), calls, value)
+        })
+        .collect();
+    contracts.sort_by_key(|c| std::cmp::Reverse(c.2));
+
+    let mut summary = format!(
+        "Contract interaction graph: {} callers and contracts, {} caller-contract pairs\nTop contracts by call count:\n",
+        projection.node_count(),
+        projection.edge_count()
+    );
+    for (contract, callers, calls, value) in contracts.into_iter().take(top) {
+        summary.push_str(&format!(
+            "{}: {} calls from {} callers, {} sent\n",
+            projection[contract], calls, callers, Amount::Usd(value)
+        ));
+    }
+    summary
+}
+
+pub fn run_contract_interactions_command(args: &ContractInteractionsArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let projection = contract_interaction_projection(&graph);
+    print!("{}", contract_usage_summary(&projection, args.top));
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    writer.write_record(["caller", "contract", "call_count", "usd_value_sent", "first_call", "last_call"])?;
+    for edge in projection.edge_references() {
+        let interaction = edge.weight();
+        writer.write_record([
+            projection[edge.source()].clone(),
+            projection[edge.target()].clone(),
+            interaction.call_count.to_string(),
+            interaction.usd_value_sent.to_string(),
+            interaction.first_call.to_string(),
+            interaction.last_call.to_string(),
+        ])?;
+    }
+    commit_csv(writer)?;
+    println!("Saved contract interactions as {}", &file_pathname);
+    Ok(())
+}
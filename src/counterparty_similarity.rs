@@ -0,0 +1,128 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::SimilarityArgs;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+// USD volume exchanged with each counterparty in either direction. Counterparties reached only by
+// unpriced edges are kept with zero volume.
+pub fn counterparty_volumes(graph: &G, node: NodeIndex) -> HashMap<NodeIndex, f64> {
+    let mut volumes: HashMap<NodeIndex, f64> = HashMap::new();
+    for direction in [Direction::Outgoing, Direction::Incoming] {
+        for edge in graph.edges_directed(node, direction) {
+            let counterparty = if direction == Direction::Outgoing { edge.target() } else { edge.source() };
+            if counterparty != node {
+                *volumes.entry(counterparty).or_default() += edge.weight().data.as_ref().map_or(0.0, |d| d.usd_value);
+            }
+        }
+    }
+    volumes
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PairSimilarity {
+    pub address_a: String,
+    pub address_b: String,
+    pub common_counterparties: usize,
+    pub jaccard: f64,
+    // Weighted Jaccard over counterparty volumes: sum of minima over sum of maxima.
+    pub volume_similarity: f64,
+    pub direct_transactions: usize,
+}
+
+// The pair itself is excluded from both counterparty sets, so transacting directly does not raise the score.
+pub fn pair_similarity(graph: &G, a: NodeIndex, b: NodeIndex, volumes_a: &HashMap<NodeIndex, f64>, volumes_b: &HashMap<NodeIndex, f64>) -> PairSimilarity {
+    let (mut common, mut union, mut minimum_sum, mut maximum_sum) = (0, 0, 0.0, 0.0);
+    for (counterparty, &volume_a) in volumes_a.iter().filter(|(&c, _)| c != b) {
+        union += 1;
+        match volumes_b.get(counterparty) {
+            Some(&volume_b) => {
+                common += 1;
+                minimum_sum += volume_a.min(volume_b);
+                maximum_sum += volume_a.max(volume_b);
+            }
+            None => maximum_sum += volume_a,
+        }
+    }
+    for (_, &volume_b) in volumes_b.iter().filter(|(&c, _)| c != a && !volumes_a.contains_key(&c)) {
+        union += 1;
+        maximum_sum += volume_b;
+    }
+    PairSimilarity {
+        address_a: graph[a].clone(),
+        address_b: graph[b].clone(),
+        common_counterparties: common,
+        jaccard: if union == 0 { 0.0 } else { common as f64 / union as f64 },
+        volume_similarity: if maximum_sum > 0.0 { minimum_sum / maximum_sum } else { 0.0 },
+        direct_transactions: graph.edges_connecting(a, b).count() + graph.edges_connecting(b, a).count(),
+    }
+}
+
+// Pairs of targets with at least one common counterparty, most similar by volume first.
+pub fn similar_pairs(graph: &G, targets: &[NodeIndex]) -> Vec<PairSimilarity> {
+    let volumes: Vec<HashMap<NodeIndex, f64>> = targets.iter().map(|&t| counterparty_volumes(graph, t)).collect();
+    let mut pairs = Vec::new();
+    for i in 0..targets.len() {
+        for j in i + 1..targets.len() {
+            let pair = pair_similarity(graph, targets[i], targets[j], &volumes[i], &volumes[j]);
+            if pair.common_counterparties > 0 {
+                pairs.push(pair);
+            }
+        }
+    }
+    pairs.sort_by(|x, y| {
+        y.volume_similarity
+            .partial_cmp(&x.volume_similarity)
+            .unwrap()
+            .then(y.common_counterparties.cmp(&x.common_counterparties))
+            .then_with(|| (&x.address_a, &x.address_b).cmp(&(&y.address_a, &y.address_b)))
+    });
+    pairs
+}
+
+fn targets(graph: &G, addresses: &[String], top: usize) -> Result<Vec<NodeIndex>> {
+    if addresses.is_empty() {
+        let mut nodes: Vec<NodeIndex> = graph.node_indices().collect();
+        nodes.sort_by_key(|&n| (std::cmp::Reverse(counterparty_volumes(graph, n).len()), graph[n].clone()));
+        nodes.truncate(top);
+        return Ok(nodes);
+    }
+    addresses
+        .iter()
+        .map(|address| {
+            let address = address.to_lowercase();
+            graph.node_indices().find(|&n| graph[n] == address).ok_or_else(|| eyre::eyre!("Address {} is not present in the graph", address))
+        })
+        .collect()
+}
+
+pub fn run_similarity_command(args: &SimilarityArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let targets = targets(&graph, &args.targets, args.top)?;
+    let pairs = similar_pairs(&graph, &targets);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for pair in pairs.iter() {
+        writer.serialize(pair)?;
+    }
+    commit_csv(writer)?;
+    println!("Saved {} pairs of {} targets with common counterparties as {}\n", pairs.len(), targets.len(), &file_pathname);
+
+    let indirect: Vec<&PairSimilarity> = pairs.iter().filter(|p| p.direct_transactions == 0).collect();
+    println!("Most similar pairs that never transact directly:");
+    for pair in indirect.iter().take(args.show) {
+        println!(
+            "  {} ~ {}: {} common counterparties, Jaccard {:.3}, volume similarity {:.3}",
+            pair.address_a, pair.address_b, pair.common_counterparties, pair.jaccard, pair.volume_similarity
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use eyre::Result;
+use petgraph::dot::{Config, Dot};
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::atomic_file::{commit_csv, csv_writer, write_atomically};
+use crate::cli::CoverageArgs;
+use crate::delta_crawl::{load_crawl_state, CrawlState};
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+// How much of an address's own history the crawl fetched. The edges of unexpanded addresses are only
+// those seen from expanded neighbours, so a sparse neighbourhood there says nothing about the address.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CrawlCoverage {
+    Expanded,
+    Partial,
+    Unexpanded,
+}
+
+impl CrawlCoverage {
+    fn color(self) -> &'static str {
+        match self {
+            CrawlCoverage::Expanded => "#66bb6a",
+            CrawlCoverage::Partial => "#ffa726",
+            CrawlCoverage::Unexpanded => "#bdbdbd",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct CoverageRow {
+    pub address: String,
+    pub coverage: CrawlCoverage,
+    // Relevance counter of addresses still queued for expansion when the crawl stopped.
+    pub queued_relevance: Option<i32>,
+    pub degree: usize,
+    // From the transaction count precheck, where it ran.
+    pub sent_transactions: Option<u64>,
+    pub oversized: bool,
+}
+
+pub fn coverage_map(graph: &G, crawl_state: &CrawlState) -> Vec<CoverageRow> {
+    let queued: HashMap<&String, i32> = crawl_state.frontier.iter().map(|(address, relevance)| (address, *relevance)).collect();
+    let mut rows: Vec<CoverageRow> = graph
+        .node_indices()
+        .map(|n| {
+            let address = &graph[n];
+            let coverage = if crawl_state.partially_fetched.contains(address) {
+                CrawlCoverage::Partial
+            } else if crawl_state.last_fetched_blocks.contains_key(address) {
+                CrawlCoverage::Expanded
+            } else {
+                CrawlCoverage::Unexpanded
+            };
+            CoverageRow {
+                address: address.clone(),
+                coverage,
+                queued_relevance: queued.get(address).copied(),
+                degree: graph.edges_directed(n, petgraph::Direction::Outgoing).count()
+                    + graph.edges_directed(n, petgraph::Direction::Incoming).count(),
+                sent_transactions: crawl_state.transaction_counts.get(address).copied(),
+                oversized: crawl_state.oversized_addresses.contains(address),
+            }
+        })
+        .collect();
+    rows.sort_by(|x, y| x.coverage.cmp(&y.coverage).then(y.degree.cmp(&x.degree)).then(x.address.cmp(&y.address)));
+    rows
+}
+
+pub fn coverage_summary(rows: &[CoverageRow]) -> String {
+    let count = |coverage: CrawlCoverage| rows.iter().filter(|r| r.coverage == coverage).count();
+    let queued = rows.iter().filter(|r| r.queued_relevance.is_some()).count();
+    let oversized = rows.iter().filter(|r| r.oversized).count();
+    format!(
+        "Expanded: {}, partially fetched: {}, never queried: {} ({} of them still queued) of {} addresses, {} above the sent transaction limit\n",
+        count(CrawlCoverage::Expanded),
+        count(CrawlCoverage::Partial),
+        count(CrawlCoverage::Unexpanded),
+        queued,
+        rows.len(),
+        oversized
+    )
+}
+
+pub fn coverage_dot(graph: &G, coverage: &HashMap<&str, CrawlCoverage>) -> String {
+    let edge_attributes = |_, _| String::new();
+    let node_attributes = |_, (_, address): (_, &String)| {
+        let color = coverage.get(address.as_str()).map_or(CrawlCoverage::Unexpanded.color(), |c| c.color());
+        format!("label = \"{}\" style = filled fillcolor = \"{}\"", address, color)
+    };
+    format!("{:?}", Dot::with_attr_getters(graph, &[Config::NodeNoLabel, Config::EdgeNoLabel], &edge_attributes, &node_attributes))
+}
+
+// A self-contained page drawn with vis-network, nodes coloured like the DOT export.
+pub fn coverage_html(graph: &G, coverage: &HashMap<&str, CrawlCoverage>, summary: &str) -> Result<String> {
+    let nodes: Vec<_> = graph
+        .node_indices()
+        .map(|n| {
+            let status = coverage.get(graph[n].as_str()).copied().unwrap_or(CrawlCoverage::Unexpanded);
+            json!({"id": n.index(), "title": format!("{} ({:?})", graph[n], status), "color": status.color()})
+        })
+        .collect();
+    let edges: Vec<_> = graph.edge_references().map(|e| json!({"from": e.source().index(), "to": e.target().index()})).collect();
+    let legend: String = [CrawlCoverage::Expanded, CrawlCoverage::Partial, CrawlCoverage::Unexpanded]
+        .iter()
+        .map(|c| format!("<span style=\"background:{}\">&nbsp;&nbsp;&nbsp;</span> {:?} ", c.color(), c))
+        .collect();
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Crawl coverage</title>
+<script src="https://unpkg.com/vis-network/standalone/umd/vis-network.min.js"></script>
+</head>
+<body>
+<p>{}</p>
+<p>{}</p>
+<div id="graph" style="width: 100%; height: 90vh;"></div>
+<script>
+new vis.Network(document.getElementById("graph"), {{nodes: {}, edges: {}}}, {{nodes: {{shape: "dot", size: 8}}, edges: {{arrows: "to"}}, physics: {{stabilization: true}}}});
+</script>
+</body>
+</html>
+"#,
+        summary.trim_end(),
+        legend,
+        serde_json::to_string(&nodes)?,
+        serde_json::to_string(&edges)?
+    ))
+}
+
+pub fn run_coverage_command(args: &CoverageArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let crawl_state = load_crawl_state()?;
+    let rows = coverage_map(&graph, &crawl_state);
+    let summary = coverage_summary(&rows);
+    print!("{}", summary);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let prefix = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&format!("{}.csv", prefix))?;
+    for row in rows.iter() {
+        writer.serialize(row)?;
+    }
+    commit_csv(writer)?;
+    let coverage: HashMap<&str, CrawlCoverage> = rows.iter().map(|r| (r.address.as_str(), r.coverage)).collect();
+    write_atomically(&format!("{}.dot", prefix), coverage_dot(&graph, &coverage).as_bytes())?;
+    write_atomically(&format!("{}.html", prefix), coverage_html(&graph, &coverage, &summary)?.as_bytes())?;
+    println!("Saved coverage map as {}.csv, .dot and .html", prefix);
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use eyre::Result;
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::throttle::{RequestStats, Throttle};
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CrawlStatus {
+    pub stage: &'static str,
+    pub frontier: usize,
+    pub visited: usize,
+    pub edges: usize,
+    pub checkpoints: usize,
+    pub requests: RequestStats,
+    pub stop_requested: bool,
+}
+
+// Shared by a running crawl and its control endpoint. The crawl publishes its status and picks up the
+// commands between addresses, so a command takes effect once the history in flight is fetched.
+#[derive(Default, Debug)]
+pub struct CrawlMonitor {
+    status: Mutex<CrawlStatus>,
+    checkpoint_requested: AtomicBool,
+    concurrency_limit: Mutex<Option<usize>>,
+    stop_requested: AtomicBool,
+}
+
+impl CrawlMonitor {
+    // Publishes the crawl's progress and applies a requested concurrency limit.
+    pub fn publish(&self, stage: &'static str, frontier: usize, visited: usize, edges: usize, throttle: &Throttle) {
+        if let Some(limit) = self.concurrency_limit.lock().unwrap().take() {
+            throttle.limit_concurrency(limit);
+            println!("Limited the requests in flight to at most {}", limit);
+        }
+        let mut status = self.status.lock().unwrap();
+        *status = CrawlStatus { stage, frontier, visited, edges, checkpoints: status.checkpoints, requests: throttle.request_stats(), stop_requested: false };
+    }
+
+    // Whether a checkpoint was requested since the last call. The crawl then writes its graph and state as
+    // save_crawl does at the end, so a crash or kill loses nothing before it.
+    pub fn take_checkpoint_request(&self) -> bool {
+        let requested = self.checkpoint_requested.swap(false, Ordering::SeqCst);
+        if requested {
+            self.status.lock().unwrap().checkpoints += 1;
+        }
+        requested
+    }
+
+    // The crawl stops as if its transaction budget were reached: it saves what it has and runs the analyses.
+    pub fn stop_requested(&self) -> bool {
+        self.stop_requested.load(Ordering::SeqCst)
+    }
+
+    fn status(&self) -> CrawlStatus {
+        let mut status = self.status.lock().unwrap().clone();
+        if status.stage.is_empty() {
+            status.stage = "starting";
+        }
+        status.stop_requested = self.stop_requested();
+        status
+    }
+}
+
+fn respond(stream: &mut TcpStream, status_line: &str, body: &str) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status_line,
+        body.len(),
+        body
+    )
+}
+
+fn handle(mut stream: TcpStream, monitor: &CrawlMonitor) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Headers and any body are ignored, commands take no parameters beyond the path.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let accepted = |command: &str| serde_json::json!({ "accepted": command }).to_string();
+    match (method, path.trim_end_matches('/')) {
+        ("GET", "/status") => respond(&mut stream, "200 OK", &serde_json::to_string(&monitor.status()).unwrap()),
+        ("POST", "/checkpoint") => {
+            monitor.checkpoint_requested.store(true, Ordering::SeqCst);
+            respond(&mut stream, "202 Accepted", &accepted("checkpoint"))
+        }
+        ("POST", "/stop") => {
+            monitor.stop_requested.store(true, Ordering::SeqCst);
+            respond(&mut stream, "202 Accepted", &accepted("stop"))
+        }
+        ("POST", path) if path.starts_with("/concurrency/") => match path["/concurrency/".len()..].parse::<usize>() {
+            Ok(limit) if limit > 0 => {
+                *monitor.concurrency_limit.lock().unwrap() = Some(limit);
+                respond(&mut stream, "202 Accepted", &accepted("concurrency"))
+            }
+            _ => respond(&mut stream, "400 Bad Request", r#"{"error":"the concurrency limit must be a positive integer"}"#),
+        },
+        _ => respond(
+            &mut stream,
+            "404 Not Found",
+            r#"{"error":"use GET /status, POST /checkpoint, POST /concurrency/<n> or POST /stop"}"#,
+        ),
+    }
+}
+
+// Serves the monitor over plain HTTP on a loopback address, one connection at a time on a thread of its
+// own, until the process exits. Nothing is authenticated, so other hosts are refused.
+pub fn serve(address: &str, monitor: Arc<CrawlMonitor>) -> Result<()> {
+    let socket_address: SocketAddr = address.parse().map_err(|_| eyre::eyre!("Expected the control address as ip:port, got {}", address))?;
+    if !socket_address.ip().is_loopback() {
+        return Err(eyre::eyre!("The control endpoint only listens on loopback addresses, got {}", socket_address));
+    }
+    let listener = TcpListener::bind(socket_address)?;
+    println!("Crawl control listening on http://{}", listener.local_addr()?);
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = handle(stream, &monitor) {
+                println!("Crawl control request failed: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
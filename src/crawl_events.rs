@@ -0,0 +1,252 @@
+use eyre::Result;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::sync::mpsc::Sender;
+
+use crate::{Transaction, DATA_STORAGE_FOLDER};
+#[cfg(feature = "crawl")]
+use crate::{
+    delta_crawl::{CrawlState, CRAWL_STATE_FILENAME, PARSED_GRAPH_FILENAME},
+    G,
+};
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "event")]
+pub enum CrawlEvent {
+    EdgeAdded { from: String, to: String, transaction: Box<Transaction> },
+    AddressExpanded { address: String, fetched_transactions: usize, new_edges: usize, requests: usize },
+    CheckpointWritten { graph_pathname: String, state_pathname: String, addresses: usize, edges: usize },
+}
+
+impl CrawlEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            CrawlEvent::EdgeAdded { .. } => "EdgeAdded",
+            CrawlEvent::AddressExpanded { .. } => "AddressExpanded",
+            CrawlEvent::CheckpointWritten { .. } => "CheckpointWritten",
+        }
+    }
+}
+
+#[cfg(feature = "crawl")]
+pub(crate) fn checkpoint_written(graph: &G, crawl_state: &CrawlState) -> CrawlEvent {
+    CrawlEvent::CheckpointWritten {
+        graph_pathname: format!("{}/{}", DATA_STORAGE_FOLDER, PARSED_GRAPH_FILENAME),
+        state_pathname: format!("{}/{}", DATA_STORAGE_FOLDER, CRAWL_STATE_FILENAME),
+        addresses: crawl_state.last_fetched_blocks.len(),
+        edges: graph.edge_count(),
+    }
+}
+
+// Sinks move with the crawl future, which may be spawned onto another thread.
+pub trait Sink: Send {
+    fn send(&mut self, event: &CrawlEvent) -> Result<()>;
+
+    // Called once after the crawl, for sinks that buffer.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub struct StdoutSink;
+
+impl Sink for StdoutSink {
+    fn send(&mut self, event: &CrawlEvent) -> Result<()> {
+        println!("{}", serde_json::to_string(event)?);
+        Ok(())
+    }
+}
+
+// One JSON object per line, appended to the file.
+pub struct JsonlSink {
+    writer: BufWriter<File>,
+}
+
+impl JsonlSink {
+    pub fn create(file_pathname: &str) -> Result<JsonlSink> {
+        let file = File::options().create(true).append(true).open(file_pathname)?;
+        Ok(JsonlSink { writer: BufWriter::new(file) })
+    }
+}
+
+impl Sink for JsonlSink {
+    fn send(&mut self, event: &CrawlEvent) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, event)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+// Hands events to another thread of an embedding program.
+pub struct ChannelSink {
+    sender: Sender<CrawlEvent>,
+}
+
+impl ChannelSink {
+    pub fn new(sender: Sender<CrawlEvent>) -> ChannelSink {
+        ChannelSink { sender }
+    }
+}
+
+impl Sink for ChannelSink {
+    fn send(&mut self, event: &CrawlEvent) -> Result<()> {
+        self.sender.send(event.clone()).map_err(|_| eyre::eyre!("The receiving end of the event channel was dropped"))
+    }
+}
+
+// Events go to a single table with the event name and its JSON encoding, written in one transaction on finish.
+#[cfg(feature = "sqlite")]
+pub struct SqliteSink {
+    connection: rusqlite::Connection,
+    pending: Vec<(&'static str, String)>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteSink {
+    pub fn open(file_pathname: &str) -> Result<SqliteSink> {
+        let connection = rusqlite::Connection::open(file_pathname)?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS crawl_events (id INTEGER PRIMARY KEY, event TEXT NOT NULL, payload TEXT NOT NULL)",
+            (),
+        )?;
+        Ok(SqliteSink { connection, pending: Vec::new() })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Sink for SqliteSink {
+    fn send(&mut self, event: &CrawlEvent) -> Result<()> {
+        self.pending.push((event.name(), serde_json::to_string(event)?));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut statement = transaction.prepare("INSERT INTO crawl_events (event, payload) VALUES (?1, ?2)")?;
+            for (event, payload) in self.pending.drain(..) {
+                statement.execute((event, payload))?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+// Publishes each new edge as JSON to a NATS subject. The client runs on its own thread and runtime, so
+// sending never blocks the crawl and works whether or not the caller is inside a tokio runtime.
+#[cfg(feature = "nats")]
+pub struct NatsSink {
+    sender: Option<Sender<Vec<u8>>>,
+    publisher: Option<std::thread::JoinHandle<Result<()>>>,
+}
+
+#[cfg(feature = "nats")]
+impl NatsSink {
+    pub fn connect(server: &str, subject: &str) -> Result<NatsSink> {
+        let (sender, receiver) = std::sync::mpsc::channel::<Vec<u8>>();
+        let (server, subject) = (server.to_string(), subject.to_string());
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let client = runtime.block_on(async_nats::connect(&server))?;
+        println!("Publishing crawl edges to {} on subject {}", &server, &subject);
+        let publisher = std::thread::spawn(move || {
+            runtime.block_on(async {
+                for payload in receiver {
+                    client.publish(subject.clone(), payload.into()).await?;
+                }
+                client.flush().await?;
+                Ok(())
+            })
+        });
+        Ok(NatsSink { sender: Some(sender), publisher: Some(publisher) })
+    }
+}
+
+#[cfg(feature = "nats")]
+impl Sink for NatsSink {
+    fn send(&mut self, event: &CrawlEvent) -> Result<()> {
+        if let (CrawlEvent::EdgeAdded { .. }, Some(sender)) = (event, &self.sender) {
+            sender.send(serde_json::to_vec(event)?).map_err(|_| eyre::eyre!("The NATS publisher thread stopped"))?;
+        }
+        Ok(())
+    }
+
+    // Closing the channel lets the publisher drain the queue and flush before the thread ends.
+    fn finish(&mut self) -> Result<()> {
+        self.sender.take();
+        match self.publisher.take() {
+            Some(publisher) => publisher.join().map_err(|_| eyre::eyre!("The NATS publisher thread panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CrawlSinks {
+    sinks: Vec<Box<dyn Sink>>,
+}
+
+impl CrawlSinks {
+    // Specs are `stdout`, `jsonl:<file>`, `sqlite:<file>` or `nats:<host:port>/<subject>`, with files relative
+    // to the data storage folder.
+    pub fn from_specs(specs: &[String]) -> Result<CrawlSinks> {
+        let mut sinks = CrawlSinks::default();
+        for spec in specs {
+            let (kind, file_name) = spec.split_once(':').unwrap_or((spec.as_str(), ""));
+            #[cfg(feature = "nats")]
+            if kind == "nats" {
+                let (server, subject) = file_name
+                    .split_once('/')
+                    .ok_or_else(|| eyre::eyre!("Expected nats:<host:port>/<subject>, got {}", spec))?;
+                sinks.add(Box::new(NatsSink::connect(server, subject)?));
+                continue;
+            }
+            let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, file_name);
+            if !file_name.is_empty() {
+                fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+            }
+            match (kind, file_name.is_empty()) {
+                ("stdout", true) => sinks.add(Box::new(StdoutSink)),
+                ("jsonl", false) => sinks.add(Box::new(JsonlSink::create(&file_pathname)?)),
+                #[cfg(feature = "sqlite")]
+                ("sqlite", false) => sinks.add(Box::new(SqliteSink::open(&file_pathname)?)),
+                _ => return Err(eyre::eyre!("Unknown event sink {}, expected stdout, jsonl:<file>, sqlite:<file> or nats:<host:port>/<subject>", spec)),
+            }
+        }
+        Ok(sinks)
+    }
+
+    pub fn add(&mut self, sink: Box<dyn Sink>) {
+        self.sinks.push(sink);
+    }
+
+    pub fn into_sinks(self) -> Vec<Box<dyn Sink>> {
+        self.sinks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    // A failing sink is reported but does not stop the crawl.
+    pub fn emit(&mut self, event: CrawlEvent) {
+        for sink in self.sinks.iter_mut() {
+            if let Err(e) = sink.send(&event) {
+                println!("Event sink failed on {}: {}", event.name(), e);
+            }
+        }
+    }
+
+    pub fn finish(&mut self) {
+        for sink in self.sinks.iter_mut() {
+            if let Err(e) = sink.finish() {
+                println!("Event sink failed to finish: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use chrono::TimeZone;
+use clap::ValueEnum;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::runtime::Runtime;
+
+use crate::atomic_file::write_atomically;
+use crate::cli::DaemonArgs;
+use crate::crawl_events::{checkpoint_written, CrawlSinks};
+use crate::drift_monitor::record_run;
+use crate::delta_crawl::{self, CrawlState, CRAWL_STATE_FILENAME, PARSED_GRAPH_FILENAME};
+use crate::notifier::{notify_all, Notifier};
+use crate::period_summary::{period_summary, period_summary_report};
+use crate::task_control::TaskControl;
+use crate::time_buckets::{bucket_start, local_label, parse_timezone};
+use crate::{deserialize_graph, parse_blockchain, read_api_key, DATA_STORAGE_FOLDER, G, TRAVERSAL_STARTING_ADDRESS};
+
+const DAEMON_STATE_FILENAME: &str = "daemon_state.json";
+// The graph as it was when the current period started, which the period's summary is compared against.
+const PERIOD_SNAPSHOT_FILENAME: &str = "period_snapshot.json";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DaemonState {
+    period_start: u64,
+}
+
+fn load_daemon_state() -> Option<DaemonState> {
+    let file = File::open(format!("{}/{}", DATA_STORAGE_FOLDER, DAEMON_STATE_FILENAME)).ok()?;
+    serde_json::from_reader(std::io::BufReader::new(file)).ok()
+}
+
+fn save_daemon_state(state: &DaemonState) -> Result<()> {
+    write_atomically(&format!("{}/{}", DATA_STORAGE_FOLDER, DAEMON_STATE_FILENAME), &serde_json::to_vec_pretty(state)?)
+}
+
+fn take_period_snapshot() -> Result<()> {
+    let graph = fs::read(format!("{}/{}", DATA_STORAGE_FOLDER, PARSED_GRAPH_FILENAME))?;
+    write_atomically(&format!("{}/{}", DATA_STORAGE_FOLDER, PERIOD_SNAPSHOT_FILENAME), &graph)
+}
+
+// Extends the saved crawl with what happened since it, or crawls from the default starting address when
+// nothing was crawled yet.
+async fn crawl_once(api_key: &str, sinks: &mut CrawlSinks) -> Result<G> {
+    let control = TaskControl::default();
+    let (graph, crawl_state) = if Path::new(&format!("{}/{}", DATA_STORAGE_FOLDER, CRAWL_STATE_FILENAME)).exists() {
+        delta_crawl::parse_blockchain_since_last_run(api_key, sinks, &control).await?
+    } else {
+        let seeds = vec![TRAVERSAL_STARTING_ADDRESS.to_lowercase()];
+        let initial_state = CrawlState { seeds: seeds.clone(), ..Default::default() };
+        parse_blockchain(&seeds, api_key, initial_state, sinks, &control).await
+    };
+    delta_crawl::save_crawl(&graph, &crawl_state)?;
+    sinks.emit(checkpoint_written(&graph, &crawl_state));
+    Ok(graph)
+}
+
+// Crawls since the last run every interval and records the drift of the graph's metrics, notifying when one
+// exceeds its threshold. When a period has ended, compares the crawled graph with the snapshot taken when the
+// period started, writes the summary into the data storage folder, sends it to the notifiers and starts the
+// next period from the graph as it is now. A failed crawl is reported and retried at the next interval.
+pub fn run_daemon_command(args: &DaemonArgs) -> Result<()> {
+    let tz = parse_timezone(&args.timezone)?;
+    let period_name = args.period.to_possible_value().unwrap().get_name().to_string();
+    let notifiers = Notifier::from_specs(&args.notifiers)?;
+    let mut sinks = CrawlSinks::from_specs(&args.sinks)?;
+    let api_key = read_api_key()?;
+    let rt = Runtime::new()?;
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let mut runs = 0;
+    loop {
+        match rt.block_on(crawl_once(&api_key, &mut sinks)) {
+            Err(e) => {
+                println!("Crawl failed, retrying in {} minutes: {}", args.interval_minutes, e);
+                rt.block_on(notify_all(&notifiers, &format!("ethparser daemon: crawl failed: {}\n", e)));
+            }
+            Ok(graph) => {
+                let (drift_report, alerts) = record_run(&graph, &args.drift)?;
+                print!("{}", drift_report);
+                if !alerts.is_empty() {
+                    rt.block_on(notify_all(&notifiers, &format!("ethparser daemon: graph drift\n{}", drift_report)));
+                }
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+                let current_period = bucket_start(now, args.period, &tz)?;
+                match load_daemon_state() {
+                    None => {
+                        take_period_snapshot()?;
+                        save_daemon_state(&DaemonState { period_start: current_period })?;
+                        println!("Summaries start with the period from {}", local_label(current_period, &tz)?);
+                    }
+                    Some(state) if state.period_start < current_period => {
+                        let (previous, _) = deserialize_graph(PERIOD_SNAPSHOT_FILENAME)?;
+                        let summary = period_summary(&previous, &graph, args.top);
+                        let title = format!("Summary of the {} from {} to {}", period_name, local_label(state.period_start, &tz)?, local_label(current_period, &tz)?);
+                        let report = period_summary_report(&summary, &title);
+                        let date = tz.timestamp_opt(state.period_start as i64, 0).unwrap().format("%Y-%m-%d");
+                        let file_pathname = format!("{}/summary_{}_{}.txt", DATA_STORAGE_FOLDER, period_name, date);
+                        write_atomically(&file_pathname, report.as_bytes())?;
+                        println!("Saved the period summary as {}", file_pathname);
+                        rt.block_on(notify_all(&notifiers, &report));
+                        take_period_snapshot()?;
+                        save_daemon_state(&DaemonState { period_start: current_period })?;
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        runs += 1;
+        if args.runs.is_some_and(|limit| runs >= limit) {
+            break;
+        }
+        std::thread::sleep(Duration::from_secs(args.interval_minutes * 60));
+    }
+    sinks.finish();
+    Ok(())
+}
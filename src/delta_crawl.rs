@@ -0,0 +1,234 @@
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+#[cfg(feature = "crawl")]
+use {core::cmp::max, priority_queue::PriorityQueue, std::cmp::Reverse, std::fs};
+
+use crate::edge_payload::EdgeSchema;
+use crate::failed_addresses::FailedAddresses;
+use crate::funding_source::FundingSource;
+use crate::history_window::HistoryWindow;
+use crate::record_validation::ValidationReport;
+use crate::skip_report::SkipReport;
+use crate::special_addresses::{SpecialAddressPolicies, SpecialAddressReport};
+use crate::tx_count_precheck::PrecheckPolicy;
+use crate::DATA_STORAGE_FOLDER;
+#[cfg(feature = "crawl")]
+use crate::{
+    atomic_file::AtomicFile, continue_traversal, crawl_events::CrawlSinks, deserialize_graph, graph_builder::GraphBuilder, graph_data_collection_procedure, http_client,
+    request_audit::{save_request_audit, AddressAudit}, request_metrics::RequestMetricsLog, serialize_graph, AddressPriority, FilteringResultInfo, G,
+    task_control::TaskControl, throttle::{eta_label, Throttle}, token_metadata::save_token_metadata, MAX_TRANSACTIONS_TO_PARSE,
+};
+
+pub const PARSED_GRAPH_FILENAME: &str = "parsed_transactions.json";
+pub const CRAWL_STATE_FILENAME: &str = "crawl_state.json";
+
+#[cfg(feature = "crawl")]
+pub struct FetchOutcome {
+    pub last_fetched_block: Option<u64>,
+    pub audit: AddressAudit,
+    pub funding_source: Option<FundingSource>,
+    pub incomplete: bool,
+    pub special_addresses: SpecialAddressReport,
+    pub validation: ValidationReport,
+    pub skipped: SkipReport,
+    pub failure: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct CrawlState {
+    pub last_fetched_blocks: HashMap<String, u64>,
+    // Addresses the first run started from, in the order given.
+    #[serde(default)]
+    pub seeds: Vec<String>,
+    // Queued addresses with their exact relevance counters, highest first, and visited addresses in visiting order.
+    #[serde(default)]
+    pub frontier: Vec<(String, i32)>,
+    #[serde(default)]
+    pub visited: Vec<String>,
+    // First funder of every address whose history was fetched from the first block.
+    #[serde(default)]
+    pub funding_sources: HashMap<String, FundingSource>,
+    // Visited addresses whose fetched history is known to miss records.
+    #[serde(default)]
+    pub partially_fetched: BTreeSet<String>,
+    // Sent transaction counts learned by the precheck, and the addresses whose count exceeded its limit.
+    #[serde(default)]
+    pub transaction_counts: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub oversized_addresses: BTreeSet<String>,
+    // Like the edge schema, the precheck policy of the first run carries over to resumed runs.
+    #[serde(default)]
+    pub precheck: Option<PrecheckPolicy>,
+    // Block range and order of the first run; delta runs fetch the part of it after the last fetched block.
+    #[serde(default)]
+    pub history_window: HistoryWindow,
+    // Edge schema chosen by the first run; resumed and delta runs keep it so the graph stays uniform.
+    #[serde(default)]
+    pub edge_schema: EdgeSchema,
+    #[serde(default)]
+    pub user_operations: bool,
+    #[serde(default)]
+    pub special_address_policies: SpecialAddressPolicies,
+    // Volume that touched genesis and burn addresses over all runs, whatever the policies did with it.
+    #[serde(default)]
+    pub special_addresses: SpecialAddressReport,
+    // Records left out of the graph over all runs because they failed checked parsing.
+    #[serde(default)]
+    pub validation: ValidationReport,
+    // Failed and contract-creation records the ingest filter left out over all runs, by hash.
+    #[serde(default)]
+    pub skipped: SkipReport,
+    // Addresses whose latest fetch gave up on a request, until retry-failed fetches them in full.
+    #[serde(default)]
+    pub failed: FailedAddresses,
+    // Request accounting is per run and written to its own report instead of the persisted state.
+    #[cfg(feature = "crawl")]
+    #[serde(skip)]
+    pub request_audit: Vec<AddressAudit>,
+}
+
+// How fetched records become edges, fixed by the first run of a crawl.
+#[cfg(feature = "crawl")]
+#[derive(Clone, Copy, Debug)]
+pub struct IngestOptions {
+    pub edge_schema: EdgeSchema,
+    pub user_operations: bool,
+    pub special_addresses: SpecialAddressPolicies,
+}
+
+#[cfg(feature = "crawl")]
+impl CrawlState {
+    pub fn ingest_options(&self) -> IngestOptions {
+        IngestOptions { edge_schema: self.edge_schema, user_operations: self.user_operations, special_addresses: self.special_address_policies }
+    }
+
+    pub fn record_fetch(&mut self, address: &str, outcome: FetchOutcome) {
+        // A failed fetch may have skipped older records, so the next delta run starts where the last full one did.
+        let entry = self.last_fetched_blocks.entry(address.to_string()).or_insert(0);
+        match &outcome.failure {
+            Some(error) => self.failed.record(address, error, outcome.audit.fetched_transactions),
+            None => {
+                if let Some(block) = outcome.last_fetched_block {
+                    *entry = max(*entry, block);
+                }
+                self.failed.resolve(address);
+            }
+        }
+        if let Some(funding_source) = outcome.funding_source {
+            self.funding_sources.entry(address.to_string()).or_insert(funding_source);
+        }
+        if outcome.incomplete {
+            self.partially_fetched.insert(address.to_string());
+        }
+        self.special_addresses.add(&outcome.special_addresses);
+        self.validation.add(&outcome.validation);
+        self.skipped.add(&outcome.skipped);
+        self.request_audit.push(outcome.audit);
+    }
+}
+
+#[cfg(feature = "crawl")]
+pub fn save_crawl(graph: &G, crawl_state: &CrawlState) -> Result<()> {
+    let info = FilteringResultInfo {
+        node_count: graph.raw_nodes().len(),
+        edge_count: graph.raw_edges().len(),
+        node_count_before_filtering: graph.raw_nodes().len(),
+        edge_count_before_filtering: graph.raw_edges().len(),
+    };
+    serialize_graph(graph, &info, PARSED_GRAPH_FILENAME)?;
+
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, CRAWL_STATE_FILENAME);
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let mut file = AtomicFile::create(&file_pathname)?;
+    serde_json::to_writer_pretty(&mut file, crawl_state)?;
+    file.commit()?;
+    println!("Saved crawl state for {} addresses as {}\n", crawl_state.last_fetched_blocks.len(), &file_pathname);
+    save_request_audit(&crawl_state.request_audit)?;
+    crawl_state.validation.save()?;
+    crawl_state.skipped.save()?;
+    crawl_state.failed.save()?;
+    save_token_metadata()?;
+    Ok(())
+}
+
+pub fn load_crawl_state() -> Result<CrawlState> {
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, CRAWL_STATE_FILENAME);
+    let file = File::open(&file_pathname)
+        .map_err(|_| eyre::eyre!("No previous run found at {}, run a full crawl first", &file_pathname))?;
+    Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+}
+
+#[cfg(feature = "crawl")]
+pub async fn resume_traversal(api_key: &str, sinks: &mut CrawlSinks, control: &TaskControl) -> Result<(G, CrawlState)> {
+    let (blockchain_graph, _) = deserialize_graph(PARSED_GRAPH_FILENAME)?;
+    let crawl_state = load_crawl_state()?;
+    if crawl_state.frontier.is_empty() {
+        return Err(eyre::eyre!("The previous run saved no traversal frontier, run a full crawl first"));
+    }
+    let mut priority_pq: PriorityQueue<String, AddressPriority> = PriorityQueue::new();
+    for (address, relevance) in crawl_state.frontier.iter() {
+        priority_pq.push(address.clone(), (*relevance, Reverse(address.clone())));
+    }
+    println!(
+        "Resuming traversal with {} visited and {} queued addresses",
+        crawl_state.visited.len(),
+        crawl_state.frontier.len()
+    );
+    let transaction_budget = blockchain_graph.edge_count() + MAX_TRANSACTIONS_TO_PARSE;
+    Ok(continue_traversal(blockchain_graph, priority_pq, crawl_state, transaction_budget, api_key, sinks, control).await)
+}
+
+#[cfg(feature = "crawl")]
+pub async fn parse_blockchain_since_last_run(api_key: &str, sinks: &mut CrawlSinks, control: &TaskControl) -> Result<(G, CrawlState)> {
+    let (blockchain_graph, _) = deserialize_graph(PARSED_GRAPH_FILENAME)?;
+    let mut crawl_state = load_crawl_state()?;
+    let client = http_client::client();
+    let throttle = Throttle::new(1).with_metrics_log(RequestMetricsLog::create()?);
+
+    let edge_count_before = blockchain_graph.edge_count();
+    let mut graph_builder = GraphBuilder::from_graph(blockchain_graph);
+    // Delta runs only revisit known addresses, so newly discovered priorities are discarded.
+    let mut unused_priority_pq: PriorityQueue<String, AddressPriority> = PriorityQueue::new();
+
+    let mut known_addresses: Vec<String> = crawl_state.last_fetched_blocks.keys().cloned().collect();
+    known_addresses.sort();
+
+    for (i, address) in known_addresses.iter().enumerate() {
+        // Addresses left out keep their last fetched block, so the next delta run catches up on them.
+        if control.is_cancelled() || throttle.is_aborted() || control.stop_requested() {
+            println!("Delta crawl stopped after {} / {} addresses", i, known_addresses.len());
+            break;
+        }
+        let window = crawl_state.history_window.starting_at(crawl_state.last_fetched_blocks[address] + 1);
+        let outcome = graph_data_collection_procedure(
+            &mut unused_priority_pq,
+            &mut graph_builder,
+            &client,
+            api_key,
+            &throttle,
+            address.clone(),
+            window,
+            crawl_state.ingest_options(),
+            sinks,
+        )
+        .await;
+        crawl_state.record_fetch(address, outcome);
+        println!("Delta crawl progress is {} / {} addresses, {}", i + 1, known_addresses.len(), eta_label(throttle.eta(i + 1, known_addresses.len())));
+        control.report("delta crawl", i + 1, Some(known_addresses.len()));
+        if let Some(monitor) = control.monitor() {
+            monitor.publish("delta crawl", known_addresses.len() - i - 1, i + 1, graph_builder.graph().edge_count(), &throttle);
+            if monitor.take_checkpoint_request() {
+                save_crawl(graph_builder.graph(), &crawl_state)?;
+            }
+        }
+    }
+
+    print!("{}", throttle.finish()?);
+    println!(
+        "Delta crawl added {} transactions to the stored graph",
+        graph_builder.graph().edge_count() - edge_count_before
+    );
+    Ok((graph_builder.into_graph(), crawl_state))
+}
@@ -0,0 +1,117 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde::Serialize;
+use std::fs;
+
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::DormancyArgs;
+use crate::time_buckets::time_label;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Reactivation {
+    pub address: String,
+    pub first_seen: u64,
+    pub last_seen: u64,
+    pub dormant_since: u64,
+    pub reactivated_at: u64,
+    pub dormant_days: f64,
+    pub reactivation_hash: String,
+    pub reactivation_counterparty: String,
+    pub reactivation_outgoing: bool,
+    pub reactivation_usd_value: f64,
+}
+
+struct Activity<'a> {
+    timestamp: u64,
+    hash: &'a str,
+    counterparty: NodeIndex,
+    outgoing: bool,
+    usd_value: f64,
+}
+
+fn node_activity(graph: &G, node: NodeIndex) -> Vec<Activity<'_>> {
+    let mut activity: Vec<Activity> = [Direction::Outgoing, Direction::Incoming]
+        .into_iter()
+        .flat_map(|direction| {
+            graph.edges_directed(node, direction).map(move |e| Activity {
+                timestamp: e.weight().timestamp,
+                hash: &e.weight().hash,
+                counterparty: if direction == Direction::Outgoing { e.target() } else { e.source() },
+                outgoing: direction == Direction::Outgoing,
+                usd_value: e.weight().data.as_ref().map_or(0.0, |d| d.usd_value),
+            })
+        })
+        .filter(|a| a.timestamp != 0)
+        .collect();
+    activity.sort_by_key(|a| a.timestamp);
+    activity
+}
+
+// Every gap longer than min_dormant_seconds between two consecutive activities of an address is a
+// dormancy period; the activity ending it is the reactivation.
+pub fn find_reactivations(graph: &G, min_dormant_seconds: u64) -> Vec<Reactivation> {
+    let mut reactivations = vec![];
+    for node in graph.node_indices() {
+        let activity = node_activity(graph, node);
+        let (Some(first), Some(last)) = (activity.first(), activity.last()) else {
+            continue;
+        };
+        for pair in activity.windows(2) {
+            let (before, after) = (&pair[0], &pair[1]);
+            let gap = after.timestamp - before.timestamp;
+            if gap <= min_dormant_seconds {
+                continue;
+            }
+            reactivations.push(Reactivation {
+                address: graph[node].clone(),
+                first_seen: first.timestamp,
+                last_seen: last.timestamp,
+                dormant_since: before.timestamp,
+                reactivated_at: after.timestamp,
+                dormant_days: gap as f64 / SECONDS_PER_DAY as f64,
+                reactivation_hash: after.hash.to_string(),
+                reactivation_counterparty: graph[after.counterparty].clone(),
+                reactivation_outgoing: after.outgoing,
+                reactivation_usd_value: after.usd_value,
+            });
+        }
+    }
+    reactivations.sort_by(|a, b| b.dormant_days.partial_cmp(&a.dormant_days).unwrap());
+    reactivations
+}
+
+pub fn run_dormancy_command(args: &DormancyArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let reactivations = find_reactivations(&graph, args.min_dormant_days * SECONDS_PER_DAY);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for reactivation in reactivations.iter() {
+        writer.serialize(reactivation)?;
+    }
+    commit_csv(writer)?;
+    for reactivation in reactivations.iter().take(10) {
+        println!(
+            "{} reactivated at {} after {:.1} days by {} ({} {})",
+            reactivation.address,
+            time_label(reactivation.reactivated_at)?,
+            reactivation.dormant_days,
+            reactivation.reactivation_hash,
+            if reactivation.reactivation_outgoing { "to" } else { "from" },
+            reactivation.reactivation_counterparty
+        );
+    }
+    println!(
+        "Found {} reactivations after more than {} dormant days, saved as {}",
+        reactivations.len(),
+        args.min_dormant_days,
+        &file_pathname
+    );
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs::{self, File};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::atomic_file::write_atomically;
+use crate::cli::{DriftArgs, DriftThresholds};
+use crate::money::Amount;
+use crate::reciprocity::reciprocal_pairs;
+use crate::time_buckets::time_label;
+use crate::{calculate_total_usd_volume, deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+pub const DRIFT_HISTORY_FILENAME: &str = "drift_history.json";
+
+// Key figures of the graph after one incremental update.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RunMetrics {
+    pub recorded_at: u64,
+    pub nodes: usize,
+    pub edges: usize,
+    pub usd_volume: f64,
+    // Connected address pairs with USD value flowing both ways, out of all connected pairs.
+    pub reciprocal_pairs: usize,
+    pub connected_pairs: usize,
+}
+
+impl RunMetrics {
+    pub fn reciprocity_percent(&self) -> f64 {
+        if self.connected_pairs == 0 {
+            0.0
+        } else {
+            100.0 * self.reciprocal_pairs as f64 / self.connected_pairs as f64
+        }
+    }
+}
+
+// Change from one run to the next. Growth rates are None when the previous run had nothing to grow from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drift {
+    pub hours: f64,
+    pub volume_growth_percent: Option<f64>,
+    pub new_node_percent: Option<f64>,
+    pub reciprocity_change_points: f64,
+}
+
+pub fn run_metrics(graph: &G, recorded_at: u64) -> RunMetrics {
+    let connected: HashSet<(usize, usize)> = graph
+        .edge_references()
+        .filter(|edge| edge.source() != edge.target())
+        .map(|edge| (edge.source().index().min(edge.target().index()), edge.source().index().max(edge.target().index())))
+        .collect();
+    RunMetrics {
+        recorded_at,
+        nodes: graph.node_count(),
+        edges: graph.edge_count(),
+        usd_volume: calculate_total_usd_volume(graph).0,
+        reciprocal_pairs: reciprocal_pairs(graph).len(),
+        connected_pairs: connected.len(),
+    }
+}
+
+fn growth_percent(previous: f64, current: f64) -> Option<f64> {
+    (previous > 0.0).then(|| 100.0 * (current - previous) / previous)
+}
+
+// The graphs of incremental runs only grow, so the new addresses are the difference in node counts.
+pub fn drift(previous: &RunMetrics, current: &RunMetrics) -> Drift {
+    Drift {
+        hours: current.recorded_at.saturating_sub(previous.recorded_at) as f64 / 3_600.0,
+        volume_growth_percent: growth_percent(previous.usd_volume, current.usd_volume),
+        new_node_percent: growth_percent(previous.nodes as f64, current.nodes as f64),
+        reciprocity_change_points: current.reciprocity_percent() - previous.reciprocity_percent(),
+    }
+}
+
+pub fn drift_alerts(drift: &Drift, thresholds: &DriftThresholds) -> Vec<String> {
+    let mut alerts = vec![];
+    if let Some(growth) = drift.volume_growth_percent.filter(|&growth| growth > thresholds.max_volume_growth_percent) {
+        alerts.push(format!("USD volume grew {:.1}%, above the {:.1}% threshold", growth, thresholds.max_volume_growth_percent));
+    }
+    if let Some(new_nodes) = drift.new_node_percent.filter(|&new_nodes| new_nodes > thresholds.max_new_node_percent) {
+        alerts.push(format!("New addresses are {:.1}% of the graph before, above the {:.1}% threshold", new_nodes, thresholds.max_new_node_percent));
+    }
+    if drift.reciprocity_change_points.abs() > thresholds.max_reciprocity_change {
+        alerts.push(format!(
+            "Reciprocity moved {:+.1} points, more than the {:.1} points threshold",
+            drift.reciprocity_change_points, thresholds.max_reciprocity_change
+        ));
+    }
+    alerts
+}
+
+fn percent_label(percent: Option<f64>) -> String {
+    percent.map_or("n/a".to_string(), |percent| format!("{:+.1}%", percent))
+}
+
+pub fn load_drift_history() -> Result<Vec<RunMetrics>> {
+    match File::open(format!("{}/{}", DATA_STORAGE_FOLDER, DRIFT_HISTORY_FILENAME)) {
+        Ok(file) => Ok(serde_json::from_reader(std::io::BufReader::new(file))?),
+        Err(_) => Ok(vec![]),
+    }
+}
+
+// Appends the graph's metrics to the drift history and describes the drift since the previous entry. The
+// alerts are empty on the first run and whenever every drift is within its threshold.
+pub fn record_run(graph: &G, thresholds: &DriftThresholds) -> Result<(String, Vec<String>)> {
+    let mut history = load_drift_history()?;
+    let current = run_metrics(graph, SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs());
+    let mut report = format!(
+        "Graph metrics at {}: {} nodes, {} edges, {} volume, reciprocity {:.1}%\n",
+        time_label(current.recorded_at)?,
+        current.nodes,
+        current.edges,
+        Amount::Usd(current.usd_volume),
+        current.reciprocity_percent()
+    );
+    let mut alerts = vec![];
+    match history.last() {
+        Some(previous) => {
+            let drift = drift(previous, &current);
+            writeln!(
+                report,
+                "Drift since the run {:.1} hours before: volume {}, new addresses {}, reciprocity {:+.1} points",
+                drift.hours,
+                percent_label(drift.volume_growth_percent),
+                percent_label(drift.new_node_percent),
+                drift.reciprocity_change_points
+            )
+            .unwrap();
+            alerts = drift_alerts(&drift, thresholds);
+            for alert in alerts.iter() {
+                writeln!(report, "Drift alert: {}", alert).unwrap();
+            }
+        }
+        None => writeln!(report, "First recorded run, drift is tracked from the next one").unwrap(),
+    }
+    history.push(current);
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    write_atomically(&format!("{}/{}", DATA_STORAGE_FOLDER, DRIFT_HISTORY_FILENAME), &serde_json::to_vec_pretty(&history)?)?;
+    Ok((report, alerts))
+}
+
+pub fn run_drift_command(args: &DriftArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let (report, _) = record_run(&graph, &args.thresholds)?;
+    print!("{}", report);
+    Ok(())
+}
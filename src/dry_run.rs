@@ -0,0 +1,113 @@
+use eyre::Result;
+use petgraph::Direction;
+use std::fs;
+
+use crate::cli::CrawlArgs;
+use crate::edge_payload::EdgeSchema;
+use crate::delta_crawl::{load_crawl_state, CRAWL_STATE_FILENAME, PARSED_GRAPH_FILENAME};
+use crate::{
+    deserialize_graph, API_DAILY_REQUEST_QUOTA, API_REQUESTS_PER_SECOND, DATA_STORAGE_FOLDER, MAX_TRANSACTIONS_PER_ADDRESS,
+    MAX_TRANSACTIONS_TO_PARSE, TRANSACTIONS_TO_REQUEST,
+};
+
+// Serialized size of an edge with digested data, used when no previous crawl output exists.
+const ESTIMATED_BYTES_PER_EDGE: f64 = 330.0;
+// Block hash, value and calldata kept by the full edge schema, for a typical token call.
+const ESTIMATED_FULL_EDGE_DATA_BYTES_PER_EDGE: f64 = 480.0;
+
+pub struct CrawlPlan {
+    pub strategy: &'static str,
+    pub addresses: f64,
+    pub transactions_per_address: f64,
+    pub requests: f64,
+    pub seconds: f64,
+    pub output_bytes: f64,
+    pub based_on_previous_run: bool,
+}
+
+pub fn requests_for_history(transactions: f64) -> f64 {
+    let fetched = transactions.min(MAX_TRANSACTIONS_PER_ADDRESS as f64);
+    (fetched / TRANSACTIONS_TO_REQUEST as f64).floor() + 1.0 // The last, partially filled page is always requested
+}
+
+pub fn plan_crawl(args: &CrawlArgs) -> CrawlPlan {
+    let previous_graph = deserialize_graph(PARSED_GRAPH_FILENAME).ok().map(|(graph, _)| graph);
+    let previous_state = load_crawl_state().ok();
+    let previous_file_size = fs::metadata(format!("{}/{}", DATA_STORAGE_FOLDER, PARSED_GRAPH_FILENAME)).map(|m| m.len()).ok();
+
+    // History length of a visited address and new edges it contributes, from the previous run when available.
+    let mut transactions_per_address = args.assumed_transactions_per_address as f64;
+    let mut new_edges_per_address = transactions_per_address;
+    let mut bytes_per_edge = ESTIMATED_BYTES_PER_EDGE;
+    let mut based_on_previous_run = false;
+    if let (Some(graph), Some(state)) = (&previous_graph, &previous_state) {
+        let visited: Vec<_> = graph.node_indices().filter(|&n| state.last_fetched_blocks.contains_key(&graph[n])).collect();
+        if !visited.is_empty() && graph.edge_count() > 0 {
+            let touching: usize = visited
+                .iter()
+                .map(|&n| graph.edges_directed(n, Direction::Outgoing).count() + graph.edges_directed(n, Direction::Incoming).count())
+                .sum();
+            transactions_per_address = touching as f64 / visited.len() as f64;
+            new_edges_per_address = graph.edge_count() as f64 / visited.len() as f64;
+            if let Some(size) = previous_file_size {
+                bytes_per_edge = size as f64 / graph.edge_count() as f64;
+            }
+            based_on_previous_run = true;
+        }
+    }
+    if args.edge_schema == EdgeSchema::Full && !previous_state.as_ref().is_some_and(|s| s.edge_schema == EdgeSchema::Full && based_on_previous_run) {
+        bytes_per_edge += ESTIMATED_FULL_EDGE_DATA_BYTES_PER_EDGE;
+    }
+
+    let (strategy, addresses, new_edges) = if args.since_last_run {
+        let addresses = previous_state.as_ref().map_or(0, |s| s.last_fetched_blocks.len()) as f64;
+        ("delta crawl of known addresses", addresses, 0.0)
+    } else if args.resume {
+        let addresses = (MAX_TRANSACTIONS_TO_PARSE as f64 / new_edges_per_address.max(1.0)).ceil();
+        ("resumed traversal from the saved frontier", addresses, MAX_TRANSACTIONS_TO_PARSE as f64)
+    } else {
+        let addresses = (MAX_TRANSACTIONS_TO_PARSE as f64 / new_edges_per_address.max(1.0)).ceil();
+        ("full crawl from the traversal starting address", addresses, MAX_TRANSACTIONS_TO_PARSE as f64)
+    };
+    // A delta run only fetches the newest page of each known address.
+    let requests_per_address = if args.since_last_run { 1.0 } else { requests_for_history(transactions_per_address) };
+    let requests = addresses * requests_per_address;
+    let existing_edges = if args.since_last_run || args.resume { previous_graph.as_ref().map_or(0, |g| g.edge_count()) as f64 } else { 0.0 };
+
+    CrawlPlan {
+        strategy,
+        addresses,
+        transactions_per_address,
+        requests,
+        seconds: requests / API_REQUESTS_PER_SECOND,
+        output_bytes: (existing_edges + new_edges) * bytes_per_edge,
+        based_on_previous_run,
+    }
+}
+
+pub fn print_crawl_plan(args: &CrawlArgs) -> Result<()> {
+    let plan = plan_crawl(args);
+    if (args.since_last_run && plan.addresses == 0.0) || (args.resume && load_crawl_state().is_err()) {
+        return Err(eyre::eyre!("No previous run found in {}/{}, run a full crawl first", DATA_STORAGE_FOLDER, CRAWL_STATE_FILENAME));
+    }
+    println!("Dry run, no requests are sent. Strategy: {}", plan.strategy);
+    println!(
+        "Limits: {} transactions in total, {} per address, pages of {}",
+        MAX_TRANSACTIONS_TO_PARSE, MAX_TRANSACTIONS_PER_ADDRESS, TRANSACTIONS_TO_REQUEST
+    );
+    println!(
+        "Estimates {}: {:.0} transactions per address",
+        if plan.based_on_previous_run { "from the previous run" } else { "from --assumed-transactions-per-address" },
+        plan.transactions_per_address
+    );
+    println!("Addresses to visit: {:.0}", plan.addresses);
+    println!(
+        "API requests: {:.0} ({:.1} days of the {} request daily quota)",
+        plan.requests,
+        plan.requests / API_DAILY_REQUEST_QUOTA as f64,
+        API_DAILY_REQUEST_QUOTA
+    );
+    println!("Wall-clock time at {} requests/s: {:.1} h", API_REQUESTS_PER_SECOND, plan.seconds / 3_600.0);
+    println!("Projected {} size: {:.1} MB", PARSED_GRAPH_FILENAME, plan.output_bytes / 1E6);
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use eyre::Result;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::annotations::Annotations;
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::G;
+
+pub const REMOVED_DUST_FILENAME: &str = "removed_dust.csv";
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RemovedDust {
+    #[serde(skip)]
+    pub edge: EdgeIndex,
+    pub hash: String,
+    pub sender: String,
+    pub recipient: String,
+    pub usd_value: f64,
+    pub timestamp: u64,
+}
+
+// Spam airdrops and poisoning dust come from senders the recipient never dealt with otherwise: a transfer
+// below the threshold is dust when it is the only transaction between the pair, either way, and nobody
+// annotated the sender. Unpriced transfers count as worth nothing.
+pub fn dust_transfers(graph: &G, below_usd: f64, annotations: &Annotations) -> Vec<RemovedDust> {
+    let mut pair_transactions: HashMap<(NodeIndex, NodeIndex), usize> = HashMap::new();
+    for edge in graph.edge_references() {
+        let pair = if edge.source() < edge.target() { (edge.source(), edge.target()) } else { (edge.target(), edge.source()) };
+        *pair_transactions.entry(pair).or_default() += 1;
+    }
+    graph
+        .edge_references()
+        .filter(|edge| edge.source() != edge.target())
+        .filter(|edge| edge.weight().data.as_ref().map_or(0.0, |d| d.usd_value) < below_usd)
+        .filter(|edge| {
+            let pair = if edge.source() < edge.target() { (edge.source(), edge.target()) } else { (edge.target(), edge.source()) };
+            pair_transactions[&pair] == 1
+        })
+        .filter(|edge| annotations.get(&graph[edge.source()]).is_none_or(|annotation| annotation.is_empty()))
+        .map(|edge| RemovedDust {
+            edge: edge.id(),
+            hash: edge.weight().hash.clone(),
+            sender: graph[edge.source()].clone(),
+            recipient: graph[edge.target()].clone(),
+            usd_value: edge.weight().data.as_ref().map_or(0.0, |d| d.usd_value),
+            timestamp: edge.weight().timestamp,
+        })
+        .collect()
+}
+
+// What the filter removed, so a recipient's history can be restored or the senders reviewed.
+pub fn save_removed_dust(removed: &[RemovedDust], pathname: &str) -> Result<()> {
+    let mut writer = csv_writer(pathname)?;
+    for dust in removed {
+        writer.serialize(dust)?;
+    }
+    commit_csv(writer)?;
+    println!("Saved {} removed dust transfers as {}", removed.len(), pathname);
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use petgraph::visit::EdgeRef;
+use std::collections::HashSet;
+
+use crate::annotations::Annotations;
+use crate::money::Amount;
+use crate::cli::{EdgeHandling, EdgeHandlingArgs};
+use crate::dust_filter::{dust_transfers, RemovedDust};
+use crate::stable_graph::{compact, remove_nodes_where, retain_edges_where, to_stable};
+use crate::{FilteringResultInfo, Transaction, G};
+
+#[derive(Default, Debug)]
+pub struct EdgeClassCount {
+    pub edges: usize,
+    pub usd_volume: f64,
+}
+
+#[derive(Default, Debug)]
+pub struct EdgeHandlingSummary {
+    pub self_transfers: EdgeClassCount,
+    pub zero_value: EdgeClassCount,
+    pub dust: Vec<RemovedDust>,
+    pub removed_edges: usize,
+}
+
+impl EdgeHandlingSummary {
+    pub fn describe(&self, args: &EdgeHandlingArgs) -> String {
+        let dust = match args.drop_dust_below {
+            Some(below_usd) => format!(
+                "Dust below {} from one-shot unannotated senders: {} edges, {} (Drop)\n",
+                Amount::Usd(below_usd),
+                self.dust.len(),
+                Amount::Usd(self.dust.iter().fold(0.0, |volume, dust| volume + dust.usd_value))
+            ),
+            None => String::new(),
+        };
+        format!(
+            "Self-transfers: {} edges, {} ({:?})\nZero-value edges: {} ({:?})\n{}Edges removed from the analysed graph: {}\n\n",
+            self.self_transfers.edges,
+            Amount::Usd(self.self_transfers.usd_volume),
+            args.self_transfers,
+            self.zero_value.edges,
+            args.zero_value,
+            dust,
+            self.removed_edges
+        )
+    }
+}
+
+pub fn is_zero_value(transaction: &Transaction) -> bool {
+    transaction.data.as_ref().map_or(0.0, |d| d.usd_value) == 0.0
+}
+
+// Both Drop and Report remove the edges from the analysed graph; Report and Keep count them in the summary.
+// Dust is only ever dropped, and the summary lists every dropped transfer; annotated senders are never dust.
+pub fn apply_edge_handling(graph: &G, args: &EdgeHandlingArgs, annotations: &Annotations) -> (G, FilteringResultInfo, EdgeHandlingSummary) {
+    let mut summary = EdgeHandlingSummary::default();
+    if let Some(below_usd) = args.drop_dust_below {
+        summary.dust = dust_transfers(graph, below_usd, annotations);
+    }
+    let dust: HashSet<usize> = summary.dust.iter().map(|dust| dust.edge.index()).collect();
+    for edge in graph.edge_references() {
+        let usd_value = edge.weight().data.as_ref().map_or(0.0, |d| d.usd_value);
+        if edge.source() == edge.target() && args.self_transfers != EdgeHandling::Drop {
+            summary.self_transfers.edges += 1;
+            summary.self_transfers.usd_volume += usd_value;
+        }
+        if is_zero_value(edge.weight()) && args.zero_value != EdgeHandling::Drop {
+            summary.zero_value.edges += 1;
+        }
+    }
+
+    let mut pruned = to_stable(graph);
+    summary.removed_edges = retain_edges_where(&mut pruned, |pruned, edge_index| {
+        let (source, target) = pruned.edge_endpoints(edge_index).unwrap();
+        let removed = (source == target && args.self_transfers != EdgeHandling::Keep)
+            || (is_zero_value(&pruned[edge_index]) && args.zero_value != EdgeHandling::Keep)
+            || dust.contains(&edge_index.index());
+        !removed
+    });
+    remove_nodes_where(&mut pruned, |pruned, node_index| pruned.neighbors_undirected(node_index).next().is_none());
+    let stage2 = compact(pruned);
+    let info = FilteringResultInfo {
+        node_count: stage2.raw_nodes().len(),
+        edge_count: stage2.raw_edges().len(),
+        node_count_before_filtering: graph.raw_nodes().len(),
+        edge_count_before_filtering: graph.raw_edges().len(),
+    };
+    (stage2, info, summary)
+}
@@ -0,0 +1,104 @@
+use eyre::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::graph_builder::GraphBuilder;
+use crate::money::Amount;
+use crate::stable_graph::remove_isolated_nodes;
+use crate::cli::{DedupArgs, DedupPolicy};
+use crate::{calculate_total_usd_volume, deserialize_graph, serialize_graph, EdgeKind, FilteringResultInfo, Transaction, G};
+
+// An edge is identified by its transaction hash plus its position inside the transaction: the trace
+// of an internal transfer or the log of a token transfer. The outer call has neither.
+#[derive(Hash, PartialEq, Eq, Clone, Debug, Serialize)]
+pub struct EdgeIdentity {
+    pub hash: String,
+    pub kind: EdgeKind,
+    pub trace_index: Option<u64>,
+    pub log_index: Option<u64>,
+}
+
+pub fn edge_identity(transaction: &Transaction) -> EdgeIdentity {
+    // Graphs saved before edge kinds were recorded mark token transfers only through their digested data.
+    let kind = match (transaction.kind, &transaction.data) {
+        (EdgeKind::Call, Some(_)) => EdgeKind::TokenTransfer,
+        (kind, _) => kind,
+    };
+    EdgeIdentity { hash: transaction.hash.clone(), kind, trace_index: transaction.trace_index, log_index: transaction.log_index }
+}
+
+#[derive(Default, Debug)]
+pub struct DedupReport {
+    pub duplicate_identities: usize,
+    pub superseded_calls: usize,
+}
+
+// Exact drops repeated identities only. PreferValueTransfers also drops the outer call of a transaction
+// whenever the same hash has token or internal transfer edges, which carry the value that actually moved.
+pub fn dedup_edges(graph: &G, policy: DedupPolicy) -> (G, FilteringResultInfo, DedupReport) {
+    let value_hashes: HashSet<&str> = graph
+        .edge_weights()
+        .filter(|t| edge_identity(t).kind != EdgeKind::Call)
+        .map(|t| t.hash.as_str())
+        .collect();
+
+    let mut report = DedupReport::default();
+    let mut seen: HashSet<EdgeIdentity> = HashSet::new();
+    let mut dedup_graph: G = G::with_capacity(graph.node_count(), graph.edge_count());
+    for node in graph.node_indices() {
+        dedup_graph.add_node(graph[node].clone());
+    }
+    for edge in graph.raw_edges() {
+        let identity = edge_identity(&edge.weight);
+        if matches!(policy, DedupPolicy::PreferValueTransfers) && identity.kind == EdgeKind::Call && value_hashes.contains(identity.hash.as_str()) {
+            report.superseded_calls += 1;
+            continue;
+        }
+        if !seen.insert(identity) {
+            report.duplicate_identities += 1;
+            continue;
+        }
+        dedup_graph.add_edge(edge.source(), edge.target(), edge.weight.clone());
+    }
+
+    let mut stage2 = dedup_graph;
+    remove_isolated_nodes(&mut stage2);
+    let info = FilteringResultInfo {
+        node_count: stage2.raw_nodes().len(),
+        edge_count: stage2.raw_edges().len(),
+        node_count_before_filtering: graph.raw_nodes().len(),
+        edge_count_before_filtering: graph.raw_edges().len(),
+    };
+    (stage2, info, report)
+}
+
+// Union of several graphs with nodes matched by address. Duplicates are kept for dedup_edges to resolve.
+pub fn combine_graphs(graphs: &[G]) -> G {
+    let mut combined = GraphBuilder::with_capacity(
+        graphs.iter().map(|graph| graph.node_count()).sum(),
+        graphs.iter().map(|graph| graph.edge_count()).sum(),
+    );
+    for graph in graphs {
+        combined.extend_edges(
+            graph.raw_edges().iter().map(|edge| (graph[edge.source()].to_lowercase(), graph[edge.target()].to_lowercase(), edge.weight.clone())),
+        );
+    }
+    combined.into_graph()
+}
+
+pub fn run_dedup_command(args: &DedupArgs) -> Result<()> {
+    let graphs = args.inputs.iter().map(|name| deserialize_graph(name).map(|(graph, _)| graph)).collect::<Result<Vec<G>>>()?;
+    let combined = combine_graphs(&graphs);
+    let (dedup_graph, info, report) = dedup_edges(&combined, args.policy);
+    let (volume_before, _) = calculate_total_usd_volume(&combined);
+    let (volume_after, _) = calculate_total_usd_volume(&dedup_graph);
+    println!(
+        "Combined {} graphs into {} edges: dropped {} repeated edge identities and {} calls superseded by value transfers",
+        graphs.len(),
+        combined.edge_count(),
+        report.duplicate_identities,
+        report.superseded_calls
+    );
+    println!("Total volume: {} before, {} after deduplication", Amount::Usd(volume_before), Amount::Usd(volume_after));
+    serialize_graph(&dedup_graph, &info, &args.output)
+}
@@ -0,0 +1,93 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{Transaction, G};
+#[cfg(feature = "crawl")]
+use crate::RawTransaction;
+
+// Saved graphs without a format version predate it and are version 1. Version 2 added nonces and the
+// optional full edge data, version 3 the edge schema; all default when missing, so older graphs load unchanged.
+pub const GRAPH_FORMAT_VERSION: u32 = 3;
+
+pub fn legacy_format_version() -> u32 {
+    1
+}
+
+// Which fields the crawl keeps on every edge, trading memory and file size for analysis capability.
+// Lean keeps hash, token and native value, timestamp and the edge's identity within its transaction;
+// standard adds block position, nonce, gas and the decoded call; full adds the fields of FullEdgeData.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeSchema {
+    Lean,
+    #[default]
+    Standard,
+    Full,
+}
+
+impl EdgeSchema {
+    pub fn retain(self, transaction: Transaction) -> Transaction {
+        match self {
+            EdgeSchema::Full => transaction,
+            EdgeSchema::Standard => Transaction { full: None, ..transaction },
+            EdgeSchema::Lean => Transaction {
+                gas_price: 0,
+                gas_used: 0,
+                transaction_type: 0,
+                max_fee_per_gas: 0,
+                max_priority_fee_per_gas: 0,
+                base_fee_per_gas: 0,
+                block_number: 0,
+                transaction_index: 0,
+                nonce: 0,
+                decoded_call: None,
+                full: None,
+                ..transaction
+            },
+        }
+    }
+
+    // The richest schema any edge carries, recorded when a graph is saved.
+    pub fn of_graph(graph: &G) -> EdgeSchema {
+        if graph.edge_weights().any(|t| t.full.is_some()) {
+            EdgeSchema::Full
+        } else if graph.edge_count() > 0 && graph.edge_weights().all(|t| t.block_number == 0 && t.gas_price == 0 && t.gas_used == 0) {
+            EdgeSchema::Lean
+        } else {
+            EdgeSchema::Standard
+        }
+    }
+}
+
+pub fn is_zero<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+// Raw transaction fields that only some analyses need, kept by the full edge schema since the input data
+// alone can be larger than the rest of the edge.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct FullEdgeData {
+    pub block_hash: String,
+    // Native value in wei, as sent by the API.
+    pub value: String,
+    pub gas_limit: u64,
+    pub cumulative_gas_used: u64,
+    pub input: String,
+    pub method_id: String,
+    pub function_name: String,
+}
+
+#[cfg(feature = "crawl")]
+impl FullEdgeData {
+    pub(crate) fn from_raw(transaction: &RawTransaction) -> FullEdgeData {
+        FullEdgeData {
+            block_hash: transaction.blockHash.clone(),
+            value: transaction.value.clone(),
+            gas_limit: transaction.gas.parse().unwrap_or_default(),
+            cumulative_gas_used: transaction.cumulativeGasUsed.parse().unwrap_or_default(),
+            input: transaction.input.clone(),
+            method_id: transaction.methodId.clone(),
+            function_name: transaction.functionName.clone(),
+        }
+    }
+}
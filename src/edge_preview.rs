@@ -0,0 +1,62 @@
+use petgraph::graph::EdgeIndex;
+use petgraph::visit::EdgeRef;
+use rand::seq::index;
+use rand::SeedableRng;
+use std::fmt::Write;
+
+use crate::annotations::Annotations;
+use crate::money::{wei_to_coin, Amount};
+use crate::seeded_rng::SeededRng;
+use crate::time_buckets::time_label;
+use crate::{EdgeKind, G};
+
+fn edge_line(graph: &G, edge: EdgeIndex, annotations: &Annotations) -> eyre::Result<String> {
+    let (source, target) = graph.edge_endpoints(edge).unwrap();
+    let transaction = &graph[edge];
+    let value = match &transaction.data {
+        Some(data) => format!("{:?} {}", data.payload, Amount::Usd(data.usd_value)),
+        None => match transaction.usd_value() {
+            Some(usd) => format!("{} ({})", Amount::Coin(wei_to_coin(transaction.value_wei)), Amount::Usd(usd)),
+            None => format!("{}", Amount::Coin(wei_to_coin(transaction.value_wei))),
+        },
+    };
+    let kind = if transaction.kind == EdgeKind::Call { String::new() } else { format!(" {:?}", transaction.kind) };
+    Ok(format!(
+        "  {} {} -> {}: {}{} {}\n",
+        time_label(transaction.timestamp)?,
+        annotations.mention(&graph[source]),
+        annotations.mention(&graph[target]),
+        value,
+        kind,
+        transaction.hash
+    ))
+}
+
+// A few edges of the highest USD value, the newest and a random few, to look over what a crawl collected
+// before the analyses. The random pick is seeded from the graph size, so the same crawl previews the same
+// edges and the console preview leaves no seed in the catalog.
+pub fn edge_preview(graph: &G, annotations: &Annotations, count: usize) -> String {
+    let mut preview = format!("Preview of the {} collected edges:\n", graph.edge_count());
+    if graph.edge_count() == 0 || count == 0 {
+        return preview;
+    }
+    let mut by_value: Vec<EdgeIndex> = graph.edge_references().filter(|e| e.weight().usd_value().is_some()).map(|e| e.id()).collect();
+    by_value.sort_by(|&a, &b| graph[b].usd_value().partial_cmp(&graph[a].usd_value()).unwrap().then(a.cmp(&b)));
+    let mut by_time: Vec<EdgeIndex> = graph.edge_indices().collect();
+    by_time.sort_by(|&a, &b| graph[b].timestamp.cmp(&graph[a].timestamp).then(a.cmp(&b)));
+    let mut rng = SeededRng::seed_from_u64(graph.edge_count() as u64);
+    let mut random: Vec<EdgeIndex> = index::sample(&mut rng, graph.edge_count(), count.min(graph.edge_count())).into_iter().map(EdgeIndex::new).collect();
+    random.sort();
+
+    for (title, edges) in [("Highest USD value", by_value), ("Most recent", by_time), ("Random", random)] {
+        if edges.is_empty() {
+            continue;
+        }
+        writeln!(preview, "{}:", title).unwrap();
+        // Edges with a timestamp out of range are left out, they would otherwise head the most recent.
+        for line in edges.iter().filter_map(|&edge| edge_line(graph, edge, annotations).ok()).take(count) {
+            preview.push_str(&line);
+        }
+    }
+    preview
+}
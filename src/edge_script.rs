@@ -0,0 +1,90 @@
+use chrono::{Datelike, TimeZone, Timelike};
+use chrono_tz::Tz;
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+use rhai::{Engine, Scope, AST};
+
+use crate::abi_decoding::DecodedCall;
+use crate::cli::FilterScriptArgs;
+use crate::graph_view::FilteredGraph;
+use crate::time_buckets::parse_timezone;
+use crate::{deserialize_graph, serialize_graph, Transaction, G};
+
+// Guards against runaway scripts; a plain condition takes a few dozen operations per edge.
+const MAX_OPERATIONS_PER_EDGE: u64 = 100_000;
+
+// Name of the called method, or an empty string for plain transfers and unknown calls.
+fn function_name(transaction: &Transaction) -> String {
+    if let Some(data) = &transaction.data {
+        return format!("{:?}", data.used_onchain_function);
+    }
+    match &transaction.decoded_call {
+        Some(DecodedCall::Transfer { .. }) => "Transfer".to_string(),
+        Some(DecodedCall::TransferFrom { .. }) => "TransferFrom".to_string(),
+        Some(DecodedCall::Approve { .. }) => "Approve".to_string(),
+        Some(DecodedCall::IncreaseAllowance { .. }) => "IncreaseAllowance".to_string(),
+        Some(DecodedCall::Swap { method, .. }) => format!("{:?}", method),
+        None => String::new(),
+    }
+}
+
+// Calendar helpers take unix seconds and use the given timezone.
+fn script_engine(tz: Tz) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS_PER_EDGE);
+    engine.register_fn("hour", move |timestamp: i64| tz.timestamp_opt(timestamp, 0).unwrap().hour() as i64);
+    engine.register_fn("weekday", move |timestamp: i64| tz.timestamp_opt(timestamp, 0).unwrap().weekday().num_days_from_monday() as i64);
+    engine.register_fn("day", move |timestamp: i64| tz.timestamp_opt(timestamp, 0).unwrap().day() as i64);
+    engine
+}
+
+// A boolean Rhai expression over the edge fields from, to, hash, value_usd, timestamp and function, e.g.
+// `value_usd > 1e6 && hour(timestamp) < 6`.
+pub struct EdgeScript {
+    engine: Engine,
+    ast: AST,
+}
+
+impl EdgeScript {
+    pub fn compile(source: &str, tz: Tz) -> Result<EdgeScript> {
+        let engine = script_engine(tz);
+        let ast = engine.compile(source).map_err(|e| eyre::eyre!("Invalid edge filter script: {}", e))?;
+        Ok(EdgeScript { engine, ast })
+    }
+
+    pub fn matches(&self, from: &str, to: &str, transaction: &Transaction) -> Result<bool> {
+        let mut scope = Scope::new();
+        scope.push_constant("from", from.to_string());
+        scope.push_constant("to", to.to_string());
+        scope.push_constant("hash", transaction.hash.clone());
+        scope.push_constant("value_usd", transaction.usd_value().unwrap_or(0.0));
+        scope.push_constant("timestamp", transaction.timestamp as i64);
+        scope.push_constant("function", function_name(transaction));
+        self.engine
+            .eval_ast_with_scope::<bool>(&mut scope, &self.ast)
+            .map_err(|e| eyre::eyre!("Edge filter script failed on {}: {}", transaction.hash, e))
+    }
+
+    pub fn filter<'a>(&self, graph: &'a G) -> Result<FilteredGraph<'a>> {
+        let mut included = vec![false; graph.edge_count()];
+        for edge in graph.edge_references() {
+            included[edge.id().index()] = self.matches(&graph[edge.source()], &graph[edge.target()], edge.weight())?;
+        }
+        Ok(FilteredGraph::new(graph).retain_edges(|edge| included[edge.id().index()]))
+    }
+}
+
+pub fn run_filter_script_command(args: &FilterScriptArgs) -> Result<()> {
+    let source = match (&args.expr, &args.script) {
+        (Some(expr), _) => expr.clone(),
+        (None, Some(pathname)) => std::fs::read_to_string(pathname)?,
+        (None, None) => return Err(eyre::eyre!("Pass the filter as --expr or --script")),
+    };
+    let script = EdgeScript::compile(&source, parse_timezone(&args.timezone)?)?;
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let view = script.filter(&graph)?;
+    let (filtered_graph, info) = view.materialize();
+    println!("Edge filter kept {} of {} edges:\n{:#?}", info.edge_count, graph.edge_count(), info);
+    serialize_graph(&filtered_graph, &info, &args.output)?;
+    Ok(())
+}
@@ -0,0 +1,220 @@
+use eyre::Result;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+
+use crate::atomic_file::{commit_csv, csv_writer, AtomicFile};
+use crate::cli::{EmbeddingFormat, EdgeWeight, EmbeddingsArgs};
+use crate::layers::select_layers;
+use crate::seeded_rng::seeded_rng;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+struct WeightedAdjacency {
+    neighbors: Vec<Vec<(usize, f64)>>,
+    neighbor_sets: Vec<HashSet<usize>>,
+}
+
+fn weighted_adjacency(graph: &G, weight: EdgeWeight) -> WeightedAdjacency {
+    // Parallel edges and both directions are collapsed into one undirected weighted link.
+    let mut aggregated: Vec<HashMap<usize, f64>> = vec![HashMap::new(); graph.node_count()];
+    for edge in graph.raw_edges() {
+        let (source, target) = (edge.source().index(), edge.target().index());
+        let edge_weight = match weight {
+            EdgeWeight::Count => 1.0,
+            EdgeWeight::UsdVolume => edge.weight.data.as_ref().map_or(0.0, |data| data.usd_value),
+        };
+        if edge_weight <= 0.0 || source == target {
+            continue;
+        }
+        *aggregated[source].entry(target).or_insert(0.0) += edge_weight;
+        *aggregated[target].entry(source).or_insert(0.0) += edge_weight;
+    }
+
+    let neighbors: Vec<Vec<(usize, f64)>> = aggregated
+        .into_iter()
+        .map(|links| {
+            let mut links: Vec<(usize, f64)> = links.into_iter().collect();
+            links.sort_by_key(|&(neighbor, _)| neighbor);
+            links
+        })
+        .collect();
+    let neighbor_sets = neighbors
+        .iter()
+        .map(|links| links.iter().map(|&(neighbor, _)| neighbor).collect())
+        .collect();
+    WeightedAdjacency { neighbors, neighbor_sets }
+}
+
+fn node2vec_walk(adjacency: &WeightedAdjacency, start: usize, args: &EmbeddingsArgs, rng: &mut impl Rng) -> Vec<usize> {
+    let mut walk = vec![start];
+    while walk.len() < args.walk_length {
+        let current = *walk.last().unwrap();
+        let links = &adjacency.neighbors[current];
+        if links.is_empty() {
+            break;
+        }
+        let biased_weights: Vec<f64> = match walk.len() {
+            1 => links.iter().map(|&(_, w)| w).collect(),
+            _ => {
+                let previous = walk[walk.len() - 2];
+                links
+                    .iter()
+                    .map(|&(next, w)| {
+                        if next == previous {
+                            w / args.return_parameter
+                        } else if adjacency.neighbor_sets[previous].contains(&next) {
+                            w
+                        } else {
+                            w / args.in_out_parameter
+                        }
+                    })
+                    .collect()
+            }
+        };
+        let choice = WeightedIndex::new(&biased_weights).unwrap().sample(rng);
+        walk.push(links[choice].0);
+    }
+    walk
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+fn train_skip_gram(walks: &[Vec<usize>], node_count: usize, args: &EmbeddingsArgs, rng: &mut impl Rng) -> Vec<Vec<f32>> {
+    let dimensions = args.dimensions;
+    let mut embeddings: Vec<Vec<f32>> = (0..node_count)
+        .map(|_| (0..dimensions).map(|_| (rng.gen::<f32>() - 0.5) / dimensions as f32).collect())
+        .collect();
+    let mut contexts: Vec<Vec<f32>> = vec![vec![0.0; dimensions]; node_count];
+
+    // Negative samples are drawn from the unigram distribution raised to 3/4, as in word2vec.
+    let mut occurrences = vec![0usize; node_count];
+    for node in walks.iter().flatten() {
+        occurrences[*node] += 1;
+    }
+    let Ok(noise_distribution) = WeightedIndex::new(occurrences.iter().map(|&c| (c as f64).powf(0.75))) else {
+        return embeddings;
+    };
+
+    let total_steps = walks.iter().map(|walk| walk.len()).sum::<usize>().max(1);
+    let mut step = 0;
+    let mut gradient = vec![0.0f32; dimensions];
+    for walk in walks {
+        for (position, &center) in walk.iter().enumerate() {
+            step += 1;
+            let learning_rate = (args.learning_rate * (1.0 - step as f32 / total_steps as f32)).max(args.learning_rate * 1e-4);
+            let window_low = position.saturating_sub(args.window);
+            let window_high = (position + args.window + 1).min(walk.len());
+
+            for (context_position, &context) in walk.iter().enumerate().take(window_high).skip(window_low) {
+                if context_position == position {
+                    continue;
+                }
+                gradient.iter_mut().for_each(|g| *g = 0.0);
+                let negatives = (0..args.negative_samples).map(|_| (noise_distribution.sample(rng), 0.0f32));
+                for (target, label) in std::iter::once((context, 1.0f32)).chain(negatives) {
+                    let dot: f32 = embeddings[center].iter().zip(&contexts[target]).map(|(a, b)| a * b).sum();
+                    let g = (label - sigmoid(dot)) * learning_rate;
+                    for d in 0..dimensions {
+                        gradient[d] += g * contexts[target][d];
+                        contexts[target][d] += g * embeddings[center][d];
+                    }
+                }
+                for d in 0..dimensions {
+                    embeddings[center][d] += gradient[d];
+                }
+            }
+        }
+    }
+    embeddings
+}
+
+fn write_csv(graph: &G, embeddings: &[Vec<f32>], file_pathname: &str) -> Result<()> {
+    let mut writer = csv_writer(file_pathname)?;
+    let mut header = vec!["address".to_string()];
+    header.extend((0..embeddings.first().map_or(0, |e| e.len())).map(|d| format!("dim_{}", d)));
+    writer.write_record(&header)?;
+    for (node, embedding) in graph.node_indices().zip(embeddings) {
+        let mut record = vec![graph[node].clone()];
+        record.extend(embedding.iter().map(|v| v.to_string()));
+        writer.write_record(&record)?;
+    }
+    commit_csv(writer)?;
+    Ok(())
+}
+
+fn write_npy(graph: &G, embeddings: &[Vec<f32>], file_pathname: &str) -> Result<()> {
+    let dimensions = embeddings.first().map_or(0, |e| e.len());
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        embeddings.len(),
+        dimensions
+    );
+    // Magic (6) + version (2) + header length (2) + header has to be a multiple of 64 bytes, ending in a newline.
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut writer = AtomicFile::create(file_pathname)?;
+    writer.write_all(b"\x93NUMPY\x01\x00")?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    for value in embeddings.iter().flatten() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.commit()?;
+
+    // Row order of the matrix, since .npy cannot carry the address strings.
+    let addresses_pathname = format!("{}.addresses.txt", file_pathname.trim_end_matches(".npy"));
+    let mut addresses_file = AtomicFile::create(&addresses_pathname)?;
+    for node in graph.node_indices() {
+        writeln!(addresses_file, "{}", graph[node])?;
+    }
+    addresses_file.commit()?;
+    println!("Saved row addresses as {}", &addresses_pathname);
+    Ok(())
+}
+
+pub fn run_embeddings_command(args: &EmbeddingsArgs) -> Result<()> {
+    if !(args.return_parameter > 0.0 && args.in_out_parameter > 0.0) {
+        return Err(eyre::eyre!(
+            "--return-parameter and --in-out-parameter must be positive, got {} and {}",
+            args.return_parameter,
+            args.in_out_parameter
+        ));
+    }
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let graph = select_layers(graph, &args.layer)?;
+    let adjacency = weighted_adjacency(&graph, args.weight);
+    let mut rng = seeded_rng(args.seed.seed);
+
+    let mut walk_starts: Vec<usize> = (0..graph.node_count()).filter(|&n| !adjacency.neighbors[n].is_empty()).collect();
+    let mut walks = Vec::with_capacity(walk_starts.len() * args.walks_per_node);
+    for _ in 0..args.walks_per_node {
+        walk_starts.shuffle(&mut rng);
+        for &start in walk_starts.iter() {
+            walks.push(node2vec_walk(&adjacency, start, args, &mut rng));
+        }
+    }
+    println!("Generated {} random walks", walks.len());
+
+    let embeddings = train_skip_gram(&walks, graph.node_count(), args, &mut rng);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let default_output = match args.format {
+        EmbeddingFormat::Csv => "embeddings.csv",
+        EmbeddingFormat::Npy => "embeddings.npy",
+    };
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output.as_deref().unwrap_or(default_output));
+    match args.format {
+        EmbeddingFormat::Csv => write_csv(&graph, &embeddings, &file_pathname)?,
+        EmbeddingFormat::Npy => write_npy(&graph, &embeddings, &file_pathname)?,
+    }
+    println!("Saved {}-dimensional embeddings for {} addresses as {}", args.dimensions, embeddings.len(), &file_pathname);
+    Ok(())
+}
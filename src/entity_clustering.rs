@@ -0,0 +1,364 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+
+use crate::analysis_pass::{AnalysisPass, Report};
+use crate::annotations::{Annotation, Annotations};
+use crate::atomic_file::{commit_csv, csv_writer, write_atomically};
+use crate::cli::EntitiesArgs;
+use crate::counterparty_similarity::{counterparty_volumes, pair_similarity};
+use crate::pricing::PriceProvider;
+use crate::{deserialize_graph, EdgeKind, DATA_STORAGE_FOLDER, G};
+
+const DEFAULT_MIN_CONFIDENCE: f64 = 0.5;
+
+// Counterparties with more neighbors than this are exchanges or popular contracts; sharing them says
+// little about ownership, so they do not propose candidate pairs.
+const MAX_CANDIDATE_HUB_DEGREE: usize = 100;
+// A deposit address forwards at least this share of what it receives, all of it to one destination
+// that collects from at least MIN_COLLECTOR_SOURCES addresses.
+const DEPOSIT_FORWARD_SHARE: f64 = 0.9;
+const MIN_COLLECTOR_SOURCES: usize = 3;
+// Co-activity is only scored for addresses active in at least this many distinct hours.
+const MIN_ACTIVE_HOURS: usize = 3;
+
+// Evidence weights, combined per pair as a noisy-or: 1 - product of (1 - weight * strength).
+const SIMILARITY_WEIGHT: f64 = 0.6;
+const SHARED_DEPOSIT_WEIGHT: f64 = 0.8;
+const GAS_PAYER_WEIGHT: f64 = 0.5;
+const FUNDED_BY_OTHER_WEIGHT: f64 = 0.4;
+const SHARED_FUNDER_WEIGHT: f64 = 0.3;
+const CO_ACTIVITY_WEIGHT: f64 = 0.3;
+
+const MAX_REPORTED_LINKS: usize = 10;
+
+struct AddressSignals {
+    volumes: HashMap<NodeIndex, f64>,
+    // Sender of the funded-by edge, else of the earliest incoming transfer.
+    funding_source: Option<NodeIndex>,
+    // Senders of the outer call whenever this address moved tokens inside another address's transaction.
+    gas_payers: HashSet<NodeIndex>,
+    active_hours: HashSet<u64>,
+}
+
+fn address_signals(graph: &G, node: NodeIndex, call_senders: &HashMap<&str, HashSet<NodeIndex>>) -> AddressSignals {
+    // A funded-by edge records the funder from the full history; the earliest incoming edge is a fallback.
+    let incoming = || graph.edges_directed(node, Direction::Incoming).filter(|e| e.source() != node);
+    let funding_source = incoming()
+        .find(|e| e.weight().kind == EdgeKind::FundedBy)
+        .or_else(|| incoming().min_by_key(|e| (e.weight().timestamp, e.weight().block_number)))
+        .map(|e| e.source());
+    let mut gas_payers = HashSet::new();
+    let mut active_hours = HashSet::new();
+    for edge in graph.edges_directed(node, Direction::Outgoing) {
+        active_hours.insert(edge.weight().timestamp / 3600);
+        if edge.weight().kind != EdgeKind::Call {
+            if let Some(senders) = call_senders.get(edge.weight().hash.as_str()) {
+                gas_payers.extend(senders.iter().filter(|&&sender| sender != node));
+            }
+        }
+    }
+    AddressSignals { volumes: counterparty_volumes(graph, node), funding_source, gas_payers, active_hours }
+}
+
+// Deposit addresses with the collector they forward to.
+fn deposit_addresses(graph: &G) -> HashMap<NodeIndex, NodeIndex> {
+    let usd = |e: petgraph::graph::EdgeReference<crate::Transaction>| e.weight().data.as_ref().map_or(0.0, |d| d.usd_value);
+    graph
+        .node_indices()
+        .filter_map(|node| {
+            let destinations: HashSet<NodeIndex> = graph.neighbors_directed(node, Direction::Outgoing).filter(|&d| d != node).collect();
+            let collector = match destinations.len() {
+                1 => *destinations.iter().next().unwrap(),
+                _ => return None,
+            };
+            let received = graph.edges_directed(node, Direction::Incoming).filter(|e| e.source() != collector).fold(0.0, |acc, e| acc + usd(e));
+            let forwarded = graph.edges_connecting(node, collector).fold(0.0, |acc, e| acc + usd(e));
+            let collector_sources = graph.neighbors_directed(collector, Direction::Incoming).collect::<HashSet<NodeIndex>>().len();
+            (received > 0.0 && forwarded >= DEPOSIT_FORWARD_SHARE * received && collector_sources >= MIN_COLLECTOR_SOURCES)
+                .then_some((node, collector))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct PairEvidence {
+    pub a: NodeIndex,
+    pub b: NodeIndex,
+    pub confidence: f64,
+    pub reasons: Vec<String>,
+}
+
+fn noisy_or(strengths: &[f64]) -> f64 {
+    1.0 - strengths.iter().fold(1.0, |acc, s| acc * (1.0 - s.clamp(0.0, 1.0)))
+}
+
+struct Clusterer<'a> {
+    graph: &'a G,
+    signals: Vec<AddressSignals>,
+    deposits: HashMap<NodeIndex, NodeIndex>,
+}
+
+impl<'a> Clusterer<'a> {
+    fn new(graph: &'a G) -> Clusterer<'a> {
+        let mut call_senders: HashMap<&str, HashSet<NodeIndex>> = HashMap::new();
+        for edge in graph.edge_references().filter(|e| e.weight().kind == EdgeKind::Call) {
+            call_senders.entry(edge.weight().hash.as_str()).or_default().insert(edge.source());
+        }
+        let signals = graph.node_indices().map(|n| address_signals(graph, n, &call_senders)).collect();
+        Clusterer { graph, signals, deposits: deposit_addresses(graph) }
+    }
+
+    // Pairs that share a counterparty below the hub limit, a funder, a gas payer or a deposit address,
+    // or where one funded or paid gas for the other.
+    fn candidate_pairs(&self) -> BTreeSet<(NodeIndex, NodeIndex)> {
+        let mut pairs = BTreeSet::new();
+        let mut add_group = |members: &[NodeIndex]| {
+            for i in 0..members.len() {
+                for j in i + 1..members.len() {
+                    pairs.insert((members[i].min(members[j]), members[i].max(members[j])));
+                }
+            }
+        };
+        let mut groups: HashMap<(u8, NodeIndex), Vec<NodeIndex>> = HashMap::new();
+        for node in self.graph.node_indices() {
+            let signals = &self.signals[node.index()];
+            for &counterparty in signals.volumes.keys() {
+                if self.signals[counterparty.index()].volumes.len() <= MAX_CANDIDATE_HUB_DEGREE {
+                    groups.entry((0, counterparty)).or_default().push(node);
+                }
+            }
+            if let Some(funder) = signals.funding_source {
+                groups.entry((1, funder)).or_default().extend([node, funder]);
+            }
+            for &payer in signals.gas_payers.iter() {
+                groups.entry((2, payer)).or_default().extend([node, payer]);
+            }
+        }
+        for (&deposit, _) in self.deposits.iter() {
+            let senders: Vec<NodeIndex> = self.graph.neighbors_directed(deposit, Direction::Incoming).chain([deposit]).collect();
+            groups.entry((3, deposit)).or_default().extend(senders);
+        }
+        for members in groups.values_mut() {
+            members.sort();
+            members.dedup();
+            add_group(members);
+        }
+        pairs
+    }
+
+    fn shares_deposit(&self, a: NodeIndex, b: NodeIndex) -> Option<NodeIndex> {
+        let sends_to = |sender: NodeIndex, deposit: NodeIndex| sender == deposit || self.graph.find_edge(sender, deposit).is_some();
+        self.graph
+            .neighbors_directed(a, Direction::Outgoing)
+            .chain([a])
+            .filter(|d| self.deposits.contains_key(d) && sends_to(b, *d))
+            .min()
+    }
+
+    fn evidence(&self, a: NodeIndex, b: NodeIndex) -> PairEvidence {
+        let (signals_a, signals_b) = (&self.signals[a.index()], &self.signals[b.index()]);
+        let address = |n: NodeIndex| self.graph[n].as_str();
+        let mut strengths = Vec::new();
+        let mut reasons = Vec::new();
+
+        let similarity = pair_similarity(self.graph, a, b, &signals_a.volumes, &signals_b.volumes);
+        if similarity.common_counterparties >= 2 {
+            // Unpriced edges leave the volume similarity at zero, so the plain set overlap stands in for it.
+            strengths.push(SIMILARITY_WEIGHT * similarity.volume_similarity.max(similarity.jaccard));
+            reasons.push(format!(
+                "{} shared counterparties, Jaccard {:.3}, volume similarity {:.3}",
+                similarity.common_counterparties, similarity.jaccard, similarity.volume_similarity
+            ));
+        }
+        if let Some(deposit) = self.shares_deposit(a, b) {
+            strengths.push(SHARED_DEPOSIT_WEIGHT);
+            reasons.push(format!("deposit address {} forwarding to {}", address(deposit), address(self.deposits[&deposit])));
+        }
+        let shared_payers: BTreeSet<&str> = signals_a.gas_payers.intersection(&signals_b.gas_payers).map(|&p| address(p)).collect();
+        if !shared_payers.is_empty() || signals_a.gas_payers.contains(&b) || signals_b.gas_payers.contains(&a) {
+            strengths.push(GAS_PAYER_WEIGHT);
+            let payers: Vec<&str> = if shared_payers.is_empty() {
+                vec![if signals_a.gas_payers.contains(&b) { address(b) } else { address(a) }]
+            } else {
+                shared_payers.into_iter().collect()
+            };
+            reasons.push(format!("gas paid by {}", payers.join(", ")));
+        }
+        if signals_a.funding_source == Some(b) || signals_b.funding_source == Some(a) {
+            strengths.push(FUNDED_BY_OTHER_WEIGHT);
+            let (funded, funder) = if signals_a.funding_source == Some(b) { (a, b) } else { (b, a) };
+            reasons.push(format!("{} first funded by {}", address(funded), address(funder)));
+        } else if let Some(funder) = signals_a.funding_source.filter(|&f| signals_b.funding_source == Some(f)) {
+            strengths.push(SHARED_FUNDER_WEIGHT);
+            reasons.push(format!("both first funded by {}", address(funder)));
+        }
+        if signals_a.active_hours.len() >= MIN_ACTIVE_HOURS && signals_b.active_hours.len() >= MIN_ACTIVE_HOURS {
+            let shared = signals_a.active_hours.intersection(&signals_b.active_hours).count();
+            let co_activity = shared as f64 / signals_a.active_hours.union(&signals_b.active_hours).count() as f64;
+            if shared > 0 {
+                strengths.push(CO_ACTIVITY_WEIGHT * co_activity);
+                reasons.push(format!("active in {} of the same hours, co-activity {:.3}", shared, co_activity));
+            }
+        }
+        PairEvidence { a, b, confidence: noisy_or(&strengths), reasons }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProbableEntity {
+    pub members: Vec<String>,
+    // Mean confidence of the pairs that linked the group.
+    pub confidence: f64,
+    pub links: Vec<PairEvidence>,
+    // Counterparties shared by at least two members, by how many members share them.
+    pub shared_counterparties: Vec<(String, usize)>,
+    pub funding_sources: Vec<String>,
+    pub gas_payers: Vec<String>,
+}
+
+fn find_root(parents: &mut [usize], node: usize) -> usize {
+    let mut root = node;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    parents[node] = root;
+    root
+}
+
+// Links every candidate pair at or above the confidence threshold and returns the resulting groups of two
+// or more addresses, most confident first.
+pub fn probable_entities(graph: &G, min_confidence: f64) -> Vec<ProbableEntity> {
+    let clusterer = Clusterer::new(graph);
+    let links: Vec<PairEvidence> =
+        clusterer.candidate_pairs().into_iter().map(|(a, b)| clusterer.evidence(a, b)).filter(|e| e.confidence >= min_confidence).collect();
+
+    let mut parents: Vec<usize> = (0..graph.node_count()).collect();
+    for link in links.iter() {
+        let (root_a, root_b) = (find_root(&mut parents, link.a.index()), find_root(&mut parents, link.b.index()));
+        parents[root_a] = root_b;
+    }
+    let mut groups: BTreeMap<usize, Vec<PairEvidence>> = BTreeMap::new();
+    for link in links.into_iter() {
+        groups.entry(find_root(&mut parents, link.a.index())).or_default().push(link);
+    }
+
+    let mut entities: Vec<ProbableEntity> = groups
+        .into_values()
+        .map(|links| {
+            let members: BTreeSet<NodeIndex> = links.iter().flat_map(|l| [l.a, l.b]).collect();
+            let mut shared: HashMap<NodeIndex, usize> = HashMap::new();
+            for &member in members.iter() {
+                for &counterparty in clusterer.signals[member.index()].volumes.keys().filter(|c| !members.contains(c)) {
+                    *shared.entry(counterparty).or_default() += 1;
+                }
+            }
+            let mut shared_counterparties: Vec<(String, usize)> =
+                shared.into_iter().filter(|(_, count)| *count >= 2).map(|(c, count)| (graph[c].clone(), count)).collect();
+            shared_counterparties.sort_by(|x, y| y.1.cmp(&x.1).then(x.0.cmp(&y.0)));
+            let signals = members.iter().map(|m| &clusterer.signals[m.index()]);
+            let funding_sources: BTreeSet<String> = signals.clone().filter_map(|s| s.funding_source).map(|f| graph[f].clone()).collect();
+            let gas_payers: BTreeSet<String> = signals.flat_map(|s| s.gas_payers.iter()).map(|&p| graph[p].clone()).collect();
+            ProbableEntity {
+                members: members.iter().map(|&m| graph[m].clone()).collect(),
+                confidence: links.iter().fold(0.0, |acc, l| acc + l.confidence) / links.len() as f64,
+                links,
+                shared_counterparties,
+                funding_sources: funding_sources.into_iter().collect(),
+                gas_payers: gas_payers.into_iter().collect(),
+            }
+        })
+        .collect();
+    entities.sort_by(|x, y| y.confidence.partial_cmp(&x.confidence).unwrap().then(y.members.len().cmp(&x.members.len())));
+    entities
+}
+
+pub fn entities_report(graph: &G, entities: &[ProbableEntity], annotations: &Annotations) -> String {
+    let clustered = entities.iter().map(|e| e.members.len()).sum::<usize>();
+    let mut report = format!("{} probable entities covering {} of {} addresses\n", entities.len(), clustered, graph.node_count());
+    for (i, entity) in entities.iter().enumerate() {
+        report.push_str(&format!("\nEntity {}: {} addresses, confidence {:.3}\n", i + 1, entity.members.len(), entity.confidence));
+        for member in entity.members.iter() {
+            report.push_str(&format!("  {}\n", annotations.mention(member)));
+        }
+        let mut links: Vec<&PairEvidence> = entity.links.iter().collect();
+        links.sort_by(|x, y| y.confidence.partial_cmp(&x.confidence).unwrap());
+        for link in links.iter().take(MAX_REPORTED_LINKS) {
+            report.push_str(&format!("  Link {} ~ {} ({:.3}): {}\n", graph[link.a], graph[link.b], link.confidence, link.reasons.join("; ")));
+        }
+        if links.len() > MAX_REPORTED_LINKS {
+            report.push_str(&format!("  ... and {} weaker links\n", links.len() - MAX_REPORTED_LINKS));
+        }
+        if !entity.shared_counterparties.is_empty() {
+            let shown: Vec<String> = entity.shared_counterparties.iter().take(5).map(|(c, count)| format!("{} ({})", c, count)).collect();
+            report.push_str(&format!("  Shared counterparties: {}\n", shown.join(", ")));
+        }
+        if !entity.funding_sources.is_empty() {
+            report.push_str(&format!("  Funding sources: {}\n", entity.funding_sources.join(", ")));
+        }
+        if !entity.gas_payers.is_empty() {
+            report.push_str(&format!("  Gas payers: {}\n", entity.gas_payers.join(", ")));
+        }
+    }
+    report
+}
+
+pub struct EntitiesPass;
+
+impl AnalysisPass for EntitiesPass {
+    fn name(&self) -> &str {
+        "entities"
+    }
+
+    // Member lines show the tags of annotated addresses, so tag changes must not hit a cached report.
+    fn parameters(&self) -> String {
+        let annotations = Annotations::load().unwrap_or_default();
+        let tagged = annotations.addresses.iter().filter(|(_, annotation)| !annotation.tags.is_empty());
+        tagged.map(|(address, annotation)| format!("{}={}", address, annotation.tag_list())).collect::<Vec<String>>().join(" ")
+    }
+
+    fn run(&self, graph: &G, _prices: &dyn PriceProvider) -> Result<Report> {
+        let entities = probable_entities(graph, DEFAULT_MIN_CONFIDENCE);
+        let metrics = BTreeMap::from([
+            ("entities".to_string(), entities.len() as f64),
+            ("clustered_addresses".to_string(), entities.iter().map(|e| e.members.len()).sum::<usize>() as f64),
+        ]);
+        Ok(Report { text: entities_report(graph, &entities, &Annotations::load()?), metrics })
+    }
+}
+
+#[derive(Serialize)]
+struct MembershipRow<'a> {
+    entity: usize,
+    address: &'a str,
+    entity_size: usize,
+    confidence: f64,
+    tags: String,
+}
+
+pub fn run_entities_command(args: &EntitiesArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let entities = probable_entities(&graph, args.min_confidence);
+    let annotations = Annotations::load()?;
+    let report = entities_report(&graph, &entities, &annotations);
+    print!("{}", report);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let report_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.report);
+    write_atomically(&report_pathname, report.as_bytes())?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for (i, entity) in entities.iter().enumerate() {
+        for member in entity.members.iter() {
+            let tags = annotations.get(member).map(Annotation::tag_list).unwrap_or_default();
+            writer.serialize(MembershipRow { entity: i + 1, address: member, entity_size: entity.members.len(), confidence: entity.confidence, tags })?;
+        }
+    }
+    commit_csv(writer)?;
+    println!("\nSaved entity report as {} and memberships as {}", &report_pathname, &file_pathname);
+    Ok(())
+}
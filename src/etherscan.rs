@@ -0,0 +1,192 @@
+use eyre::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "crawl")]
+use {
+    crate::{exit_status::{ClassifiedError, FailureKind}, history_window::SortDirection, http_client, RawTransaction},
+    reqwest::Client,
+};
+
+#[cfg(feature = "crawl")]
+const API_URL: &str = "https://api.bscscan.com/api";
+// Addresses balancemulti accepts in one request.
+#[cfg(feature = "crawl")]
+pub const MAX_BALANCE_ADDRESSES: usize = 20;
+
+// Reply of the account, block and contract modules. Errors come back with status 0 and a message in place of
+// the result, so a result that fails to decode carries the reason in the decoding error.
+#[derive(Debug, Deserialize)]
+pub struct ApiResponse<T> {
+    pub status: String,
+    pub message: String,
+    pub result: T,
+}
+
+// Reply of the JSON-RPC proxy module, without a result when the call failed or found nothing.
+#[derive(Debug, Deserialize)]
+pub struct ProxyResponse<T> {
+    pub result: Option<T>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InternalTransaction {
+    pub block_number: String,
+    pub time_stamp: String,
+    pub hash: String,
+    pub from: String,
+    pub to: String,
+    pub value: String,
+    pub contract_address: String,
+    pub input: String,
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub gas: String,
+    pub gas_used: String,
+    #[serde(default)]
+    pub trace_id: String,
+    pub is_error: String,
+    #[serde(default)]
+    pub err_code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenTransfer {
+    pub block_number: String,
+    pub time_stamp: String,
+    pub hash: String,
+    pub nonce: String,
+    pub block_hash: String,
+    pub from: String,
+    pub contract_address: String,
+    pub to: String,
+    pub value: String,
+    pub token_name: String,
+    pub token_symbol: String,
+    pub token_decimal: String,
+    pub transaction_index: String,
+    pub gas: String,
+    pub gas_price: String,
+    pub gas_used: String,
+    pub cumulative_gas_used: String,
+    pub input: String,
+    pub confirmations: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct AccountBalance {
+    pub account: String,
+    // Wei as a decimal string.
+    pub balance: String,
+}
+
+pub fn decode<T: DeserializeOwned>(body_bytes: &[u8]) -> Result<T> {
+    serde_json::from_slice::<T>(body_bytes).map_err(|_| eyre::eyre!("Failed to decode JSON response: {}", String::from_utf8_lossy(body_bytes)))
+}
+
+// Failures the API reports in place of a result that no retry fixes. The reply's text is all that tells them
+// apart, so this is the one place reading it.
+#[cfg(feature = "crawl")]
+fn api_error(body_bytes: &[u8]) -> Option<ClassifiedError> {
+    let reply: ApiResponse<String> = serde_json::from_slice(body_bytes).ok()?;
+    let reason = reply.result.to_lowercase();
+    let kind = if reason.contains("invalid api key") {
+        FailureKind::InvalidKey
+    } else if reason.contains("max daily") || reason.contains("daily limit") {
+        FailureKind::QuotaExhausted
+    } else {
+        return None;
+    };
+    Some(ClassifiedError::new(kind, format!("{}: {}", reply.message, reply.result)))
+}
+
+// Block range, page and order of the account module's transaction lists.
+#[cfg(feature = "crawl")]
+#[derive(Clone, Copy, Debug)]
+pub struct TransactionQuery {
+    pub start_block: u64,
+    pub end_block: u64,
+    pub page: usize,
+    pub offset: usize,
+    pub sort: SortDirection,
+}
+
+#[cfg(feature = "crawl")]
+impl TransactionQuery {
+    fn parameters(&self, address: &str) -> Vec<(&'static str, String)> {
+        vec![
+            ("address", address.to_string()),
+            ("startblock", self.start_block.to_string()),
+            ("endblock", self.end_block.to_string()),
+            ("page", self.page.to_string()),
+            ("offset", self.offset.to_string()),
+            ("sort", self.sort.as_str().to_string()),
+        ]
+    }
+}
+
+// Typed endpoints of the explorer API over a shared client. Every call returns the decoded reply with the size
+// of its body, for the request metrics; checking the status is left to the caller, since an empty history and
+// a failure both come back with status 0.
+#[cfg(feature = "crawl")]
+pub struct Etherscan<'a> {
+    client: &'a Client,
+    api_key: &'a str,
+}
+
+#[cfg(feature = "crawl")]
+impl<'a> Etherscan<'a> {
+    pub fn new(client: &'a Client, api_key: &'a str) -> Etherscan<'a> {
+        Etherscan { client, api_key }
+    }
+
+    // Any endpoint by module and action, for the ones without a typed method.
+    pub async fn call<T: DeserializeOwned>(&self, module: &str, action: &str, parameters: &[(&str, String)]) -> Result<(T, usize)> {
+        let response = self
+            .client
+            .get(API_URL)
+            .query(&[("module", module), ("action", action)])
+            .query(parameters)
+            .query(&[("apikey", self.api_key)])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(ClassifiedError::new(FailureKind::Network, format!("Response status errored: {}", response.status())).into());
+        }
+        let body_bytes = http_client::body_bytes(response).await?;
+        match decode(&body_bytes) {
+            Ok(reply) => Ok((reply, body_bytes.len())),
+            Err(e) => Err(api_error(&body_bytes).map_or(e, eyre::Report::from)),
+        }
+    }
+
+    pub async fn proxy<T: DeserializeOwned>(&self, action: &str, parameters: &[(&str, String)]) -> Result<(ProxyResponse<T>, usize)> {
+        self.call("proxy", action, parameters).await
+    }
+
+    pub async fn txlist(&self, address: &str, query: &TransactionQuery) -> Result<(ApiResponse<Vec<RawTransaction>>, usize)> {
+        self.call("account", "txlist", &query.parameters(address)).await
+    }
+
+    pub async fn txlistinternal(&self, address: &str, query: &TransactionQuery) -> Result<(ApiResponse<Vec<InternalTransaction>>, usize)> {
+        self.call("account", "txlistinternal", &query.parameters(address)).await
+    }
+
+    pub async fn tokentx(&self, address: &str, query: &TransactionQuery) -> Result<(ApiResponse<Vec<TokenTransfer>>, usize)> {
+        self.call("account", "tokentx", &query.parameters(address)).await
+    }
+
+    // Latest native balances of up to MAX_BALANCE_ADDRESSES addresses.
+    pub async fn balancemulti(&self, addresses: &[String]) -> Result<(ApiResponse<Vec<AccountBalance>>, usize)> {
+        if addresses.len() > MAX_BALANCE_ADDRESSES {
+            return Err(eyre::eyre!("balancemulti takes at most {} addresses, got {}", MAX_BALANCE_ADDRESSES, addresses.len()));
+        }
+        self.call("account", "balancemulti", &[("address", addresses.join(",")), ("tag", "latest".to_string())]).await
+    }
+
+    // Deployed bytecode at the latest block, 0x for addresses without code.
+    pub async fn getcode(&self, address: &str) -> Result<(ProxyResponse<String>, usize)> {
+        self.proxy("eth_getCode", &[("address", address.to_string()), ("tag", "latest".to_string())]).await
+    }
+}
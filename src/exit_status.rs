@@ -0,0 +1,122 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::sync::Mutex;
+
+use crate::atomic_file::write_atomically;
+use crate::DATA_STORAGE_FOLDER;
+
+pub const ERRORS_FILENAME: &str = "errors.json";
+
+// In order of precedence: the first kind among the recorded failures decides the exit code.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    InvalidKey,
+    QuotaExhausted,
+    Network,
+    // Any other error that stopped the command.
+    Error,
+    // The command finished, but left something out: a cancelled crawl, rejected records, a failed pass.
+    Partial,
+}
+
+impl FailureKind {
+    // Clap exits with 2 on usage errors, so the codes start at 3.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            FailureKind::Error => 1,
+            FailureKind::InvalidKey => 3,
+            FailureKind::QuotaExhausted => 4,
+            FailureKind::Network => 5,
+            FailureKind::Partial => 6,
+        }
+    }
+}
+
+// An error whose kind is known where it is raised, e.g. the API rejecting the key, so the exit code does not
+// depend on the wording of any message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassifiedError {
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+impl ClassifiedError {
+    pub fn new(kind: FailureKind, message: impl Into<String>) -> ClassifiedError {
+        ClassifiedError { kind, message: message.into() }
+    }
+}
+
+impl fmt::Display for ClassifiedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ClassifiedError {}
+
+// The kind of the first classified error in the chain, with transport errors of the HTTP client counted as
+// network failures. None for errors of any other kind.
+pub fn classify(error: &eyre::Report) -> Option<FailureKind> {
+    error.chain().find_map(|cause| {
+        if let Some(classified) = cause.downcast_ref::<ClassifiedError>() {
+            return Some(classified.kind);
+        }
+        #[cfg(feature = "crawl")]
+        if let Some(transport) = cause.downcast_ref::<reqwest::Error>() {
+            if transport.is_connect() || transport.is_timeout() || transport.is_request() || transport.is_body() {
+                return Some(FailureKind::Network);
+            }
+        }
+        None
+    })
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub kind: FailureKind,
+    pub detail: String,
+}
+
+static FAILURES: Lazy<Mutex<Vec<Failure>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+pub fn record(kind: FailureKind, detail: impl Into<String>) {
+    let failure = Failure { kind, detail: detail.into() };
+    let mut failures = FAILURES.lock().unwrap();
+    if !failures.contains(&failure) {
+        failures.push(failure);
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct ErrorSummary {
+    exit_code: i32,
+    command: String,
+    failures: Vec<Failure>,
+    // Files the command finished writing. Every file is written atomically, so these are complete; files
+    // the command did not get to keep their contents from earlier runs.
+    valid_outputs: Vec<String>,
+}
+
+// Exit code of the command from what was recorded. When anything failed, errors.json in the data storage
+// folder says what and which outputs were written; a successful command removes an errors.json left by an
+// earlier run.
+pub fn finish(command: &str, valid_outputs: Vec<String>) -> i32 {
+    let failures = FAILURES.lock().unwrap().clone();
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, ERRORS_FILENAME);
+    let Some(exit_code) = failures.iter().map(|failure| failure.kind).min().map(FailureKind::exit_code) else {
+        let _ = fs::remove_file(&file_pathname);
+        return 0;
+    };
+    let summary = ErrorSummary { exit_code, command: command.to_string(), failures, valid_outputs };
+    let written = fs::create_dir_all(DATA_STORAGE_FOLDER)
+        .map_err(eyre::Report::from)
+        .and_then(|_| write_atomically(&file_pathname, serde_json::to_string_pretty(&summary).unwrap().as_bytes()));
+    match written {
+        Ok(()) => println!("Exiting with code {}, failures are summarized in {}", exit_code, file_pathname),
+        Err(e) => println!("Exiting with code {}, could not write {}: {}", exit_code, file_pathname, e),
+    }
+    exit_code
+}
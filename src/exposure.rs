@@ -0,0 +1,171 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fs;
+
+use crate::annotations::Annotations;
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::{EdgeWeight, ExposureArgs};
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ExposureRow {
+    pub address: String,
+    // Stationary probability of a walker that starts at a flagged address, follows the money proportionally to
+    // the edge weights and jumps back to a flagged address with the restart probability. Sums to one.
+    pub exposure: f64,
+    // Exposure relative to the most exposed address that is not flagged itself, from 0 to 1.
+    pub relative_exposure: f64,
+    // Fewest edges a walk needs from a flagged address, None for addresses no walk reaches.
+    pub hops: Option<usize>,
+    pub flagged: bool,
+}
+
+pub struct ExposureParameters {
+    pub weight: EdgeWeight,
+    pub undirected: bool,
+    pub restart_probability: f64,
+    pub iterations: usize,
+    pub tolerance: f64,
+}
+
+// Transition links per node, weights summed over parallel edges and normalized to probabilities.
+fn transitions(graph: &G, weight: EdgeWeight, undirected: bool) -> Vec<Vec<(usize, f64)>> {
+    let mut aggregated: Vec<HashMap<usize, f64>> = vec![HashMap::new(); graph.node_count()];
+    for edge in graph.raw_edges() {
+        let (source, target) = (edge.source().index(), edge.target().index());
+        let edge_weight = match weight {
+            EdgeWeight::Count => 1.0,
+            EdgeWeight::UsdVolume => edge.weight.data.as_ref().map_or(0.0, |data| data.usd_value),
+        };
+        if edge_weight <= 0.0 || source == target {
+            continue;
+        }
+        *aggregated[source].entry(target).or_insert(0.0) += edge_weight;
+        if undirected {
+            *aggregated[target].entry(source).or_insert(0.0) += edge_weight;
+        }
+    }
+    aggregated
+        .into_iter()
+        .map(|links| {
+            let total: f64 = links.values().sum();
+            let mut links: Vec<(usize, f64)> = links.into_iter().map(|(neighbor, w)| (neighbor, w / total)).collect();
+            links.sort_by_key(|&(neighbor, _)| neighbor);
+            links
+        })
+        .collect()
+}
+
+fn hops_from(links: &[Vec<(usize, f64)>], flagged: &[usize]) -> Vec<Option<usize>> {
+    let mut hops = vec![None; links.len()];
+    let mut queue: VecDeque<usize> = flagged.iter().copied().collect();
+    flagged.iter().for_each(|&n| hops[n] = Some(0));
+    while let Some(n) = queue.pop_front() {
+        for &(neighbor, _) in links[n].iter() {
+            if hops[neighbor].is_none() {
+                hops[neighbor] = Some(hops[n].unwrap() + 1);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    hops
+}
+
+// Personalized PageRank by power iteration, restarting uniformly over the flagged addresses. Walkers at an
+// address without outgoing links restart too, so no probability leaks away.
+pub fn exposure_scores(graph: &G, flagged: &[NodeIndex], parameters: &ExposureParameters) -> Vec<ExposureRow> {
+    let links = transitions(graph, parameters.weight, parameters.undirected);
+    let flagged: Vec<usize> = flagged.iter().map(|n| n.index()).collect::<BTreeSet<usize>>().into_iter().collect();
+    let restart = 1.0 / flagged.len() as f64;
+    let mut scores = vec![0.0; graph.node_count()];
+    flagged.iter().for_each(|&n| scores[n] = restart);
+    for iteration in 0..parameters.iterations {
+        let mut next = vec![0.0; scores.len()];
+        let mut restarting = parameters.restart_probability;
+        for (n, &score) in scores.iter().enumerate() {
+            if links[n].is_empty() {
+                restarting += (1.0 - parameters.restart_probability) * score;
+            }
+            for &(neighbor, probability) in links[n].iter() {
+                next[neighbor] += (1.0 - parameters.restart_probability) * score * probability;
+            }
+        }
+        flagged.iter().for_each(|&n| next[n] += restarting * restart);
+        let change: f64 = next.iter().zip(scores.iter()).map(|(a, b)| (a - b).abs()).sum();
+        scores = next;
+        if change < parameters.tolerance {
+            println!("Exposure converged after {} iterations", iteration + 1);
+            break;
+        }
+    }
+
+    let hops = hops_from(&links, &flagged);
+    let highest_unflagged = (0..scores.len()).filter(|n| !flagged.contains(n)).map(|n| scores[n]).fold(0.0, f64::max);
+    let mut rows: Vec<ExposureRow> = graph
+        .node_indices()
+        .map(|n| ExposureRow {
+            address: graph[n].clone(),
+            exposure: scores[n.index()],
+            relative_exposure: if flagged.contains(&n.index()) {
+                1.0
+            } else if highest_unflagged > 0.0 {
+                scores[n.index()] / highest_unflagged
+            } else {
+                0.0
+            },
+            hops: hops[n.index()],
+            flagged: flagged.contains(&n.index()),
+        })
+        .collect();
+    rows.sort_by(|a, b| b.exposure.partial_cmp(&a.exposure).unwrap().then(a.address.cmp(&b.address)));
+    rows
+}
+
+// Graph nodes of the given addresses and of the addresses annotated with any of the tags.
+pub fn flagged_nodes(graph: &G, annotations: &Annotations, tags: &[String], addresses: &[String]) -> Vec<NodeIndex> {
+    let mut flagged_addresses: BTreeSet<String> = addresses.iter().map(|address| address.to_lowercase()).collect();
+    for tag in tags.iter() {
+        flagged_addresses.extend(annotations.with_tag(tag).map(|(address, _)| address.clone()));
+    }
+    graph.node_indices().filter(|&n| flagged_addresses.contains(&graph[n].to_lowercase())).collect()
+}
+
+pub fn run_exposure_command(args: &ExposureArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let annotations = Annotations::load()?;
+    let flagged = flagged_nodes(&graph, &annotations, &args.tags, &args.flagged);
+    if flagged.is_empty() {
+        return Err(eyre::eyre!("None of the flagged addresses is in the graph, tag addresses {} with annotate or pass --flagged", args.tags.join(" or ")));
+    }
+    println!("Scoring exposure to {} flagged addresses", flagged.len());
+    let parameters = ExposureParameters {
+        weight: args.weight,
+        undirected: args.undirected,
+        restart_probability: args.restart_probability,
+        iterations: args.iterations,
+        tolerance: args.tolerance,
+    };
+    let rows = exposure_scores(&graph, &flagged, &parameters);
+
+    for row in rows.iter().filter(|row| !row.flagged && row.exposure > 0.0).take(args.show) {
+        println!(
+            "{}: exposure {:.6} ({:.3} of the highest), {} hops from a flagged address",
+            annotations.mention(&row.address),
+            row.exposure,
+            row.relative_exposure,
+            row.hops.unwrap()
+        );
+    }
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for row in rows.iter() {
+        writer.serialize(row)?;
+    }
+    commit_csv(writer)?;
+    let exposed = rows.iter().filter(|row| !row.flagged && row.exposure > 0.0).count();
+    println!("{} of {} unflagged addresses are exposed, saved the scores as {}", exposed, rows.len() - flagged.len(), &file_pathname);
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[cfg(feature = "crawl")]
+use {
+    crate::{
+        atomic_file::write_atomically, cli::RetryFailedArgs, crawl_events::CrawlSinks, delta_crawl::{load_crawl_state, save_crawl, PARSED_GRAPH_FILENAME},
+        deserialize_graph, exit_status::{self, FailureKind}, graph_builder::GraphBuilder, graph_data_collection_procedure, http_client, read_api_key, request_metrics::RequestMetricsLog,
+        throttle::{eta_label, Throttle}, AddressPriority, DATA_STORAGE_FOLDER,
+    },
+    eyre::Result,
+    priority_queue::PriorityQueue,
+    std::cmp::Reverse,
+    std::fmt::Write,
+    tokio::runtime::Runtime,
+};
+
+#[cfg(feature = "crawl")]
+pub const FAILED_ADDRESSES_FILENAME: &str = "failed_addresses.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FailedFetch {
+    // Error of the request that ran out of attempts in the latest failed fetch.
+    pub error: String,
+    // Fetches of the address in a row that failed, over crawl runs and retries.
+    pub failures: usize,
+    // Records fetched before the failure, which are in the graph.
+    pub records_kept: usize,
+}
+
+// Addresses whose history fetch gave up on a request, kept until a retry fetches them in full.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct FailedAddresses {
+    pub addresses: BTreeMap<String, FailedFetch>,
+}
+
+#[cfg(feature = "crawl")]
+impl FailedAddresses {
+    pub fn record(&mut self, address: &str, error: &str, records_kept: usize) {
+        let failures = self.addresses.get(address).map_or(0, |failed| failed.failures) + 1;
+        self.addresses.insert(address.to_string(), FailedFetch { error: error.to_string(), failures, records_kept });
+    }
+
+    pub fn resolve(&mut self, address: &str) {
+        self.addresses.remove(address);
+    }
+
+    pub fn describe(&self) -> String {
+        let mut description = format!("Failed addresses: {}\n", self.addresses.len());
+        for (address, failed) in self.addresses.iter() {
+            writeln!(description, "  {}: {} failed fetches, {} records kept, {}", address, failed.failures, failed.records_kept, failed.error).unwrap();
+        }
+        if !self.addresses.is_empty() {
+            writeln!(description, "Run retry-failed to fetch them again").unwrap();
+        }
+        description
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, FAILED_ADDRESSES_FILENAME);
+        write_atomically(&file_pathname, serde_json::to_string_pretty(&self.addresses)?.as_bytes())
+    }
+}
+
+// Fetches the whole history window of every failed address again into the stored graph. Records the failed
+// fetch kept are deduplicated by hash, and counterparties found on the way join the frontier for --resume.
+#[cfg(feature = "crawl")]
+pub fn run_retry_failed_command(args: &RetryFailedArgs) -> Result<()> {
+    let (blockchain_graph, _) = deserialize_graph(PARSED_GRAPH_FILENAME)?;
+    let mut crawl_state = load_crawl_state()?;
+    let mut addresses: Vec<String> = crawl_state.failed.addresses.keys().cloned().collect();
+    if !args.addresses.is_empty() {
+        let selected: Vec<String> = args.addresses.iter().map(|address| address.to_lowercase()).collect();
+        if let Some(unknown) = selected.iter().find(|address| !addresses.contains(address)) {
+            return Err(eyre::eyre!("{} is not among the failed addresses in {}", unknown, FAILED_ADDRESSES_FILENAME));
+        }
+        addresses = selected;
+    }
+    if addresses.is_empty() {
+        println!("No failed addresses to retry");
+        return Ok(());
+    }
+
+    let api_key = read_api_key()?;
+    let client = http_client::client();
+    let throttle = Throttle::new(1).with_metrics_log(RequestMetricsLog::create()?);
+    let edge_count_before = blockchain_graph.edge_count();
+    let mut graph_builder = GraphBuilder::from_graph(blockchain_graph);
+    let mut priority_pq: PriorityQueue<String, AddressPriority> = PriorityQueue::new();
+    for (address, relevance) in crawl_state.frontier.iter() {
+        priority_pq.push(address.clone(), (*relevance, Reverse(address.clone())));
+    }
+    let mut sinks = CrawlSinks::default();
+    let rt = Runtime::new()?;
+
+    let mut recovered = 0;
+    for (i, address) in addresses.iter().enumerate() {
+        if throttle.is_aborted() {
+            println!("Retry stopped after {} / {} addresses", i, addresses.len());
+            break;
+        }
+        let outcome = rt.block_on(graph_data_collection_procedure(
+            &mut priority_pq,
+            &mut graph_builder,
+            &client,
+            &api_key,
+            &throttle,
+            address.clone(),
+            crawl_state.history_window,
+            crawl_state.ingest_options(),
+            &mut sinks,
+        ));
+        if outcome.failure.is_none() {
+            recovered += 1;
+        }
+        crawl_state.record_fetch(address, outcome);
+        println!("Retry progress is {} / {} addresses, {}", i + 1, addresses.len(), eta_label(throttle.eta(i + 1, addresses.len())));
+    }
+    print!("{}", throttle.finish()?);
+
+    crawl_state.frontier = priority_pq
+        .into_sorted_iter()
+        .filter(|(address, _)| !crawl_state.visited.contains(address))
+        .map(|(address, (relevance, _))| (address, relevance))
+        .collect();
+    let graph = graph_builder.into_graph();
+    println!(
+        "Recovered {} of {} failed addresses, adding {} transactions to the stored graph",
+        recovered,
+        addresses.len(),
+        graph.edge_count() - edge_count_before
+    );
+    print!("{}", crawl_state.failed.describe());
+    if !crawl_state.failed.addresses.is_empty() {
+        exit_status::record(FailureKind::Partial, format!("{} addresses could not be fetched in full, see {}", crawl_state.failed.addresses.len(), FAILED_ADDRESSES_FILENAME));
+    }
+    save_crawl(&graph, &crawl_state)
+}
@@ -0,0 +1,169 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::FeaturesArgs;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+const ROUND_NUMBER_USD_MULTIPLE: f64 = 100.0;
+const NIGHT_HOURS_UTC: std::ops::Range<u64> = 0..6;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct NodeFeatures {
+    pub address: String,
+    pub in_degree: usize,
+    pub out_degree: usize,
+    pub unique_in_counterparties: usize,
+    pub unique_out_counterparties: usize,
+    pub total_in_usd: f64,
+    pub total_out_usd: f64,
+    pub mean_transfer_usd: f64,
+    pub max_transfer_usd: f64,
+    pub std_transfer_usd: f64,
+    pub first_seen: u64,
+    pub last_seen: u64,
+    pub activity_span_seconds: u64,
+    pub burstiness: f64,
+    pub counterparty_diversity: f64,
+    pub out_volume_entropy_bits: f64,
+    pub out_volume_entropy_normalized: f64,
+    pub round_number_fraction: f64,
+    pub night_day_ratio: f64,
+}
+
+fn mean_and_std(values: &[f64]) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    (mean, variance.sqrt())
+}
+
+// Goh-Barabasi burstiness of inter-event times: -1 periodic, 0 Poisson, close to 1 bursty.
+fn burstiness(sorted_timestamps: &[u64]) -> f64 {
+    let inter_event_times: Vec<f64> = sorted_timestamps.windows(2).map(|w| (w[1] - w[0]) as f64).collect();
+    let (mean, std) = mean_and_std(&inter_event_times);
+    if mean + std == 0.0 {
+        0.0
+    } else {
+        (std - mean) / (std + mean)
+    }
+}
+
+// Shannon entropy of outgoing USD volume across recipients. Near zero for one-to-one conduits, close to
+// log2(recipients) for distributors spraying equal amounts; the normalized value divides by that maximum.
+pub fn out_volume_entropy(graph: &G, node: NodeIndex) -> (f64, f64) {
+    let mut volume_by_recipient: HashMap<NodeIndex, f64> = HashMap::new();
+    for edge in graph.edges_directed(node, Direction::Outgoing) {
+        if let Some(data) = &edge.weight().data {
+            *volume_by_recipient.entry(edge.target()).or_default() += data.usd_value;
+        }
+    }
+    let total: f64 = volume_by_recipient.values().fold(0.0, |acc, v| acc + v);
+    if total <= 0.0 || volume_by_recipient.len() < 2 {
+        return (0.0, 0.0);
+    }
+    let entropy = volume_by_recipient
+        .values()
+        .filter(|&&v| v > 0.0)
+        .map(|v| v / total)
+        .fold(0.0, |acc, p| acc - p * p.log2());
+    (entropy, entropy / (volume_by_recipient.len() as f64).log2())
+}
+
+pub fn extract_node_features(graph: &G) -> Vec<NodeFeatures> {
+    graph
+        .node_indices()
+        .map(|node| {
+            let incoming: Vec<_> = graph.edges_directed(node, Direction::Incoming).collect();
+            let outgoing: Vec<_> = graph.edges_directed(node, Direction::Outgoing).collect();
+
+            let in_counterparties: HashSet<_> = incoming.iter().map(|e| e.source()).collect();
+            let out_counterparties: HashSet<_> = outgoing.iter().map(|e| e.target()).collect();
+            let all_counterparties: HashSet<_> = in_counterparties.union(&out_counterparties).collect();
+
+            let usd_in: Vec<f64> = incoming.iter().filter_map(|e| e.weight().data.as_ref().map(|d| d.usd_value)).collect();
+            let usd_out: Vec<f64> = outgoing.iter().filter_map(|e| e.weight().data.as_ref().map(|d| d.usd_value)).collect();
+            let usd_all: Vec<f64> = usd_in.iter().chain(usd_out.iter()).copied().collect();
+            let (mean_transfer_usd, std_transfer_usd) = mean_and_std(&usd_all);
+            let round_transfers = usd_all
+                .iter()
+                .filter(|&&v| v >= ROUND_NUMBER_USD_MULTIPLE && v % ROUND_NUMBER_USD_MULTIPLE == 0.0)
+                .count();
+
+            let mut timestamps: Vec<u64> = incoming
+                .iter()
+                .chain(outgoing.iter())
+                .map(|e| e.weight().timestamp)
+                .filter(|&t| t != 0)
+                .collect();
+            timestamps.sort_unstable();
+            let first_seen = timestamps.first().copied().unwrap_or_default();
+            let last_seen = timestamps.last().copied().unwrap_or_default();
+
+            let night_count = timestamps.iter().filter(|&&t| NIGHT_HOURS_UTC.contains(&(t % 86_400 / 3_600))).count();
+            let day_count = timestamps.len() - night_count;
+            let night_day_ratio = match (night_count, day_count) {
+                (0, _) => 0.0,
+                (_, 0) => f64::INFINITY,
+                (night, day) => night as f64 / day as f64,
+            };
+
+            let transaction_count = incoming.len() + outgoing.len();
+            let (out_volume_entropy_bits, out_volume_entropy_normalized) = out_volume_entropy(graph, node);
+            NodeFeatures {
+                address: graph[node].clone(),
+                in_degree: incoming.len(),
+                out_degree: outgoing.len(),
+                unique_in_counterparties: in_counterparties.len(),
+                unique_out_counterparties: out_counterparties.len(),
+                total_in_usd: usd_in.iter().fold(0.0, |acc, v| acc + v),
+                total_out_usd: usd_out.iter().fold(0.0, |acc, v| acc + v),
+                mean_transfer_usd,
+                max_transfer_usd: usd_all.iter().copied().fold(0.0, f64::max),
+                std_transfer_usd,
+                first_seen,
+                last_seen,
+                activity_span_seconds: last_seen - first_seen,
+                burstiness: burstiness(&timestamps),
+                counterparty_diversity: if transaction_count == 0 { 0.0 } else { all_counterparties.len() as f64 / transaction_count as f64 },
+                out_volume_entropy_bits,
+                out_volume_entropy_normalized,
+                round_number_fraction: if usd_all.is_empty() { 0.0 } else { round_transfers as f64 / usd_all.len() as f64 },
+                night_day_ratio,
+            }
+        })
+        .collect()
+}
+
+pub fn write_features_csv(features: &[NodeFeatures], file_pathname: &str) -> Result<()> {
+    let mut writer = csv_writer(file_pathname)?;
+    for row in features {
+        writer.serialize(row)?;
+    }
+    commit_csv(writer)?;
+    Ok(())
+}
+
+pub fn run_features_command(args: &FeaturesArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let features = extract_node_features(&graph);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    write_features_csv(&features, &file_pathname)?;
+    println!("Saved features for {} addresses as {}", features.len(), &file_pathname);
+    #[cfg(feature = "polars")]
+    if let Some(parquet) = &args.parquet {
+        let parquet_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, parquet);
+        crate::polars_integration::write_features_parquet(&features, &parquet_pathname)?;
+        println!("Saved features for {} addresses as {}", features.len(), &parquet_pathname);
+    }
+    Ok(())
+}
@@ -0,0 +1,214 @@
+use eyre::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+
+use crate::money::{wei_to_coin, wei_to_gwei, Amount};
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::FeesArgs;
+use crate::pricing::{PriceCache, PriceSeries};
+use crate::time_buckets::{bucket_start, local_label, next_bucket_start, parse_timezone, CalendarPeriod};
+use crate::{deserialize_graph, Transaction, DATA_STORAGE_FOLDER, G};
+use chrono_tz::Tz;
+
+// Price per gas the sender paid. An EIP-1559 transaction pays the base fee plus its priority fee, capped by its
+// max fee, which needs the block's base fee; without it the receipt's gasPrice is the price paid. None when
+// neither is known, which the fee analytics count as unknown rather than guess.
+pub fn effective_gas_price(transaction: &Transaction) -> Option<u64> {
+    if transaction.transaction_type == 2 && transaction.base_fee_per_gas != 0 && transaction.max_fee_per_gas != 0 {
+        return Some(transaction.max_fee_per_gas.min(transaction.base_fee_per_gas.saturating_add(transaction.max_priority_fee_per_gas)));
+    }
+    (transaction.gas_price != 0).then_some(transaction.gas_price)
+}
+
+pub fn effective_fee_wei(transaction: &Transaction) -> Option<u128> {
+    effective_gas_price(transaction).map(|gas_price| gas_price as u128 * transaction.gas_used as u128)
+}
+
+// The token transfers and decoded transfers of a transaction copy its hash and gas fields, so its fee is charged at
+// the first edge seen with that hash. Pseudonymized graphs blank the hashes, leaving every edge charged on its own.
+pub fn charges_fee<'a>(charged_hashes: &mut HashSet<&'a str>, transaction: &'a Transaction) -> bool {
+    transaction.hash.is_empty() || charged_hashes.insert(transaction.hash.as_str())
+}
+
+pub fn percentile(sorted_values: &[f64], fraction: f64) -> f64 {
+    if sorted_values.is_empty() {
+        return 0.0;
+    }
+    let rank = ((fraction * sorted_values.len() as f64).ceil() as usize).clamp(1, sorted_values.len());
+    sorted_values[rank - 1]
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FeePeriod {
+    pub period_start: u64,
+    pub period_end: u64,
+    pub period_label: String,
+    pub transaction_count: usize,
+    pub gas_price_gwei_p10: f64,
+    pub gas_price_gwei_p50: f64,
+    pub gas_price_gwei_p90: f64,
+    pub gas_price_gwei_max: f64,
+    // EIP-1559 transactions among transaction_count, and the median of their priority fee caps.
+    pub dynamic_fee_transactions: usize,
+    pub max_priority_fee_gwei_p50: Option<f64>,
+    pub total_fee_coin: f64,
+    pub total_fee_usd: Option<f64>,
+    pub parsed_volume_usd: f64,
+}
+
+// Fees are converted at the hourly price of the transaction when a price cache is given, otherwise the
+// fee_usd stamped by enrich-usd is used. Edges without a timestamp or with one out of range are left out.
+pub fn fee_percentiles_over_time(graph: &G, period: CalendarPeriod, tz: &Tz, mut prices: Option<(&mut PriceCache, &str)>) -> Result<Vec<FeePeriod>> {
+    // Every edge adds to the parsed volume of its period, the fee statistics take each transaction once.
+    let mut buckets: BTreeMap<u64, (Vec<&Transaction>, Vec<&Transaction>)> = BTreeMap::new();
+    let mut charged_hashes: HashSet<&str> = HashSet::new();
+    for transaction in graph.edge_weights().filter(|t| t.timestamp != 0 && effective_gas_price(t).is_some()) {
+        let Ok(start) = bucket_start(transaction.timestamp, period, tz) else {
+            continue;
+        };
+        let (edges, transactions) = buckets.entry(start).or_default();
+        edges.push(transaction);
+        if charges_fee(&mut charged_hashes, transaction) {
+            transactions.push(transaction);
+        }
+    }
+
+    buckets
+        .into_iter()
+        .map(|(period_start, (edges, transactions))| {
+            let mut gas_prices: Vec<f64> = transactions.iter().filter_map(|t| effective_gas_price(t).map(wei_to_gwei)).collect();
+            gas_prices.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let mut priority_fees: Vec<f64> =
+                transactions.iter().filter(|t| t.transaction_type == 2).map(|t| wei_to_gwei(t.max_priority_fee_per_gas)).collect();
+            priority_fees.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let total_fee_usd = match prices.as_mut() {
+                Some((cache, symbol)) => {
+                    let fees = transactions.iter().filter_map(|t| Some((t.timestamp, wei_to_coin(effective_fee_wei(t)?))));
+                    cache.convert_batch(symbol, fees).into_iter().sum::<Option<f64>>()
+                }
+                None => transactions.iter().map(|t| t.fee_usd).sum::<Option<f64>>(), // Stamped by enrich-usd
+            };
+            Ok(FeePeriod {
+                period_start,
+                period_end: next_bucket_start(period_start, period, tz)?,
+                period_label: local_label(period_start, tz)?,
+                transaction_count: transactions.len(),
+                gas_price_gwei_p10: percentile(&gas_prices, 0.1),
+                gas_price_gwei_p50: percentile(&gas_prices, 0.5),
+                gas_price_gwei_p90: percentile(&gas_prices, 0.9),
+                gas_price_gwei_max: *gas_prices.last().unwrap(),
+                dynamic_fee_transactions: priority_fees.len(),
+                max_priority_fee_gwei_p50: (!priority_fees.is_empty()).then(|| percentile(&priority_fees, 0.5)),
+                total_fee_coin: transactions.iter().filter_map(|t| effective_fee_wei(t)).map(wei_to_coin).sum(),
+                total_fee_usd,
+                parsed_volume_usd: edges.iter().filter_map(|t| t.data.as_ref()).fold(0.0, |acc, d| acc + d.usd_value),
+            })
+        })
+        .collect()
+}
+
+pub fn pearson_correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let n = xs.len() as f64;
+    if xs.len() < 2 {
+        return None;
+    }
+    let (mean_x, mean_y) = (xs.iter().sum::<f64>() / n, ys.iter().sum::<f64>() / n);
+    let covariance: f64 = xs.iter().zip(ys).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance_x: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let variance_y: f64 = ys.iter().map(|y| (y - mean_y).powi(2)).sum();
+    (variance_x > 0.0 && variance_y > 0.0).then(|| covariance / (variance_x * variance_y).sqrt())
+}
+
+fn spike_line(start: u64, end: u64, peak: f64, volume: f64, tz: &Tz) -> Result<String> {
+    Ok(format!(
+        "  {} - {}: peak median {}, parsed volume {}\n",
+        local_label(start, tz)?,
+        local_label(end, tz)?,
+        Amount::Gwei(peak),
+        Amount::Usd(volume)
+    ))
+}
+
+// Consecutive periods whose median gas price exceeds spike_factor times the median over all periods.
+pub fn fee_spike_summary(periods: &[FeePeriod], spike_factor: f64, tz: &Tz) -> Result<String> {
+    let mut medians: Vec<f64> = periods.iter().map(|p| p.gas_price_gwei_p50).collect();
+    medians.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let baseline = percentile(&medians, 0.5);
+    let threshold = baseline * spike_factor;
+
+    let mut summary = format!(
+        "Fee spike periods (median gas price above {} = {} x baseline {}):\n",
+        Amount::Gwei(threshold), spike_factor, Amount::Gwei(baseline)
+    );
+    let mut spike_volume = 0.0;
+    let mut spike_count = 0;
+    let mut current_spike: Option<(u64, u64, f64, f64)> = None; // start, end, peak gwei, volume
+    for period in periods.iter() {
+        if period.gas_price_gwei_p50 > threshold {
+            spike_count += 1;
+            spike_volume += period.parsed_volume_usd;
+            current_spike = match current_spike {
+                Some((start, end, peak, volume)) if end == period.period_start => {
+                    Some((start, period.period_end, peak.max(period.gas_price_gwei_p50), volume + period.parsed_volume_usd))
+                }
+                previous => {
+                    if let Some((start, end, peak, volume)) = previous {
+                        summary.push_str(&spike_line(start, end, peak, volume, tz)?);
+                    }
+                    Some((period.period_start, period.period_end, period.gas_price_gwei_p50, period.parsed_volume_usd))
+                }
+            };
+        }
+    }
+    if let Some((start, end, peak, volume)) = current_spike {
+        summary.push_str(&spike_line(start, end, peak, volume, tz)?);
+    }
+
+    let total_volume = periods.iter().fold(0.0, |acc, p| acc + p.parsed_volume_usd);
+    summary.push_str(&format!(
+        "Spike periods: {} / {}, holding {} of {} parsed volume\n",
+        spike_count,
+        periods.len(),
+        Amount::Usd(spike_volume),
+        Amount::Usd(total_volume)
+    ));
+    let gas_medians: Vec<f64> = periods.iter().map(|p| p.gas_price_gwei_p50).collect();
+    let volumes: Vec<f64> = periods.iter().map(|p| p.parsed_volume_usd).collect();
+    match pearson_correlation(&gas_medians, &volumes) {
+        Some(r) => summary.push_str(&format!("Correlation of median gas price with parsed volume: {:.3}\n", r)),
+        None => summary.push_str("Correlation of median gas price with parsed volume: undefined\n"),
+    }
+    Ok(summary)
+}
+
+pub fn run_fees_command(args: &FeesArgs) -> Result<()> {
+    let tz = parse_timezone(&args.timezone)?;
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let price_series = args.prices.as_ref().map(|name| PriceSeries::from_csv(&format!("{}/{}", DATA_STORAGE_FOLDER, name))).transpose()?;
+    let mut price_cache = price_series.as_ref().map(|series| PriceCache::new(series));
+    let periods = fee_percentiles_over_time(&graph, args.period, &tz, price_cache.as_mut().map(|cache| (cache, args.fee_symbol.as_str())))?;
+    if let Some(cache) = &price_cache {
+        println!("Resolved {} hourly {} prices", cache.resolved_hours(), args.fee_symbol);
+    }
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for period in periods.iter() {
+        writer.serialize(period)?;
+    }
+    commit_csv(writer)?;
+    println!("Saved fee percentiles for {} periods as {}\n", periods.len(), &file_pathname);
+    let mut charged_hashes: HashSet<&str> = HashSet::new();
+    let charged: Vec<&Transaction> = graph.edge_weights().filter(|t| charges_fee(&mut charged_hashes, t)).collect();
+    let total_fee_wei: u128 = charged.iter().filter_map(|t| effective_fee_wei(t)).sum();
+    println!("Total fees paid: {} ({})", Amount::Coin(wei_to_coin(total_fee_wei)), Amount::Wei(total_fee_wei));
+    let unknown = charged.iter().filter(|t| t.gas_used != 0 && effective_gas_price(t).is_none()).count();
+    if unknown > 0 {
+        println!("{} transactions used gas at an unknown price and are left out of the fees", unknown);
+    }
+
+    print!("{}", fee_spike_summary(&periods, args.spike_factor, &tz)?);
+    Ok(())
+}
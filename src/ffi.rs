@@ -0,0 +1,171 @@
+// C ABI for embedding the analysis engine. Graphs are opaque handles owned by the caller and released with
+// ethparser_graph_free. Failing calls return NULL or -1 and leave a message for ethparser_last_error.
+use eyre::Result;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::fs::File;
+use std::ptr;
+
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::graph_view::FilteredGraph;
+use crate::tx_graph::TxGraph;
+use crate::G;
+
+pub struct EthGraph {
+    graph: G,
+}
+
+#[repr(C)]
+pub struct EthGraphStats {
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub total_usd_volume: f64,
+    pub mean_usd_value: f64,
+}
+
+#[derive(Serialize)]
+struct EdgeRow<'a> {
+    from: &'a str,
+    to: &'a str,
+    hash: &'a str,
+    timestamp: u64,
+    block_number: u64,
+    payload: Option<String>,
+    usd_value: Option<f64>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+fn c_str<'a>(s: *const c_char, name: &str) -> Result<&'a str> {
+    if s.is_null() {
+        return Err(eyre::eyre!("{} is NULL", name));
+    }
+    Ok(unsafe { CStr::from_ptr(s) }.to_str()?)
+}
+
+fn graph_ref<'a>(graph: *const EthGraph) -> Result<&'a G> {
+    unsafe { graph.as_ref() }.map(|g| &g.graph).ok_or_else(|| eyre::eyre!("graph is NULL"))
+}
+
+fn into_handle(result: Result<G>) -> *mut EthGraph {
+    match result {
+        Ok(graph) => Box::into_raw(Box::new(EthGraph { graph })),
+        Err(e) => {
+            set_last_error(e.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+fn into_status(result: Result<()>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            set_last_error(e.to_string());
+            -1
+        }
+    }
+}
+
+/// Message of the last failed call on this thread, or NULL. Valid until the next failing call.
+#[no_mangle]
+pub extern "C" fn ethparser_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Loads a graph saved by ethparser. The path is used as given, not resolved under the json folder.
+///
+/// # Safety
+/// path must be NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ethparser_graph_load(path: *const c_char) -> *mut EthGraph {
+    into_handle((|| {
+        let path = c_str(path, "path")?;
+        let file = File::open(path).map_err(|e| eyre::eyre!("Failed to open {}: {}", path, e))?;
+        let (graph, _) = TxGraph::from_reader(std::io::BufReader::new(file))?;
+        Ok(graph.into_petgraph())
+    })())
+}
+
+/// # Safety
+/// graph must be NULL or a handle returned by this library that was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn ethparser_graph_free(graph: *mut EthGraph) {
+    if !graph.is_null() {
+        drop(unsafe { Box::from_raw(graph) });
+    }
+}
+
+/// New graph with the stablecoin transfers valued within [min_usd, max_usd]. payload is NULL for any
+/// stablecoin, or a name such as "USDC". Nodes left without edges are dropped.
+///
+/// # Safety
+/// graph must be NULL or a live handle, payload NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ethparser_graph_filter(graph: *const EthGraph, min_usd: f64, max_usd: f64, payload: *const c_char) -> *mut EthGraph {
+    into_handle((|| {
+        let graph = graph_ref(graph)?;
+        let payload = if payload.is_null() { None } else { Some(c_str(payload, "payload")?) };
+        let view = FilteredGraph::new(graph).retain_edges(|edge| match &edge.weight().data {
+            Some(data) => {
+                data.usd_value >= min_usd && data.usd_value <= max_usd && payload.is_none_or(|p| format!("{:?}", data.payload) == p)
+            }
+            None => false,
+        });
+        Ok(view.materialize().0)
+    })())
+}
+
+/// Fills stats for the graph. Returns 0 on success and -1 on failure.
+///
+/// # Safety
+/// graph must be NULL or a live handle, stats NULL or writable memory for one EthGraphStats.
+#[no_mangle]
+pub unsafe extern "C" fn ethparser_graph_stats(graph: *const EthGraph, stats: *mut EthGraphStats) -> c_int {
+    into_status((|| {
+        let graph = graph_ref(graph)?;
+        let stats = unsafe { stats.as_mut() }.ok_or_else(|| eyre::eyre!("stats is NULL"))?;
+        let (total_usd_volume, mean_usd_value) = FilteredGraph::new(graph).usd_volume();
+        *stats = EthGraphStats {
+            node_count: graph.node_count(),
+            edge_count: graph.edge_count(),
+            total_usd_volume,
+            mean_usd_value: if graph.edge_count() == 0 { 0.0 } else { mean_usd_value },
+        };
+        Ok(())
+    })())
+}
+
+/// Writes one CSV row per edge to path. Returns 0 on success and -1 on failure.
+///
+/// # Safety
+/// graph must be NULL or a live handle, path NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ethparser_graph_export_csv(graph: *const EthGraph, path: *const c_char) -> c_int {
+    into_status((|| {
+        let graph = graph_ref(graph)?;
+        let mut writer = csv_writer(c_str(path, "path")?)?;
+        for edge in graph.edge_indices() {
+            let (source, target) = graph.edge_endpoints(edge).unwrap();
+            let transaction = &graph[edge];
+            writer.serialize(EdgeRow {
+                from: &graph[source],
+                to: &graph[target],
+                hash: &transaction.hash,
+                timestamp: transaction.timestamp,
+                block_number: transaction.block_number,
+                payload: transaction.data.as_ref().map(|d| format!("{:?}", d.payload)),
+                usd_value: transaction.data.as_ref().map(|d| d.usd_value),
+            })?;
+        }
+        commit_csv(writer)
+    })())
+}
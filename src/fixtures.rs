@@ -0,0 +1,193 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use rand::Rng;
+use serde::Serialize;
+use std::fs;
+
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::GenFixtureArgs;
+use crate::seeded_rng::{seeded_rng, SeededRng};
+use crate::{serialize_graph, DigestedData, FilteringResultInfo, OnchainFunction, Payload, Transaction, DATA_STORAGE_FOLDER, G};
+
+const SECONDS_PER_HOUR: u64 = 3_600;
+// Gas of a plain token transfer, used when the fixture carries fees.
+const TOKEN_TRANSFER_GAS: u64 = 60_000;
+const GWEI: u64 = 1_000_000_000;
+
+// One structure of a fixture, each on addresses of its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pattern {
+    // `count` transfers from one address to another.
+    Pair(usize),
+    // `count` transfers alternating between two addresses.
+    TwoWay(usize),
+    Chain(usize),
+    Cycle(usize),
+    // A hub paying `leaves` addresses.
+    Star(usize),
+    // `leaves` addresses paying a hub.
+    FanIn(usize),
+    Random(usize, usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PatternSpec {
+    pattern: Pattern,
+    // Token amount of every transfer of the pattern, defaulting to --usd.
+    usd: Option<f64>,
+}
+
+// Every odd transfer is an EIP-1559 transaction once there is a base fee, and every fourth one has a max fee
+// below base plus priority fee, so the cap decides its price. They carry no receipt gasPrice, so only the base fee
+// prices them.
+fn fee_fields(args: &GenFixtureArgs, transfer_index: u64) -> Transaction {
+    if args.base_fee_gwei == 0 || transfer_index.is_multiple_of(2) {
+        return Transaction { gas_price: args.gas_price_gwei * GWEI, ..Default::default() };
+    }
+    let (base_fee, priority_fee) = (args.base_fee_gwei * GWEI, args.priority_fee_gwei * GWEI);
+    let max_fee = if transfer_index % 4 == 3 { base_fee + priority_fee / 2 } else { 2 * base_fee + priority_fee };
+    Transaction {
+        transaction_type: 2,
+        max_fee_per_gas: max_fee,
+        max_priority_fee_per_gas: priority_fee,
+        base_fee_per_gas: base_fee,
+        ..Default::default()
+    }
+}
+
+fn parse_count(spec: &str, value: &str) -> Result<usize> {
+    value.parse().map_err(|_| eyre::eyre!("Expected a count in pattern {}, got {}", spec, value))
+}
+
+// Patterns are written kind:count, or random:nodes:edges, with an optional @amount, e.g. chain:5@2500.
+fn parse_pattern(spec: &str) -> Result<PatternSpec> {
+    let (shape, usd) = match spec.split_once('@') {
+        Some((shape, usd)) => (shape, Some(usd.parse().map_err(|_| eyre::eyre!("Expected an amount after @ in pattern {}", spec))?)),
+        None => (spec, None),
+    };
+    let parts: Vec<&str> = shape.split(':').collect();
+    let pattern = match parts.as_slice() {
+        ["pair", count] => Pattern::Pair(parse_count(spec, count)?),
+        ["two-way", count] => Pattern::TwoWay(parse_count(spec, count)?),
+        ["chain", length] => Pattern::Chain(parse_count(spec, length)?.max(2)),
+        ["cycle", length] => Pattern::Cycle(parse_count(spec, length)?.max(2)),
+        ["star", leaves] => Pattern::Star(parse_count(spec, leaves)?),
+        ["fan-in", leaves] => Pattern::FanIn(parse_count(spec, leaves)?),
+        ["random", nodes, edges] => Pattern::Random(parse_count(spec, nodes)?.max(2), parse_count(spec, edges)?),
+        _ => {
+            return Err(eyre::eyre!(
+                "Unknown pattern {}, expected pair:N, two-way:N, chain:N, cycle:N, star:N, fan-in:N or random:NODES:EDGES",
+                spec
+            ))
+        }
+    };
+    Ok(PatternSpec { pattern, usd })
+}
+
+// Transfers of a pattern as offsets into the addresses it gets, and the number of addresses it needs.
+fn pattern_transfers(pattern: Pattern, rng: &mut SeededRng) -> (usize, Vec<(usize, usize)>) {
+    match pattern {
+        Pattern::Pair(count) => (2, vec![(0, 1); count]),
+        Pattern::TwoWay(count) => (2, (0..count).map(|i| if i % 2 == 0 { (0, 1) } else { (1, 0) }).collect()),
+        Pattern::Chain(length) => (length, (1..length).map(|i| (i - 1, i)).collect()),
+        Pattern::Cycle(length) => (length, (0..length).map(|i| (i, (i + 1) % length)).collect()),
+        Pattern::Star(leaves) => (leaves + 1, (1..=leaves).map(|leaf| (0, leaf)).collect()),
+        Pattern::FanIn(leaves) => (leaves + 1, (1..=leaves).map(|leaf| (leaf, 0)).collect()),
+        Pattern::Random(nodes, edges) => {
+            let transfers = (0..edges)
+                .map(|_| {
+                    let from = rng.gen_range(0..nodes);
+                    let to = (from + rng.gen_range(1..nodes)) % nodes;
+                    (from, to)
+                })
+                .collect();
+            (nodes, transfers)
+        }
+    }
+}
+
+// Addresses count up from 0x…01, skipping the zero address that crawls treat as a burn address.
+fn fixture_address(index: usize) -> String {
+    format!("0x{:040x}", index + 1)
+}
+
+// Builds the fixture graph. Transfers are spaced `interval` seconds and one block apart in pattern order.
+pub fn fixture_graph(specs: &[String], args: &GenFixtureArgs, rng: &mut SeededRng) -> Result<G> {
+    let mut graph = G::new();
+    let mut transfer_index: u64 = 0;
+    for spec in specs {
+        let spec = parse_pattern(spec)?;
+        let (node_count, transfers) = pattern_transfers(spec.pattern, rng);
+        let first = graph.node_count();
+        let nodes: Vec<NodeIndex> = (first..first + node_count).map(|index| graph.add_node(fixture_address(index))).collect();
+        for (from, to) in transfers {
+            let base = spec.usd.unwrap_or(args.usd);
+            let usd_value = base * (1.0 + args.value_spread * rng.gen_range(-1.0..=1.0));
+            let transaction = Transaction {
+                hash: format!("0x{:064x}", transfer_index + 1),
+                timestamp: args.start_timestamp + transfer_index * args.interval,
+                block_number: args.start_block + transfer_index,
+                gas_used: if args.gas_price_gwei > 0 || args.base_fee_gwei > 0 { TOKEN_TRANSFER_GAS } else { 0 },
+                contract_call: true,
+                data: Some(DigestedData {
+                    payload: if args.mixed_tokens && transfer_index % 2 == 1 { Payload::USDC } else { Payload::BSCUSD },
+                    usd_value: (usd_value * 100.0).round() / 100.0,
+                    used_onchain_function: OnchainFunction::Transfer,
+                }),
+                ..fee_fields(args, transfer_index)
+            };
+            graph.add_edge(nodes[from], nodes[to], transaction);
+            transfer_index += 1;
+        }
+    }
+    Ok(graph)
+}
+
+#[derive(Serialize)]
+struct PriceRow<'a> {
+    timestamp: u64,
+    symbol: &'a str,
+    usd_price: f64,
+}
+
+// Hourly prices over the span of the fixture: the stablecoins at par, the fee coin on a seeded random walk.
+fn write_fixture_prices(graph: &G, args: &GenFixtureArgs, rng: &mut SeededRng) -> Result<usize> {
+    let last_timestamp = graph.raw_edges().iter().map(|e| e.weight.timestamp).max().unwrap_or(args.start_timestamp);
+    let first_hour = args.start_timestamp - args.start_timestamp % SECONDS_PER_HOUR;
+    let mut writer = csv_writer(&format!("{}/{}", DATA_STORAGE_FOLDER, args.prices_output))?;
+    let mut coin_price = args.coin_price;
+    let mut rows = 0;
+    for hour in (first_hour..=last_timestamp).step_by(SECONDS_PER_HOUR as usize) {
+        for symbol in ["BSCUSD", "USDC"] {
+            writer.serialize(PriceRow { timestamp: hour, symbol, usd_price: 1.0 })?;
+        }
+        writer.serialize(PriceRow { timestamp: hour, symbol: &args.fee_symbol, usd_price: (coin_price * 100.0).round() / 100.0 })?;
+        coin_price *= 1.0 + args.coin_volatility * rng.gen_range(-1.0..=1.0);
+        rows += 3;
+    }
+    commit_csv(writer)?;
+    Ok(rows)
+}
+
+pub fn run_gen_fixture_command(args: &GenFixtureArgs) -> Result<()> {
+    let mut rng = seeded_rng(Some(args.seed));
+    let graph = fixture_graph(&args.patterns, args, &mut rng)?;
+    let info = FilteringResultInfo {
+        node_count_before_filtering: graph.node_count(),
+        edge_count_before_filtering: graph.edge_count(),
+        node_count: graph.node_count(),
+        edge_count: graph.edge_count(),
+    };
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    serialize_graph(&graph, &info, &args.output)?;
+    let rows = write_fixture_prices(&graph, args, &mut rng)?;
+    println!(
+        "Generated {} addresses and {} transfers as {}, with {} price points in {}",
+        graph.node_count(),
+        graph.edge_count(),
+        args.output,
+        rows,
+        args.prices_output
+    );
+    Ok(())
+}
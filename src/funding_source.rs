@@ -0,0 +1,83 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::cli::FundedByArgs;
+use crate::delta_crawl::load_crawl_state;
+use crate::{deserialize_graph, serialize_graph, EdgeKind, FilteringResultInfo, Transaction, G};
+#[cfg(feature = "crawl")]
+use crate::{record_validation::ParsedRecord, RawTransaction};
+
+// The transfer that paid for an address's first own transaction: the earliest successful incoming
+// transfer with value that precedes every transaction the address sent.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FundingSource {
+    pub funder: String,
+    pub hash: String,
+    pub timestamp: u64,
+    pub block_number: u64,
+}
+
+// Only meaningful for a history fetched from the first block. Addresses that sent before receiving any
+// value were funded through internal or token transfers the normal transaction list does not show.
+#[cfg(feature = "crawl")]
+pub(crate) fn first_funding(address: &str, history: &[(&RawTransaction, ParsedRecord)]) -> Option<FundingSource> {
+    let position = |parsed: &ParsedRecord| (parsed.block_number, parsed.transaction_index);
+    let first_sent = history.iter().filter(|(t, _)| t.from == address).map(|(_, parsed)| position(parsed)).min();
+    history
+        .iter()
+        .filter(|(t, parsed)| t.to == address && t.from != address && t.from != "GENESIS" && parsed.value_wei != 0 && t.isError == "0")
+        .min_by_key(|(_, parsed)| position(parsed))
+        .filter(|(_, parsed)| first_sent.is_none_or(|first| position(parsed) < first))
+        .map(|(t, parsed)| FundingSource {
+            funder: t.from.clone(),
+            hash: t.hash.clone(),
+            timestamp: parsed.timestamp,
+            block_number: parsed.block_number,
+        })
+}
+
+// Adds a FundedBy edge from each recorded funder to the address it funded, once per address. Returns the
+// number of edges added.
+pub fn add_funded_by_edges(graph: &mut G, sources: &HashMap<String, FundingSource>) -> usize {
+    let mut node_indices: HashMap<String, NodeIndex> = graph.node_indices().map(|n| (graph[n].clone(), n)).collect();
+    let already_funded: Vec<NodeIndex> =
+        graph.raw_edges().iter().filter(|e| e.weight.kind == EdgeKind::FundedBy).map(|e| e.target()).collect();
+    let mut funded: Vec<(&String, &FundingSource)> = sources.iter().filter(|(address, _)| node_indices.contains_key(*address)).collect();
+    funded.sort_by_key(|(address, _)| *address);
+
+    let mut added = 0;
+    for (address, source) in funded {
+        let target = node_indices[address];
+        if already_funded.contains(&target) {
+            continue;
+        }
+        let origin = *node_indices.entry(source.funder.clone()).or_insert_with(|| graph.add_node(source.funder.clone()));
+        let edge = Transaction {
+            hash: source.hash.clone(),
+            timestamp: source.timestamp,
+            block_number: source.block_number,
+            kind: EdgeKind::FundedBy,
+            ..Default::default()
+        };
+        graph.add_edge(origin, target, edge);
+        added += 1;
+    }
+    added
+}
+
+pub fn run_funded_by_command(args: &FundedByArgs) -> Result<()> {
+    let (mut graph, _) = deserialize_graph(&args.input)?;
+    let crawl_state = load_crawl_state()?;
+    let node_count_before = graph.node_count();
+    let added = add_funded_by_edges(&mut graph, &crawl_state.funding_sources);
+    println!("Added {} funded-by edges from {} recorded funding sources", added, crawl_state.funding_sources.len());
+    let info = FilteringResultInfo {
+        node_count_before_filtering: node_count_before,
+        edge_count_before_filtering: graph.edge_count() - added,
+        node_count: graph.node_count(),
+        edge_count: graph.edge_count(),
+    };
+    serialize_graph(&graph, &info, &args.output)
+}
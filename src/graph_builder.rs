@@ -0,0 +1,78 @@
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use std::collections::{HashMap, HashSet};
+
+use crate::{Transaction, G};
+
+// Builds an address graph with nodes interned by address and transactions remembered by hash. Known
+// addresses are looked up without allocating, and only hashes are kept for deduplication rather than
+// copies of every inserted transaction.
+pub struct GraphBuilder {
+    graph: G,
+    node_indices: HashMap<String, NodeIndex>,
+    hashes: HashSet<String>,
+}
+
+impl GraphBuilder {
+    pub fn with_capacity(nodes: usize, edges: usize) -> GraphBuilder {
+        GraphBuilder {
+            graph: G::with_capacity(nodes, edges),
+            node_indices: HashMap::with_capacity(nodes),
+            hashes: HashSet::with_capacity(edges),
+        }
+    }
+
+    // Continues building on an existing graph, e.g. one restored from a previous crawl.
+    pub fn from_graph(graph: G) -> GraphBuilder {
+        let node_indices = graph.node_indices().map(|node| (graph[node].clone(), node)).collect();
+        let hashes = graph.edge_weights().map(|transaction| transaction.hash.clone()).collect();
+        GraphBuilder { graph, node_indices, hashes }
+    }
+
+    // Makes room for a batch up front, so that it is inserted without intermediate reallocation.
+    pub fn reserve(&mut self, nodes: usize, edges: usize) {
+        self.graph.reserve_nodes(nodes);
+        self.graph.reserve_edges(edges);
+        self.node_indices.reserve(nodes);
+        self.hashes.reserve(edges);
+    }
+
+    pub fn node(&mut self, address: &str) -> NodeIndex {
+        if let Some(&node) = self.node_indices.get(address) {
+            return node;
+        }
+        let node = self.graph.add_node(address.to_string());
+        self.node_indices.insert(address.to_string(), node);
+        node
+    }
+
+    pub fn contains_transaction(&self, hash: &str) -> bool {
+        self.hashes.contains(hash)
+    }
+
+    pub fn add_edge(&mut self, from: &str, to: &str, transaction: Transaction) -> EdgeIndex {
+        let (source, target) = (self.node(from), self.node(to));
+        if !self.hashes.contains(transaction.hash.as_str()) {
+            self.hashes.insert(transaction.hash.clone());
+        }
+        self.graph.add_edge(source, target, transaction)
+    }
+
+    // Bulk insertion reserving edge capacity for the batch size the iterator reports. How many of the
+    // endpoints are new is unknown, so node capacity is left to with_capacity or reserve.
+    pub fn extend_edges<S: AsRef<str>>(&mut self, edges: impl IntoIterator<Item = (S, S, Transaction)>) {
+        let edges = edges.into_iter();
+        let (batch_size, _) = edges.size_hint();
+        self.reserve(0, batch_size);
+        for (from, to, transaction) in edges {
+            self.add_edge(from.as_ref(), to.as_ref(), transaction);
+        }
+    }
+
+    pub fn graph(&self) -> &G {
+        &self.graph
+    }
+
+    pub fn into_graph(self) -> G {
+        self.graph
+    }
+}
@@ -0,0 +1,713 @@
+use chrono::{TimeZone, Timelike};
+use eyre::Result;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::Direction;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::QueryArgs;
+use crate::money::wei_to_coin;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+// A single-hop pattern query over a saved graph:
+//
+//   MATCH a-[t]->b WHERE t.usd > 10000 AND b.label = 'Binance' RETURN a.address, sum(t.usd) AS volume
+//   ORDER BY volume DESC LIMIT 10
+//
+// Patterns are a-[t]->b, a<-[t]-b, or a-[t]-b for both directions. Nodes have address, label, in_degree
+// and out_degree; edges have hash, usd, value_usd, fee_usd, timestamp, block,
+// transaction_index, nonce, value_coin, payload, kind and function.
+// RETURN items using count, sum, min, max or avg are aggregated over the groups formed by the other items.
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(f64),
+    Text(String),
+    Symbol(&'static str),
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    const SYMBOLS: [&str; 17] = ["<=", ">=", "!=", "<>", "-", "[", "]", ">", "<", "=", ",", ".", "(", ")", "*", "+", "/"];
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.' || (chars[i - 1] == 'e' && chars[i] == '-')) {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            tokens.push(Token::Number(number.parse().map_err(|_| eyre::eyre!("Invalid number {}", number))?));
+        } else if c == '\'' || c == '"' {
+            let end = chars[i + 1..].iter().position(|&q| q == c).ok_or_else(|| eyre::eyre!("Unterminated string in query"))?;
+            tokens.push(Token::Text(chars[i + 1..i + 1 + end].iter().collect()));
+            i += end + 2;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let symbol = SYMBOLS.iter().find(|s| rest.starts_with(**s)).ok_or_else(|| eyre::eyre!("Unexpected character {} in query", c))?;
+            tokens.push(Token::Symbol(symbol));
+            i += symbol.len();
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl Value {
+    fn is_true(&self) -> bool {
+        matches!(self, Value::Bool(true))
+    }
+
+    fn number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    // Nulls sort first; values of different types are ordered by type.
+    fn compare(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::Text(_) => 3,
+        }
+    }
+
+    fn render(&self) -> String {
+        match self {
+            Value::Null => String::new(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::Text(s) => s.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Aggregate {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Value),
+    Property(String, String),
+    Alias(String),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    // None counts rows, as in count(*).
+    Aggregate(Aggregate, Option<Box<Expr>>),
+}
+
+impl Expr {
+    fn has_aggregate(&self) -> bool {
+        match self {
+            Expr::Aggregate(..) => true,
+            Expr::Unary(_, e) => e.has_aggregate(),
+            Expr::Binary(_, a, b) => a.has_aggregate() || b.has_aggregate(),
+            Expr::Call(_, args) => args.iter().any(Expr::has_aggregate),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PatternDirection {
+    Outgoing,
+    Incoming,
+    Both,
+}
+
+#[derive(Debug)]
+struct Query {
+    from: String,
+    edge: String,
+    to: String,
+    direction: PatternDirection,
+    filter: Option<Expr>,
+    items: Vec<(Expr, String)>,
+    order: Option<(Expr, bool)>, // expression, descending
+    limit: Option<usize>,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(eyre::eyre!("Expected {} but found {:?}", keyword, self.peek()))
+        }
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<()> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(eyre::eyre!("Expected {} but found {:?}", symbol, self.peek()))
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(w)) => Ok(w),
+            other => Err(eyre::eyre!("Expected a name but found {:?}", other)),
+        }
+    }
+
+    fn query(&mut self) -> Result<Query> {
+        self.expect_keyword("MATCH")?;
+        let from = self.identifier()?;
+        let incoming = self.eat_symbol("<");
+        self.expect_symbol("-")?;
+        self.expect_symbol("[")?;
+        let edge = self.identifier()?;
+        self.expect_symbol("]")?;
+        self.expect_symbol("-")?;
+        let outgoing = self.eat_symbol(">");
+        let direction = match (incoming, outgoing) {
+            (false, true) => PatternDirection::Outgoing,
+            (true, false) => PatternDirection::Incoming,
+            (false, false) => PatternDirection::Both,
+            (true, true) => return Err(eyre::eyre!("A pattern edge points one way, use a-[t]-b for both directions")),
+        };
+        let to = self.identifier()?;
+        if from == to || from == edge || to == edge {
+            return Err(eyre::eyre!("Pattern names must be distinct"));
+        }
+
+        let filter = if self.eat_keyword("WHERE") { Some(self.expression()?) } else { None };
+        self.expect_keyword("RETURN")?;
+        let mut items = Vec::new();
+        loop {
+            let start = self.position;
+            let expr = self.expression()?;
+            let name = if self.eat_keyword("AS") { self.identifier()? } else { self.source_text(start) };
+            items.push((expr, name));
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+        let order = if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            let expr = self.expression()?;
+            let descending = self.eat_keyword("DESC");
+            if !descending {
+                self.eat_keyword("ASC");
+            }
+            Some((expr, descending))
+        } else {
+            None
+        };
+        let limit = if self.eat_keyword("LIMIT") {
+            match self.next() {
+                Some(Token::Number(n)) if n >= 0.0 && n.fract() == 0.0 => Some(n as usize),
+                other => return Err(eyre::eyre!("Expected a row count after LIMIT but found {:?}", other)),
+            }
+        } else {
+            None
+        };
+        if let Some(token) = self.peek() {
+            return Err(eyre::eyre!("Unexpected {:?} after the end of the query", token));
+        }
+        Ok(Query { from, edge, to, direction, filter, items, order, limit })
+    }
+
+    // Column header for an item without AS, rebuilt from its tokens.
+    fn source_text(&self, start: usize) -> String {
+        self.tokens[start..self.position]
+            .iter()
+            .map(|token| match token {
+                Token::Word(w) => w.clone(),
+                Token::Number(n) => n.to_string(),
+                Token::Text(s) => format!("'{}'", s),
+                Token::Symbol(s) => s.to_string(),
+            })
+            .collect()
+    }
+
+    fn expression(&mut self) -> Result<Expr> {
+        let mut left = self.conjunction()?;
+        while self.eat_keyword("OR") {
+            left = Expr::Binary("OR", Box::new(left), Box::new(self.conjunction()?));
+        }
+        Ok(left)
+    }
+
+    fn conjunction(&mut self) -> Result<Expr> {
+        let mut left = self.negation()?;
+        while self.eat_keyword("AND") {
+            left = Expr::Binary("AND", Box::new(left), Box::new(self.negation()?));
+        }
+        Ok(left)
+    }
+
+    fn negation(&mut self) -> Result<Expr> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Unary("NOT", Box::new(self.negation()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let left = self.sum()?;
+        for operator in ["<=", ">=", "!=", "<>", "<", ">", "="] {
+            if self.eat_symbol(operator) {
+                let operator = if operator == "<>" { "!=" } else { operator };
+                return Ok(Expr::Binary(operator, Box::new(left), Box::new(self.sum()?)));
+            }
+        }
+        if self.eat_keyword("CONTAINS") {
+            return Ok(Expr::Binary("CONTAINS", Box::new(left), Box::new(self.sum()?)));
+        }
+        if self.eat_keyword("STARTS") {
+            self.expect_keyword("WITH")?;
+            return Ok(Expr::Binary("STARTS WITH", Box::new(left), Box::new(self.sum()?)));
+        }
+        Ok(left)
+    }
+
+    fn sum(&mut self) -> Result<Expr> {
+        let mut left = self.product()?;
+        loop {
+            let operator = if self.eat_symbol("+") {
+                "+"
+            } else if self.eat_symbol("-") {
+                "-"
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Binary(operator, Box::new(left), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr> {
+        let mut left = self.unary()?;
+        loop {
+            let operator = if self.eat_symbol("*") {
+                "*"
+            } else if self.eat_symbol("/") {
+                "/"
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Binary(operator, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat_symbol("-") {
+            return Ok(Expr::Unary("-", Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Literal(Value::Number(n))),
+            Some(Token::Text(s)) => Ok(Expr::Literal(Value::Text(s))),
+            Some(Token::Symbol("(")) => {
+                let expr = self.expression()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Some(Token::Word(word)) => {
+                match word.to_uppercase().as_str() {
+                    "TRUE" => return Ok(Expr::Literal(Value::Bool(true))),
+                    "FALSE" => return Ok(Expr::Literal(Value::Bool(false))),
+                    "NULL" => return Ok(Expr::Literal(Value::Null)),
+                    _ => {}
+                }
+                if self.eat_symbol(".") {
+                    return Ok(Expr::Property(word, self.identifier()?));
+                }
+                if !self.eat_symbol("(") {
+                    return Ok(Expr::Alias(word));
+                }
+                let aggregate = match word.to_lowercase().as_str() {
+                    "count" => Some(Aggregate::Count),
+                    "sum" => Some(Aggregate::Sum),
+                    "min" => Some(Aggregate::Min),
+                    "max" => Some(Aggregate::Max),
+                    "avg" => Some(Aggregate::Avg),
+                    _ => None,
+                };
+                if let Some(aggregate) = aggregate {
+                    let argument = if aggregate == Aggregate::Count && self.eat_symbol("*") { None } else { Some(Box::new(self.expression()?)) };
+                    self.expect_symbol(")")?;
+                    return Ok(Expr::Aggregate(aggregate, argument));
+                }
+                let mut args = Vec::new();
+                if !self.eat_symbol(")") {
+                    loop {
+                        args.push(self.expression()?);
+                        if self.eat_symbol(")") {
+                            break;
+                        }
+                        self.expect_symbol(",")?;
+                    }
+                }
+                Ok(Expr::Call(word.to_lowercase(), args))
+            }
+            other => Err(eyre::eyre!("Unexpected {:?} in expression", other)),
+        }
+    }
+}
+
+fn parse_query(query: &str) -> Result<Query> {
+    Parser { tokens: tokenize(query)?, position: 0 }.query()
+}
+
+// One match of the pattern: the edge and the nodes bound to the from and to names.
+#[derive(Clone, Copy)]
+struct Binding {
+    edge: EdgeIndex,
+    from: NodeIndex,
+    to: NodeIndex,
+}
+
+struct Context<'a> {
+    graph: &'a G,
+    query: &'a Query,
+    labels: &'a HashMap<String, String>,
+}
+
+impl Context<'_> {
+    fn node_property(&self, node: NodeIndex, property: &str) -> Result<Value> {
+        let address = &self.graph[node];
+        Ok(match property {
+            "address" => Value::Text(address.clone()),
+            "label" => self.labels.get(address).map_or(Value::Null, |label| Value::Text(label.clone())),
+            "in_degree" => Value::Number(self.graph.edges_directed(node, Direction::Incoming).count() as f64),
+            "out_degree" => Value::Number(self.graph.edges_directed(node, Direction::Outgoing).count() as f64),
+            _ => return Err(eyre::eyre!("Unknown node property {}", property)),
+        })
+    }
+
+    fn edge_property(&self, edge: EdgeIndex, property: &str) -> Result<Value> {
+        let transaction = &self.graph[edge];
+        let number = |n: Option<f64>| n.map_or(Value::Null, Value::Number);
+        Ok(match property {
+            "hash" => Value::Text(transaction.hash.clone()),
+            "usd" => number(transaction.data.as_ref().map(|d| d.usd_value)),
+            "value_usd" => number(transaction.value_usd),
+            "fee_usd" => number(transaction.fee_usd),
+            "timestamp" => Value::Number(transaction.timestamp as f64),
+            "block" => Value::Number(transaction.block_number as f64),
+            "transaction_index" => Value::Number(transaction.transaction_index as f64),
+            "nonce" => Value::Number(transaction.nonce as f64),
+            "value_coin" => Value::Number(wei_to_coin(transaction.value_wei)),
+            "payload" => transaction.data.as_ref().map_or(Value::Null, |d| Value::Text(format!("{:?}", d.payload))),
+            "kind" => Value::Text(format!("{:?}", transaction.kind)),
+            "function" => transaction.data.as_ref().map_or(Value::Null, |d| Value::Text(format!("{:?}", d.used_onchain_function))),
+            _ => return Err(eyre::eyre!("Unknown edge property {}", property)),
+        })
+    }
+
+    // Evaluates over a group of bindings. Aggregates fold the whole group, anything else reads its first row.
+    fn eval(&self, expr: &Expr, group: &[Binding], aliases: &HashMap<String, Value>) -> Result<Value> {
+        Ok(match expr {
+            Expr::Literal(value) => value.clone(),
+            Expr::Alias(name) => aliases.get(name).cloned().ok_or_else(|| eyre::eyre!("Unknown name {}", name))?,
+            Expr::Property(variable, property) => {
+                let binding = group[0];
+                if *variable == self.query.from {
+                    self.node_property(binding.from, property)?
+                } else if *variable == self.query.to {
+                    self.node_property(binding.to, property)?
+                } else if *variable == self.query.edge {
+                    self.edge_property(binding.edge, property)?
+                } else {
+                    return Err(eyre::eyre!("{} is not bound by the MATCH pattern", variable));
+                }
+            }
+            Expr::Unary(operator, operand) => {
+                let value = self.eval(operand, group, aliases)?;
+                match (*operator, value) {
+                    ("NOT", Value::Bool(b)) => Value::Bool(!b),
+                    ("-", Value::Number(n)) => Value::Number(-n),
+                    _ => Value::Null,
+                }
+            }
+            Expr::Binary(operator, left, right) => {
+                let left = self.eval(left, group, aliases)?;
+                // Short-circuits so a WHERE can guard properties that are null on some edges.
+                match (*operator, &left) {
+                    ("AND", Value::Bool(false)) => return Ok(Value::Bool(false)),
+                    ("OR", Value::Bool(true)) => return Ok(Value::Bool(true)),
+                    _ => {}
+                }
+                binary(operator, left, self.eval(right, group, aliases)?)
+            }
+            Expr::Call(function, args) => {
+                let args = args.iter().map(|arg| self.eval(arg, group, aliases)).collect::<Result<Vec<Value>>>()?;
+                call(function, &args)?
+            }
+            Expr::Aggregate(aggregate, argument) => {
+                let values = match argument {
+                    Some(argument) => group
+                        .iter()
+                        .map(|binding| self.eval(argument, std::slice::from_ref(binding), aliases))
+                        .collect::<Result<Vec<Value>>>()?
+                        .into_iter()
+                        .filter(|value| *value != Value::Null)
+                        .collect(),
+                    None => vec![Value::Bool(true); group.len()],
+                };
+                let numbers: Vec<f64> = values.iter().filter_map(Value::number).collect();
+                match aggregate {
+                    Aggregate::Count => Value::Number(values.len() as f64),
+                    Aggregate::Sum => Value::Number(numbers.iter().fold(0.0, |acc, n| acc + n)),
+                    Aggregate::Avg if numbers.is_empty() => Value::Null,
+                    Aggregate::Avg => Value::Number(numbers.iter().fold(0.0, |acc, n| acc + n) / numbers.len() as f64),
+                    Aggregate::Min => values.into_iter().min_by(|a, b| a.compare(b)).unwrap_or(Value::Null),
+                    Aggregate::Max => values.into_iter().max_by(|a, b| a.compare(b)).unwrap_or(Value::Null),
+                }
+            }
+        })
+    }
+}
+
+// Comparisons between values of different types, or with null, are null and so filter the row out.
+fn binary(operator: &str, left: Value, right: Value) -> Value {
+    match (operator, &left, &right) {
+        ("AND", Value::Bool(a), Value::Bool(b)) => Value::Bool(*a && *b),
+        ("OR", Value::Bool(a), Value::Bool(b)) => Value::Bool(*a || *b),
+        ("+", Value::Number(a), Value::Number(b)) => Value::Number(a + b),
+        ("-", Value::Number(a), Value::Number(b)) => Value::Number(a - b),
+        ("*", Value::Number(a), Value::Number(b)) => Value::Number(a * b),
+        ("/", Value::Number(a), Value::Number(b)) if *b != 0.0 => Value::Number(a / b),
+        ("+", Value::Text(a), Value::Text(b)) => Value::Text(format!("{}{}", a, b)),
+        ("CONTAINS", Value::Text(a), Value::Text(b)) => Value::Bool(a.contains(b.as_str())),
+        ("STARTS WITH", Value::Text(a), Value::Text(b)) => Value::Bool(a.starts_with(b.as_str())),
+        ("=" | "!=" | "<" | "<=" | ">" | ">=", _, _) if left.rank() == right.rank() && left != Value::Null => {
+            let ordering = left.compare(&right);
+            Value::Bool(match operator {
+                "=" => ordering == Ordering::Equal,
+                "!=" => ordering != Ordering::Equal,
+                "<" => ordering == Ordering::Less,
+                "<=" => ordering != Ordering::Greater,
+                ">" => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            })
+        }
+        _ => Value::Null,
+    }
+}
+
+fn call(function: &str, args: &[Value]) -> Result<Value> {
+    Ok(match (function, args) {
+        ("lower", [Value::Text(s)]) => Value::Text(s.to_lowercase()),
+        ("upper", [Value::Text(s)]) => Value::Text(s.to_uppercase()),
+        ("abs", [Value::Number(n)]) => Value::Number(n.abs()),
+        ("hour", [Value::Number(timestamp)]) => {
+            Value::Number(chrono::Utc.timestamp_opt(*timestamp as i64, 0).single().map_or(0, |t| t.hour()) as f64)
+        }
+        ("lower" | "upper" | "abs" | "hour", [_]) => Value::Null,
+        _ => return Err(eyre::eyre!("Unknown function {} with {} arguments", function, args.len())),
+    })
+}
+
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+fn matches(graph: &G, direction: PatternDirection) -> Vec<Binding> {
+    let mut bindings = Vec::new();
+    for edge in graph.edge_indices() {
+        let (source, target) = graph.edge_endpoints(edge).unwrap();
+        if direction != PatternDirection::Incoming {
+            bindings.push(Binding { edge, from: source, to: target });
+        }
+        if direction != PatternDirection::Outgoing {
+            bindings.push(Binding { edge, from: target, to: source });
+        }
+    }
+    bindings
+}
+
+pub fn run_query(graph: &G, query_text: &str, labels: &HashMap<String, String>) -> Result<QueryResult> {
+    let query = parse_query(query_text)?;
+    let context = Context { graph, query: &query, labels };
+    let no_aliases = HashMap::new();
+
+    let mut bindings = Vec::new();
+    for binding in matches(graph, query.direction) {
+        let keep = match &query.filter {
+            Some(filter) => context.eval(filter, &[binding], &no_aliases)?.is_true(),
+            None => true,
+        };
+        if keep {
+            bindings.push(binding);
+        }
+    }
+
+    // Without aggregates every match is its own group; with them, matches group by the other items.
+    let groups: Vec<Vec<Binding>> = if query.items.iter().any(|(expr, _)| expr.has_aggregate()) {
+        let mut keyed: HashMap<Vec<String>, usize> = HashMap::new();
+        let mut groups: Vec<Vec<Binding>> = Vec::new();
+        for binding in bindings {
+            let key = query
+                .items
+                .iter()
+                .filter(|(expr, _)| !expr.has_aggregate())
+                .map(|(expr, _)| context.eval(expr, &[binding], &no_aliases).map(|value| value.render()))
+                .collect::<Result<Vec<String>>>()?;
+            let index = *keyed.entry(key).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[index].push(binding);
+        }
+        groups
+    } else {
+        bindings.into_iter().map(|binding| vec![binding]).collect()
+    };
+
+    let mut rows: Vec<(Vec<Value>, Value)> = Vec::new();
+    for group in groups.iter() {
+        let mut aliases = HashMap::new();
+        let mut row = Vec::new();
+        for (expr, name) in query.items.iter() {
+            let value = context.eval(expr, group, &aliases)?;
+            aliases.insert(name.clone(), value.clone());
+            row.push(value);
+        }
+        let sort_key = match &query.order {
+            Some((expr, _)) => context.eval(expr, group, &aliases)?,
+            None => Value::Null,
+        };
+        rows.push((row, sort_key));
+    }
+    if let Some((_, descending)) = &query.order {
+        rows.sort_by(|a, b| if *descending { b.1.compare(&a.1) } else { a.1.compare(&b.1) });
+    }
+    rows.truncate(query.limit.unwrap_or(usize::MAX));
+
+    Ok(QueryResult {
+        columns: query.items.iter().map(|(_, name)| name.clone()).collect(),
+        rows: rows.into_iter().map(|(row, _)| row.iter().map(Value::render).collect()).collect(),
+    })
+}
+
+fn format_table(result: &QueryResult) -> String {
+    let widths: Vec<usize> = (0..result.columns.len())
+        .map(|i| result.rows.iter().map(|row| row[i].len()).chain([result.columns[i].len()]).max().unwrap())
+        .collect();
+    let line = |cells: &[String]| {
+        let padded: Vec<String> = cells.iter().zip(widths.iter()).map(|(cell, &width)| format!("{:<width$}", cell, width = width)).collect();
+        format!("{}\n", padded.join(" | ").trim_end())
+    };
+    let mut table = line(&result.columns);
+    table.push_str(&format!("{}\n", widths.iter().map(|&width| "-".repeat(width)).collect::<Vec<String>>().join("-+-")));
+    for row in result.rows.iter() {
+        table.push_str(&line(row));
+    }
+    table.push_str(&format!("({} rows)\n", result.rows.len()));
+    table
+}
+
+// Address labels from a CSV with address,label columns in the data storage folder.
+pub fn load_labels(name: &str) -> Result<HashMap<String, String>> {
+    let mut labels = HashMap::new();
+    for (row, record) in csv::Reader::from_path(format!("{}/{}", DATA_STORAGE_FOLDER, name))?.records().enumerate() {
+        let record = record?;
+        let (Some(address), Some(label)) = (record.get(0), record.get(1)) else {
+            return Err(eyre::eyre!("Row {} of {} has no label, expected address,label", row + 1, name));
+        };
+        labels.insert(address.to_lowercase(), label.to_string());
+    }
+    Ok(labels)
+}
+
+pub fn run_query_command(args: &QueryArgs) -> Result<()> {
+    let labels = args.labels.as_ref().map(|name| load_labels(name)).transpose()?.unwrap_or_default();
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let result = run_query(&graph, &args.query, &labels)?;
+    match &args.output {
+        Some(output) => {
+            fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+            let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, output);
+            let mut writer = csv_writer(&file_pathname)?;
+            writer.write_record(&result.columns)?;
+            for row in result.rows.iter() {
+                writer.write_record(row)?;
+            }
+            commit_csv(writer)?;
+            println!("Saved {} rows as {}", result.rows.len(), &file_pathname);
+        }
+        None => print!("{}", format_table(&result)),
+    }
+    Ok(())
+}
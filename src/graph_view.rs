@@ -0,0 +1,82 @@
+use petgraph::graph::EdgeReference;
+use petgraph::visit::{EdgeFiltered, EdgeRef, IntoEdgeReferences};
+
+use crate::stable_graph::remove_isolated_nodes;
+use crate::{FilteringResultInfo, Transaction, G};
+
+// A subgraph selected by a mask over the edges of a borrowed graph. Chained filters only narrow the
+// mask, so nothing is copied until materialize is called.
+#[derive(Clone)]
+pub struct FilteredGraph<'a> {
+    graph: &'a G,
+    included_edges: Vec<bool>,
+    filtered: bool,
+}
+
+impl<'a> FilteredGraph<'a> {
+    pub fn new(graph: &'a G) -> FilteredGraph<'a> {
+        FilteredGraph { graph, included_edges: vec![true; graph.edge_count()], filtered: false }
+    }
+
+    pub fn retain_edges(mut self, predicate: impl Fn(EdgeReference<'a, Transaction>) -> bool) -> FilteredGraph<'a> {
+        for edge in self.graph.edge_references() {
+            let included = &mut self.included_edges[edge.id().index()];
+            *included = *included && predicate(edge);
+        }
+        self.filtered = true;
+        self
+    }
+
+    // The view implements petgraph's visit traits, so graph algorithms can run on it directly.
+    pub fn view(&self) -> EdgeFiltered<&'a G, impl Fn(EdgeReference<'a, Transaction>) -> bool + '_> {
+        EdgeFiltered::from_fn(self.graph, move |edge: EdgeReference<'a, Transaction>| self.included_edges[edge.id().index()])
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.included_edges.iter().filter(|&&included| included).count()
+    }
+
+    // Nodes touching at least one included edge, the ones materialize keeps.
+    fn connected_nodes(&self) -> Vec<bool> {
+        let mut connected = vec![false; self.graph.node_count()];
+        for edge in self.view().edge_references() {
+            connected[edge.source().index()] = true;
+            connected[edge.target().index()] = true;
+        }
+        connected
+    }
+
+    // An unfiltered view keeps every node, matching node_count of the underlying graph.
+    pub fn node_count(&self) -> usize {
+        if !self.filtered {
+            return self.graph.node_count();
+        }
+        self.connected_nodes().into_iter().filter(|&connected| connected).count()
+    }
+
+    pub fn usd_volume(&self) -> (f64, f64) {
+        let total: f64 = self.view().edge_references().filter_map(|e| e.weight().data.as_ref()).fold(0.0, |acc, d| acc + d.usd_value);
+        (total, total / self.edge_count() as f64)
+    }
+
+    pub fn info_relative_to(&self, before: &FilteredGraph) -> FilteringResultInfo {
+        FilteringResultInfo {
+            node_count: self.node_count(),
+            edge_count: self.edge_count(),
+            node_count_before_filtering: before.node_count(),
+            edge_count_before_filtering: before.edge_count(),
+        }
+    }
+
+    // Every filter ends here, so the addresses a filter leaves without transactions are removed in one place.
+    pub fn materialize(&self) -> (G, FilteringResultInfo) {
+        let mut graph = self.graph.filter_map(
+            |_, node| Some(node.clone()),
+            |edge_index, transaction| self.included_edges[edge_index.index()].then(|| transaction.clone()),
+        );
+        if self.filtered {
+            remove_isolated_nodes(&mut graph);
+        }
+        (graph, self.info_relative_to(&FilteredGraph::new(self.graph)))
+    }
+}
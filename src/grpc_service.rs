@@ -0,0 +1,291 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+use crate::cli::ServeGrpcArgs;
+use crate::crawl_events::{checkpoint_written, CrawlEvent, CrawlSinks, Sink};
+use crate::shared_graph::{SharedGraph, SharedGraphSink};
+use crate::task_control::{CancellationToken, TaskControl};
+use crate::delta_crawl::PARSED_GRAPH_FILENAME;
+use crate::{delta_crawl, deserialize_graph, parse_blockchain, read_api_key, serialize_graph, FilteringResultInfo, DATA_STORAGE_FOLDER, G, TRAVERSAL_STARTING_ADDRESS};
+
+pub mod proto {
+    tonic::include_proto!("ethparser");
+}
+
+use proto::crawler_server::{Crawler, CrawlerServer};
+use proto::{
+    CrawlMode, CrawlProgress, CrawlStatus, Neighbor, NeighborsReply, NeighborsRequest, PathReply, PathRequest, ProgressRequest,
+    SnapshotReply, SnapshotRequest, StartCrawlRequest, StatusRequest, StopCrawlRequest,
+};
+
+const DEFAULT_SNAPSHOT_FILENAME: &str = "snapshot_transactions.json";
+
+#[derive(Default)]
+struct ServiceState {
+    crawl: Option<JoinHandle<()>>,
+    cancellation: CancellationToken,
+    mode: CrawlMode,
+    addresses_expanded: u64,
+    edges_added: u64,
+    last_error: String,
+}
+
+impl ServiceState {
+    fn status(&self, graph: &SharedGraph) -> CrawlStatus {
+        CrawlStatus {
+            running: self.crawl.as_ref().is_some_and(|crawl| !crawl.is_finished()),
+            mode: self.mode as i32,
+            addresses_expanded: self.addresses_expanded,
+            edges_added: self.edges_added,
+            last_error: self.last_error.clone(),
+            graph_nodes: graph.node_count() as u64,
+            graph_edges: graph.edge_count() as u64,
+        }
+    }
+}
+
+// Counts crawl progress into the service state and forwards it to StreamProgress subscribers.
+struct ProgressSink {
+    state: Arc<Mutex<ServiceState>>,
+    progress: broadcast::Sender<CrawlProgress>,
+}
+
+impl Sink for ProgressSink {
+    fn send(&mut self, event: &CrawlEvent) -> Result<()> {
+        let progress = match event {
+            CrawlEvent::EdgeAdded { .. } => return Ok(()),
+            CrawlEvent::AddressExpanded { address, fetched_transactions, new_edges, requests } => {
+                let mut state = self.state.lock().unwrap();
+                state.addresses_expanded += 1;
+                state.edges_added += *new_edges as u64;
+                CrawlProgress {
+                    event: "AddressExpanded".to_string(),
+                    address: address.clone(),
+                    fetched_transactions: *fetched_transactions as u64,
+                    new_edges: *new_edges as u64,
+                    requests: *requests as u64,
+                    graph_edges: 0,
+                }
+            }
+            CrawlEvent::CheckpointWritten { edges, .. } => {
+                CrawlProgress { event: "CheckpointWritten".to_string(), graph_edges: *edges as u64, ..Default::default() }
+            }
+        };
+        let _ = self.progress.send(progress); // No subscribers is not an error
+        Ok(())
+    }
+}
+
+pub struct CrawlerService {
+    state: Arc<Mutex<ServiceState>>,
+    // Queried while a crawl appends to it, and replaced by the saved graph when the crawl completes.
+    graph: Arc<SharedGraph>,
+    progress: broadcast::Sender<CrawlProgress>,
+}
+
+async fn crawl(mode: CrawlMode, state: Arc<Mutex<ServiceState>>, graph: Arc<SharedGraph>, progress: broadcast::Sender<CrawlProgress>, control: TaskControl) {
+    // The crawl reports only the edges it adds, so the shared graph starts from what the crawl builds on.
+    match mode {
+        CrawlMode::Full => graph.replace(G::default()),
+        CrawlMode::Resume | CrawlMode::SinceLastRun => {
+            if let Ok((saved, _)) = deserialize_graph(PARSED_GRAPH_FILENAME) {
+                graph.replace(saved);
+            }
+        }
+    }
+    let mut sinks = CrawlSinks::default();
+    sinks.add(Box::new(ProgressSink { state: state.clone(), progress }));
+    sinks.add(Box::new(SharedGraphSink::new(graph.clone())));
+    let api_key = match read_api_key() {
+        Ok(api_key) => api_key,
+        Err(e) => {
+            state.lock().unwrap().last_error = e.to_string();
+            return;
+        }
+    };
+    let crawled = match mode {
+        CrawlMode::Full => {
+            let seeds = vec![TRAVERSAL_STARTING_ADDRESS.to_lowercase()];
+            let initial_state = delta_crawl::CrawlState { seeds: seeds.clone(), ..Default::default() };
+            Ok(parse_blockchain(&seeds, &api_key, initial_state, &mut sinks, &control).await)
+        }
+        CrawlMode::Resume => delta_crawl::resume_traversal(&api_key, &mut sinks, &control).await,
+        CrawlMode::SinceLastRun => delta_crawl::parse_blockchain_since_last_run(&api_key, &mut sinks, &control).await,
+    };
+    let saved = crawled.and_then(|(crawled_graph, crawl_state)| {
+        delta_crawl::save_crawl(&crawled_graph, &crawl_state)?;
+        sinks.emit(checkpoint_written(&crawled_graph, &crawl_state));
+        Ok(crawled_graph)
+    });
+    sinks.finish();
+    match saved {
+        Ok(crawled_graph) => graph.replace(crawled_graph),
+        Err(e) => state.lock().unwrap().last_error = e.to_string(),
+    }
+}
+
+fn find_node(graph: &G, address: &str) -> Result<NodeIndex, Status> {
+    let address = address.to_lowercase();
+    graph
+        .node_indices()
+        .find(|&n| graph[n] == address)
+        .ok_or_else(|| Status::not_found(format!("Address {} is not in the graph", address)))
+}
+
+fn shortest_path(graph: &G, from: NodeIndex, to: NodeIndex, undirected: bool) -> Vec<NodeIndex> {
+    let mut predecessors: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(node) = queue.pop_front() {
+        if node == to {
+            let mut path = vec![to];
+            while let Some(&previous) = predecessors.get(path.last().unwrap()) {
+                path.push(previous);
+            }
+            path.reverse();
+            return path;
+        }
+        let neighbors: Vec<NodeIndex> =
+            if undirected { graph.neighbors_undirected(node).collect() } else { graph.neighbors_directed(node, Direction::Outgoing).collect() };
+        for neighbor in neighbors {
+            if neighbor != from && !predecessors.contains_key(&neighbor) {
+                predecessors.insert(neighbor, node);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    vec![]
+}
+
+#[tonic::async_trait]
+impl Crawler for CrawlerService {
+    async fn start_crawl(&self, request: Request<StartCrawlRequest>) -> Result<Response<CrawlStatus>, Status> {
+        if !Path::new("api_key.txt").exists() {
+            return Err(Status::failed_precondition("Please provide an Etherscan API key (put it inside api_key.txt)"));
+        }
+        let mode = request.into_inner().mode();
+        let mut state = self.state.lock().unwrap();
+        if state.status(&self.graph).running {
+            return Err(Status::already_exists("A crawl is already running"));
+        }
+        state.mode = mode;
+        state.addresses_expanded = 0;
+        state.edges_added = 0;
+        state.last_error.clear();
+        state.cancellation = CancellationToken::new();
+        let control = TaskControl::new(state.cancellation.clone());
+        state.crawl = Some(tokio::spawn(crawl(mode, self.state.clone(), self.graph.clone(), self.progress.clone(), control)));
+        println!("Started {:?} crawl", mode);
+        Ok(Response::new(state.status(&self.graph)))
+    }
+
+    async fn stop_crawl(&self, _request: Request<StopCrawlRequest>) -> Result<Response<CrawlStatus>, Status> {
+        let mut state = self.state.lock().unwrap();
+        match &state.crawl {
+            // The crawl finishes the address in flight and saves what it collected, so it can be resumed.
+            Some(crawl) if !crawl.is_finished() && !state.cancellation.is_cancelled() => {
+                state.cancellation.cancel();
+                state.last_error = "Stopped before completion, the partial crawl is saved for a resume".to_string();
+                println!("Stopping {:?} crawl", state.mode);
+            }
+            _ => return Err(Status::failed_precondition("No crawl is running")),
+        }
+        Ok(Response::new(state.status(&self.graph)))
+    }
+
+    async fn get_status(&self, _request: Request<StatusRequest>) -> Result<Response<CrawlStatus>, Status> {
+        Ok(Response::new(self.state.lock().unwrap().status(&self.graph)))
+    }
+
+    type StreamProgressStream = Pin<Box<dyn Stream<Item = Result<CrawlProgress, Status>> + Send>>;
+
+    async fn stream_progress(&self, _request: Request<ProgressRequest>) -> Result<Response<Self::StreamProgressStream>, Status> {
+        let stream = BroadcastStream::new(self.progress.subscribe())
+            .map(|progress| progress.map_err(|lagged| Status::data_loss(format!("Progress stream fell behind: {}", lagged))));
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn neighbors(&self, request: Request<NeighborsRequest>) -> Result<Response<NeighborsReply>, Status> {
+        let request = request.into_inner();
+        self.graph.read(|graph| {
+            let node = find_node(graph, &request.address)?;
+            let directions: &[Direction] = match request.direction() {
+                proto::Direction::Both => &[Direction::Outgoing, Direction::Incoming],
+                proto::Direction::Outgoing => &[Direction::Outgoing],
+                proto::Direction::Incoming => &[Direction::Incoming],
+            };
+
+            let mut by_neighbor: HashMap<NodeIndex, (u64, f64)> = HashMap::new();
+            for &direction in directions {
+                for edge in graph.edges_directed(node, direction) {
+                    let neighbor = if direction == Direction::Outgoing { edge.target() } else { edge.source() };
+                    let entry = by_neighbor.entry(neighbor).or_default();
+                    entry.0 += 1;
+                    entry.1 += edge.weight().data.as_ref().map_or(0.0, |d| d.usd_value);
+                }
+            }
+            let mut neighbors: Vec<Neighbor> = by_neighbor
+                .into_iter()
+                .map(|(n, (transactions, usd_volume))| Neighbor { address: graph[n].clone(), transactions, usd_volume })
+                .collect();
+            neighbors.sort_by(|a, b| b.transactions.cmp(&a.transactions).then_with(|| a.address.cmp(&b.address)));
+            Ok(Response::new(NeighborsReply { neighbors }))
+        })
+    }
+
+    async fn shortest_path(&self, request: Request<PathRequest>) -> Result<Response<PathReply>, Status> {
+        let request = request.into_inner();
+        // A search may visit the whole graph, so it runs on a snapshot rather than holding up the crawl.
+        let graph = self.graph.snapshot();
+        let (from, to) = (find_node(&graph, &request.from)?, find_node(&graph, &request.to)?);
+        let addresses = shortest_path(&graph, from, to, request.undirected).into_iter().map(|n| graph[n].clone()).collect();
+        Ok(Response::new(PathReply { addresses }))
+    }
+
+    async fn save_snapshot(&self, request: Request<SnapshotRequest>) -> Result<Response<SnapshotReply>, Status> {
+        let output = request.into_inner().output;
+        let output = if output.is_empty() { DEFAULT_SNAPSHOT_FILENAME.to_string() } else { output };
+        if output.contains(['/', '\\']) || output.starts_with('.') {
+            return Err(Status::invalid_argument(format!("{} is not a file name in the data folder", output)));
+        }
+        let snapshot = self.graph.snapshot();
+        let info = FilteringResultInfo {
+            node_count: snapshot.node_count(),
+            edge_count: snapshot.edge_count(),
+            node_count_before_filtering: snapshot.node_count(),
+            edge_count_before_filtering: snapshot.edge_count(),
+        };
+        let reply = SnapshotReply {
+            pathname: format!("{}/{}", DATA_STORAGE_FOLDER, output),
+            generation: snapshot.generation,
+            graph_nodes: info.node_count as u64,
+            graph_edges: info.edge_count as u64,
+        };
+        tokio::task::spawn_blocking(move || serialize_graph(&snapshot, &info, &output))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(reply))
+    }
+}
+
+pub fn run_serve_grpc_command(args: &ServeGrpcArgs) -> Result<()> {
+    // Queries work on an empty graph until a crawl adds to it.
+    let graph = deserialize_graph(&args.input).map(|(graph, _)| graph).unwrap_or_default();
+    let (progress, _) = broadcast::channel(1024);
+    let service = CrawlerService { state: Arc::new(Mutex::new(ServiceState::default())), graph: Arc::new(SharedGraph::new(graph)), progress };
+    let address = args.listen.parse()?;
+    println!("Serving gRPC on {}", &args.listen);
+    tokio::runtime::Runtime::new()?.block_on(tonic::transport::Server::builder().add_service(CrawlerServer::new(service)).serve(address))?;
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "crawl")]
+use {
+    crate::cli::CrawlArgs, crate::etherscan::{ApiResponse, Etherscan}, crate::http_client, crate::time_buckets::time_label, chrono::{NaiveDate, NaiveDateTime},
+    eyre::Result, reqwest::Client,
+};
+
+pub const LATEST_BLOCK: u64 = 99_999_999;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    /// Oldest transactions first
+    #[value(name = "asc")]
+    Ascending,
+    /// Newest transactions first
+    #[default]
+    #[value(name = "desc")]
+    Descending,
+}
+
+impl SortDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortDirection::Ascending => "asc",
+            SortDirection::Descending => "desc",
+        }
+    }
+
+    pub fn reversed(self) -> SortDirection {
+        match self {
+            SortDirection::Ascending => SortDirection::Descending,
+            SortDirection::Descending => SortDirection::Ascending,
+        }
+    }
+}
+
+// Block range and order in which address histories are fetched. The order decides which end of a
+// history is kept when it holds more records than the per-address cap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryWindow {
+    pub start_block: u64,
+    pub end_block: u64,
+    pub sort: SortDirection,
+}
+
+impl Default for HistoryWindow {
+    fn default() -> HistoryWindow {
+        HistoryWindow { start_block: 0, end_block: LATEST_BLOCK, sort: SortDirection::Descending }
+    }
+}
+
+#[cfg(feature = "crawl")]
+impl HistoryWindow {
+    // The part of the window from a block on, e.g. the blocks newer than the last run.
+    pub fn starting_at(self, start_block: u64) -> HistoryWindow {
+        HistoryWindow { start_block: self.start_block.max(start_block), ..self }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("blocks {}..={}, {}", self.start_block, self.end_block, self.sort.as_str())
+    }
+}
+
+// Unix seconds or a UTC date, optionally with a time: 2024-03-01, 2024-03-01T12:00 or 2024-03-01T12:00:30.
+#[cfg(feature = "crawl")]
+pub fn parse_anchor(text: &str) -> Result<u64> {
+    if let Ok(timestamp) = text.parse::<u64>() {
+        return Ok(timestamp);
+    }
+    let datetime = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(text, "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)))
+        .ok_or_else(|| eyre::eyre!("Anchor {} is neither unix seconds nor a date like 2024-03-01T12:00", text))?;
+    Ok(datetime.and_utc().timestamp().max(0) as u64)
+}
+
+#[cfg(feature = "crawl")]
+async fn block_by_time(timestamp: u64, closest: &str, client: &Client, api_key: &str) -> Result<u64> {
+    let parameters = [("timestamp", timestamp.to_string()), ("closest", closest.to_string())];
+    let (response, _): (ApiResponse<String>, usize) = Etherscan::new(client, api_key).call("block", "getblocknobytime", &parameters).await?;
+    if response.status != "1" {
+        return Err(eyre::eyre!("Block lookup for {} failed: {} {}", time_label(timestamp)?, response.message, response.result));
+    }
+    Ok(response.result.parse()?)
+}
+
+// The blocks mined within half_width seconds on either side of a timestamp, which costs two requests.
+#[cfg(feature = "crawl")]
+pub async fn window_around(timestamp: u64, half_width: u64, sort: SortDirection, api_key: &str) -> Result<HistoryWindow> {
+    let client = http_client::client();
+    let start_block = block_by_time(timestamp.saturating_sub(half_width), "after", &client, api_key).await?;
+    // Lookups of future timestamps fail, so the end of the window is capped at the present.
+    let end = (timestamp + half_width).min(chrono::Utc::now().timestamp().max(0) as u64);
+    let end_block = block_by_time(end, "before", &client, api_key).await?;
+    println!("Fetching from {} to {}, blocks {}..={}", time_label(timestamp.saturating_sub(half_width))?, time_label(end)?, start_block, end_block);
+    Ok(HistoryWindow { start_block, end_block, sort })
+}
+
+// The window given on the command line, with an anchor turned into blocks by window_around.
+#[cfg(feature = "crawl")]
+pub async fn resolve_window(args: &CrawlArgs, api_key: &str) -> Result<HistoryWindow> {
+    let window = match &args.anchor {
+        Some(anchor) => window_around(parse_anchor(anchor)?, (args.anchor_window_hours * 3_600.0) as u64, args.sort, api_key).await?,
+        None => HistoryWindow {
+            start_block: args.from_block.unwrap_or(0),
+            end_block: args.to_block.unwrap_or(LATEST_BLOCK),
+            sort: args.sort,
+        },
+    };
+    if window.start_block > window.end_block {
+        return Err(eyre::eyre!("The history window {} is empty", window.describe()));
+    }
+    Ok(window)
+}
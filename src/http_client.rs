@@ -0,0 +1,75 @@
+use eyre::Result;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING};
+use reqwest::{Client, Response};
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+// Hosts every crawl talks to. They are resolved once per process and pinned on the clients, so
+// connections opened after an idle timeout skip the lookup.
+const KNOWN_HOSTS: [&str; 1] = ["api.bscscan.com"];
+
+// Crawls keep one connection per request in flight busy, well below this.
+const MAX_IDLE_CONNECTIONS_PER_HOST: usize = 16;
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// A stalled response fails and is retried instead of hanging the crawl.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+static RESOLVED_HOSTS: OnceLock<HashMap<&'static str, Vec<SocketAddr>>> = OnceLock::new();
+
+// Lookups run side by side; a host that fails to resolve is left to the client's own resolver.
+fn resolved_hosts() -> &'static HashMap<&'static str, Vec<SocketAddr>> {
+    RESOLVED_HOSTS.get_or_init(|| {
+        std::thread::scope(|scope| {
+            let lookups: Vec<_> = KNOWN_HOSTS.iter().map(|&host| (host, scope.spawn(move || (host, 443).to_socket_addrs()))).collect();
+            lookups
+                .into_iter()
+                .filter_map(|(host, lookup)| match lookup.join().unwrap() {
+                    Ok(addresses) => Some((host, addresses.collect::<Vec<SocketAddr>>())),
+                    Err(e) => {
+                        println!("Could not resolve {}, leaving it to the client: {}", host, e);
+                        None
+                    }
+                })
+                .filter(|(_, addresses)| !addresses.is_empty())
+                .collect()
+        })
+    })
+}
+
+// The client for Etherscan and RPC requests: compressed responses, kept-alive pooled connections and
+// pre-resolved hosts. Bodies have to be read with `body_bytes`, which undoes the compression.
+pub fn client() -> Client {
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("br, gzip"));
+    let mut builder = Client::builder()
+        .default_headers(headers)
+        .pool_max_idle_per_host(MAX_IDLE_CONNECTIONS_PER_HOST)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .tcp_keepalive(TCP_KEEPALIVE)
+        .tcp_nodelay(true)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT);
+    for (host, addresses) in resolved_hosts() {
+        builder = builder.resolve_to_addrs(host, addresses);
+    }
+    builder.build().unwrap()
+}
+
+// The decoded body of a response, whichever encoding the server picked from the ones `client` accepts.
+pub async fn body_bytes(response: Response) -> Result<Vec<u8>> {
+    let encoding = response.headers().get(CONTENT_ENCODING).and_then(|value| value.to_str().ok()).map(|value| value.trim().to_lowercase());
+    let body = response.bytes().await?;
+    let mut decoded = vec![];
+    match encoding.as_deref() {
+        None | Some("identity") => return Ok(body.to_vec()),
+        Some("gzip") => flate2::read::GzDecoder::new(&body[..]).read_to_end(&mut decoded)?,
+        Some("br") => brotli_decompressor::Decompressor::new(&body[..], 4096).read_to_end(&mut decoded)?,
+        Some(other) => return Err(eyre::eyre!("Unsupported response encoding {}", other)),
+    };
+    Ok(decoded)
+}
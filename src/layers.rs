@@ -0,0 +1,207 @@
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use strum::IntoEnumIterator;
+
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::{LayerArgs, LayersArgs};
+use crate::graph_view::FilteredGraph;
+use crate::money::{wei_to_coin, Amount};
+use crate::pricing::{PriceCache, PriceSeries};
+use crate::{deserialize_graph, EdgeKind, Payload, Transaction, DATA_STORAGE_FOLDER, G};
+
+// One asset's transfers in a graph that mixes them: the native coin sent with calls, each tracked stablecoin,
+// and every other token whose transfers were decoded from call input. An edge belongs to one layer per asset
+// it moved, so a call carrying coin and decoded as a token transfer counts in both.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Layer {
+    Native,
+    Stablecoin(Payload),
+    Token(String),
+}
+
+impl Layer {
+    // native, a stablecoin name such as usdc, or token:<contract address>.
+    pub fn parse(spec: &str) -> Result<Layer> {
+        let spec = spec.trim().to_lowercase();
+        if spec == "native" {
+            return Ok(Layer::Native);
+        }
+        if let Some(token) = spec.strip_prefix("token:") {
+            return Ok(Layer::Token(token.to_string()));
+        }
+        Payload::iter().find(|payload| format!("{:?}", payload).to_lowercase() == spec).map(Layer::Stablecoin).ok_or_else(|| {
+            let stablecoins: Vec<String> = Payload::iter().map(|payload| format!("{:?}", payload).to_lowercase()).collect();
+            eyre::eyre!("Unknown layer {}, expected native, {} or token:<contract address>", spec, stablecoins.join(", "))
+        })
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Layer::Native => "native".to_string(),
+            Layer::Stablecoin(payload) => format!("{:?}", payload).to_lowercase(),
+            Layer::Token(token) => format!("token:{}", token),
+        }
+    }
+}
+
+pub fn edge_layers(transaction: &Transaction) -> Vec<Layer> {
+    let mut layers = vec![];
+    if transaction.value_wei > 0 {
+        layers.push(Layer::Native);
+    }
+    if let Some(data) = &transaction.data {
+        layers.push(Layer::Stablecoin(data.payload.clone()));
+    }
+    if let (EdgeKind::DecodedTransfer, Some(transfer)) = (transaction.kind, &transaction.decoded_transfer) {
+        layers.push(Layer::Token(transfer.token.to_lowercase()));
+    }
+    layers
+}
+
+// What the edge moved in the layer's own unit: coin, stablecoin dollars, or whole tokens, falling back on the
+// raw integer amount for tokens the crawl could not resolve the decimals of.
+fn layer_amount(transaction: &Transaction, layer: &Layer) -> f64 {
+    match layer {
+        Layer::Native => wei_to_coin(transaction.value_wei),
+        Layer::Stablecoin(_) => transaction.data.as_ref().map_or(0.0, |data| data.usd_value),
+        Layer::Token(_) => transaction
+            .decoded_transfer
+            .as_ref()
+            .and_then(|transfer| transfer.normalized_amount().or_else(|| transfer.amount.parse().ok()))
+            .unwrap_or(0.0),
+    }
+}
+
+// Stablecoins are worth what enrich-usd stamped on them, or face value; native coin is priced at the hour of
+// the transfer when prices are given, otherwise worth what enrich-usd stamped. Decoded tokens stay unpriced.
+fn layer_usd_value(transaction: &Transaction, layer: &Layer, prices: &mut Option<PriceCache>, coin_symbol: &str) -> Option<f64> {
+    match layer {
+        Layer::Native => match prices.as_mut() {
+            Some(prices) => prices.usd_price(coin_symbol, transaction.timestamp).map(|price| price * wei_to_coin(transaction.value_wei)),
+            None => transaction.value_usd,
+        },
+        Layer::Stablecoin(_) => transaction.usd_value(),
+        Layer::Token(_) => None,
+    }
+}
+
+// Keeps the edges in any of the selected layers, or the whole graph when none is selected.
+pub fn select_layers(graph: G, args: &LayerArgs) -> Result<G> {
+    if args.layers.is_empty() {
+        return Ok(graph);
+    }
+    let selected = args.layers.iter().map(|spec| Layer::parse(spec)).collect::<Result<Vec<Layer>>>()?;
+    let (selection, _) = FilteredGraph::new(&graph).retain_edges(|edge| edge_layers(edge.weight()).iter().any(|layer| selected.contains(layer))).materialize();
+    println!(
+        "Kept {} of {} edges in layers {}",
+        selection.edge_count(),
+        graph.edge_count(),
+        selected.iter().map(Layer::name).collect::<Vec<String>>().join(", ")
+    );
+    Ok(selection)
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LayerPairTotal {
+    pub layer: String,
+    pub sender: String,
+    pub recipient: String,
+    pub transfers: usize,
+    pub amount: f64,
+    pub usd_value: Option<f64>,
+    // Transfers in the layer without a USD value, left out of usd_value.
+    pub unpriced_transfers: usize,
+}
+
+// Totals per directed pair in every asset layer, and in a combined usd layer summing the priced transfers of
+// all assets, whose amount is the USD value. Sorted by layer, then pair.
+pub fn layer_pair_totals(graph: &G, selected: &[Layer], prices: &mut Option<PriceCache>, coin_symbol: &str) -> Vec<LayerPairTotal> {
+    let mut totals: BTreeMap<(String, String, String), LayerPairTotal> = BTreeMap::new();
+    for edge in graph.edge_references() {
+        let (sender, recipient) = (&graph[edge.source()], &graph[edge.target()]);
+        let mut combined_usd = None;
+        for layer in edge_layers(edge.weight()).into_iter().filter(|layer| selected.is_empty() || selected.contains(layer)) {
+            let usd_value = layer_usd_value(edge.weight(), &layer, prices, coin_symbol);
+            let total = totals.entry((layer.name(), sender.clone(), recipient.clone())).or_insert_with(|| LayerPairTotal {
+                layer: layer.name(),
+                sender: sender.clone(),
+                recipient: recipient.clone(),
+                transfers: 0,
+                amount: 0.0,
+                usd_value: None,
+                unpriced_transfers: 0,
+            });
+            total.transfers += 1;
+            total.amount += layer_amount(edge.weight(), &layer);
+            match usd_value {
+                Some(usd_value) => {
+                    total.usd_value = Some(total.usd_value.unwrap_or(0.0) + usd_value);
+                    combined_usd = Some(combined_usd.unwrap_or(0.0) + usd_value);
+                }
+                None => total.unpriced_transfers += 1,
+            }
+        }
+        if let Some(usd_value) = combined_usd {
+            let total = totals.entry(("usd".to_string(), sender.clone(), recipient.clone())).or_insert_with(|| LayerPairTotal {
+                layer: "usd".to_string(),
+                sender: sender.clone(),
+                recipient: recipient.clone(),
+                transfers: 0,
+                amount: 0.0,
+                usd_value: Some(0.0),
+                unpriced_transfers: 0,
+            });
+            total.transfers += 1;
+            total.amount += usd_value;
+            total.usd_value = Some(total.amount);
+        }
+    }
+    totals.into_values().collect()
+}
+
+pub fn layers_summary(totals: &[LayerPairTotal]) -> String {
+    let mut layers: BTreeMap<&str, (usize, usize, f64, f64, usize)> = BTreeMap::new();
+    for total in totals {
+        let layer = layers.entry(total.layer.as_str()).or_default();
+        layer.0 += 1;
+        layer.1 += total.transfers;
+        layer.2 += total.amount;
+        layer.3 += total.usd_value.unwrap_or(0.0);
+        layer.4 += total.unpriced_transfers;
+    }
+    let mut summary = String::new();
+    for (layer, (pairs, transfers, amount, usd_value, unpriced_transfers)) in layers {
+        summary.push_str(&format!(
+            "Layer {}: {} pairs, {} transfers, amount {:.6}, {} ({} transfers unpriced)\n",
+            layer,
+            pairs,
+            transfers,
+            amount,
+            Amount::Usd(usd_value),
+            unpriced_transfers
+        ));
+    }
+    summary
+}
+
+pub fn run_layers_command(args: &LayersArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let selected = args.layer.layers.iter().map(|spec| Layer::parse(spec)).collect::<Result<Vec<Layer>>>()?;
+    let price_series = args.prices.as_ref().map(|name| PriceSeries::from_csv(&format!("{}/{}", DATA_STORAGE_FOLDER, name))).transpose()?;
+    let mut prices = price_series.as_ref().map(|series| PriceCache::new(series));
+    let totals = layer_pair_totals(&graph, &selected, &mut prices, &args.coin_symbol);
+    print!("{}", layers_summary(&totals));
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for total in totals.iter() {
+        writer.serialize(total)?;
+    }
+    commit_csv(writer)?;
+    println!("Saved {} per-pair layer totals as {}", totals.len(), &file_pathname);
+    Ok(())
+}
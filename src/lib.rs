@@ -0,0 +1,1271 @@
+mod activity_heatmap;
+pub mod abi_decoding;
+mod address_poisoning;
+pub mod analysis_pass;
+mod address_report;
+pub mod annotations;
+mod approval_risk;
+mod atomic_file;
+mod balance_history;
+#[cfg(feature = "crawl")]
+mod block_rewards;
+mod bridges;
+mod case_export;
+pub mod cli;
+mod compaction;
+mod compare;
+#[cfg(feature = "crawl")]
+mod contract_detection;
+mod contract_interactions;
+mod counterparty_similarity;
+mod coverage_map;
+#[cfg(feature = "crawl")]
+pub mod crawl_control;
+pub mod crawl_events;
+#[cfg(feature = "crawl")]
+mod daemon;
+mod delta_crawl;
+mod dormancy;
+mod drift_monitor;
+mod dry_run;
+mod dust_filter;
+mod edge_handling;
+mod edge_identity;
+mod edge_payload;
+#[cfg(feature = "crawl")]
+mod edge_preview;
+#[cfg(feature = "scripting")]
+mod edge_script;
+mod embeddings;
+mod entity_clustering;
+pub mod etherscan;
+pub mod exit_status;
+mod exposure;
+mod failed_addresses;
+mod features;
+mod fees;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod fixtures;
+mod funding_source;
+pub mod graph_builder;
+mod graph_query;
+pub mod graph_view;
+#[cfg(feature = "grpc")]
+mod grpc_service;
+mod history_window;
+#[cfg(feature = "crawl")]
+mod http_client;
+mod layers;
+mod mev;
+mod mixers;
+mod money;
+mod near_duplicates;
+#[cfg(feature = "crawl")]
+mod notifier;
+#[cfg(feature = "crawl")]
+mod pagination;
+mod pair_distribution;
+mod pass_cache;
+mod period_summary;
+mod pipeline;
+#[cfg(feature = "polars")]
+pub mod polars_integration;
+mod price_correlation;
+pub mod pricing;
+mod profiles;
+mod pseudonymization;
+#[cfg(feature = "crawl")]
+mod quick_look;
+mod reciprocity;
+mod record_validation;
+#[cfg(feature = "crawl")]
+mod request_audit;
+#[cfg(feature = "crawl")]
+mod request_metrics;
+pub mod risk_scoring;
+mod rolling_flow;
+mod sampling;
+mod seed_attribution;
+#[cfg(feature = "crawl")]
+mod seed_transaction;
+mod seeded_rng;
+#[cfg(feature = "grpc")]
+pub mod shared_graph;
+mod skip_report;
+mod sparse_matrix;
+mod special_addresses;
+#[cfg(feature = "spectral")]
+mod spectral;
+pub mod stable_graph;
+pub mod stats;
+mod strategy_benchmark;
+mod structuring;
+pub mod task_control;
+mod temporal_motifs;
+#[cfg(feature = "crawl")]
+mod throttle;
+mod time_buckets;
+mod token_holders;
+#[cfg(feature = "crawl")]
+mod token_metadata;
+mod tx_count_precheck;
+pub mod tx_graph;
+#[cfg(feature = "crawl")]
+mod two_pass_crawl;
+mod usd_enrichment;
+mod workspace;
+mod zip_archive;
+
+use graph_view::FilteredGraph;
+use money::Amount;
+use eyre::Result;
+use petgraph::{graph::{EdgeIndex, NodeIndex}, Directed};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use petgraph::Graph;
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use {core::cmp::min, plotters::{coord::Shift, prelude::*}};
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+#[cfg(feature = "crawl")]
+use {priority_queue::PriorityQueue, reqwest::Client, std::cmp::Reverse, std::fs::File, std::io::Read, std::time::Instant, tokio::runtime::Runtime};
+
+pub type Response = etherscan::ApiResponse<Vec<RawTransaction>>;
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RawTransaction {
+    blockHash: String,
+    blockNumber: String,
+    from: String,
+    to: String,
+    gas: String,
+    gasPrice: String,
+    gasUsed: String,
+    hash: String,
+    value: String,
+    nonce: String,
+    transactionIndex: String,
+    timeStamp: String,
+    isError: String,
+    txreceipt_status: String,
+    input: String,
+    contractAddress: String,
+    cumulativeGasUsed: String,
+    functionName: String,
+    methodId: String,
+    // EIP-2718 type and EIP-1559 fee caps, empty on legacy transactions and in responses that predate them.
+    #[serde(default, rename = "type")]
+    txType: String,
+    #[serde(default)]
+    maxFeePerGas: String,
+    #[serde(default)]
+    maxPriorityFeePerGas: String,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct Transaction {
+    hash: String,
+    #[serde(default)]
+    timestamp: u64,
+    // Zero when the lean edge schema dropped them, and then left out of saved graphs.
+    #[serde(default, skip_serializing_if = "edge_payload::is_zero")]
+    gas_price: u64,
+    #[serde(default, skip_serializing_if = "edge_payload::is_zero")]
+    gas_used: u64,
+    // Zero for legacy transactions, 2 with the fee caps below for EIP-1559 ones.
+    #[serde(default, skip_serializing_if = "edge_payload::is_zero")]
+    transaction_type: u8,
+    #[serde(default, skip_serializing_if = "edge_payload::is_zero")]
+    max_fee_per_gas: u64,
+    #[serde(default, skip_serializing_if = "edge_payload::is_zero")]
+    max_priority_fee_per_gas: u64,
+    // Of the including block, when known; Etherscan's transaction lists leave it out.
+    #[serde(default, skip_serializing_if = "edge_payload::is_zero")]
+    base_fee_per_gas: u64,
+    #[serde(default, skip_serializing_if = "edge_payload::is_zero")]
+    block_number: u64,
+    #[serde(default, skip_serializing_if = "edge_payload::is_zero")]
+    transaction_index: u64,
+    #[serde(default, skip_serializing_if = "edge_payload::is_zero")]
+    nonce: u64,
+    #[serde(default)]
+    contract_call: bool,
+    // Native coin sent with the call; token transfers carry their value in data instead.
+    #[serde(default, skip_serializing_if = "edge_payload::is_zero")]
+    value_wei: u128,
+    #[serde(default)]
+    kind: EdgeKind,
+    #[serde(default)]
+    trace_index: Option<u64>,
+    #[serde(default)]
+    log_index: Option<u64>,
+    data: Option<DigestedData>,
+    // Stamped by the enrich-usd pass with prices at the hour of the transfer.
+    #[serde(default)]
+    value_usd: Option<f64>,
+    #[serde(default)]
+    fee_usd: Option<f64>,
+    #[serde(default)]
+    decoded_call: Option<abi_decoding::DecodedCall>,
+    #[serde(default)]
+    decoded_transfer: Option<abi_decoding::DecodedTransfer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    full: Option<Box<edge_payload::FullEdgeData>>,
+}
+
+impl Transaction {
+    // Stamped by enrich-usd when it ran, otherwise the stablecoin amount of a parsed transfer; None for edges
+    // nobody priced.
+    pub fn usd_value(&self) -> Option<f64> {
+        self.value_usd.or(self.data.as_ref().map(|data| data.usd_value))
+    }
+}
+
+// Which record an edge was built from. One transaction hash can yield several edges of different kinds.
+#[derive(Hash, PartialEq, Eq, Serialize, Deserialize, Debug, Clone, Copy, Default)]
+enum EdgeKind {
+    #[default]
+    Call,
+    TokenTransfer,
+    Internal,
+    // Token movement decoded from the input of a call to a contract other than the tracked stablecoins.
+    DecodedTransfer,
+    // First-funding relationship from the funder to the funded address, added on request after a crawl.
+    FundedBy,
+    // Call of an ERC-4337 smart account to its target, decoded from the EntryPoint bundle that carried it.
+    UserOperation,
+    // Reward and priority fees of a block produced by the target, from the block reward node, added on request.
+    BlockReward,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct DigestedData {
+    payload: Payload,
+    usd_value: f64,
+    used_onchain_function: OnchainFunction,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FilteringResultInfo {
+    node_count_before_filtering: usize,
+    edge_count_before_filtering: usize,
+    node_count: usize,
+    edge_count: usize,
+}
+
+// Loaded through tx_graph's GraphHeader, which mirrors these fields, so keep the two in sync.
+#[derive(Serialize, Deserialize)]
+struct SerializableGraph {
+    #[serde(default = "edge_payload::legacy_format_version")]
+    format_version: u32,
+    #[serde(default)]
+    edge_schema: edge_payload::EdgeSchema,
+    info: FilteringResultInfo,
+    nodes: Vec<String>,
+    edges: Vec<(usize, usize, Transaction)>,
+}
+
+pub type G = Graph<String, Transaction, Directed>;
+
+// Relevance counter of a queued address. Ties go to the smallest address, so the pop order depends only on
+// the queue contents and a frontier restored from the crawl state replays the same traversal.
+#[cfg(feature = "crawl")]
+type AddressPriority = (i32, Reverse<String>);
+
+#[derive(Hash, PartialEq, Eq, Serialize, Deserialize, Debug, Clone, EnumIter)]
+enum OnchainFunction {
+    Transfer,
+    TransferFrom,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Hash, PartialEq, Eq, Deserialize, Serialize, Debug, Clone, EnumIter)]
+enum Payload {
+    BSCUSD,
+    USDC,
+}
+
+#[cfg(feature = "crawl")]
+const SATOSHI_TO_COIN_CONVERSION_FACTOR: usize = 1E18 as usize; // 1E18  for bscscan
+
+#[cfg(feature = "crawl")]
+const TRAVERSAL_STARTING_ADDRESS: &str = "0x94453A61CAbCd51ef1031f527Fd2b76f659423e7";
+const MAX_TRANSACTIONS_TO_PARSE: usize = 10_000_000;
+const TRANSACTIONS_TO_REQUEST: usize = 10_000; // <= 10000. Page size for each request.
+const MAX_TRANSACTIONS_PER_ADDRESS: usize = 50_000; // Above 10000, deeper history is fetched with asc/desc stitching and block-window bisection.
+const API_REQUESTS_PER_SECOND: f64 = 5.0; // Etherscan free tier rate limit
+const API_DAILY_REQUEST_QUOTA: usize = 100_000; // Etherscan free tier daily limit
+const DATA_STORAGE_FOLDER: &str = "json";
+
+static CONTRACT_ADDRESSES: Lazy<Mutex<HashMap<Payload, String>>> = Lazy::new(|| {
+    let mut m = HashMap::new();
+    let bscusd_contract = "0x55d398326f99059fF775485246999027B3197955".to_string().to_lowercase();
+    let usdc_contract = "0x8AC76a51cc950d9822D68b83fE1Ad97B32Cd580d".to_string().to_lowercase();
+    m.insert(Payload::BSCUSD, bscusd_contract);
+    m.insert(Payload::USDC, usdc_contract);
+    Mutex::new(m)
+});
+
+#[cfg(feature = "crawl")]
+struct OnchainFunctionDescription {
+    method_id: String,
+    function_name: String,
+    input_lenth: usize,
+    value_slice:(usize, usize),
+    to_slice: (usize, usize),
+    from_slice:Option<(usize, usize)>,
+}
+
+#[cfg(feature = "crawl")]
+static METHOD_IDS: Lazy<Mutex<HashMap<OnchainFunction, OnchainFunctionDescription>>> = Lazy::new(|| {
+    let mut m = HashMap::new();
+    m.insert(OnchainFunction::Transfer,
+    OnchainFunctionDescription {
+        method_id: "0xa9059cbb".to_string(),
+        function_name:  "transfer(address _to, uint256 _value)".to_string(),
+        input_lenth: 138,
+        to_slice: (10, 74),
+        value_slice: (74, 138),
+        from_slice: None,
+    });
+    m.insert(OnchainFunction::TransferFrom,
+    OnchainFunctionDescription {
+        method_id: "0x23b872dd".to_string(),
+        function_name:  "transferFrom(address _from, address _to, uint256 _value)".to_string(),
+        input_lenth: 202,
+        from_slice: Some((10, 74)),
+        to_slice: (74, 138),
+        value_slice: (138, 202),
+    });
+    Mutex::new(m)
+});
+
+pub fn parse_response(body_bytes: &[u8]) -> Result<Response> {
+    etherscan::decode(body_bytes)
+}
+
+#[cfg(feature = "crawl")]
+#[allow(clippy::too_many_arguments)]
+async fn graph_data_collection_procedure(
+    address_priority_pq: &mut PriorityQueue<String, AddressPriority>,
+    graph_builder: &mut graph_builder::GraphBuilder,
+    client: &Client,
+    api_key: &str,
+    throttle: &throttle::Throttle,
+    address_to_check: String,
+    window: history_window::HistoryWindow,
+    ingest: delta_crawl::IngestOptions,
+    sinks: &mut crawl_events::CrawlSinks,
+) -> delta_crawl::FetchOutcome {
+    let history = pagination::get_address_history(&address_to_check, &window, client, api_key, throttle).await;
+    token_metadata::resolve_history_tokens(&history, client, api_key, throttle).await;
+    insert_address_history(address_priority_pq, graph_builder, address_to_check, &history, window.start_block, ingest, sinks)
+}
+
+// Bundled account calls get one edge each, numbered in bundle order through trace_index so that every call keeps
+// its own edge identity. Token transfers requested by a call get a decoded transfer edge with the same number.
+// Accounts and targets gain relevance like the endpoints of plain transactions, so the crawl follows the real actors.
+#[cfg(feature = "crawl")]
+fn add_user_operation_edges(
+    address_priority_pq: &mut PriorityQueue<String, AddressPriority>,
+    graph_builder: &mut graph_builder::GraphBuilder,
+    transaction: &RawTransaction,
+    bundle: &Transaction,
+    ingest: delta_crawl::IngestOptions,
+    special_report: &mut special_addresses::SpecialAddressReport,
+    validation: &mut record_validation::ValidationReport,
+) {
+    let Some(operations) = abi_decoding::decode_handle_ops(&transaction.input) else { return };
+    let calls = operations.iter().flat_map(|operation| operation.calls.iter().map(move |call| (&operation.sender, call)));
+    for (sequence, (sender, call)) in calls.enumerate() {
+        for address in [sender, &call.target].into_iter().filter(|address| !special_addresses::is_special(address)) {
+            if !address_priority_pq.change_priority_by(address, |x: &mut AddressPriority| x.0 += 1) {
+                address_priority_pq.push(address.clone(), (1, Reverse(address.clone())));
+            }
+        }
+        let value_wei = match record_validation::checked_wei("callValue", &call.value) {
+            Ok(value_wei) => value_wei,
+            Err(rejection) => {
+                validation.reject(sender, &transaction.hash, rejection);
+                continue;
+            }
+        };
+        let decoded_call = abi_decoding::decode_input(&call.data);
+        let decoded_transfer = decoded_call.as_ref().and_then(|decoded| abi_decoding::implied_transfer(decoded, sender, &call.target)).map(token_metadata::with_metadata);
+        let call_edge = Transaction {
+            kind: EdgeKind::UserOperation,
+            value_wei,
+            contract_call: call.data.len() > 2,
+            trace_index: Some(sequence as u64),
+            decoded_call,
+            decoded_transfer: None,
+            full: None,
+            ..bundle.clone()
+        };
+        add_routed_edge(graph_builder, ingest, special_report, sender, &call.target, ingest.edge_schema.retain(call_edge.clone()));
+        if let Some(decoded_transfer) = decoded_transfer {
+            let (decoded_from, decoded_to) = (decoded_transfer.from.clone(), decoded_transfer.to.clone());
+            let transfer_edge = Transaction { kind: EdgeKind::DecodedTransfer, value_wei: 0, decoded_call: None, decoded_transfer: Some(decoded_transfer), ..call_edge };
+            add_routed_edge(graph_builder, ingest, special_report, &decoded_from, &decoded_to, ingest.edge_schema.retain(transfer_edge));
+        }
+    }
+}
+
+// Edges touching genesis or burn addresses are renamed or dropped as the special address policies say.
+#[cfg(feature = "crawl")]
+fn add_routed_edge(
+    graph_builder: &mut graph_builder::GraphBuilder,
+    ingest: delta_crawl::IngestOptions,
+    special_report: &mut special_addresses::SpecialAddressReport,
+    from: &str,
+    to: &str,
+    transaction: Transaction,
+) {
+    if let Some((from, to)) = ingest.special_addresses.route(from, to, &transaction, special_report) {
+        graph_builder.add_edge(&from, &to, transaction);
+    }
+}
+
+// Genesis allocations carry native value and have no sender, so they bypass the token and call decoding.
+#[cfg(feature = "crawl")]
+fn add_genesis_edge(
+    graph_builder: &mut graph_builder::GraphBuilder,
+    transaction: &RawTransaction,
+    parsed: &record_validation::ParsedRecord,
+    ingest: delta_crawl::IngestOptions,
+    special_report: &mut special_addresses::SpecialAddressReport,
+) {
+    let allocation = Transaction {
+        hash: transaction.hash.clone(),
+        timestamp: parsed.timestamp,
+        block_number: parsed.block_number,
+        value_wei: parsed.value_wei,
+        kind: EdgeKind::Call,
+        ..Default::default()
+    };
+    add_routed_edge(graph_builder, ingest, special_report, &transaction.from, &transaction.to, ingest.edge_schema.retain(allocation));
+}
+
+// Adds the edges of a fetched history to the graph and raises the relevance of every counterparty.
+#[cfg(feature = "crawl")]
+fn insert_address_history(
+    address_priority_pq: &mut PriorityQueue<String, AddressPriority>,
+    graph_builder: &mut graph_builder::GraphBuilder,
+    address_to_check: String,
+    history: &pagination::AddressHistory,
+    start_block: u64,
+    ingest: delta_crawl::IngestOptions,
+    sinks: &mut crawl_events::CrawlSinks,
+) -> delta_crawl::FetchOutcome {
+    let edge_schema = ingest.edge_schema;
+    let mut special_report = special_addresses::SpecialAddressReport::default();
+    let address_history = &history.transactions;
+    println!("Fetched {} transactions for {} in {} requests", address_history.len(), &address_to_check, history.requests);
+    let edge_count_before = graph_builder.graph().edge_count();
+    graph_builder.reserve(address_history.len(), address_history.len());
+    let mut validation = record_validation::ValidationReport::default();
+    let mut skipped = skip_report::SkipReport::default();
+    let records = record_validation::validate_history(&address_to_check, address_history, &mut validation);
+
+    let pq_timer: Instant = Instant::now();
+    for &(transaction, parsed) in records.iter() {
+        if transaction.from == special_addresses::GENESIS {
+            if !graph_builder.contains_transaction(&transaction.hash) {
+                add_genesis_edge(graph_builder, transaction, &parsed, ingest, &mut special_report);
+            }
+            continue;
+        }
+        if transaction.contractAddress.is_empty()
+        && transaction.isError == "0"
+        && !graph_builder.contains_transaction(&transaction.hash)
+        {
+            // Special addresses are sinks of the graph, their histories are not what the crawl is after.
+            if !special_addresses::is_special(&transaction.to) && !address_priority_pq.change_priority_by(&transaction.to, |x: &mut AddressPriority| { x.0 += 1 }){
+                address_priority_pq.push(transaction.to.clone(), (1, Reverse(transaction.to.clone())));
+            }
+            if !address_priority_pq.change_priority_by(&transaction.from, |x: &mut AddressPriority| { x.0 += 1 }){
+                address_priority_pq.push(transaction.from.clone(), (1, Reverse(transaction.from.clone())));
+            }
+
+            if transaction.value == "0" {
+                'outer: for payload in Payload::iter() {
+                    let contract_address_mutex = CONTRACT_ADDRESSES.lock().unwrap();
+                    let contract_address = contract_address_mutex.get(&payload).unwrap();
+
+                    for onchain_function in OnchainFunction::iter() {
+                        let descriptions = METHOD_IDS.lock().unwrap();
+                        let description = descriptions.get(&onchain_function).unwrap();
+
+                        if transaction.to == *contract_address
+                        && transaction.methodId == description.method_id
+                        && transaction.input.len() == description.input_lenth
+                        {
+                            assert_eq!(&transaction.input[0..10], description.method_id, "{:?}", dbg!(transaction));
+                            assert!(transaction.functionName == description.function_name, "{:?}", dbg!(transaction));
+
+                            let (value_slice_low, value_slice_high) = description.value_slice;
+                            let (to_slice_low, to_slice_high) = description.to_slice;
+
+                            let real_transaction_source =
+                                if let Some((from_slice_low, from_slice_high)) = description.from_slice {
+                                    transaction.input[from_slice_low..from_slice_high].to_string()
+                                } else {
+                                    transaction.from.clone()
+                                };
+
+                            let real_transaction_destination = transaction.input[to_slice_low..to_slice_high].to_string(); // Real transaction destination
+                            let usd_value = match record_validation::checked_token_amount("input", &transaction.input[value_slice_low..value_slice_high]) {
+                                Ok(usd_value) => usd_value,
+                                Err(rejection) => {
+                                    validation.reject(&address_to_check, &transaction.hash, rejection);
+                                    break 'outer;
+                                }
+                            };
+
+                            let digested_transaction = Transaction {
+                                hash: transaction.hash.clone(),
+                                timestamp: parsed.timestamp,
+                                gas_price: parsed.gas_price,
+                                gas_used: parsed.gas_used,
+                                transaction_type: parsed.transaction_type,
+                                max_fee_per_gas: parsed.max_fee_per_gas,
+                                max_priority_fee_per_gas: parsed.max_priority_fee_per_gas,
+                                base_fee_per_gas: 0,
+                                block_number: parsed.block_number,
+                                transaction_index: parsed.transaction_index,
+                                nonce: parsed.nonce,
+                                contract_call: transaction.input.len() > 2,
+                                value_wei: 0,
+                                kind: EdgeKind::TokenTransfer,
+                                trace_index: None,
+                                log_index: None,
+                                value_usd: None,
+                                fee_usd: None,
+                                decoded_call: None,
+                                decoded_transfer: None,
+                                full: (edge_schema == edge_payload::EdgeSchema::Full).then(|| Box::new(edge_payload::FullEdgeData::from_raw(transaction))),
+                                data: Some(
+                                    DigestedData {
+                                        payload: payload.clone(),
+                                        usd_value,
+                                        used_onchain_function: onchain_function,
+                                    }
+                                )
+                            };
+                            let digested_transaction = edge_schema.retain(digested_transaction);
+                            add_routed_edge(graph_builder, ingest, &mut special_report, &real_transaction_source, &real_transaction_destination, digested_transaction);
+
+                            break 'outer
+                        }
+                    }
+                }
+
+                let undigested_transaction = Transaction {
+                    hash: transaction.hash.clone(),
+                    timestamp: parsed.timestamp,
+                    gas_price: parsed.gas_price,
+                    gas_used: parsed.gas_used,
+                    transaction_type: parsed.transaction_type,
+                    max_fee_per_gas: parsed.max_fee_per_gas,
+                    max_priority_fee_per_gas: parsed.max_priority_fee_per_gas,
+                    base_fee_per_gas: 0,
+                    block_number: parsed.block_number,
+                    transaction_index: parsed.transaction_index,
+                    nonce: parsed.nonce,
+                    contract_call: transaction.input.len() > 2, // Anything beyond "0x" is calldata
+                    value_wei: parsed.value_wei,
+                    kind: EdgeKind::Call,
+                    trace_index: None,
+                    log_index: None,
+                    value_usd: None,
+                    fee_usd: None,
+                    decoded_call: abi_decoding::decode_input(&transaction.input),
+                    decoded_transfer: None,
+                    full: (edge_schema == edge_payload::EdgeSchema::Full).then(|| Box::new(edge_payload::FullEdgeData::from_raw(transaction))),
+                    data: None
+                };
+
+                let decoded_transfer = undigested_transaction
+                    .decoded_call
+                    .as_ref()
+                    .filter(|_| !CONTRACT_ADDRESSES.lock().unwrap().values().any(|contract| *contract == transaction.to))
+                    .and_then(|call| abi_decoding::implied_transfer(call, &transaction.from, &transaction.to))
+                    .map(token_metadata::with_metadata);
+
+                // This is a catch-all branch for undigested transactions.
+                // The "target" may end up being not a real transaction destination, but a contract address.
+                // Hash will be unique tho.
+                let undigested_transaction = edge_schema.retain(undigested_transaction);
+                add_routed_edge(graph_builder, ingest, &mut special_report, &transaction.from, &transaction.to, undigested_transaction.clone());
+
+                // Bundled ERC-4337 calls would otherwise all show up as bundler to EntryPoint.
+                if ingest.user_operations && abi_decoding::ENTRY_POINTS.contains(&transaction.to.as_str()) {
+                    add_user_operation_edges(address_priority_pq, graph_builder, transaction, &undigested_transaction, ingest, &mut special_report, &mut validation);
+                }
+
+                // Routed token movements get an edge of their own between the real sender and recipient.
+                if let Some(decoded_transfer) = decoded_transfer {
+                    let (decoded_from, decoded_to) = (decoded_transfer.from.clone(), decoded_transfer.to.clone());
+                    let transfer_edge = Transaction {
+                        kind: EdgeKind::DecodedTransfer,
+                        value_wei: 0,
+                        decoded_call: None,
+                        decoded_transfer: Some(decoded_transfer),
+                        ..undigested_transaction
+                    };
+                    add_routed_edge(graph_builder, ingest, &mut special_report, &decoded_from, &decoded_to, transfer_edge);
+                }
+            }
+        } else if let Some(reason) = skip_report::SkipReport::skip_reason(transaction) {
+            skipped.skip(transaction, reason, parsed.value_wei);
+        }
+    }
+    println!("Editing priority addresses and graph manipulation took {:<9} mks (PriorityQueue)", pq_timer.elapsed().as_micros());
+
+    let blockchain_graph = graph_builder.graph();
+    if !sinks.is_empty() {
+        // Edges are only ever appended, so the new ones are the tail of the edge list.
+        for edge_index in blockchain_graph.edge_indices().skip(edge_count_before) {
+            let (source, target) = blockchain_graph.edge_endpoints(edge_index).unwrap();
+            sinks.emit(crawl_events::CrawlEvent::EdgeAdded {
+                from: blockchain_graph[source].clone(),
+                to: blockchain_graph[target].clone(),
+                transaction: Box::new(blockchain_graph[edge_index].clone()),
+            });
+        }
+        sinks.emit(crawl_events::CrawlEvent::AddressExpanded {
+            address: address_to_check.clone(),
+            fetched_transactions: address_history.len(),
+            new_edges: blockchain_graph.edge_count() - edge_count_before,
+            requests: history.requests,
+        });
+    }
+
+    delta_crawl::FetchOutcome {
+        last_fetched_block: records.iter().map(|(_, parsed)| parsed.block_number).max(),
+        funding_source: if start_block == 0 { funding_source::first_funding(&address_to_check, &records) } else { None },
+        validation,
+        skipped,
+        failure: history.failure.clone(),
+        incomplete: history.is_incomplete(),
+        special_addresses: special_report,
+        audit: request_audit::AddressAudit::new(
+            &address_to_check,
+            history.requests,
+            address_history.len(),
+            blockchain_graph.edge_count() - edge_count_before,
+        )
+        .with_nonce_audit(address_history, start_block == 0),
+    }
+}
+
+#[cfg(feature = "crawl")]
+async fn parse_blockchain(
+    starting_addresses: &[String],
+    api_key: &str,
+    crawl_state: delta_crawl::CrawlState,
+    sinks: &mut crawl_events::CrawlSinks,
+    control: &task_control::TaskControl,
+) -> (Graph<String, Transaction>, delta_crawl::CrawlState) {
+    let mut path_priority_pq: PriorityQueue<String, AddressPriority> = PriorityQueue::new();
+    for starting_address in starting_addresses {
+        let starting_address = starting_address.to_lowercase();
+        path_priority_pq.push(starting_address.clone(), (1, Reverse(starting_address)));
+    }
+    continue_traversal(Graph::new(), path_priority_pq, crawl_state, MAX_TRANSACTIONS_TO_PARSE, api_key, sinks, control).await
+}
+
+// Runs the traversal until the graph holds transaction_budget edges, the queue runs dry or the crawl is cancelled,
+// then stores the frontier in the crawl state.
+#[cfg(feature = "crawl")]
+async fn continue_traversal(
+    blockchain_graph: G,
+    mut path_priority_pq: PriorityQueue<String, AddressPriority>,
+    mut crawl_state: delta_crawl::CrawlState,
+    transaction_budget: usize,
+    api_key: &str,
+    sinks: &mut crawl_events::CrawlSinks,
+    control: &task_control::TaskControl,
+) -> (G, delta_crawl::CrawlState) {
+    let client = http_client::client();
+    // The traversal fetches one address at a time, the throttle only paces its requests.
+    let throttle = throttle::Throttle::new(1).with_metrics_log_if_created();
+    let mut graph_builder = graph_builder::GraphBuilder::from_graph(blockchain_graph);
+    let initial_edge_count = graph_builder.graph().edge_count();
+
+    loop {
+        if control.is_cancelled() || throttle.is_aborted() || control.stop_requested() {
+            println!("Crawl stopped with {} transactions collected", graph_builder.graph().edge_count());
+            break;
+        }
+        let pq_timer: Instant = Instant::now();
+        let next_address = std::iter::from_fn(|| path_priority_pq.pop()).map(|(a, _)| a).find(|a| !crawl_state.visited.contains(a));
+        let Some(next_address) = next_address else {
+            println!("No addresses left to crawl, {} transactions collected", graph_builder.graph().edge_count());
+            break;
+        };
+        println!("Searching for the next address took {:<9} mks (PriorityQueue)", pq_timer.elapsed().as_micros());
+
+        crawl_state.visited.push(next_address.clone());
+
+        if let Some(policy) = crawl_state.precheck {
+            if let Some(count) = tx_count_precheck::get_transaction_count_with_retry(&next_address, &client, api_key, &throttle).await {
+                crawl_state.transaction_counts.insert(next_address.clone(), count);
+                if count > policy.max_sent_transactions {
+                    crawl_state.oversized_addresses.insert(next_address.clone());
+                    println!("{} sent {} transactions, above the limit of {} ({:?})", next_address, count, policy.max_sent_transactions, policy.action);
+                    if policy.action == tx_count_precheck::OversizedAction::Skip {
+                        continue;
+                    }
+                }
+            }
+        }
+
+        let future = graph_data_collection_procedure(
+                &mut path_priority_pq,
+                &mut graph_builder,
+                &client,
+                api_key,
+                &throttle,
+                next_address.clone(),
+                crawl_state.history_window,
+                crawl_state.ingest_options(),
+                sinks,
+            );
+            let outcome = future.await;
+            crawl_state.record_fetch(&next_address, outcome);
+
+            let current_edge_count = graph_builder.graph().edge_count();
+            control.report("crawl", current_edge_count, Some(transaction_budget));
+            if let Some(monitor) = control.monitor() {
+                monitor.publish("crawl", path_priority_pq.len(), crawl_state.visited.len(), current_edge_count, &throttle);
+                if monitor.take_checkpoint_request() {
+                    crawl_state.frontier = path_priority_pq.clone().into_sorted_iter().map(|(address, (relevance, _))| (address, relevance)).collect();
+                    // A failed checkpoint is reported and the crawl goes on, the final save tries again.
+                    if let Err(e) = delta_crawl::save_crawl(graph_builder.graph(), &crawl_state) {
+                        eprintln!("Could not save the checkpoint: {:#}", e);
+                    }
+                }
+            }
+            if current_edge_count >= transaction_budget {
+                break;
+            };
+            println!(
+                "Transaction count is {} / {}, {}",
+                current_edge_count,
+                transaction_budget,
+                throttle::eta_label(throttle.eta(current_edge_count - initial_edge_count, transaction_budget.saturating_sub(initial_edge_count)))
+            );
+        }
+    match throttle.finish() {
+        Ok(summary) => print!("{}", summary),
+        Err(e) => eprintln!("Could not save the request metrics: {:#}", e),
+    }
+    crawl_state.frontier = path_priority_pq.into_sorted_iter().map(|(address, (relevance, _))| (address, relevance)).collect();
+    (graph_builder.into_graph(), crawl_state)
+}
+
+fn serialize_graph(filtered_graph: &G, info: &FilteringResultInfo, pathname: &str) -> Result<()> {
+    assert_eq!(filtered_graph.raw_edges().len(), info.edge_count);
+    assert_eq!(filtered_graph.raw_nodes().len(), info.node_count);
+
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    for node in filtered_graph.node_indices() {
+        nodes.push(filtered_graph[node].clone());
+    }
+    for edge in filtered_graph.edge_indices() {
+        let (source, target) = filtered_graph.edge_endpoints(edge).unwrap();
+        edges.push((source.index(), target.index(), filtered_graph[edge].clone()));
+    }
+
+    let serializable_graph = SerializableGraph {
+        format_version: edge_payload::GRAPH_FORMAT_VERSION,
+        edge_schema: edge_payload::EdgeSchema::of_graph(filtered_graph),
+        info: info.clone(),
+        nodes,
+        edges,
+    };
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, pathname);
+    fs::create_dir_all(DATA_STORAGE_FOLDER).unwrap();
+    let mut file = atomic_file::AtomicFile::create(&file_pathname)?;
+    serde_json::to_writer_pretty(&mut file, &serializable_graph)?;
+    file.commit()?;
+    workspace::record_graph(&file_pathname, info.node_count, info.edge_count)?;
+    println!("\nSaved graph as {}\n", &file_pathname);
+    Ok(())
+}
+
+fn deserialize_graph(pathname: &str) -> Result<(G, FilteringResultInfo)> {
+    let (graph, info) = tx_graph::TxGraph::load(pathname)?;
+    Ok((graph.into_petgraph(), info))
+}
+
+#[cfg(feature = "crawl")]
+fn read_api_key() -> Result<String> {
+    let missing = || exit_status::ClassifiedError::new(exit_status::FailureKind::InvalidKey, "Please provide an Etherscan API key (put it inside api_key.txt)");
+    let mut api_key: String = String::new();
+    File::open("api_key.txt").map_err(|_| missing())?.read_to_string(&mut api_key)?;
+    api_key = api_key.trim().to_string();
+    if api_key.is_empty() {
+        return Err(missing().into());
+    }
+    Ok(api_key)
+}
+
+fn filter_stablecoin_transactions_by_value<'a>(graph: &FilteredGraph<'a>, lower_usd_bound: f64, upper_usd_bound: f64) -> (FilteredGraph<'a>, FilteringResultInfo) {
+    let filtered_graph = graph.clone().retain_edges(|edge| {
+        if let Some(data) = &edge.weight().data {
+            let usd_value = data.usd_value;
+            usd_value >= lower_usd_bound && usd_value <= upper_usd_bound
+        } else {false}
+    });
+    let info = filtered_graph.info_relative_to(graph);
+    (filtered_graph, info)
+}
+
+fn calculate_total_usd_volume(graph: &G) -> (f64, f64) {
+    let mut total_volume_usd = 0.0;
+    for edge in graph.edge_references() {
+        let transaction = edge.weight();
+            if let Some(data) = &transaction.data {
+                total_volume_usd += data.usd_value;
+            }
+    }
+    let mean_value_usd = total_volume_usd / graph.edge_count() as f64;
+    (total_volume_usd, mean_value_usd)
+}
+
+// Transactions grouped by directed (sender, recipient) pair, in edge insertion order.
+fn edges_by_pair(graph: &G) -> HashMap<(NodeIndex, NodeIndex), Vec<EdgeIndex>> {
+    let mut pairs: HashMap<(NodeIndex, NodeIndex), Vec<EdgeIndex>> = HashMap::new();
+    for edge_index in graph.edge_indices() {
+        let (source, target) = graph.edge_endpoints(edge_index).unwrap();
+        pairs.entry((source, target)).or_default().push(edge_index);
+    }
+    pairs
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn plot_distribution_multicolor(graph: &G, root: &mut DrawingArea<BitMapBackend<'_>, Shift>, min_log_value: f64, description: &str) {
+    let colors = [BLUE.mix(0.5), RED.mix(0.5)];
+    assert_eq!(colors.len(), Payload::iter().len());
+
+    let mixed_transaction_log_values = graph
+        .raw_edges()
+        .iter()
+        .filter(|t|t.weight.data.is_some())
+        .map(|t|
+                f64::log10(t.weight.data.as_ref().unwrap().usd_value)
+            )
+        .collect::<Vec<f64>>();
+
+    let mixed_max_log_value = mixed_transaction_log_values
+        .iter()
+        .max_by(|a, b| a.partial_cmp(b).unwrap())
+        .unwrap_or(&2.0)
+        .to_owned();
+
+    let bucket_count = 200;
+    let bucket_width = (mixed_max_log_value-min_log_value) / bucket_count as f64;
+    let mut mixed_buckets: Vec<u32> = vec![0u32; bucket_count];
+
+    for log_value in mixed_transaction_log_values.iter() {
+        let bucket_index = min((((log_value - min_log_value) / (mixed_max_log_value - min_log_value)) * (bucket_count as f64)).floor() as usize, bucket_count - 1);
+        mixed_buckets[bucket_index] += 1;
+    }
+
+    let mixed_max_count = *mixed_buckets[1..].iter().max().unwrap(); // slicing to skip 0-valued transactions
+    drop(mixed_buckets);
+
+    root.fill(&WHITE).unwrap();
+    let mut chart = ChartBuilder::on(root)
+        .margin(5)
+        .caption(description, ("sans-serif", 20))
+        .x_label_area_size(40)
+        .y_label_area_size(40)
+        .build_cartesian_2d(0.0 .. 9.0 , 0u32 ..mixed_max_count)
+        .unwrap();
+    chart.configure_mesh().x_desc("log10(value in USD), at the moment of transaction").y_desc("Number of transactions").draw().unwrap();
+
+      for (i, payload) in Payload::iter().enumerate() {
+          let transaction_log_values = graph
+          .raw_edges()
+          .iter()
+          .filter(|t|t.weight.data.is_some() && t.weight.data.as_ref().unwrap().payload == payload)
+          .map(|t|
+                  f64::log10(t.weight.data.as_ref().unwrap().usd_value)
+              )
+          .collect::<Vec<f64>>();
+
+          let mut buckets: Vec<u32> = vec![0u32; bucket_count];
+
+          for log_value in transaction_log_values.iter() {
+              let bucket_index = min((((log_value - min_log_value) / (mixed_max_log_value - min_log_value)) * (bucket_count as f64)).floor() as usize, bucket_count - 1);
+              buckets[bucket_index] += 1;
+          }
+
+          let mut rectangles_to_draw = vec![];
+
+          for (bucket_index, &count) in buckets.iter().enumerate() {
+              let bar_left = min_log_value + bucket_index as f64 * bucket_width;
+              let bar_right = bar_left + bucket_width;
+              let bar_top = ((count as f64 / mixed_max_count as f64) * mixed_max_count as f64) as u32;
+
+                  rectangles_to_draw.push(
+                      Rectangle::new(
+                          [(bar_left, 0), (bar_right, bar_top)],
+                          colors[i].filled()
+                      )
+                  );
+          }
+
+          chart.draw_series(rectangles_to_draw).unwrap();
+      }
+
+      let (legend_x, legend_y) = ( root.dim_in_pixel().0 as i32 / 5 * 4, root.dim_in_pixel().1 as i32 / 5);
+      for (i, payload) in Payload::iter().enumerate() {
+          root.draw(&Text::new(
+              format!("{:?}", payload),
+              (legend_x + 20, legend_y - i as i32 * 20),
+              ("sans-serif", 15),
+          ))
+          .unwrap();
+          root.draw(&Rectangle::new(
+              [(legend_x, legend_y - i as i32 * 20), (legend_x + 15, legend_y - i as i32 * 20 + 10)],
+              colors[i].filled(),
+          ))
+          .unwrap();
+      }
+
+    root.present().unwrap();
+}
+
+fn filtering_by_value<'a>(graph: &FilteredGraph<'a>, lower_usd_bound: f64, upper_usd_bound: f64, result_log: &mut String) -> (FilteredGraph<'a>, FilteringResultInfo) {
+    assert!(lower_usd_bound >= 0.0);
+    assert!(upper_usd_bound >= lower_usd_bound);
+    let (filtered_graph, filtering_info) = filter_stablecoin_transactions_by_value(graph, lower_usd_bound, upper_usd_bound);
+    let (filtered_graph_volume, filtered_graph_mean_value) = filtered_graph.usd_volume();
+
+    let filtering_log = format!(
+        "For transactions filtered in {:.3e} to {:.3e} Range:\nTotal volume: {}, Mean value: {}, Edges: {}, Nodes: {}\n{:#?}",
+        lower_usd_bound, upper_usd_bound, Amount::Usd(filtered_graph_volume), Amount::Usd(filtered_graph_mean_value), filtering_info.edge_count, filtering_info.node_count, filtering_info
+    );
+    println!("{}", &filtering_log);
+    result_log.push_str(&filtering_log);
+    result_log.push('\n');
+    (filtered_graph, filtering_info)
+}
+
+fn filtering_by_variant<'a>(graph: &FilteredGraph<'a>, required_variant: Payload, results_log: &mut String) -> (FilteredGraph<'a>, FilteringResultInfo) {
+    let filtered_graph = graph.clone().retain_edges(|edge| {
+        if let Some(data) = &edge.weight().data {data.payload == required_variant} else {false}
+    });
+    let filtering_info = filtered_graph.info_relative_to(graph);
+
+    let (filtered_graph_volume, filtered_graph_mean_value) = filtered_graph.usd_volume();
+    let filtering_log = format!(
+        "\nFor {:?} transactions:\nTotal volume: {}, Mean value: {}, Edges: {}, Nodes: {}\n{:#?}",
+        required_variant, Amount::Usd(filtered_graph_volume), Amount::Usd(filtered_graph_mean_value), filtering_info.edge_count, filtering_info.node_count, filtering_info
+    );
+    println!("{}", &filtering_log);
+    results_log.push_str(&filtering_log);
+    results_log.push('\n');
+
+    (filtered_graph, filtering_info)
+}
+
+// Value and stablecoin filters reported after every crawl. Returns the nonzero-value graph with the report and
+// touches no files, so it also runs client-side on a graph loaded with TxGraph::from_reader.
+pub fn filter_report(parsed_graph: &G) -> (G, FilteringResultInfo, String) {
+    let mut result_log = String::new();
+    // Filters only narrow edge masks over the parsed graph; the nonzero graph is the one copy that is materialized.
+    let (nonzero_view, _) = filtering_by_value(&FilteredGraph::new(parsed_graph), 1.0E-9, f64::MAX, &mut result_log);
+    for variant in Payload::iter() {
+        let (variant_view, _variant_filtering_info) = filtering_by_variant(&nonzero_view, variant.clone(), &mut result_log);
+        let (_range_view, _range_filtering_info) = filtering_by_value(&variant_view, 10.0, 1000.0, &mut result_log);
+    }
+    let (nonzero_graph, nonzero_filtering_info) = nonzero_view.materialize();
+    (nonzero_graph, nonzero_filtering_info, result_log)
+}
+
+// Entry point of the ethparser binary. The library target also serves fuzz targets and, built without the crawl
+// feature, wasm32 consumers of the graph model, filters and statistics.
+pub fn run() {
+    run_with_passes(analysis_pass::PassRegistry::with_builtin_passes())
+}
+
+// The ethparser binary with extra analysis passes, which run in the analyze command and after every crawl.
+pub fn run_with_passes(passes: analysis_pass::PassRegistry) {
+    let command_line = std::env::args().skip(1).collect::<Vec<String>>().join(" ");
+    workspace::start_session(command_line.clone());
+    let cli = profiles::parse_cli(std::env::args_os().collect());
+    money::configure(money::MoneyFormat {
+        usd_precision: cli.usd_precision,
+        coin_precision: cli.coin_precision,
+        gwei_precision: cli.gwei_precision,
+        thousands_separator: cli.thousands_separator,
+    });
+    time_buckets::configure_display_timezone(cli.display_timezone);
+    let command = cli.command.unwrap_or(cli::Command::Crawl(cli::CrawlArgs::default()));
+    // Errors are classified by type for the exit code. A panic is a bug, still caught so errors.json lists
+    // the outputs written before it.
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| run_command(command, &passes))) {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            println!("Error: {:#}", e);
+            exit_status::record(exit_status::classify(&e).unwrap_or(exit_status::FailureKind::Error), format!("{:#}", e));
+        }
+        Err(panic) => {
+            let message = panic.downcast_ref::<String>().cloned().or_else(|| panic.downcast_ref::<&str>().map(|m| m.to_string())).unwrap_or_default();
+            exit_status::record(exit_status::FailureKind::Error, message);
+        }
+    }
+    let exit_code = exit_status::finish(&command_line, workspace::session_outputs());
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
+}
+
+fn run_command(command: cli::Command, passes: &analysis_pass::PassRegistry) -> Result<()> {
+    match command {
+        cli::Command::Crawl(args) => run_crawl(&args, passes),
+        cli::Command::Sample(args) => sampling::run_sample_command(&args),
+        cli::Command::Pseudonymize(args) => pseudonymization::run_pseudonymize_command(&args),
+        cli::Command::Embeddings(args) => embeddings::run_embeddings_command(&args),
+        cli::Command::Features(args) => features::run_features_command(&args),
+        cli::Command::Fees(args) => fees::run_fees_command(&args),
+        cli::Command::Mev(args) => mev::run_mev_command(&args),
+        cli::Command::BridgeFlows(args) => bridges::run_bridge_flows_command(&args),
+        cli::Command::TokenHolders(args) => token_holders::run_token_holders_command(&args),
+        cli::Command::ContractInteractions(args) => contract_interactions::run_contract_interactions_command(&args),
+        cli::Command::RollingFlow(args) => rolling_flow::run_rolling_flow_command(&args),
+        cli::Command::Dormancy(args) => dormancy::run_dormancy_command(&args),
+        cli::Command::Structuring(args) => structuring::run_structuring_command(&args),
+        cli::Command::ExportMatrix(args) => sparse_matrix::run_export_matrix_command(&args),
+        #[cfg(feature = "spectral")]
+        cli::Command::Spectral(args) => spectral::run_spectral_command(&args),
+        cli::Command::Stats(args) => stats::run_stats_command(&args),
+        cli::Command::TemporalMotifs(args) => temporal_motifs::run_temporal_motifs_command(&args),
+        cli::Command::Dedup(args) => edge_identity::run_dedup_command(&args),
+        cli::Command::Reciprocity(args) => reciprocity::run_reciprocity_command(&args),
+        cli::Command::PriceCorrelation(args) => price_correlation::run_price_correlation_command(&args),
+        cli::Command::EnrichUsd(args) => usd_enrichment::run_enrich_usd_command(&args),
+        cli::Command::ActivityHeatmap(args) => activity_heatmap::run_activity_heatmap_command(&args),
+        cli::Command::Approvals(args) => approval_risk::run_approvals_command(&args),
+        #[cfg(feature = "crawl")]
+        cli::Command::DetectContracts(args) => contract_detection::run_detect_contracts_command(&args),
+        #[cfg(feature = "scripting")]
+        cli::Command::FilterScript(args) => edge_script::run_filter_script_command(&args),
+        cli::Command::List => workspace::run_list_command(),
+        cli::Command::Describe(args) => workspace::run_describe_command(&args),
+        cli::Command::Gc(args) => workspace::run_gc_command(&args),
+        cli::Command::Analyze(args) => analysis_pass::run_analyze_command(&args, passes),
+        #[cfg(feature = "grpc")]
+        cli::Command::ServeGrpc(args) => grpc_service::run_serve_grpc_command(&args),
+        cli::Command::Query(args) => graph_query::run_query_command(&args),
+        cli::Command::Compare(args) => compare::run_compare_command(&args),
+        cli::Command::Similarity(args) => counterparty_similarity::run_similarity_command(&args),
+        cli::Command::Entities(args) => entity_clustering::run_entities_command(&args),
+        cli::Command::FundedBy(args) => funding_source::run_funded_by_command(&args),
+        cli::Command::Report(args) => address_report::run_report_command(&args),
+        cli::Command::Balance(args) => balance_history::run_balance_command(&args),
+        cli::Command::Coverage(args) => coverage_map::run_coverage_command(&args),
+        #[cfg(feature = "crawl")]
+        cli::Command::BlockRewards(args) => block_rewards::run_block_rewards_command(&args),
+        cli::Command::Annotate(args) => annotations::run_annotate_command(&args),
+        cli::Command::Annotations(args) => annotations::run_annotations_command(&args),
+        cli::Command::ExportCase(args) => case_export::run_export_case_command(&args, passes),
+        cli::Command::GenFixture(args) => fixtures::run_gen_fixture_command(&args),
+        cli::Command::SeedMap(args) => seed_attribution::run_seed_map_command(&args),
+        cli::Command::PeriodSummary(args) => period_summary::run_period_summary_command(&args),
+        #[cfg(feature = "crawl")]
+        cli::Command::Daemon(args) => daemon::run_daemon_command(&args),
+        cli::Command::Compact(args) => compaction::run_compact_command(&args),
+        cli::Command::Layers(args) => layers::run_layers_command(&args),
+        cli::Command::BenchmarkStrategies(args) => strategy_benchmark::run_benchmark_strategies_command(&args),
+        cli::Command::Exposure(args) => exposure::run_exposure_command(&args),
+        cli::Command::Mixers(args) => mixers::run_mixers_command(&args),
+        cli::Command::Risk(args) => risk_scoring::run_risk_command(&args, passes.scorers()),
+        #[cfg(feature = "crawl")]
+        cli::Command::Quick(args) => quick_look::run_quick_command(&args),
+        #[cfg(feature = "crawl")]
+        cli::Command::RetryFailed(args) => failed_addresses::run_retry_failed_command(&args),
+        cli::Command::PairDistribution(args) => pair_distribution::run_pair_distribution_command(&args),
+        cli::Command::Drift(args) => drift_monitor::run_drift_command(&args),
+        #[cfg(feature = "polars")]
+        cli::Command::ToParquet(args) => polars_integration::run_to_parquet_command(&args),
+        #[cfg(feature = "polars")]
+        cli::Command::FromParquet(args) => polars_integration::run_from_parquet_command(&args),
+    }
+}
+
+fn run_crawl(args: &cli::CrawlArgs, passes: &analysis_pass::PassRegistry) -> Result<()> {
+    #[cfg(feature = "crawl")]
+    return run_crawl_with_sinks(args, crawl_events::CrawlSinks::default(), passes);
+    #[cfg(not(feature = "crawl"))]
+    {
+        let _ = passes; // A dry run analyses nothing
+        if !args.dry_run {
+            return Err(eyre::eyre!("ethparser was built without the crawl feature, only --dry-run is available"));
+        }
+        dry_run::print_crawl_plan(args)
+    }
+}
+
+// Crawl events go to the sinks given here and to those named by --sink.
+#[cfg(feature = "crawl")]
+pub fn run_crawl_with_sinks(args: &cli::CrawlArgs, sinks: crawl_events::CrawlSinks, passes: &analysis_pass::PassRegistry) -> Result<()> {
+    run_crawl_with_control(args, sinks, passes, &task_control::TaskControl::default())
+}
+
+// A cancelled crawl still saves what it collected, with its frontier for a resumed crawl, and skips the analyses.
+#[cfg(feature = "crawl")]
+pub fn run_crawl_with_control(
+    args: &cli::CrawlArgs,
+    mut sinks: crawl_events::CrawlSinks,
+    passes: &analysis_pass::PassRegistry,
+    control: &task_control::TaskControl,
+) -> Result<()> {
+    if args.dry_run {
+        return dry_run::print_crawl_plan(args);
+    }
+    for sink in crawl_events::CrawlSinks::from_specs(&args.sinks)?.into_sinks() {
+        sinks.add(sink);
+    }
+    let monitored_control;
+    let control = match &args.control {
+        Some(address) => {
+            let monitor = std::sync::Arc::new(crawl_control::CrawlMonitor::default());
+            crawl_control::serve(address, monitor.clone())?;
+            monitored_control = control.clone().with_monitor(monitor);
+            &monitored_control
+        }
+        None => control,
+    };
+    let async_timer: Instant = Instant::now();
+    let api_key = read_api_key()?;
+    let rt = Runtime::new()?;
+    let mut result_log = String::new();
+    let (mut parsed_graph, crawl_state) = if args.since_last_run {
+        rt.block_on(delta_crawl::parse_blockchain_since_last_run(&api_key, &mut sinks, control))?
+    } else if args.resume {
+        rt.block_on(delta_crawl::resume_traversal(&api_key, &mut sinks, control))?
+    } else {
+        let (starting_addresses, history_window) = match &args.seed_transaction {
+            Some(hash) => {
+                let seed = rt.block_on(seed_transaction::fetch_seed(hash, &api_key))?;
+                println!("Seeding the crawl with {} from transaction {} in block {}", seed.endpoints().join(" and "), seed.hash, seed.block_number);
+                let half_width = (args.seed_window_days * 86_400.0) as u64;
+                (seed.endpoints(), rt.block_on(history_window::window_around(seed.timestamp, half_width, args.sort, &api_key))?)
+            }
+            None if !args.seeds.is_empty() => {
+                let mut seeds: Vec<String> = vec![];
+                for seed in args.seeds.iter().map(|seed| seed.to_lowercase()) {
+                    if !seeds.contains(&seed) {
+                        seeds.push(seed);
+                    }
+                }
+                (seeds, rt.block_on(history_window::resolve_window(args, &api_key))?)
+            }
+            None => (vec![TRAVERSAL_STARTING_ADDRESS.to_lowercase()], rt.block_on(history_window::resolve_window(args, &api_key))?),
+        };
+        let initial_state = delta_crawl::CrawlState {
+            edge_schema: args.edge_schema,
+            user_operations: args.user_operations,
+            special_address_policies: special_addresses::SpecialAddressPolicies::from_args(args),
+            precheck: args.max_sent_transactions.map(|max_sent_transactions| tx_count_precheck::PrecheckPolicy {
+                max_sent_transactions,
+                action: args.oversized_action,
+            }),
+            history_window,
+            seeds: starting_addresses.clone(),
+            ..Default::default()
+        };
+        println!("Fetching address histories over {}", initial_state.history_window.describe());
+        if args.two_pass {
+            rt.block_on(two_pass_crawl::parse_blockchain_two_pass(&starting_addresses, &api_key, initial_state, args, &mut sinks, control))
+        } else {
+            rt.block_on(parse_blockchain(&starting_addresses, &api_key, initial_state, &mut sinks, control))
+        }
+    };
+    if args.funded_by_edges {
+        let added = funding_source::add_funded_by_edges(&mut parsed_graph, &crawl_state.funding_sources);
+        println!("Added {} funded-by edges from {} recorded funding sources", added, crawl_state.funding_sources.len());
+    }
+    let special_s = crawl_state.special_addresses.describe(&crawl_state.special_address_policies);
+    print!("{}", &special_s);
+    result_log.push_str(&special_s);
+    let validation_s = crawl_state.validation.describe();
+    print!("{}", &validation_s);
+    result_log.push_str(&validation_s);
+    let skipped_s = crawl_state.skipped.describe();
+    print!("{}", &skipped_s);
+    result_log.push_str(&skipped_s);
+    if !crawl_state.failed.addresses.is_empty() {
+        let failed_s = crawl_state.failed.describe();
+        print!("{}", &failed_s);
+        result_log.push_str(&failed_s);
+        exit_status::record(exit_status::FailureKind::Partial, format!("{} addresses could not be fetched in full, see {}", crawl_state.failed.addresses.len(), failed_addresses::FAILED_ADDRESSES_FILENAME));
+    }
+    if crawl_state.validation.rejected() > 0 {
+        exit_status::record(exit_status::FailureKind::Partial, format!("{} records were rejected, see {}", crawl_state.validation.rejected(), record_validation::REJECTED_RECORDS_FILENAME));
+    }
+    if !crawl_state.oversized_addresses.is_empty() {
+        println!("{} addresses exceeded the sent transaction limit", crawl_state.oversized_addresses.len());
+    }
+    delta_crawl::save_crawl(&parsed_graph, &crawl_state)?;
+    sinks.emit(crawl_events::checkpoint_written(&parsed_graph, &crawl_state));
+    sinks.finish();
+    println!("Async operations took {:.3} s\n", async_timer.elapsed().as_secs_f64());
+    if control.is_cancelled() {
+        println!("The crawl was cancelled, run with --resume to continue it");
+        exit_status::record(exit_status::FailureKind::Partial, "The crawl was cancelled before the analyses, run with --resume to continue it");
+        return Ok(());
+    }
+
+    // The raw crawl is saved as is; edge handling options only shape the analysed graph.
+    let (parsed_graph, _, edge_handling_summary) = edge_handling::apply_edge_handling(&parsed_graph, &args.edge_handling, &annotations::Annotations::load()?);
+    let edge_handling_s = edge_handling_summary.describe(&args.edge_handling);
+    print!("{}", &edge_handling_s);
+    result_log.push_str(&edge_handling_s);
+    if args.edge_handling.drop_dust_below.is_some() {
+        dust_filter::save_removed_dust(&edge_handling_summary.dust, &format!("{}/{}", DATA_STORAGE_FOLDER, dust_filter::REMOVED_DUST_FILENAME))?;
+    }
+
+    let local_timer: Instant = Instant::now();
+    let (parsed_graph_volume, _) = calculate_total_usd_volume(&parsed_graph);
+    let parsed_s = format!(
+        "For all parsed transactions:\nTotal volume: {}, Edges: {}, Nodes: {}\n\n",
+        Amount::Usd(parsed_graph_volume), parsed_graph.edge_count(), parsed_graph.node_count()
+    );
+    print!("{}", &parsed_s);
+    result_log.push_str(&parsed_s);
+    if args.preview_edges > 0 {
+        println!("{}", edge_preview::edge_preview(&parsed_graph, &annotations::Annotations::load()?, args.preview_edges));
+    }
+
+    let (nonzero_graph, nonzero_filtering_info, filtering_log) = filter_report(&parsed_graph);
+    result_log.push_str(&filtering_log);
+    serialize_graph(&nonzero_graph, &nonzero_filtering_info, "filtered_transactions_polygon.json")?;
+    let plot_pathname = format!("{}/main_graph_multicolor.png", DATA_STORAGE_FOLDER);
+    let mut graph_multicolor_root = BitMapBackend::new(&plot_pathname, (720, 480)).into_drawing_area();
+    plot_distribution_multicolor(&nonzero_graph, &mut graph_multicolor_root, 0.0, "Value distribution for transactions with tokens (for all parsed transactions)");
+    workspace::record_artifact(&plot_pathname)?;
+
+    let reports = passes.run_with_control(&nonzero_graph, &analysis_pass::NoPrices, &args.passes, None, control)?;
+    let analysis_log = analysis_pass::save_reports(reports, "filtered_transactions_polygon.json", None, "analysis_manifest.json")?;
+    result_log.push_str(&analysis_log);
+
+    atomic_file::write_atomically(&format!("{}/result.txt", DATA_STORAGE_FOLDER), result_log.as_bytes())?;
+
+    println!("Local operations took {:.3} s", local_timer.elapsed().as_secs_f64());
+    println!("Local + async operations took {:.3} s", async_timer.elapsed().as_secs_f64());
+    Ok(())
+}
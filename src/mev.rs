@@ -0,0 +1,164 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+
+use crate::atomic_file::write_atomically;
+use crate::cli::MevArgs;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MevPattern {
+    Sandwich,
+    Backrun,
+}
+
+#[derive(Debug, Clone)]
+pub struct MevCandidate {
+    pub pattern: MevPattern,
+    pub block_number: u64,
+    pub attacker: NodeIndex,
+    pub counterparty: NodeIndex,
+    pub victim: NodeIndex,
+    pub front_hash: Option<String>,
+    pub victim_hash: String,
+    pub back_hash: String,
+}
+
+struct BlockInteraction<'a> {
+    transaction_index: u64,
+    parties: (NodeIndex, NodeIndex),
+    hash: &'a str,
+}
+
+impl BlockInteraction<'_> {
+    fn involves(&self, node: NodeIndex) -> bool {
+        self.parties.0 == node || self.parties.1 == node
+    }
+
+    fn other_party(&self, node: NodeIndex) -> NodeIndex {
+        if self.parties.0 == node { self.parties.1 } else { self.parties.0 }
+    }
+
+    fn same_pair(&self, other: &BlockInteraction) -> bool {
+        self.parties == other.parties || self.parties == (other.parties.1, other.parties.0)
+    }
+}
+
+pub fn detect_mev_candidates(graph: &G) -> Vec<MevCandidate> {
+    let mut blocks: BTreeMap<u64, Vec<BlockInteraction>> = BTreeMap::new();
+    for edge in graph.edge_references() {
+        let transaction = edge.weight();
+        if transaction.block_number == 0 || edge.source() == edge.target() {
+            continue; // Block position was not retained for this edge
+        }
+        blocks.entry(transaction.block_number).or_default().push(BlockInteraction {
+            transaction_index: transaction.transaction_index,
+            parties: (edge.source(), edge.target()),
+            hash: &transaction.hash,
+        });
+    }
+
+    let mut candidates = vec![];
+    for (block_number, mut interactions) in blocks {
+        if interactions.len() < 2 {
+            continue;
+        }
+        interactions.sort_by_key(|i| i.transaction_index);
+        let mut sandwich_hashes: HashSet<&str> = HashSet::new();
+
+        // Sandwich: A<->X, then V<->X, then A<->X again, strictly ordered within the block.
+        for (front_position, front) in interactions.iter().enumerate() {
+            for back in interactions[front_position + 1..].iter() {
+                if back.transaction_index <= front.transaction_index || !front.same_pair(back) {
+                    continue;
+                }
+                for victim in interactions.iter() {
+                    if victim.transaction_index <= front.transaction_index || victim.transaction_index >= back.transaction_index {
+                        continue;
+                    }
+                    for counterparty in [front.parties.0, front.parties.1] {
+                        let attacker = front.other_party(counterparty);
+                        if victim.involves(counterparty) && !victim.involves(attacker) {
+                            sandwich_hashes.extend([front.hash, victim.hash, back.hash]);
+                            candidates.push(MevCandidate {
+                                pattern: MevPattern::Sandwich,
+                                block_number,
+                                attacker,
+                                counterparty,
+                                victim: victim.other_party(counterparty),
+                                front_hash: Some(front.hash.to_string()),
+                                victim_hash: victim.hash.to_string(),
+                                back_hash: back.hash.to_string(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        // Backrun: V<->X immediately followed by A<->X from a different party, without a front leg.
+        for pair in interactions.windows(2) {
+            let (victim, back) = (&pair[0], &pair[1]);
+            if back.transaction_index != victim.transaction_index + 1
+                || sandwich_hashes.contains(victim.hash)
+                || sandwich_hashes.contains(back.hash)
+            {
+                continue;
+            }
+            for counterparty in [victim.parties.0, victim.parties.1] {
+                let attacker = back.other_party(counterparty);
+                if back.involves(counterparty) && !victim.involves(attacker) {
+                    candidates.push(MevCandidate {
+                        pattern: MevPattern::Backrun,
+                        block_number,
+                        attacker,
+                        counterparty,
+                        victim: victim.other_party(counterparty),
+                        front_hash: None,
+                        victim_hash: victim.hash.to_string(),
+                        back_hash: back.hash.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    candidates
+}
+
+pub fn mev_report(graph: &G, candidates: &[MevCandidate]) -> String {
+    let count = |pattern: MevPattern| candidates.iter().filter(|c| c.pattern == pattern).count();
+    let mut report = format!(
+        "MEV candidates: {} sandwich, {} backrun\n",
+        count(MevPattern::Sandwich),
+        count(MevPattern::Backrun)
+    );
+    for candidate in candidates {
+        report.push_str(&format!(
+            "\n{:?} in block {}:\nAttacker: {}, Counterparty: {}, Victim: {}\nFront: {}, Victim tx: {}, Back: {}\n",
+            candidate.pattern,
+            candidate.block_number,
+            graph[candidate.attacker],
+            graph[candidate.counterparty],
+            graph[candidate.victim],
+            candidate.front_hash.as_deref().unwrap_or("-"),
+            candidate.victim_hash,
+            candidate.back_hash,
+        ));
+    }
+    report
+}
+
+pub fn run_mev_command(args: &MevArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let candidates = detect_mev_candidates(&graph);
+    let report = mev_report(&graph, &candidates);
+    println!("{}", report.lines().next().unwrap_or_default());
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    write_atomically(&file_pathname, report.as_bytes())?;
+    println!("Saved MEV report as {}", &file_pathname);
+    Ok(())
+}
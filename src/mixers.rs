@@ -0,0 +1,172 @@
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write;
+use std::fs;
+
+use crate::analysis_pass::{AnalysisPass, Report};
+use crate::annotations::Annotations;
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::MixersArgs;
+use crate::money::{wei_to_coin, Amount};
+use crate::pricing::PriceProvider;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+// Tornado Cash ETH pools of 0.1, 1, 10 and 100 ETH and its router on Ethereum mainnet. Mixers of other chains
+// are tagged mixer with annotate or passed with --mixer.
+pub const KNOWN_MIXERS: [&str; 5] = [
+    "0x12d66f87a04a9e220743712ce6d9bb1b5616b8fc",
+    "0x47ce0c6ed5b0ce3d3a51fdb1c52dc66a7c3c2936",
+    "0x910cbd523d972eb0a6f4cae4618ad62622b39dbf",
+    "0xa160cdab225685da1d56aa342ad8841c3b53f291",
+    "0xd90e2f925da726b50c4ed8d0fb90ad053324f31b",
+];
+pub const MIXER_TAG: &str = "mixer";
+pub const MIXER_USER_TAG: &str = "mixer-user";
+const SHOWN_ADDRESSES: usize = 10;
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct MixerInteractions {
+    pub address: String,
+    pub deposits: usize,
+    pub deposited_coin: f64,
+    // Over the transfers with a USD value, native ones once enrich-usd priced them.
+    pub deposited_usd: f64,
+    pub withdrawals: usize,
+    pub withdrawn_coin: f64,
+    pub withdrawn_usd: f64,
+    pub mixers: usize,
+    pub first_timestamp: u64,
+    pub last_timestamp: u64,
+    // Shortest time from one of the address's deposits to a later withdrawal to it, the usual tell of an
+    // address taking back its own funds.
+    pub shortest_round_trip_seconds: Option<u64>,
+}
+
+// An address's row with the mixers it used and the times of its deposits and withdrawals.
+type Accumulated = (MixerInteractions, BTreeSet<String>, Vec<u64>, Vec<u64>);
+
+// Deposits are edges into a mixer and withdrawals edges out of one, per counterparty that is no mixer itself.
+pub fn mixer_interactions(graph: &G, mixers: &HashSet<String>) -> Vec<MixerInteractions> {
+    let mut interactions: BTreeMap<String, Accumulated> = BTreeMap::new();
+    for edge in graph.edge_references() {
+        let (source, target) = (graph[edge.source()].to_lowercase(), graph[edge.target()].to_lowercase());
+        let (address, mixer, deposit) = match (mixers.contains(&source), mixers.contains(&target)) {
+            (false, true) => (&graph[edge.source()], target, true),
+            (true, false) => (&graph[edge.target()], source, false),
+            _ => continue,
+        };
+        let transaction = edge.weight();
+        let (row, used, deposit_times, withdrawal_times) = interactions
+            .entry(address.clone())
+            .or_insert_with(|| (MixerInteractions { address: address.clone(), first_timestamp: u64::MAX, ..Default::default() }, BTreeSet::new(), vec![], vec![]));
+        if deposit {
+            row.deposits += 1;
+            row.deposited_coin += wei_to_coin(transaction.value_wei);
+            row.deposited_usd += transaction.usd_value().unwrap_or(0.0);
+            deposit_times.push(transaction.timestamp);
+        } else {
+            row.withdrawals += 1;
+            row.withdrawn_coin += wei_to_coin(transaction.value_wei);
+            row.withdrawn_usd += transaction.usd_value().unwrap_or(0.0);
+            withdrawal_times.push(transaction.timestamp);
+        }
+        used.insert(mixer);
+        row.first_timestamp = row.first_timestamp.min(transaction.timestamp);
+        row.last_timestamp = row.last_timestamp.max(transaction.timestamp);
+    }
+    let mut rows: Vec<MixerInteractions> = interactions
+        .into_values()
+        .map(|(mut row, used, deposit_times, withdrawal_times)| {
+            row.mixers = used.len();
+            row.shortest_round_trip_seconds = deposit_times
+                .iter()
+                .flat_map(|&deposited| withdrawal_times.iter().filter(move |&&withdrawn| withdrawn >= deposited).map(move |&withdrawn| withdrawn - deposited))
+                .min();
+            row
+        })
+        .collect();
+    rows.sort_by(|a, b| (b.deposited_usd + b.withdrawn_usd).partial_cmp(&(a.deposited_usd + a.withdrawn_usd)).unwrap().then(a.address.cmp(&b.address)));
+    rows
+}
+
+pub fn mixer_report(rows: &[MixerInteractions]) -> String {
+    let deposits: usize = rows.iter().map(|row| row.deposits).sum();
+    let withdrawals: usize = rows.iter().map(|row| row.withdrawals).sum();
+    let mut report = format!(
+        "Mixer interactions: {} addresses, {} deposits of {} ({}), {} withdrawals of {} ({})\n",
+        rows.len(),
+        deposits,
+        Amount::Coin(rows.iter().fold(0.0, |total, row| total + row.deposited_coin)),
+        Amount::Usd(rows.iter().fold(0.0, |total, row| total + row.deposited_usd)),
+        withdrawals,
+        Amount::Coin(rows.iter().fold(0.0, |total, row| total + row.withdrawn_coin)),
+        Amount::Usd(rows.iter().fold(0.0, |total, row| total + row.withdrawn_usd)),
+    );
+    for row in rows.iter().take(SHOWN_ADDRESSES) {
+        writeln!(
+            report,
+            "  {}: {} deposits ({}), {} withdrawals ({}) with {} mixers{}",
+            row.address,
+            row.deposits,
+            Amount::Usd(row.deposited_usd),
+            row.withdrawals,
+            Amount::Usd(row.withdrawn_usd),
+            row.mixers,
+            row.shortest_round_trip_seconds.map_or(String::new(), |seconds| format!(", withdrew {:.1}h after a deposit", seconds as f64 / 3_600.0))
+        )
+        .unwrap();
+    }
+    report
+}
+
+pub struct MixersPass;
+
+impl AnalysisPass for MixersPass {
+    fn name(&self) -> &str {
+        "mixers"
+    }
+
+    fn run(&self, graph: &G, _prices: &dyn PriceProvider) -> Result<Report> {
+        let rows = mixer_interactions(graph, &KNOWN_MIXERS.iter().map(|mixer| mixer.to_string()).collect());
+        let metrics = BTreeMap::from([
+            ("mixer_users".to_string(), rows.len() as f64),
+            ("mixer_deposit_usd".to_string(), rows.iter().fold(0.0, |total, row| total + row.deposited_usd)),
+        ]);
+        Ok(Report { text: mixer_report(&rows), metrics })
+    }
+}
+
+pub fn run_mixers_command(args: &MixersArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let mut annotations = Annotations::load()?;
+    let mut mixers: HashSet<String> = KNOWN_MIXERS.iter().map(|mixer| mixer.to_string()).collect();
+    mixers.extend(args.mixers.iter().map(|mixer| mixer.to_lowercase()));
+    mixers.extend(annotations.with_tag(MIXER_TAG).map(|(address, _)| address.clone()));
+    let rows = mixer_interactions(&graph, &mixers);
+    print!("{}", mixer_report(&rows));
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for row in rows.iter() {
+        writer.serialize(row)?;
+    }
+    commit_csv(writer)?;
+    println!("Saved the mixer interactions of {} addresses as {}", rows.len(), &file_pathname);
+
+    if args.annotate {
+        let graph_addresses: HashSet<String> = graph.node_weights().map(|address| address.to_lowercase()).collect();
+        let used_mixers: Vec<&String> = mixers.iter().filter(|mixer| graph_addresses.contains(*mixer)).collect();
+        for mixer in used_mixers.iter() {
+            annotations.add_tag(mixer, MIXER_TAG);
+        }
+        for row in rows.iter() {
+            annotations.add_tag(&row.address, MIXER_USER_TAG);
+        }
+        annotations.save()?;
+        println!("Tagged {} mixers as {} and {} addresses as {}", used_mixers.len(), MIXER_TAG, rows.len(), MIXER_USER_TAG);
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use once_cell::sync::OnceCell;
+use std::fmt;
+
+pub const WEI_PER_GWEI: f64 = 1E9;
+pub const WEI_PER_COIN: f64 = 1E18;
+
+#[derive(Clone, Debug)]
+pub struct MoneyFormat {
+    pub usd_precision: usize,
+    pub coin_precision: usize,
+    pub gwei_precision: usize,
+    pub thousands_separator: Option<char>,
+}
+
+impl Default for MoneyFormat {
+    fn default() -> MoneyFormat {
+        MoneyFormat { usd_precision: 0, coin_precision: 6, gwei_precision: 3, thousands_separator: None }
+    }
+}
+
+static MONEY_FORMAT: OnceCell<MoneyFormat> = OnceCell::new();
+
+// Set once at startup from the command line; reports formatted before that use the defaults.
+pub fn configure(format: MoneyFormat) {
+    MONEY_FORMAT.set(format).ok();
+}
+
+fn money_format() -> &'static MoneyFormat {
+    MONEY_FORMAT.get_or_init(MoneyFormat::default)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Amount {
+    Usd(f64),
+    Coin(f64),
+    Gwei(f64),
+    Wei(u128),
+}
+
+pub fn wei_to_gwei(wei: u64) -> f64 {
+    wei as f64 / WEI_PER_GWEI
+}
+
+pub fn wei_to_coin(wei: u128) -> f64 {
+    wei as f64 / WEI_PER_COIN
+}
+
+fn group_thousands(number: &str, separator: Option<char>) -> String {
+    let Some(separator) = separator else {
+        return number.to_string();
+    };
+    let (sign, unsigned) = number.strip_prefix('-').map_or(("", number), |rest| ("-", rest));
+    let (integer, fraction) = unsigned.split_once('.').map_or((unsigned, None), |(i, f)| (i, Some(f)));
+    let mut grouped = String::new();
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    match fraction {
+        Some(fraction) => format!("{}{}.{}", sign, grouped, fraction),
+        None => format!("{}{}", sign, grouped),
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = money_format();
+        let (number, unit) = match *self {
+            Amount::Usd(value) => (format!("{:.*}", format.usd_precision, value), "USD"),
+            Amount::Coin(value) => (format!("{:.*}", format.coin_precision, value), "ETH"),
+            Amount::Gwei(value) => (format!("{:.*}", format.gwei_precision, value), "gwei"),
+            Amount::Wei(value) => (value.to_string(), "wei"),
+        };
+        write!(f, "{} {}", group_thousands(&number, format.thousands_separator), unit)
+    }
+}
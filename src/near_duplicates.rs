@@ -0,0 +1,180 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::Direction;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::analysis_pass::{AnalysisPass, Report};
+use crate::pricing::PriceProvider;
+use crate::G;
+
+// Signature lengths. The counterparty signature is split into bands for locality-sensitive hashing:
+// addresses agreeing on every hash of at least one band become a candidate pair, which a pair with
+// counterparty similarity s does with probability 1 - (1 - s^rows)^bands.
+const COUNTERPARTY_HASHES: usize = 64;
+const TIMING_HASHES: usize = 32;
+const BANDS: usize = 16;
+const ROWS_PER_BAND: usize = COUNTERPARTY_HASHES / BANDS;
+// Addresses with fewer counterparties are alike by chance, e.g. every leaf of a payout.
+const MIN_COUNTERPARTIES: usize = 3;
+// Buckets this large come from a shared hub band rather than from near duplicates and are skipped, so
+// candidate generation stays linear in the number of addresses.
+const MAX_BUCKET_SIZE: usize = 200;
+const COUNTERPARTY_WEIGHT: f64 = 0.7;
+const MIN_SIMILARITY: f64 = 0.6;
+const MAX_REPORTED_PAIRS: usize = 50;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// Hashes are spelled out rather than taken from std, so fingerprints and reports do not change with the
+// compiler version.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, &byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+// The i-th hash of the signature is the minimum over the tokens of an independent mix of the token.
+fn minhash(tokens: &HashSet<u64>, length: usize) -> Vec<u64> {
+    (0..length as u64)
+        .map(|i| tokens.iter().map(|&token| mix(token ^ mix(i.wrapping_add(1)))).min().unwrap_or(u64::MAX))
+        .collect()
+}
+
+// Share of agreeing hashes, which estimates the Jaccard similarity of the token sets.
+fn estimated_similarity(a: &[u64], b: &[u64]) -> f64 {
+    a.iter().zip(b).filter(|(x, y)| x == y).count() as f64 / a.len() as f64
+}
+
+struct Fingerprint {
+    node: NodeIndex,
+    counterparties: Vec<u64>,
+    // Empty when the address sent nothing, in which case only counterparties are compared.
+    timing: Vec<u64>,
+}
+
+// Counterparties in either direction, and the hours of day, weekdays and gaps between consecutive
+// transactions the address sent, gaps bucketed by powers of two.
+fn fingerprint(graph: &G, node: NodeIndex) -> Option<Fingerprint> {
+    let counterparties: HashSet<u64> = graph.neighbors_undirected(node).filter(|&n| n != node).map(|n| fnv1a(graph[n].as_bytes())).collect();
+    if counterparties.len() < MIN_COUNTERPARTIES {
+        return None;
+    }
+    let mut sent: Vec<u64> = graph.edges_directed(node, Direction::Outgoing).map(|e| e.weight().timestamp).filter(|&t| t != 0).collect();
+    sent.sort_unstable();
+    sent.dedup();
+    let mut timing: HashSet<u64> = HashSet::new();
+    for &timestamp in sent.iter() {
+        timing.insert(fnv1a(format!("hour {}", timestamp / 3600 % 24).as_bytes()));
+        // 1970-01-01 was a Thursday; the exact weekday names do not matter, only that they agree.
+        timing.insert(fnv1a(format!("weekday {}", timestamp / 86_400 % 7).as_bytes()));
+    }
+    for gap in sent.windows(2).map(|pair| pair[1] - pair[0]) {
+        timing.insert(fnv1a(format!("gap {}", 64 - gap.leading_zeros()).as_bytes()));
+    }
+    let timing = if timing.is_empty() { vec![] } else { minhash(&timing, TIMING_HASHES) };
+    Some(Fingerprint { node, counterparties: minhash(&counterparties, COUNTERPARTY_HASHES), timing })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NearDuplicate {
+    pub a: NodeIndex,
+    pub b: NodeIndex,
+    pub similarity: f64,
+    pub counterparty_similarity: f64,
+    pub timing_similarity: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+pub struct NearDuplicateSearch {
+    pub fingerprinted: usize,
+    pub candidate_pairs: usize,
+    pub skipped_buckets: usize,
+    pub pairs: Vec<NearDuplicate>,
+}
+
+pub fn near_duplicates(graph: &G, min_similarity: f64) -> NearDuplicateSearch {
+    let fingerprints: Vec<Fingerprint> = graph.node_indices().filter_map(|n| fingerprint(graph, n)).collect();
+    let mut search = NearDuplicateSearch { fingerprinted: fingerprints.len(), ..Default::default() };
+
+    let mut candidates: HashSet<(usize, usize)> = HashSet::new();
+    for band in 0..BANDS {
+        let mut buckets: HashMap<&[u64], Vec<usize>> = HashMap::new();
+        for (i, fingerprint) in fingerprints.iter().enumerate() {
+            buckets.entry(&fingerprint.counterparties[band * ROWS_PER_BAND..(band + 1) * ROWS_PER_BAND]).or_default().push(i);
+        }
+        for bucket in buckets.values().filter(|bucket| bucket.len() > 1) {
+            if bucket.len() > MAX_BUCKET_SIZE {
+                search.skipped_buckets += 1;
+                continue;
+            }
+            for (k, &i) in bucket.iter().enumerate() {
+                candidates.extend(bucket[k + 1..].iter().map(|&j| (i, j)));
+            }
+        }
+    }
+    search.candidate_pairs = candidates.len();
+
+    for (i, j) in candidates {
+        let (x, y) = (&fingerprints[i], &fingerprints[j]);
+        let counterparty_similarity = estimated_similarity(&x.counterparties, &y.counterparties);
+        let timing_similarity = (!x.timing.is_empty() && !y.timing.is_empty()).then(|| estimated_similarity(&x.timing, &y.timing));
+        let similarity = timing_similarity.map_or(counterparty_similarity, |timing| {
+            COUNTERPARTY_WEIGHT * counterparty_similarity + (1.0 - COUNTERPARTY_WEIGHT) * timing
+        });
+        if similarity >= min_similarity {
+            let (a, b) = if graph[x.node] < graph[y.node] { (x.node, y.node) } else { (y.node, x.node) };
+            search.pairs.push(NearDuplicate { a, b, similarity, counterparty_similarity, timing_similarity });
+        }
+    }
+    search.pairs.sort_by(|x, y| y.similarity.total_cmp(&x.similarity).then_with(|| (&graph[x.a], &graph[x.b]).cmp(&(&graph[y.a], &graph[y.b]))));
+    search
+}
+
+pub fn near_duplicates_report(graph: &G, search: &NearDuplicateSearch) -> String {
+    let mut report = format!(
+        "Fingerprinted {} addresses with at least {} counterparties; {} candidate pairs from {} bands of {} hashes ({} oversized buckets skipped)\n",
+        search.fingerprinted, MIN_COUNTERPARTIES, search.candidate_pairs, BANDS, ROWS_PER_BAND, search.skipped_buckets
+    );
+    writeln!(report, "{} near-duplicate pairs with similarity of at least {}", search.pairs.len(), MIN_SIMILARITY).unwrap();
+    for pair in search.pairs.iter().take(MAX_REPORTED_PAIRS) {
+        let timing = pair.timing_similarity.map_or("n/a".to_string(), |timing| format!("{:.2}", timing));
+        writeln!(
+            report,
+            "  {} {} similarity {:.2} (counterparties {:.2}, timing {})",
+            graph[pair.a], graph[pair.b], pair.similarity, pair.counterparty_similarity, timing
+        )
+        .unwrap();
+    }
+    if search.pairs.len() > MAX_REPORTED_PAIRS {
+        writeln!(report, "  ... and {} more", search.pairs.len() - MAX_REPORTED_PAIRS).unwrap();
+    }
+    report
+}
+
+pub struct NearDuplicatesPass;
+
+impl AnalysisPass for NearDuplicatesPass {
+    fn name(&self) -> &str {
+        "near_duplicates"
+    }
+
+    fn parameters(&self) -> String {
+        format!("hashes={}+{} bands={} min_similarity={}", COUNTERPARTY_HASHES, TIMING_HASHES, BANDS, MIN_SIMILARITY)
+    }
+
+    fn run(&self, graph: &G, _prices: &dyn PriceProvider) -> Result<Report> {
+        let search = near_duplicates(graph, MIN_SIMILARITY);
+        let metrics = BTreeMap::from([
+            ("fingerprinted_addresses".to_string(), search.fingerprinted as f64),
+            ("candidate_pairs".to_string(), search.candidate_pairs as f64),
+            ("near_duplicate_pairs".to_string(), search.pairs.len() as f64),
+        ]);
+        Ok(Report { text: near_duplicates_report(graph, &search), metrics })
+    }
+}
@@ -0,0 +1,59 @@
+use eyre::Result;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use serde_json::json;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+use crate::{http_client, DATA_STORAGE_FOLDER};
+
+// Where the daemon sends what it has to report. Files are appended to, so they keep every message.
+pub enum Notifier {
+    Stdout,
+    File(String),
+    // Posted as {"text": ...}, which Slack, Mattermost and Discord-compatible incoming webhooks accept.
+    Webhook { url: String, client: Client },
+}
+
+impl Notifier {
+    pub fn from_specs(specs: &[String]) -> Result<Vec<Notifier>> {
+        let mut notifiers = vec![];
+        for spec in specs {
+            let (kind, target) = spec.split_once(':').unwrap_or((spec.as_str(), ""));
+            match (kind, target.is_empty()) {
+                ("stdout", true) => notifiers.push(Notifier::Stdout),
+                ("file", false) => {
+                    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+                    notifiers.push(Notifier::File(format!("{}/{}", DATA_STORAGE_FOLDER, target)));
+                }
+                ("webhook", false) => notifiers.push(Notifier::Webhook { url: target.to_string(), client: http_client::client() }),
+                _ => return Err(eyre::eyre!("Unknown notifier {}, expected stdout, file:<file> or webhook:<url>", spec)),
+            }
+        }
+        Ok(notifiers)
+    }
+
+    pub async fn notify(&self, text: &str) -> Result<()> {
+        match self {
+            Notifier::Stdout => print!("{}", text),
+            Notifier::File(file_pathname) => {
+                let mut file = OpenOptions::new().create(true).append(true).open(file_pathname)?;
+                writeln!(file, "{}", text)?;
+            }
+            Notifier::Webhook { url, client } => {
+                let body = serde_json::to_string(&json!({ "text": text }))?;
+                client.post(url).header(CONTENT_TYPE, "application/json").body(body).send().await?.error_for_status()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// A notifier that fails is reported, the others still get the message.
+pub async fn notify_all(notifiers: &[Notifier], text: &str) {
+    for notifier in notifiers {
+        if let Err(e) = notifier.notify(text).await {
+            println!("Could not send a notification: {}", e);
+        }
+    }
+}
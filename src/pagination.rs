@@ -0,0 +1,240 @@
+use reqwest::Client;
+use std::collections::HashSet;
+
+use crate::exit_status::{self, FailureKind};
+use crate::history_window::{HistoryWindow, SortDirection};
+use crate::request_metrics::RequestMetric;
+use crate::throttle::{is_rate_limit_error, RequestOutcome, Throttle};
+use crate::etherscan::{Etherscan, TransactionQuery};
+use crate::{RawTransaction, MAX_TRANSACTIONS_PER_ADDRESS, TRANSACTIONS_TO_REQUEST};
+
+// Etherscan only serves records with page * offset <= 10000 for any single query.
+const MAX_RESULT_WINDOW: usize = 10_000;
+
+// Network failures in a row, each paced by the throttle, before the network is taken to be down.
+const MAX_CONSECUTIVE_NETWORK_FAILURES: usize = 20;
+// Failed attempts of one request with any other error, rate limits aside, before the address is given up on.
+const MAX_FAILED_ATTEMPTS: usize = 5;
+
+// Every attempt is counted in `requests`, since failed responses consume API quota as well. Once the throttle
+// is aborted, by this or by a concurrent request, the records fetched so far are all the address gets. Once a
+// request runs out of attempts its error is left in `failure` and the address's remaining requests are skipped.
+async fn get_transactions_with_retry(
+    address: &str,
+    query: &TransactionQuery,
+    client: &Client,
+    api_key: &str,
+    throttle: &Throttle,
+    requests: &mut usize,
+    failure: &mut Option<String>,
+) -> Vec<RawTransaction> {
+    let mut attempt = 0;
+    let mut network_failures = 0;
+    let mut failed_attempts = 0;
+    loop {
+        if throttle.is_aborted() {
+            exit_status::record(FailureKind::Partial, format!("The history of {} was cut short when the crawl stopped", address));
+            break vec![];
+        }
+        if failure.is_some() {
+            break vec![];
+        }
+        attempt += 1;
+        *requests += 1;
+        let started = throttle.acquire().await;
+        let metric = RequestMetric {
+            started_at_ms: 0,
+            address: address.to_string(),
+            start_block: query.start_block,
+            end_block: query.end_block,
+            page: query.page,
+            offset: query.offset,
+            sort: query.sort.as_str(),
+            attempt,
+            outcome: "",
+            latency_ms: 0.0,
+            bytes: 0,
+            results: 0,
+        };
+        match Etherscan::new(client, api_key).txlist(address, query).await {
+            Err(e) => {
+                throttle.record(started, if is_rate_limit_error(&e) { RequestOutcome::RateLimited } else { RequestOutcome::Failed }, metric);
+                println!("Incorrect response for {}:\n{}", address, e);
+                match exit_status::classify(&e) {
+                    Some(FailureKind::Network) => {
+                        network_failures += 1;
+                        if network_failures >= MAX_CONSECUTIVE_NETWORK_FAILURES {
+                            throttle.abort(FailureKind::Network, format!("{} network failures in a row, the last one: {:#}", network_failures, e));
+                        }
+                    }
+                    Some(kind) => throttle.abort(kind, format!("{:#}", e)),
+                    None => {
+                        network_failures = 0;
+                        if !is_rate_limit_error(&e) {
+                            failed_attempts += 1;
+                        }
+                        if failed_attempts >= MAX_FAILED_ATTEMPTS {
+                            println!("Giving up on {} after {} failed attempts", address, failed_attempts);
+                            *failure = Some(format!("{:#}", e));
+                        }
+                    }
+                }
+            }
+            Ok((t, bytes)) => {
+                throttle.record(started, RequestOutcome::Success, RequestMetric { bytes, results: t.result.len(), ..metric });
+                println!(
+                    "Correct response for {} with {} transactions (blocks {}..={}, page {}, {})",
+                    address, t.result.len(), query.start_block, query.end_block, query.page, query.sort.as_str()
+                );
+                break t.result;
+            }
+        }
+    }
+}
+
+pub struct AddressHistory {
+    pub transactions: Vec<RawTransaction>,
+    pub requests: usize,
+    // Error of the request that ran out of attempts, after which the rest of the history was not fetched.
+    pub failure: Option<String>,
+    seen_hashes: HashSet<String>,
+    // Set when a single block holds more records than the result window can serve.
+    oversized_block: bool,
+}
+
+impl AddressHistory {
+    // Pages and windows overlap on boundary blocks, so every record passes a duplicate-hash guard.
+    fn extend(&mut self, transactions: Vec<RawTransaction>) {
+        for transaction in transactions {
+            if self.seen_hashes.insert(transaction.hash.clone()) {
+                self.transactions.push(transaction);
+            }
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.transactions.len() >= MAX_TRANSACTIONS_PER_ADDRESS
+    }
+
+    // Whether records are known to be missing, either cut by the per-address cap or by an oversized block.
+    pub fn is_incomplete(&self) -> bool {
+        self.is_full() || self.oversized_block
+    }
+}
+
+fn block_number(transaction: &RawTransaction) -> Option<u64> {
+    transaction.blockNumber.parse().ok()
+}
+
+// Pages through one block window in one direction. Returns the fetched records and whether the
+// result window was exhausted, meaning the block range holds more records than could be served.
+#[allow(clippy::too_many_arguments)]
+async fn fetch_window(
+    address: &str,
+    start_block: u64,
+    end_block: u64,
+    sort: SortDirection,
+    client: &Client,
+    api_key: &str,
+    throttle: &Throttle,
+    requests: &mut usize,
+    failure: &mut Option<String>,
+) -> (Vec<RawTransaction>, bool) {
+    let offset = TRANSACTIONS_TO_REQUEST.min(MAX_RESULT_WINDOW);
+    let mut transactions = vec![];
+    let mut page = 1;
+    loop {
+        let query = TransactionQuery { start_block, end_block, page, offset, sort };
+        let page_transactions = get_transactions_with_retry(address, &query, client, api_key, throttle, requests, failure).await;
+        let page_was_full = page_transactions.len() >= offset;
+        transactions.extend(page_transactions);
+        if !page_was_full {
+            return (transactions, false);
+        }
+        if (page + 1) * offset > MAX_RESULT_WINDOW {
+            return (transactions, true);
+        }
+        page += 1;
+    }
+}
+
+// The first `offset` records of an address in the window's order in a single request, enough to discover its main counterparties.
+pub async fn get_address_sample(address: &str, window: &HistoryWindow, offset: usize, client: &Client, api_key: &str, throttle: &Throttle) -> AddressHistory {
+    let mut history = AddressHistory { transactions: vec![], requests: 0, failure: None, seen_hashes: HashSet::new(), oversized_block: false };
+    let query = TransactionQuery {
+        start_block: window.start_block,
+        end_block: window.end_block,
+        page: 1,
+        offset: offset.min(MAX_RESULT_WINDOW),
+        sort: window.sort,
+    };
+    let transactions = get_transactions_with_retry(address, &query, client, api_key, throttle, &mut history.requests, &mut history.failure).await;
+    history.extend(transactions);
+    history
+}
+
+// The block at the far end of a batch fetched in the given order, where a continuation would start.
+fn boundary_block(transactions: &[RawTransaction], sort: SortDirection) -> Option<u64> {
+    let blocks = transactions.iter().filter_map(block_number);
+    match sort {
+        SortDirection::Descending => blocks.min(),
+        SortDirection::Ascending => blocks.max(),
+    }
+}
+
+pub async fn get_address_history(address: &str, window: &HistoryWindow, client: &Client, api_key: &str, throttle: &Throttle) -> AddressHistory {
+    let mut history = AddressHistory { transactions: vec![], requests: 0, failure: None, seen_hashes: HashSet::new(), oversized_block: false };
+    let (start_block, end_block) = (window.start_block, window.end_block);
+
+    // The preferred end of the history comes first, so the per-address cap cuts off the other end.
+    let (preferred, truncated) = fetch_window(address, start_block, end_block, window.sort, client, api_key, throttle, &mut history.requests, &mut history.failure).await;
+    let preferred_boundary = boundary_block(&preferred, window.sort);
+    history.extend(preferred);
+    if !truncated || history.is_full() {
+        return history;
+    }
+
+    // Stitch the records from the other end of the history.
+    let (other, truncated) = fetch_window(address, start_block, end_block, window.sort.reversed(), client, api_key, throttle, &mut history.requests, &mut history.failure).await;
+    let other_boundary = boundary_block(&other, window.sort.reversed());
+    history.extend(other);
+    let (gap_low, gap_high) = match window.sort {
+        SortDirection::Descending => (other_boundary, preferred_boundary),
+        SortDirection::Ascending => (preferred_boundary, other_boundary),
+    };
+    let (Some(gap_low), Some(gap_high)) = (gap_low, gap_high) else {
+        return history;
+    };
+    if !truncated || gap_low >= gap_high || history.is_full() {
+        return history;
+    }
+
+    // Fall back to bisecting the unfetched middle of the history into smaller block windows, visiting the
+    // preferred end first.
+    println!("Bisecting blocks {}..={} for {}", gap_low, gap_high, address);
+    let mut windows = vec![(gap_low, gap_high)];
+    while let Some((low, high)) = windows.pop() {
+        if history.is_full() {
+            println!("Reached {} transactions for {}, history is incomplete", MAX_TRANSACTIONS_PER_ADDRESS, address);
+            break;
+        }
+        let (window_transactions, truncated) = fetch_window(address, low, high, SortDirection::Descending, client, api_key, throttle, &mut history.requests, &mut history.failure).await;
+        let lowest_fetched_block = window_transactions.iter().filter_map(block_number).min().unwrap_or(low);
+        history.extend(window_transactions);
+
+        if truncated {
+            if lowest_fetched_block <= low {
+                println!("Block {} alone exceeds the result window for {}, history is incomplete", low, address);
+                history.oversized_block = true;
+                continue;
+            }
+            let middle = low + (lowest_fetched_block - low) / 2;
+            let (older, newer) = ((low, middle), (middle + 1, lowest_fetched_block));
+            match window.sort {
+                SortDirection::Descending => windows.extend([older, newer]),
+                SortDirection::Ascending => windows.extend([newer, older]),
+            }
+        }
+    }
+    history
+}
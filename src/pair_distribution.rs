@@ -0,0 +1,139 @@
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::annotations::Annotations;
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::PairDistributionArgs;
+use crate::fees::percentile;
+use crate::money::Amount;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+// Transfer sizes of one direction of a pair, without percentiles when no transfer that way has a USD value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DirectionSizes {
+    pub transfers: usize,
+    pub p50_usd: Option<f64>,
+    pub p90_usd: Option<f64>,
+    pub max_usd: Option<f64>,
+    // Transfers without a USD value, left out of the percentiles.
+    pub unpriced_transfers: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairDistribution {
+    // The lower address first, so each pair appears once with both directions.
+    pub address_a: String,
+    pub address_b: String,
+    pub a_to_b: DirectionSizes,
+    pub b_to_a: DirectionSizes,
+}
+
+impl PairDistribution {
+    pub fn transfers(&self) -> usize {
+        self.a_to_b.transfers + self.b_to_a.transfers
+    }
+}
+
+#[derive(Serialize)]
+struct PairDistributionRow<'a> {
+    address_a: &'a str,
+    address_b: &'a str,
+    a_to_b_transfers: usize,
+    a_to_b_p50_usd: Option<f64>,
+    a_to_b_p90_usd: Option<f64>,
+    a_to_b_max_usd: Option<f64>,
+    a_to_b_unpriced_transfers: usize,
+    b_to_a_transfers: usize,
+    b_to_a_p50_usd: Option<f64>,
+    b_to_a_p90_usd: Option<f64>,
+    b_to_a_max_usd: Option<f64>,
+    b_to_a_unpriced_transfers: usize,
+}
+
+// Transfer count and the USD values of the priced transfers of one direction.
+type DirectionValues = (usize, Vec<f64>);
+
+fn direction_sizes(transfers: usize, mut values: Vec<f64>) -> DirectionSizes {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let priced = !values.is_empty();
+    DirectionSizes {
+        transfers,
+        p50_usd: priced.then(|| percentile(&values, 0.5)),
+        p90_usd: priced.then(|| percentile(&values, 0.9)),
+        max_usd: values.last().copied(),
+        unpriced_transfers: transfers - values.len(),
+    }
+}
+
+// Size percentiles per direction of every pair with at least min_transfers transfers between them, most
+// transfers first. A steady p50 close to p90 and max is a recurring payment, a max far above p90 a settlement.
+pub fn pair_distributions(graph: &G, min_transfers: usize) -> Vec<PairDistribution> {
+    let mut pairs: BTreeMap<(&str, &str), [DirectionValues; 2]> = BTreeMap::new();
+    for edge in graph.edge_references() {
+        let (source, target) = (graph[edge.source()].as_str(), graph[edge.target()].as_str());
+        if source == target {
+            continue;
+        }
+        let (key, direction) = if source < target { ((source, target), 0) } else { ((target, source), 1) };
+        let (transfers, values) = &mut pairs.entry(key).or_default()[direction];
+        *transfers += 1;
+        values.extend(edge.weight().usd_value());
+    }
+    let mut distributions: Vec<PairDistribution> = pairs
+        .into_iter()
+        .filter(|(_, directions)| directions[0].0 + directions[1].0 >= min_transfers)
+        .map(|((address_a, address_b), [a_to_b, b_to_a])| PairDistribution {
+            address_a: address_a.to_string(),
+            address_b: address_b.to_string(),
+            a_to_b: direction_sizes(a_to_b.0, a_to_b.1),
+            b_to_a: direction_sizes(b_to_a.0, b_to_a.1),
+        })
+        .collect();
+    distributions.sort_by(|a, b| b.transfers().cmp(&a.transfers()).then(a.address_a.cmp(&b.address_a)).then(a.address_b.cmp(&b.address_b)));
+    distributions
+}
+
+fn direction_text(sizes: &DirectionSizes) -> String {
+    match (sizes.p50_usd, sizes.p90_usd, sizes.max_usd) {
+        (Some(p50), Some(p90), Some(max)) => format!("{} transfers, p50 {}, p90 {}, max {}", sizes.transfers, Amount::Usd(p50), Amount::Usd(p90), Amount::Usd(max)),
+        _ => format!("{} transfers", sizes.transfers),
+    }
+}
+
+pub fn run_pair_distribution_command(args: &PairDistributionArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let annotations = Annotations::load()?;
+    let distributions = pair_distributions(&graph, args.min_transfers);
+    println!("{} pairs with at least {} transfers", distributions.len(), args.min_transfers);
+    for pair in distributions.iter().take(args.show) {
+        println!("{} <-> {}", annotations.mention(&pair.address_a), annotations.mention(&pair.address_b));
+        println!("  a to b: {}", direction_text(&pair.a_to_b));
+        println!("  b to a: {}", direction_text(&pair.b_to_a));
+    }
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for pair in distributions.iter() {
+        writer.serialize(PairDistributionRow {
+            address_a: &pair.address_a,
+            address_b: &pair.address_b,
+            a_to_b_transfers: pair.a_to_b.transfers,
+            a_to_b_p50_usd: pair.a_to_b.p50_usd,
+            a_to_b_p90_usd: pair.a_to_b.p90_usd,
+            a_to_b_max_usd: pair.a_to_b.max_usd,
+            a_to_b_unpriced_transfers: pair.a_to_b.unpriced_transfers,
+            b_to_a_transfers: pair.b_to_a.transfers,
+            b_to_a_p50_usd: pair.b_to_a.p50_usd,
+            b_to_a_p90_usd: pair.b_to_a.p90_usd,
+            b_to_a_max_usd: pair.b_to_a.max_usd,
+            b_to_a_unpriced_transfers: pair.b_to_a.unpriced_transfers,
+        })?;
+    }
+    commit_csv(writer)?;
+    println!("Saved the size distributions of {} pairs as {}", distributions.len(), &file_pathname);
+    Ok(())
+}
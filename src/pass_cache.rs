@@ -0,0 +1,94 @@
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+
+use crate::analysis_pass::Report;
+use crate::atomic_file::write_atomically;
+use crate::pricing::PriceChecks;
+use crate::{DATA_STORAGE_FOLDER, G};
+
+// Pass reports are cached in a subfolder of the workspace, which the catalog and gc leave alone.
+const CACHE_FOLDER: &str = "pass_cache";
+
+#[derive(Serialize, Deserialize)]
+struct CachedReport {
+    pass: String,
+    parameters: String,
+    graph_hash: String,
+    prices_hash: String,
+    report: Report,
+}
+
+fn hex_digest(hasher: Sha256) -> String {
+    hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Content hash over addresses, endpoints and transactions in index order, so a graph saved and loaded
+// again hashes the same while any filter that drops an edge changes the hash.
+pub fn graph_hash(graph: &G) -> String {
+    let mut hasher = Sha256::new();
+    for node in graph.node_indices() {
+        hasher.update(graph[node].as_bytes());
+        hasher.update([0]);
+    }
+    for edge in graph.edge_references() {
+        hasher.update((edge.source().index() as u64).to_le_bytes());
+        hasher.update((edge.target().index() as u64).to_le_bytes());
+        hasher.update(serde_json::to_vec(edge.weight()).unwrap());
+    }
+    hex_digest(hasher)
+}
+
+// Reports of passes over one graph with one price series. A cached report is reused when the pass name,
+// its parameters and the ethparser version match as well.
+pub struct PassCache {
+    graph_hash: String,
+    prices_hash: String,
+}
+
+impl PassCache {
+    // Prices are named as in the analyze arguments, relative to the data storage folder. The checks are
+    // part of the key, since smoothing changes the prices the passes see.
+    pub fn new(graph: &G, prices: Option<&str>, checks: &PriceChecks) -> Result<PassCache> {
+        let prices_hash = match prices {
+            Some(name) => {
+                let mut hasher = Sha256::new();
+                hasher.update(fs::read(format!("{}/{}", DATA_STORAGE_FOLDER, name))?);
+                hasher.update(format!("{:?}", checks).as_bytes());
+                hex_digest(hasher)
+            }
+            None => "none".to_string(),
+        };
+        Ok(PassCache { graph_hash: graph_hash(graph), prices_hash })
+    }
+
+    fn pathname(&self, pass: &str, parameters: &str) -> String {
+        let mut hasher = Sha256::new();
+        for part in [env!("CARGO_PKG_VERSION"), &self.graph_hash, &self.prices_hash, pass, parameters] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        format!("{}/{}/{}.json", DATA_STORAGE_FOLDER, CACHE_FOLDER, hex_digest(hasher))
+    }
+
+    // Unreadable or mismatching entries count as misses and are overwritten by the next put.
+    pub fn get(&self, pass: &str, parameters: &str) -> Option<Report> {
+        let cached: CachedReport = serde_json::from_slice(&fs::read(self.pathname(pass, parameters)).ok()?).ok()?;
+        let matches = cached.pass == pass && cached.parameters == parameters && cached.graph_hash == self.graph_hash && cached.prices_hash == self.prices_hash;
+        matches.then_some(cached.report)
+    }
+
+    pub fn put(&self, pass: &str, parameters: &str, report: &Report) -> Result<()> {
+        fs::create_dir_all(format!("{}/{}", DATA_STORAGE_FOLDER, CACHE_FOLDER))?;
+        let cached = CachedReport {
+            pass: pass.to_string(),
+            parameters: parameters.to_string(),
+            graph_hash: self.graph_hash.clone(),
+            prices_hash: self.prices_hash.clone(),
+            report: report.clone(),
+        };
+        write_atomically(&self.pathname(pass, parameters), serde_json::to_string(&cached)?.as_bytes())
+    }
+}
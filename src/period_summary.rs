@@ -0,0 +1,127 @@
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+
+use crate::approval_risk::{find_approvals, Approval};
+use crate::atomic_file::write_atomically;
+use crate::cli::PeriodSummaryArgs;
+use crate::money::Amount;
+use crate::reciprocity::reciprocal_pairs;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+#[derive(Debug, Clone)]
+pub struct NewCounterparty {
+    pub address: String,
+    pub usd_volume: f64,
+    pub transfers: usize,
+}
+
+// What changed between the snapshot of the previous period and the graph now.
+#[derive(Debug, Clone)]
+pub struct PeriodSummary {
+    pub previous_nodes: usize,
+    pub nodes: usize,
+    pub previous_edges: usize,
+    pub edges: usize,
+    pub previous_usd_volume: f64,
+    pub usd_volume: f64,
+    pub new_addresses: usize,
+    // New addresses by the USD volume they exchanged, largest first.
+    pub top_new_counterparties: Vec<NewCounterparty>,
+    // Alerts the graph raises now that it did not raise in the snapshot: two-way pairs returning most of
+    // their transfers within a day, and live unlimited allowances to spenders not known to be verified.
+    pub new_pass_through_pairs: Vec<(String, String)>,
+    pub new_risky_approvals: Vec<Approval>,
+}
+
+fn usd_volume(graph: &G) -> f64 {
+    graph.edge_weights().filter_map(|transaction| transaction.data.as_ref()).fold(0.0, |acc, data| acc + data.usd_value)
+}
+
+fn pass_through_pairs(graph: &G) -> HashSet<(String, String)> {
+    reciprocal_pairs(graph).into_iter().filter(|pair| pair.is_pass_through()).map(|pair| (pair.address_a, pair.address_b)).collect()
+}
+
+fn risky_approvals(graph: &G) -> Vec<Approval> {
+    find_approvals(graph).into_iter().filter(|a| a.latest && a.unlimited && a.amount != "0" && a.spender_verified != Some(true)).collect()
+}
+
+pub fn period_summary(previous: &G, current: &G, top: usize) -> PeriodSummary {
+    let known: HashSet<&String> = previous.node_weights().collect();
+    let mut new_counterparties: HashMap<&String, NewCounterparty> = HashMap::new();
+    for edge in current.edge_references() {
+        let usd_value = edge.weight().data.as_ref().map_or(0.0, |data| data.usd_value);
+        for address in [&current[edge.source()], &current[edge.target()]] {
+            if known.contains(address) {
+                continue;
+            }
+            let entry = new_counterparties.entry(address).or_insert_with(|| NewCounterparty { address: address.clone(), usd_volume: 0.0, transfers: 0 });
+            entry.usd_volume += usd_value;
+            entry.transfers += 1;
+        }
+    }
+    let new_addresses = current.node_weights().filter(|address| !known.contains(address)).count();
+    let mut top_new_counterparties: Vec<NewCounterparty> = new_counterparties.into_values().collect();
+    top_new_counterparties.sort_by(|x, y| y.usd_volume.total_cmp(&x.usd_volume).then_with(|| x.address.cmp(&y.address)));
+    top_new_counterparties.truncate(top);
+
+    let previous_pass_through = pass_through_pairs(previous);
+    let mut new_pass_through_pairs: Vec<(String, String)> = pass_through_pairs(current).into_iter().filter(|pair| !previous_pass_through.contains(pair)).collect();
+    new_pass_through_pairs.sort();
+    let previous_approvals: HashSet<String> = risky_approvals(previous).into_iter().map(|approval| approval.hash).collect();
+    let new_risky_approvals = risky_approvals(current).into_iter().filter(|approval| !previous_approvals.contains(&approval.hash)).collect();
+
+    PeriodSummary {
+        previous_nodes: previous.node_count(),
+        nodes: current.node_count(),
+        previous_edges: previous.edge_count(),
+        edges: current.edge_count(),
+        previous_usd_volume: usd_volume(previous),
+        usd_volume: usd_volume(current),
+        new_addresses,
+        top_new_counterparties,
+        new_pass_through_pairs,
+        new_risky_approvals,
+    }
+}
+
+pub fn period_summary_report(summary: &PeriodSummary, title: &str) -> String {
+    let mut report = format!("{}\n", title);
+    writeln!(report, "Addresses: {} -> {} ({} new)", summary.previous_nodes, summary.nodes, summary.new_addresses).unwrap();
+    writeln!(report, "Transactions: {} -> {} (+{})", summary.previous_edges, summary.edges, summary.edges.saturating_sub(summary.previous_edges)).unwrap();
+    writeln!(
+        report,
+        "USD volume: {} -> {} ({})",
+        Amount::Usd(summary.previous_usd_volume),
+        Amount::Usd(summary.usd_volume),
+        Amount::Usd(summary.usd_volume - summary.previous_usd_volume)
+    )
+    .unwrap();
+    writeln!(report, "New alerts: {} pass-through pairs, {} unlimited approvals to unverified spenders", summary.new_pass_through_pairs.len(), summary.new_risky_approvals.len()).unwrap();
+    for (a, b) in summary.new_pass_through_pairs.iter() {
+        writeln!(report, "  pass-through {} <-> {}", a, b).unwrap();
+    }
+    for approval in summary.new_risky_approvals.iter() {
+        writeln!(report, "  {} approved {} unlimited {} in {}", approval.owner, approval.spender, approval.token, approval.hash).unwrap();
+    }
+    writeln!(report, "Top new counterparties:").unwrap();
+    for counterparty in summary.top_new_counterparties.iter() {
+        writeln!(report, "  {} {} in {} transfers", counterparty.address, Amount::Usd(counterparty.usd_volume), counterparty.transfers).unwrap();
+    }
+    report
+}
+
+pub fn run_period_summary_command(args: &PeriodSummaryArgs) -> Result<()> {
+    let (previous, _) = deserialize_graph(&args.previous)?;
+    let (current, _) = deserialize_graph(&args.input)?;
+    let summary = period_summary(&previous, &current, args.top);
+    let report = period_summary_report(&summary, &format!("Changes from {} to {}", args.previous, args.input));
+    print!("{}", report);
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    write_atomically(&file_pathname, report.as_bytes())?;
+    println!("Saved the summary as {}", file_pathname);
+    Ok(())
+}
@@ -0,0 +1,265 @@
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs;
+
+use crate::analysis_pass::{load_analysis_input, price_checks, save_reports, NoPrices, PassRegistry, Report};
+use crate::annotations::Annotations;
+use crate::atomic_file::write_atomically;
+use crate::cli::AnalyzeArgs;
+use crate::graph_view::FilteredGraph;
+use crate::layers::{edge_layers, Layer};
+use crate::pass_cache::PassCache;
+use crate::pricing::{PriceProvider, PriceSeries};
+use crate::reciprocity::reciprocal_pairs;
+use crate::task_control::TaskControl;
+use crate::{filter_stablecoin_transactions_by_value, serialize_graph, FilteringResultInfo, Payload, DATA_STORAGE_FOLDER, G};
+
+// A repeatable study, e.g.
+//
+//   input = "parsed_transactions.json"
+//
+//   [[step]]
+//   kind = "filter-value"
+//   min_usd = 10.0
+//   max_usd = 1000.0
+//
+//   [[step]]
+//   kind = "two-way"
+//
+//   [[step]]
+//   kind = "stats"
+//
+//   [[step]]
+//   kind = "export"
+//   format = "graphml"
+//   output = "study.graphml"
+//
+// input, prices and manifest fall back to the analyze arguments when left out.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Pipeline {
+    input: Option<String>,
+    prices: Option<String>,
+    manifest: Option<String>,
+    #[serde(rename = "step")]
+    steps: Vec<Step>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+enum Step {
+    // Keeps stablecoin transfers worth between the bounds, inclusive.
+    FilterValue { min_usd: f64, max_usd: f64 },
+    FilterPayload { payload: Payload },
+    // Keeps the edges in any of the asset layers, named as for --layer.
+    FilterLayer { layers: Vec<String> },
+    // Keeps the transactions between addresses that sent value to each other in both directions.
+    TwoWay,
+    Stats,
+    // Registered analysis passes, all of them when none are listed.
+    Analyze {
+        #[serde(default)]
+        passes: Vec<String>,
+    },
+    Export { format: ExportFormat, output: String },
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    Json,
+    Graphml,
+}
+
+impl Step {
+    fn name(&self) -> &'static str {
+        match self {
+            Step::FilterValue { .. } => "filter-value",
+            Step::FilterPayload { .. } => "filter-payload",
+            Step::FilterLayer { .. } => "filter-layer",
+            Step::TwoWay => "two-way",
+            Step::Stats => "stats",
+            Step::Analyze { .. } => "analyze",
+            Step::Export { .. } => "export",
+        }
+    }
+}
+
+fn load_pipeline(pathname: &str) -> Result<Pipeline> {
+    let text = fs::read_to_string(pathname).map_err(|e| eyre::eyre!("Could not read pipeline {}: {}", pathname, e))?;
+    let pipeline: Pipeline = toml::from_str(&text).map_err(|e| eyre::eyre!("Invalid pipeline {}: {}", pathname, e))?;
+    if pipeline.steps.is_empty() {
+        return Err(eyre::eyre!("Pipeline {} has no steps", pathname));
+    }
+    Ok(pipeline)
+}
+
+// Problems found before anything runs, so a typo in the last step does not waste the earlier ones.
+fn validate(pipeline: &Pipeline, passes: &PassRegistry) -> Result<()> {
+    let mut outputs = HashSet::new();
+    for (i, step) in pipeline.steps.iter().enumerate() {
+        match step {
+            Step::FilterValue { min_usd, max_usd } if !(*min_usd >= 0.0 && max_usd >= min_usd) => {
+                return Err(eyre::eyre!("Step {} has an empty value range {} to {}", i + 1, min_usd, max_usd));
+            }
+            Step::FilterLayer { layers } => {
+                for layer in layers {
+                    Layer::parse(layer).map_err(|e| eyre::eyre!("Step {}: {}", i + 1, e))?;
+                }
+            }
+            Step::Analyze { passes: selected } => {
+                if let Some(unknown) = selected.iter().find(|name| !passes.names().contains(&name.as_str())) {
+                    return Err(eyre::eyre!("Step {} names unknown analysis pass {}, registered passes are {}", i + 1, unknown, passes.names().join(", ")));
+                }
+            }
+            Step::Export { output, .. } if !outputs.insert(output) => {
+                return Err(eyre::eyre!("Step {} exports to {} a second time", i + 1, output));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn retain<'a>(graph: &'a G, predicate: impl Fn(petgraph::graph::EdgeReference<'a, crate::Transaction>) -> bool) -> G {
+    FilteredGraph::new(graph).retain_edges(predicate).materialize().0
+}
+
+fn two_way(graph: &G) -> (G, usize) {
+    let pairs: HashSet<(String, String)> = reciprocal_pairs(graph).into_iter().map(|pair| (pair.address_a, pair.address_b)).collect();
+    let filtered = retain(graph, |edge| {
+        let (from, to) = (&graph[edge.source()], &graph[edge.target()]);
+        pairs.contains(&(from.clone(), to.clone())) || pairs.contains(&(to.clone(), from.clone()))
+    });
+    (filtered, pairs.len())
+}
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// GraphML for Gephi, yEd or networkx, with the address and any annotation on nodes and the headline
+// transaction fields on edges.
+pub fn graphml(graph: &G, annotations: &Annotations) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    for (id, domain, attribute, kind) in [
+        ("address", "node", "address", "string"),
+        ("tags", "node", "tags", "string"),
+        ("notes", "node", "notes", "string"),
+        ("hash", "edge", "hash", "string"),
+        ("timestamp", "edge", "timestamp", "long"),
+        ("block_number", "edge", "block_number", "long"),
+        ("kind", "edge", "kind", "string"),
+        ("payload", "edge", "payload", "string"),
+        ("usd_value", "edge", "usd_value", "double"),
+    ] {
+        writeln!(xml, "  <key id=\"{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"{}\"/>", id, domain, attribute, kind).unwrap();
+    }
+    xml.push_str("  <graph id=\"transactions\" edgedefault=\"directed\">\n");
+    for node in graph.node_indices() {
+        write!(xml, "    <node id=\"n{}\"><data key=\"address\">{}</data>", node.index(), escape_xml(&graph[node])).unwrap();
+        if let Some(annotation) = annotations.get(&graph[node]) {
+            write!(xml, "<data key=\"tags\">{}</data><data key=\"notes\">{}</data>", escape_xml(&annotation.tag_list()), escape_xml(&annotation.note_list())).unwrap();
+        }
+        xml.push_str("</node>\n");
+    }
+    for edge in graph.edge_references() {
+        let transaction = edge.weight();
+        write!(
+            xml,
+            "    <edge id=\"e{}\" source=\"n{}\" target=\"n{}\"><data key=\"hash\">{}</data><data key=\"timestamp\">{}</data><data key=\"block_number\">{}</data><data key=\"kind\">{:?}</data>",
+            edge.id().index(),
+            edge.source().index(),
+            edge.target().index(),
+            escape_xml(&transaction.hash),
+            transaction.timestamp,
+            transaction.block_number,
+            transaction.kind
+        )
+        .unwrap();
+        if let Some(data) = &transaction.data {
+            write!(xml, "<data key=\"payload\">{:?}</data><data key=\"usd_value\">{}</data>", data.payload, data.usd_value).unwrap();
+        }
+        xml.push_str("</edge>\n");
+    }
+    xml.push_str("  </graph>\n</graphml>\n");
+    xml
+}
+
+fn export(graph: &G, info: &FilteringResultInfo, format: ExportFormat, output: &str) -> Result<()> {
+    match format {
+        ExportFormat::Json => serialize_graph(graph, info, output),
+        ExportFormat::Graphml => {
+            fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+            let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, output);
+            write_atomically(&file_pathname, graphml(graph, &Annotations::load()?).as_bytes())?;
+            println!("Saved GraphML as {}", &file_pathname);
+            Ok(())
+        }
+    }
+}
+
+// Runs the steps in order on one working graph: filters replace it, analyses and exports read it. Analysis
+// reports of all steps end up in one manifest, named after the step that produced them.
+pub fn run_pipeline(pathname: &str, args: &AnalyzeArgs, passes: &PassRegistry) -> Result<()> {
+    let pipeline = load_pipeline(pathname)?;
+    validate(&pipeline, passes)?;
+    let input = pipeline.input.as_deref().unwrap_or(&args.input);
+    let prices_name = pipeline.prices.as_deref().or(args.prices.as_deref());
+    let checks = price_checks(args);
+    let price_series = prices_name.map(|name| PriceSeries::from_csv_checked(&format!("{}/{}", DATA_STORAGE_FOLDER, name), &checks)).transpose()?;
+    let prices: &dyn PriceProvider = match &price_series {
+        Some(series) => series,
+        None => &NoPrices,
+    };
+
+    let mut graph = load_analysis_input(input, args)?;
+    let (nodes_before, edges_before) = (graph.node_count(), graph.edge_count());
+    let mut reports: Vec<(String, Result<Report>)> = vec![];
+    for (i, step) in pipeline.steps.iter().enumerate() {
+        println!("Pipeline step {} / {}: {}", i + 1, pipeline.steps.len(), step.name());
+        match step {
+            Step::FilterValue { min_usd, max_usd } => {
+                graph = filter_stablecoin_transactions_by_value(&FilteredGraph::new(&graph), *min_usd, *max_usd).0.materialize().0;
+            }
+            Step::FilterPayload { payload } => {
+                graph = retain(&graph, |edge| edge.weight().data.as_ref().is_some_and(|data| data.payload == *payload));
+            }
+            Step::FilterLayer { layers } => {
+                let selected = layers.iter().map(|spec| Layer::parse(spec)).collect::<Result<Vec<Layer>>>()?;
+                graph = retain(&graph, |edge| edge_layers(edge.weight()).iter().any(|layer| selected.contains(layer)));
+            }
+            Step::TwoWay => {
+                let (filtered, pair_count) = two_way(&graph);
+                println!("Found {} two-way pairs", pair_count);
+                graph = filtered;
+            }
+            Step::Stats | Step::Analyze { .. } => {
+                let selected = match step {
+                    Step::Analyze { passes: selected } => selected.clone(),
+                    _ => vec!["stats".to_string()],
+                };
+                // Hashed per step, so reports of steps before a changed filter are still found in the cache.
+                let cache = (!args.no_cache).then(|| PassCache::new(&graph, prices_name, &checks)).transpose()?;
+                let analyses = passes.run_with_control(&graph, prices, &selected, cache.as_ref(), &TaskControl::default())?;
+                reports.extend(analyses.into_iter().map(|(name, report)| (format!("step{}_{}", i + 1, name), report)));
+            }
+            Step::Export { format, output } => {
+                let info = FilteringResultInfo {
+                    node_count_before_filtering: nodes_before,
+                    edge_count_before_filtering: edges_before,
+                    node_count: graph.node_count(),
+                    edge_count: graph.edge_count(),
+                };
+                export(&graph, &info, *format, output)?;
+            }
+        }
+        println!("Working graph has {} nodes and {} edges", graph.node_count(), graph.edge_count());
+    }
+    let manifest = pipeline.manifest.as_deref().unwrap_or(&args.manifest);
+    print!("{}", save_reports(reports, input, prices_name.zip(price_series.as_ref()), manifest)?);
+    Ok(())
+}
@@ -0,0 +1,258 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use polars::prelude::*;
+use std::fs::{self, File};
+
+use crate::annotations::{Annotation, Annotations};
+use crate::atomic_file::AtomicFile;
+use crate::cli::{FromParquetArgs, ToParquetArgs};
+use crate::features::NodeFeatures;
+use crate::{deserialize_graph, serialize_graph, DigestedData, FilteringResultInfo, Transaction, DATA_STORAGE_FOLDER, G};
+
+pub trait ToPolars {
+    fn to_polars(&self) -> PolarsResult<(DataFrame, DataFrame)>;
+}
+
+impl ToPolars for G {
+    fn to_polars(&self) -> PolarsResult<(DataFrame, DataFrame)> {
+        let node_ids: Vec<u32> = self.node_indices().map(|n| n.index() as u32).collect();
+        let addresses: Vec<&str> = self.node_indices().map(|n| self[n].as_str()).collect();
+        let nodes = df!(
+            "node_id" => node_ids,
+            "address" => addresses,
+        )?;
+
+        let edges: Vec<_> = self.edge_references().collect();
+        let data = |e: &petgraph::graph::EdgeReference<'_, Transaction>| e.weight().data.clone();
+        let edges = df!(
+            "source" => edges.iter().map(|e| e.source().index() as u32).collect::<Vec<_>>(),
+            "target" => edges.iter().map(|e| e.target().index() as u32).collect::<Vec<_>>(),
+            "hash" => edges.iter().map(|e| e.weight().hash.clone()).collect::<Vec<_>>(),
+            "timestamp" => edges.iter().map(|e| e.weight().timestamp).collect::<Vec<_>>(),
+            "gas_price" => edges.iter().map(|e| e.weight().gas_price).collect::<Vec<_>>(),
+            "gas_used" => edges.iter().map(|e| e.weight().gas_used).collect::<Vec<_>>(),
+            "transaction_type" => edges.iter().map(|e| e.weight().transaction_type as u32).collect::<Vec<_>>(),
+            "max_fee_per_gas" => edges.iter().map(|e| e.weight().max_fee_per_gas).collect::<Vec<_>>(),
+            "max_priority_fee_per_gas" => edges.iter().map(|e| e.weight().max_priority_fee_per_gas).collect::<Vec<_>>(),
+            "base_fee_per_gas" => edges.iter().map(|e| e.weight().base_fee_per_gas).collect::<Vec<_>>(),
+            "block_number" => edges.iter().map(|e| e.weight().block_number).collect::<Vec<_>>(),
+            "transaction_index" => edges.iter().map(|e| e.weight().transaction_index).collect::<Vec<_>>(),
+            "nonce" => edges.iter().map(|e| e.weight().nonce).collect::<Vec<_>>(),
+            "contract_call" => edges.iter().map(|e| e.weight().contract_call).collect::<Vec<_>>(),
+            "value_wei" => edges.iter().map(|e| e.weight().value_wei.to_string()).collect::<Vec<_>>(),
+            "kind" => edges.iter().map(|e| format!("{:?}", e.weight().kind)).collect::<Vec<_>>(),
+            "trace_index" => edges.iter().map(|e| e.weight().trace_index).collect::<Vec<_>>(),
+            "log_index" => edges.iter().map(|e| e.weight().log_index).collect::<Vec<_>>(),
+            "payload" => edges.iter().map(|e| data(e).map(|d| format!("{:?}", d.payload))).collect::<Vec<_>>(),
+            "usd_value" => edges.iter().map(|e| data(e).map(|d| d.usd_value)).collect::<Vec<_>>(),
+            "onchain_function" => edges.iter().map(|e| data(e).map(|d| format!("{:?}", d.used_onchain_function))).collect::<Vec<_>>(),
+            "value_usd" => edges.iter().map(|e| e.weight().value_usd).collect::<Vec<_>>(),
+            "fee_usd" => edges.iter().map(|e| e.weight().fee_usd).collect::<Vec<_>>(),
+            "decoded_call" => edges.iter().map(|e| e.weight().decoded_call.as_ref().map(|c| serde_json::to_string(c).unwrap())).collect::<Vec<_>>(),
+            "decoded_transfer" => edges.iter().map(|e| e.weight().decoded_transfer.as_ref().map(|t| serde_json::to_string(t).unwrap())).collect::<Vec<_>>(),
+            "full" => edges.iter().map(|e| e.weight().full.as_ref().map(|f| serde_json::to_string(f).unwrap())).collect::<Vec<_>>(),
+        )?;
+        Ok((nodes, edges))
+    }
+}
+
+// The edge columns of the first exports; files without any of them are not graph exports.
+const REQUIRED_EDGE_COLUMNS: [&str; 7] = ["source", "target", "hash", "timestamp", "payload", "usd_value", "onchain_function"];
+
+fn or_nulls<T: Clone>(series: Option<Series>, height: usize, read: impl FnOnce(&Series) -> PolarsResult<Vec<Option<T>>>) -> Result<Vec<Option<T>>> {
+    Ok(match series {
+        Some(series) => read(&series)?,
+        None => vec![None; height],
+    })
+}
+
+fn parse_unit_variant<T: serde::de::DeserializeOwned>(name: &str) -> Result<T> {
+    Ok(serde_json::from_value(serde_json::Value::String(name.to_string()))?)
+}
+
+pub fn graph_from_polars(nodes: &DataFrame, edges: &DataFrame) -> Result<G> {
+    let node_ids = nodes.column("node_id")?.as_materialized_series().cast(&DataType::UInt32)?;
+    let addresses = nodes.column("address")?.as_materialized_series().str()?.clone();
+
+    // Node ids are positional in the serialized format, so sort them before insertion.
+    let mut id_and_address: Vec<(u32, String)> = node_ids
+        .u32()?
+        .into_iter()
+        .zip(&addresses)
+        .map(|(id, address)| match (id, address) {
+            (Some(id), Some(address)) => Ok((id, address.to_string())),
+            _ => Err(eyre::eyre!("Null node_id or address in nodes DataFrame")),
+        })
+        .collect::<Result<_>>()?;
+    id_and_address.sort_by_key(|(id, _)| *id);
+
+    let mut graph: G = G::with_capacity(id_and_address.len(), edges.height());
+    for (expected_id, (id, address)) in id_and_address.into_iter().enumerate() {
+        if id as usize != expected_id {
+            return Err(eyre::eyre!("Node ids must be contiguous, missing id {}", expected_id));
+        }
+        graph.add_node(address);
+    }
+
+    // Every other column was added after the first exports and is read as all null from files without it.
+    let column = |name: &str, data_type: DataType| -> Result<Option<Series>> {
+        if !REQUIRED_EDGE_COLUMNS.contains(&name) && !edges.schema().contains(name) {
+            return Ok(None);
+        }
+        Ok(Some(edges.column(name)?.as_materialized_series().cast(&data_type)?))
+    };
+    let height = edges.height();
+    let u32_values = |name: &str| or_nulls(column(name, DataType::UInt32)?, height, |series| Ok(series.u32()?.into_iter().collect()));
+    let u64_values = |name: &str| or_nulls(column(name, DataType::UInt64)?, height, |series| Ok(series.u64()?.into_iter().collect()));
+    let f64_values = |name: &str| or_nulls(column(name, DataType::Float64)?, height, |series| Ok(series.f64()?.into_iter().collect()));
+    let bool_values = |name: &str| or_nulls(column(name, DataType::Boolean)?, height, |series| Ok(series.bool()?.into_iter().collect()));
+    let string_values =
+        |name: &str| or_nulls(column(name, DataType::String)?, height, |series| Ok(series.str()?.into_iter().map(|v| v.map(str::to_string)).collect()));
+
+    let sources = u32_values("source")?;
+    let targets = u32_values("target")?;
+    let hashes = string_values("hash")?;
+    let timestamps = u64_values("timestamp")?;
+    let gas_prices = u64_values("gas_price")?;
+    let gas_used = u64_values("gas_used")?;
+    let transaction_types = u64_values("transaction_type")?;
+    let max_fees = u64_values("max_fee_per_gas")?;
+    let max_priority_fees = u64_values("max_priority_fee_per_gas")?;
+    let base_fees = u64_values("base_fee_per_gas")?;
+    let block_numbers = u64_values("block_number")?;
+    let transaction_indices = u64_values("transaction_index")?;
+    let nonces = u64_values("nonce")?;
+    let contract_calls = bool_values("contract_call")?;
+    let values_wei = string_values("value_wei")?;
+    let kinds = string_values("kind")?;
+    let trace_indices = u64_values("trace_index")?;
+    let log_indices = u64_values("log_index")?;
+    let payloads = string_values("payload")?;
+    let usd_values = f64_values("usd_value")?;
+    let functions = string_values("onchain_function")?;
+    let values_usd = f64_values("value_usd")?;
+    let fees_usd = f64_values("fee_usd")?;
+    let decoded_calls = string_values("decoded_call")?;
+    let decoded_transfers = string_values("decoded_transfer")?;
+    let full = string_values("full")?;
+
+    for row in 0..edges.height() {
+        let (Some(source), Some(target)) = (sources[row], targets[row]) else {
+            return Err(eyre::eyre!("Null source or target in edges DataFrame"));
+        };
+        if source as usize >= graph.node_count() || target as usize >= graph.node_count() {
+            return Err(eyre::eyre!("Edge {} -> {} references an unknown node", source, target));
+        }
+        let data = match (&payloads[row], usd_values[row], &functions[row]) {
+            (Some(payload), Some(usd_value), Some(function)) => Some(DigestedData {
+                payload: parse_unit_variant(payload)?,
+                usd_value,
+                used_onchain_function: parse_unit_variant(function)?,
+            }),
+            _ => None,
+        };
+        let transaction = Transaction {
+            hash: hashes[row].clone().unwrap_or_default(),
+            timestamp: timestamps[row].unwrap_or_default(),
+            gas_price: gas_prices[row].unwrap_or_default(),
+            gas_used: gas_used[row].unwrap_or_default(),
+            transaction_type: transaction_types[row].map(u8::try_from).transpose()?.unwrap_or_default(),
+            max_fee_per_gas: max_fees[row].unwrap_or_default(),
+            max_priority_fee_per_gas: max_priority_fees[row].unwrap_or_default(),
+            base_fee_per_gas: base_fees[row].unwrap_or_default(),
+            block_number: block_numbers[row].unwrap_or_default(),
+            transaction_index: transaction_indices[row].unwrap_or_default(),
+            nonce: nonces[row].unwrap_or_default(),
+            contract_call: contract_calls[row].unwrap_or_default(),
+            value_wei: values_wei[row].as_deref().map(str::parse).transpose()?.unwrap_or_default(),
+            kind: kinds[row].as_deref().map(parse_unit_variant).transpose()?.unwrap_or_default(),
+            trace_index: trace_indices[row],
+            log_index: log_indices[row],
+            data,
+            value_usd: values_usd[row],
+            fee_usd: fees_usd[row],
+            decoded_call: decoded_calls[row].as_deref().map(serde_json::from_str).transpose()?,
+            decoded_transfer: decoded_transfers[row].as_deref().map(serde_json::from_str).transpose()?,
+            full: full[row].as_deref().map(serde_json::from_str).transpose()?,
+        };
+        graph.add_edge(NodeIndex::new(source as usize), NodeIndex::new(target as usize), transaction);
+    }
+    Ok(graph)
+}
+
+// The columns of node_features.csv, one row per address.
+pub fn features_to_polars(features: &[NodeFeatures]) -> PolarsResult<DataFrame> {
+    df!(
+        "address" => features.iter().map(|f| f.address.as_str()).collect::<Vec<_>>(),
+        "in_degree" => features.iter().map(|f| f.in_degree as u64).collect::<Vec<_>>(),
+        "out_degree" => features.iter().map(|f| f.out_degree as u64).collect::<Vec<_>>(),
+        "unique_in_counterparties" => features.iter().map(|f| f.unique_in_counterparties as u64).collect::<Vec<_>>(),
+        "unique_out_counterparties" => features.iter().map(|f| f.unique_out_counterparties as u64).collect::<Vec<_>>(),
+        "total_in_usd" => features.iter().map(|f| f.total_in_usd).collect::<Vec<_>>(),
+        "total_out_usd" => features.iter().map(|f| f.total_out_usd).collect::<Vec<_>>(),
+        "mean_transfer_usd" => features.iter().map(|f| f.mean_transfer_usd).collect::<Vec<_>>(),
+        "max_transfer_usd" => features.iter().map(|f| f.max_transfer_usd).collect::<Vec<_>>(),
+        "std_transfer_usd" => features.iter().map(|f| f.std_transfer_usd).collect::<Vec<_>>(),
+        "first_seen" => features.iter().map(|f| f.first_seen).collect::<Vec<_>>(),
+        "last_seen" => features.iter().map(|f| f.last_seen).collect::<Vec<_>>(),
+        "activity_span_seconds" => features.iter().map(|f| f.activity_span_seconds).collect::<Vec<_>>(),
+        "burstiness" => features.iter().map(|f| f.burstiness).collect::<Vec<_>>(),
+        "counterparty_diversity" => features.iter().map(|f| f.counterparty_diversity).collect::<Vec<_>>(),
+        "out_volume_entropy_bits" => features.iter().map(|f| f.out_volume_entropy_bits).collect::<Vec<_>>(),
+        "out_volume_entropy_normalized" => features.iter().map(|f| f.out_volume_entropy_normalized).collect::<Vec<_>>(),
+        "round_number_fraction" => features.iter().map(|f| f.round_number_fraction).collect::<Vec<_>>(),
+        "night_day_ratio" => features.iter().map(|f| f.night_day_ratio).collect::<Vec<_>>(),
+    )
+}
+
+pub fn write_features_parquet(features: &[NodeFeatures], file_pathname: &str) -> Result<()> {
+    let mut features = features_to_polars(features)?;
+    let mut file = AtomicFile::create(file_pathname)?;
+    ParquetWriter::new(&mut file).finish(&mut features)?;
+    file.commit()?;
+    Ok(())
+}
+
+fn parquet_pathnames(prefix: &str) -> (String, String) {
+    (
+        format!("{}/{}_nodes.parquet", DATA_STORAGE_FOLDER, prefix),
+        format!("{}/{}_edges.parquet", DATA_STORAGE_FOLDER, prefix),
+    )
+}
+
+pub fn run_to_parquet_command(args: &ToParquetArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let (mut nodes, mut edges) = graph.to_polars()?;
+    // Analyst annotations ride along as columns, empty for addresses without one.
+    let annotations = Annotations::load()?;
+    let annotation = |n: NodeIndex| annotations.get(&graph[n]);
+    nodes.with_column(Column::new("tags".into(), graph.node_indices().map(|n| annotation(n).map(Annotation::tag_list).unwrap_or_default()).collect::<Vec<String>>()))?;
+    nodes.with_column(Column::new("notes".into(), graph.node_indices().map(|n| annotation(n).map(Annotation::note_list).unwrap_or_default()).collect::<Vec<String>>()))?;
+    println!("{}\n{}", nodes.head(Some(5)), edges.head(Some(5)));
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let (nodes_pathname, edges_pathname) = parquet_pathnames(&args.prefix);
+    let (mut nodes_file, mut edges_file) = (AtomicFile::create(&nodes_pathname)?, AtomicFile::create(&edges_pathname)?);
+    ParquetWriter::new(&mut nodes_file).finish(&mut nodes)?;
+    ParquetWriter::new(&mut edges_file).finish(&mut edges)?;
+    nodes_file.commit()?;
+    edges_file.commit()?;
+    println!("Saved {} and {}", &nodes_pathname, &edges_pathname);
+    Ok(())
+}
+
+pub fn run_from_parquet_command(args: &FromParquetArgs) -> Result<()> {
+    let (nodes_pathname, edges_pathname) = parquet_pathnames(&args.prefix);
+    let nodes = ParquetReader::new(File::open(&nodes_pathname)?).finish()?;
+    let edges = ParquetReader::new(File::open(&edges_pathname)?).finish()?;
+    let graph = graph_from_polars(&nodes, &edges)?;
+
+    let info = FilteringResultInfo {
+        node_count: graph.raw_nodes().len(),
+        edge_count: graph.raw_edges().len(),
+        node_count_before_filtering: graph.raw_nodes().len(),
+        edge_count_before_filtering: graph.raw_edges().len(),
+    };
+    serialize_graph(&graph, &info, &args.output)
+}
@@ -0,0 +1,88 @@
+use eyre::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::PriceCorrelationArgs;
+use crate::fees::pearson_correlation;
+use crate::pricing::{PriceCache, PriceSeries};
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+const SECONDS_PER_HOUR: u64 = 3_600;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LagCorrelation {
+    pub lag_hours: i64,
+    pub correlation: Option<f64>,
+    pub samples: usize,
+}
+
+// Parsed USD volume for every hour between the first and last transfer, zero for hours without transfers.
+pub fn hourly_volume(graph: &G) -> Vec<(u64, f64)> {
+    let mut volumes: BTreeMap<u64, f64> = BTreeMap::new();
+    for transaction in graph.edge_weights().filter(|t| t.timestamp != 0) {
+        if let Some(data) = &transaction.data {
+            *volumes.entry(transaction.timestamp / SECONDS_PER_HOUR * SECONDS_PER_HOUR).or_default() += data.usd_value;
+        }
+    }
+    let (Some(&first), Some(&last)) = (volumes.keys().next(), volumes.keys().next_back()) else {
+        return vec![];
+    };
+    (first..=last).step_by(SECONDS_PER_HOUR as usize).map(|hour| (hour, volumes.get(&hour).copied().unwrap_or(0.0))).collect()
+}
+
+// A positive lag pairs the volume of each hour with the price `lag` hours later, so a strong positive
+// lag means volume leads the price.
+pub fn lagged_cross_correlation(volume: &[(u64, f64)], prices: &mut PriceCache, symbol: &str, max_lag_hours: i64) -> Vec<LagCorrelation> {
+    (-max_lag_hours..=max_lag_hours)
+        .map(|lag_hours| {
+            let (volumes, lagged_prices): (Vec<f64>, Vec<f64>) = volume
+                .iter()
+                .filter_map(|&(hour, usd_volume)| {
+                    let price_hour = hour.checked_add_signed(lag_hours * SECONDS_PER_HOUR as i64)?;
+                    Some((usd_volume, prices.usd_price(symbol, price_hour)?))
+                })
+                .unzip();
+            LagCorrelation { lag_hours, correlation: pearson_correlation(&volumes, &lagged_prices), samples: volumes.len() }
+        })
+        .collect()
+}
+
+pub fn run_price_correlation_command(args: &PriceCorrelationArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let price_series = PriceSeries::from_csv(&format!("{}/{}", DATA_STORAGE_FOLDER, args.prices))?;
+    let mut prices = PriceCache::new(&price_series);
+    let volume = hourly_volume(&graph);
+    let correlations = lagged_cross_correlation(&volume, &mut prices, &args.symbol, args.max_lag_hours);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for correlation in correlations.iter() {
+        writer.serialize(correlation)?;
+    }
+    commit_csv(writer)?;
+
+    let strongest = correlations
+        .iter()
+        .filter_map(|c| Some((c, c.correlation?)))
+        .max_by(|a, b| a.1.abs().partial_cmp(&b.1.abs()).unwrap());
+    match strongest {
+        Some((c, r)) => println!(
+            "Strongest correlation of hourly parsed volume with {} price: {:.3} at lag {} h ({} hours, {})",
+            args.symbol,
+            r,
+            c.lag_hours,
+            c.samples,
+            match c.lag_hours {
+                0 => "simultaneous",
+                lag if lag > 0 => "volume leads price",
+                _ => "price leads volume",
+            }
+        ),
+        None => println!("Correlation with {} price is undefined, the series do not overlap or are constant", args.symbol),
+    }
+    println!("Saved {} lagged correlations over {} hours as {}", correlations.len(), volume.len(), &file_pathname);
+    Ok(())
+}
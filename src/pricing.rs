@@ -0,0 +1,204 @@
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Mutex;
+
+const SECONDS_PER_HOUR: u64 = 3_600;
+
+pub trait PriceProvider {
+    // USD price of one unit of `symbol` during the hour starting at `hour_start`.
+    fn hourly_usd_price(&self, symbol: &str, hour_start: u64) -> Option<f64>;
+}
+
+#[derive(Deserialize)]
+struct PriceRecord {
+    timestamp: u64,
+    symbol: String,
+    usd_price: f64,
+}
+
+// What loading a price series checks for. Gaps and jumps are only reported, since a quiet market or a
+// crash look the same in the data; negative, zero and non-numeric prices are dropped.
+#[derive(Debug, Clone, Copy)]
+pub struct PriceChecks {
+    pub max_gap_hours: u64,
+    // Consecutive points further apart than this factor, either way, are an implausible jump.
+    pub max_jump_factor: f64,
+    // Replace a single point that jumps away and straight back by the mean of its neighbours.
+    pub smooth_outliers: bool,
+}
+
+impl Default for PriceChecks {
+    fn default() -> PriceChecks {
+        PriceChecks { max_gap_hours: 24, max_jump_factor: 3.0, smooth_outliers: false }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PricePoint {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub usd_price: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PriceGap {
+    pub symbol: String,
+    pub from: u64,
+    pub to: u64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PriceJump {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub previous_usd_price: f64,
+    pub usd_price: f64,
+    // Set when the point was replaced, to the price used instead.
+    pub smoothed_to: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct PriceCheckReport {
+    pub rejected: Vec<PricePoint>,
+    // Rows for a symbol and timestamp already seen; the last one wins.
+    pub duplicates: usize,
+    pub gaps: Vec<PriceGap>,
+    pub jumps: Vec<PriceJump>,
+}
+
+impl PriceCheckReport {
+    pub fn describe(&self) -> String {
+        let smoothed = self.jumps.iter().filter(|jump| jump.smoothed_to.is_some()).count();
+        format!(
+            "Price checks: {} points rejected, {} duplicates, {} gaps, {} implausible jumps ({} smoothed)",
+            self.rejected.len(),
+            self.duplicates,
+            self.gaps.len(),
+            self.jumps.len(),
+            smoothed
+        )
+    }
+}
+
+// The price points an analysis actually read, for the manifest.
+#[derive(Serialize, Debug, Clone)]
+pub struct PriceAudit {
+    pub checks: PriceCheckReport,
+    pub used_points: Vec<PricePoint>,
+}
+
+fn is_jump(previous: f64, price: f64, max_jump_factor: f64) -> bool {
+    price.max(previous) / price.min(previous) > max_jump_factor
+}
+
+fn check_series(symbol: &str, series: &mut BTreeMap<u64, f64>, checks: &PriceChecks, report: &mut PriceCheckReport) {
+    let points: Vec<(u64, f64)> = series.iter().map(|(&timestamp, &price)| (timestamp, price)).collect();
+    // The way back from a lone outlier is part of the same anomaly rather than a jump of its own.
+    let mut after_outlier = false;
+    for i in 1..points.len() {
+        let ((previous_timestamp, previous), (timestamp, price)) = (points[i - 1], points[i]);
+        if timestamp - previous_timestamp > checks.max_gap_hours * SECONDS_PER_HOUR {
+            report.gaps.push(PriceGap { symbol: symbol.to_string(), from: previous_timestamp, to: timestamp });
+        }
+        if std::mem::take(&mut after_outlier) || !is_jump(previous, price, checks.max_jump_factor) {
+            continue;
+        }
+        // A lone outlier jumps away from its predecessor and its successor, which agree with each other.
+        let next = points.get(i + 1).map(|&(_, next)| next);
+        after_outlier = next.is_some_and(|next| is_jump(price, next, checks.max_jump_factor) && !is_jump(previous, next, checks.max_jump_factor));
+        let smoothed_to = (checks.smooth_outliers && after_outlier).then(|| (previous + next.unwrap()) / 2.0);
+        if let Some(smoothed) = smoothed_to {
+            series.insert(timestamp, smoothed);
+        }
+        report.jumps.push(PriceJump { symbol: symbol.to_string(), timestamp, previous_usd_price: previous, usd_price: price, smoothed_to });
+    }
+}
+
+// Price points loaded from a CSV with timestamp,symbol,usd_price columns.
+pub struct PriceSeries {
+    prices: HashMap<String, BTreeMap<u64, f64>>,
+    checks: PriceCheckReport,
+    // Symbol and timestamp of every point a lookup returned.
+    used: Mutex<BTreeSet<(String, u64)>>,
+}
+
+impl PriceSeries {
+    pub fn from_csv(file_pathname: &str) -> Result<PriceSeries> {
+        PriceSeries::from_csv_checked(file_pathname, &PriceChecks::default())
+    }
+
+    pub fn from_csv_checked(file_pathname: &str, checks: &PriceChecks) -> Result<PriceSeries> {
+        let mut prices: HashMap<String, BTreeMap<u64, f64>> = HashMap::new();
+        let mut report = PriceCheckReport::default();
+        for record in csv::Reader::from_path(file_pathname)?.deserialize() {
+            let record: PriceRecord = record?;
+            let symbol = record.symbol.to_uppercase();
+            if !(record.usd_price.is_finite() && record.usd_price > 0.0) {
+                report.rejected.push(PricePoint { symbol, timestamp: record.timestamp, usd_price: record.usd_price });
+                continue;
+            }
+            if prices.entry(symbol).or_default().insert(record.timestamp, record.usd_price).is_some() {
+                report.duplicates += 1;
+            }
+        }
+        let mut symbols: Vec<String> = prices.keys().cloned().collect();
+        symbols.sort();
+        for symbol in symbols.iter() {
+            check_series(symbol, prices.get_mut(symbol).unwrap(), checks, &mut report);
+        }
+        println!("Loaded prices for {} symbols from {}", prices.len(), file_pathname);
+        println!("{}", report.describe());
+        Ok(PriceSeries { prices, checks: report, used: Mutex::new(BTreeSet::new()) })
+    }
+
+    pub fn audit(&self) -> PriceAudit {
+        let used_points = self
+            .used
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(symbol, timestamp)| PricePoint { symbol: symbol.clone(), timestamp: *timestamp, usd_price: self.prices[symbol][timestamp] })
+            .collect();
+        PriceAudit { checks: self.checks.clone(), used_points }
+    }
+}
+
+impl PriceProvider for PriceSeries {
+    // The latest price point within or before the hour is used.
+    fn hourly_usd_price(&self, symbol: &str, hour_start: u64) -> Option<f64> {
+        let symbol = symbol.to_uppercase();
+        let (&timestamp, &price) = self.prices.get(&symbol)?.range(..hour_start + SECONDS_PER_HOUR).next_back()?;
+        self.used.lock().unwrap().insert((symbol, timestamp));
+        Some(price)
+    }
+}
+
+// Per-analysis memo in front of a provider, so each symbol-hour is looked up once.
+pub struct PriceCache<'a> {
+    provider: &'a dyn PriceProvider,
+    memo: HashMap<(String, u64), Option<f64>>,
+}
+
+impl<'a> PriceCache<'a> {
+    pub fn new(provider: &'a dyn PriceProvider) -> PriceCache<'a> {
+        PriceCache { provider, memo: HashMap::new() }
+    }
+
+    pub fn usd_price(&mut self, symbol: &str, timestamp: u64) -> Option<f64> {
+        let hour_start = timestamp / SECONDS_PER_HOUR * SECONDS_PER_HOUR;
+        *self
+            .memo
+            .entry((symbol.to_string(), hour_start))
+            .or_insert_with(|| self.provider.hourly_usd_price(symbol, hour_start))
+    }
+
+    // Converts (timestamp, amount) pairs of one symbol; None where no price is known.
+    pub fn convert_batch(&mut self, symbol: &str, amounts: impl IntoIterator<Item = (u64, f64)>) -> Vec<Option<f64>> {
+        amounts.into_iter().map(|(timestamp, amount)| self.usd_price(symbol, timestamp).map(|price| price * amount)).collect()
+    }
+
+    pub fn resolved_hours(&self) -> usize {
+        self.memo.len()
+    }
+}
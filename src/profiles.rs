@@ -0,0 +1,86 @@
+use clap::{CommandFactory, FromArgMatches, ValueEnum};
+use std::ffi::OsString;
+
+use crate::cli::Cli;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Profile {
+    /// A first look: two-pass discovery one hop out, the 100 most relevant histories in the lean schema,
+    /// graph statistics only
+    QuickScan,
+    /// Everything about a case: full edges, account abstraction calls, funding edges and flagged instead of
+    /// skipped oversized histories, every analysis pass
+    DeepTrace,
+    /// Watching deposit flows: exchange-sized histories stay leaves, approval and entity reports, daily
+    /// daemon summaries every 15 minutes
+    ExchangeMonitor,
+}
+
+impl Profile {
+    // Options the profile puts in front of those given to the subcommand. Options given on the command line
+    // override the profile's, repeatable ones add to them.
+    pub fn arguments(self, subcommand: &str) -> &'static [&'static str] {
+        match (self, subcommand) {
+            (Profile::QuickScan, "crawl") => &[
+                "--two-pass",
+                "--discovery-depth",
+                "1",
+                "--discovery-offset",
+                "50",
+                "--backfill-addresses",
+                "100",
+                "--edge-schema",
+                "lean",
+                "--pass",
+                "stats",
+            ],
+            (Profile::QuickScan, "analyze") => &["--pass", "stats"],
+            (Profile::DeepTrace, "crawl") => &[
+                "--edge-schema",
+                "full",
+                "--user-operations",
+                "--funded-by-edges",
+                "--max-sent-transactions",
+                "100000",
+                "--oversized-action",
+                "flag",
+            ],
+            (Profile::ExchangeMonitor, "crawl") => &[
+                "--max-sent-transactions",
+                "20000",
+                "--oversized-action",
+                "skip",
+                "--pass",
+                "stats",
+                "--pass",
+                "approvals",
+                "--pass",
+                "entities",
+            ],
+            (Profile::ExchangeMonitor, "analyze") => &["--pass", "stats", "--pass", "approvals", "--pass", "entities", "--pass", "near_duplicates"],
+            (Profile::ExchangeMonitor, "daemon") => &["--period", "day", "--interval-minutes", "15"],
+            _ => &[],
+        }
+    }
+}
+
+// Parses the command line, then again with the options of the selected profile inserted right after the
+// subcommand, which is crawl when none is given.
+pub fn parse_cli(mut args: Vec<OsString>) -> Cli {
+    let matches = Cli::command().get_matches_from(&args);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let Some(profile) = cli.profile else { return cli };
+    let subcommand = matches.subcommand_name().unwrap_or("crawl").to_string();
+    let position = match matches.subcommand_name() {
+        Some(name) => args.iter().skip(1).position(|arg| arg == name).unwrap() + 2,
+        None => {
+            args.push(OsString::from("crawl"));
+            args.len()
+        }
+    };
+    let arguments = profile.arguments(&subcommand);
+    println!("Profile {} adds to {}: {}", profile.to_possible_value().unwrap().get_name(), subcommand, arguments.join(" "));
+    args.splice(position..position, arguments.iter().map(OsString::from));
+    let matches = Cli::command().mut_subcommand(&subcommand, |command| command.args_override_self(true)).get_matches_from(&args);
+    Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+}
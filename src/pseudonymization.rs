@@ -0,0 +1,112 @@
+use eyre::Result;
+use rand::distributions::{Alphanumeric, DistString};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use crate::abi_decoding::DecodedCall;
+use crate::cli::{PseudonymScheme, PseudonymizeArgs};
+use crate::{deserialize_graph, serialize_graph, Transaction, G};
+
+fn salted_hash_pseudonym(address: &str, salt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(address.to_lowercase().as_bytes());
+    let digest = hasher.finalize();
+    let hex: String = digest[..10].iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("anon_{}", hex)
+}
+
+// Addresses inside decoded payloads, which include token contracts and swap paths that need not be nodes.
+fn decoded_addresses(transaction: &Transaction) -> Vec<&String> {
+    let mut addresses = Vec::new();
+    match &transaction.decoded_call {
+        Some(DecodedCall::Transfer { to, .. }) => addresses.push(to),
+        Some(DecodedCall::TransferFrom { from, to, .. }) => addresses.extend([from, to]),
+        Some(DecodedCall::Approve { spender, .. } | DecodedCall::IncreaseAllowance { spender, .. }) => addresses.push(spender),
+        Some(DecodedCall::Swap { path, to, .. }) => addresses.extend(path.iter().chain([to])),
+        None => {}
+    }
+    if let Some(transfer) = &transaction.decoded_transfer {
+        addresses.extend([&transfer.from, &transfer.to, &transfer.token]);
+    }
+    addresses
+}
+
+// Keyed by lowercase address, so an address spelled with another case in a decoded payload gets the same pseudonym.
+fn pseudonym_mapping(graph: &G, scheme: PseudonymScheme, salt: &str) -> HashMap<String, String> {
+    let mut addresses: Vec<String> = graph
+        .raw_nodes()
+        .iter()
+        .map(|node| &node.weight)
+        .chain(graph.raw_edges().iter().flat_map(|edge| decoded_addresses(&edge.weight)))
+        .map(|address| address.to_lowercase())
+        .collect();
+    addresses.sort();
+    addresses.dedup();
+    match scheme {
+        PseudonymScheme::SaltedHash => addresses
+            .into_iter()
+            .map(|address| {
+                let pseudonym = salted_hash_pseudonym(&address, salt);
+                (address, pseudonym)
+            })
+            .collect(),
+        // Ids follow sorted address order, so the crawl order (and thus the starting address) is not revealed.
+        PseudonymScheme::Sequential => addresses.into_iter().enumerate().map(|(i, address)| (address, format!("addr_{}", i))).collect(),
+    }
+}
+
+fn pseudonym(mapping: &HashMap<String, String>, address: &str) -> String {
+    mapping[&address.to_lowercase()].clone()
+}
+
+fn pseudonymize_decoded_call(call: &DecodedCall, mapping: &HashMap<String, String>) -> DecodedCall {
+    let mut call = call.clone();
+    match &mut call {
+        DecodedCall::Transfer { to, .. } => *to = pseudonym(mapping, to),
+        DecodedCall::TransferFrom { from, to, .. } => (*from, *to) = (pseudonym(mapping, from), pseudonym(mapping, to)),
+        DecodedCall::Approve { spender, .. } | DecodedCall::IncreaseAllowance { spender, .. } => *spender = pseudonym(mapping, spender),
+        DecodedCall::Swap { path, to, .. } => {
+            path.iter_mut().for_each(|token| *token = pseudonym(mapping, token));
+            *to = pseudonym(mapping, to);
+        }
+    }
+    call
+}
+
+// Besides the addresses, drops what pins an edge to one on-chain transaction: the hash, the raw API fields with
+// calldata and block hash, and the block position and nonce, which together name the transaction as well.
+pub fn pseudonymize_graph(graph: &G, scheme: PseudonymScheme, salt: &str) -> G {
+    let mapping = pseudonym_mapping(graph, scheme, salt);
+    graph.map(
+        |_node_index, address| pseudonym(&mapping, address),
+        |_edge_index, transaction| {
+            let mut stripped_transaction = transaction.clone();
+            stripped_transaction.hash = String::new();
+            stripped_transaction.full = None;
+            stripped_transaction.block_number = 0;
+            stripped_transaction.transaction_index = 0;
+            stripped_transaction.nonce = 0;
+            stripped_transaction.decoded_call = transaction.decoded_call.as_ref().map(|call| pseudonymize_decoded_call(call, &mapping));
+            if let Some(transfer) = &mut stripped_transaction.decoded_transfer {
+                transfer.from = pseudonym(&mapping, &transfer.from);
+                transfer.to = pseudonym(&mapping, &transfer.to);
+                transfer.token = pseudonym(&mapping, &transfer.token);
+            }
+            stripped_transaction
+        },
+    )
+}
+
+pub fn run_pseudonymize_command(args: &PseudonymizeArgs) -> Result<()> {
+    let (graph, info) = deserialize_graph(&args.input)?;
+    let salt = args
+        .salt
+        .clone()
+        .unwrap_or_else(|| Alphanumeric.sample_string(&mut rand::thread_rng(), 32)); // Not stored anywhere on purpose
+
+    let pseudonymized_graph = pseudonymize_graph(&graph, args.scheme, &salt);
+    serialize_graph(&pseudonymized_graph, &info, &args.output)?;
+    println!("Pseudonymized {} addresses with {:?} scheme", pseudonymized_graph.node_count(), args.scheme);
+    Ok(())
+}
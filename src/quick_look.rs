@@ -0,0 +1,66 @@
+use eyre::Result;
+use priority_queue::PriorityQueue;
+use std::fs;
+use std::time::Instant;
+use tokio::runtime::Runtime;
+
+use crate::address_report::{address_report, summary_text};
+use crate::annotations::Annotations;
+use crate::cli::QuickArgs;
+use crate::crawl_events::CrawlSinks;
+use crate::delta_crawl::CrawlState;
+use crate::graph_builder::GraphBuilder;
+use crate::graph_query::load_labels;
+use crate::history_window::HistoryWindow;
+use crate::pagination::get_address_sample;
+use crate::throttle::Throttle;
+use crate::time_buckets::parse_timezone;
+use crate::{http_client, insert_address_history, read_api_key, serialize_graph, AddressPriority, FilteringResultInfo, DATA_STORAGE_FOLDER};
+
+// A single request for the newest transactions of one address, ingested as the crawl would into a one-hop
+// graph. No crawl state, request log or token lookups, so it answers in about the time of that request.
+pub fn run_quick_command(args: &QuickArgs) -> Result<()> {
+    let started = Instant::now();
+    let tz = parse_timezone(&args.timezone)?;
+    let labels = args.labels.as_ref().map(|name| load_labels(name)).transpose()?.unwrap_or_default();
+    let annotations = Annotations::load()?;
+    let address = args.address.to_lowercase();
+
+    let api_key = read_api_key()?;
+    let client = http_client::client();
+    let throttle = Throttle::new(1);
+    let history = Runtime::new()?.block_on(get_address_sample(&address, &HistoryWindow::default(), args.recent, &client, &api_key, &throttle));
+    if let Some(failure) = &history.failure {
+        return Err(eyre::eyre!("Could not fetch the transactions of {}: {}", address, failure));
+    }
+    if history.transactions.is_empty() {
+        return Err(eyre::eyre!("No transactions found for {}", address));
+    }
+
+    let mut graph_builder = GraphBuilder::with_capacity(history.transactions.len(), history.transactions.len());
+    let mut unused_priority_pq: PriorityQueue<String, AddressPriority> = PriorityQueue::new();
+    let ingest = CrawlState::default().ingest_options();
+    insert_address_history(&mut unused_priority_pq, &mut graph_builder, address.clone(), &history, 0, ingest, &mut CrawlSinks::default());
+    let graph = graph_builder.into_graph();
+    let report = address_report(&graph, &address, &labels, &annotations, &tz)?;
+    println!();
+    print!("{}", summary_text(&report, &labels, &annotations, &tz)?);
+    println!(
+        "\nFrom the {} newest transactions{}, in {:.1} s",
+        history.transactions.len(),
+        if history.transactions.len() >= args.recent { ", older ones are not included" } else { "" },
+        started.elapsed().as_secs_f64()
+    );
+
+    if let Some(output) = &args.output {
+        fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+        let info = FilteringResultInfo {
+            node_count: graph.node_count(),
+            edge_count: graph.edge_count(),
+            node_count_before_filtering: graph.node_count(),
+            edge_count_before_filtering: graph.edge_count(),
+        };
+        serialize_graph(&graph, &info, output)?;
+    }
+    Ok(())
+}
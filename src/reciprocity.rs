@@ -0,0 +1,249 @@
+use eyre::Result;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+
+use crate::money::Amount;
+use crate::atomic_file::{commit_csv, csv_writer, write_atomically};
+use crate::cli::ReciprocityArgs;
+use crate::entity_clustering::{probable_entities, ProbableEntity};
+use crate::fees::percentile;
+use crate::graph_builder::GraphBuilder;
+use crate::graph_query::load_labels;
+use crate::time_buckets::local_label;
+use crate::{deserialize_graph, edges_by_pair, Transaction, DATA_STORAGE_FOLDER, G};
+
+const HOUR_SECONDS: u64 = 3_600;
+const DAY_SECONDS: u64 = 86_400;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReciprocalPair {
+    pub address_a: String,
+    pub address_b: String,
+    pub a_to_b_usd: f64,
+    pub b_to_a_usd: f64,
+    pub a_to_b_count: usize,
+    pub b_to_a_count: usize,
+    pub round_trip_ratio: f64,
+    pub round_trip_usd: f64,
+    // What a sent to b minus what b sent back.
+    pub net_usd: f64,
+    pub min_lag_seconds: Option<u64>,
+    pub p25_lag_seconds: Option<u64>,
+    pub median_lag_seconds: Option<u64>,
+    pub p75_lag_seconds: Option<u64>,
+    // Transfers, in either direction, answered by an opposite transfer within the hour and within the day.
+    // Most of a pair's transfers coming back this fast marks a pass-through account.
+    pub returned_within_1h: usize,
+    pub returned_within_24h: usize,
+}
+
+impl ReciprocalPair {
+    pub fn transfers(&self) -> usize {
+        self.a_to_b_count + self.b_to_a_count
+    }
+
+    // Most transfers answered within a day.
+    pub fn is_pass_through(&self) -> bool {
+        self.returned_within_24h * 2 > self.transfers()
+    }
+}
+
+fn directed_transfers(graph: &G, edge_indices: &[EdgeIndex]) -> Vec<(u64, f64)> {
+    edge_indices
+        .iter()
+        .filter_map(|&e| Some((graph[e].timestamp, graph[e].data.as_ref()?.usd_value)))
+        .filter(|&(_, usd_value)| usd_value > 0.0)
+        .collect()
+}
+
+// Lag from each transfer to the first later transfer in the opposite direction.
+fn opposing_lags(forward: &[(u64, f64)], backward: &[(u64, f64)]) -> Vec<u64> {
+    let mut events: Vec<(u64, bool)> = forward
+        .iter()
+        .map(|t| (t.0, true))
+        .chain(backward.iter().map(|t| (t.0, false)))
+        .filter(|e| e.0 != 0)
+        .collect();
+    events.sort_unstable();
+    let mut lags = vec![];
+    for (i, &(timestamp, direction)) in events.iter().enumerate() {
+        if let Some(&(reply, _)) = events[i + 1..].iter().find(|e| e.1 != direction) {
+            lags.push(reply - timestamp);
+        }
+    }
+    lags
+}
+
+pub fn reciprocal_pairs(graph: &G) -> Vec<ReciprocalPair> {
+    let pairs = edges_by_pair(graph);
+    let mut reciprocal = vec![];
+    for (&(a, b), a_to_b_edges) in pairs.iter() {
+        // Each unordered pair is visited once, from its lower index; self-transfers are not round trips.
+        if a.index() >= b.index() {
+            continue;
+        }
+        let Some(b_to_a_edges) = pairs.get(&(b, a)) else {
+            continue;
+        };
+        let (forward, backward) = (directed_transfers(graph, a_to_b_edges), directed_transfers(graph, b_to_a_edges));
+        let a_to_b_usd = forward.iter().fold(0.0, |acc, t| acc + t.1);
+        let b_to_a_usd = backward.iter().fold(0.0, |acc, t| acc + t.1);
+        if a_to_b_usd == 0.0 || b_to_a_usd == 0.0 {
+            continue;
+        }
+        let mut lags: Vec<f64> = opposing_lags(&forward, &backward).into_iter().map(|lag| lag as f64).collect();
+        lags.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        reciprocal.push(ReciprocalPair {
+            address_a: graph[a].clone(),
+            address_b: graph[b].clone(),
+            a_to_b_usd,
+            b_to_a_usd,
+            a_to_b_count: forward.len(),
+            b_to_a_count: backward.len(),
+            round_trip_ratio: a_to_b_usd.min(b_to_a_usd) / a_to_b_usd.max(b_to_a_usd),
+            round_trip_usd: a_to_b_usd.min(b_to_a_usd),
+            net_usd: a_to_b_usd - b_to_a_usd,
+            min_lag_seconds: lags.first().map(|&lag| lag as u64),
+            p25_lag_seconds: (!lags.is_empty()).then(|| percentile(&lags, 0.25) as u64),
+            median_lag_seconds: (!lags.is_empty()).then(|| percentile(&lags, 0.5) as u64),
+            p75_lag_seconds: (!lags.is_empty()).then(|| percentile(&lags, 0.75) as u64),
+            returned_within_1h: lags.iter().filter(|&&lag| lag <= HOUR_SECONDS as f64).count(),
+            returned_within_24h: lags.iter().filter(|&&lag| lag <= DAY_SECONDS as f64).count(),
+        });
+    }
+    // Pairs are found in hash order, so ties go to the smaller address pair and the report reads the same every run.
+    reciprocal.sort_by(|x, y| {
+        y.round_trip_usd.partial_cmp(&x.round_trip_usd).unwrap().then_with(|| (&x.address_a, &x.address_b).cmp(&(&y.address_a, &y.address_b)))
+    });
+    reciprocal
+}
+
+// Entity of every address that belongs to one: a labelled address is its label, and the members of a probable
+// entity share the label of a labelled member, else a numbered entity name. Labels win over clustering, so a
+// labelled address never joins an entity of another label.
+pub fn entity_resolution(graph: &G, labels: &HashMap<String, String>, entities: &[ProbableEntity]) -> HashMap<String, String> {
+    let mut resolution: HashMap<String, String> =
+        graph.node_weights().filter_map(|address| Some((address.clone(), labels.get(address)?.clone()))).collect();
+    for (i, entity) in entities.iter().enumerate() {
+        let name = entity.members.iter().filter_map(|member| labels.get(member)).min().cloned().unwrap_or_else(|| format!("entity-{}", i + 1));
+        for member in entity.members.iter().filter(|member| !labels.contains_key(*member)) {
+            resolution.insert(member.clone(), name.clone());
+        }
+    }
+    resolution
+}
+
+// The graph with every address replaced by its entity. Transfers between addresses of one entity move nothing
+// between entities and are left out. Returns the collapsed graph and the number of transfers left out.
+pub fn collapse_entities(graph: &G, resolution: &HashMap<String, String>) -> (G, usize) {
+    let entity = |address: &String| resolution.get(address).unwrap_or(address).clone();
+    let mut graph_builder = GraphBuilder::with_capacity(graph.node_count(), graph.edge_count());
+    let mut internal = 0;
+    for edge in graph.edge_references() {
+        let (source, target) = (entity(&graph[edge.source()]), entity(&graph[edge.target()]));
+        if source == target && edge.source() != edge.target() {
+            internal += 1;
+            continue;
+        }
+        graph_builder.add_edge(&source, &target, edge.weight().clone());
+    }
+    (graph_builder.into_graph(), internal)
+}
+
+// Every priced transfer of every pair in time order, under a header with the pair's totals. Transfers worth
+// less than the dust floor are summed into one line per direction instead, so the totals stay exact.
+pub fn transfer_log(graph: &G, pairs: &[ReciprocalPair], dust_floor_usd: f64) -> Result<String> {
+    let node_indices: HashMap<&str, NodeIndex> = graph.node_indices().map(|n| (graph[n].as_str(), n)).collect();
+    let mut log = String::new();
+    for pair in pairs {
+        let (a, b) = (node_indices[pair.address_a.as_str()], node_indices[pair.address_b.as_str()]);
+        writeln!(
+            log,
+            "{} <-> {}: {} in {} transfers / {} in {} transfers, net {}",
+            pair.address_a,
+            pair.address_b,
+            Amount::Usd(pair.a_to_b_usd),
+            pair.a_to_b_count,
+            Amount::Usd(pair.b_to_a_usd),
+            pair.b_to_a_count,
+            Amount::Usd(pair.net_usd)
+        )
+        .unwrap();
+        let mut transfers: Vec<(&Transaction, &str)> = graph
+            .edges_connecting(a, b)
+            .map(|e| (e.weight(), "->"))
+            .chain(graph.edges_connecting(b, a).map(|e| (e.weight(), "<-")))
+            .filter(|(t, _)| t.data.as_ref().is_some_and(|data| data.usd_value > 0.0))
+            .collect();
+        transfers.sort_by(|x, y| (x.0.timestamp, &x.0.hash).cmp(&(y.0.timestamp, &y.0.hash)));
+        let mut dust: BTreeMap<&str, (usize, f64)> = BTreeMap::new();
+        for (transaction, direction) in transfers {
+            let usd_value = transaction.data.as_ref().unwrap().usd_value;
+            if usd_value < dust_floor_usd {
+                let entry = dust.entry(direction).or_default();
+                *entry = (entry.0 + 1, entry.1 + usd_value);
+                continue;
+            }
+            writeln!(log, "  {} {} {} {}", local_label(transaction.timestamp, &chrono_tz::UTC)?, direction, Amount::Usd(usd_value), transaction.hash).unwrap();
+        }
+        for (direction, (count, usd_value)) in dust {
+            writeln!(log, "  {} {} dust txs totaling {}", direction, count, Amount::Usd(usd_value)).unwrap();
+        }
+    }
+    Ok(log)
+}
+
+pub fn run_reciprocity_command(args: &ReciprocityArgs) -> Result<()> {
+    let (mut graph, _) = deserialize_graph(&args.input)?;
+    let pairs = if args.by_entity {
+        let labels = args.labels.as_ref().map(|name| load_labels(name)).transpose()?.unwrap_or_default();
+        let resolution = entity_resolution(&graph, &labels, &probable_entities(&graph, args.min_confidence));
+        let (collapsed, internal) = collapse_entities(&graph, &resolution);
+        println!(
+            "Netting by entity: {} addresses collapsed into {} nodes, {} transfers within an entity left out",
+            graph.node_count(),
+            collapsed.node_count(),
+            internal
+        );
+        graph = collapsed;
+        reciprocal_pairs(&graph)
+    } else {
+        reciprocal_pairs(&graph)
+    };
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for pair in pairs.iter() {
+        writer.serialize(pair)?;
+    }
+    commit_csv(writer)?;
+    for pair in pairs.iter().take(10) {
+        println!(
+            "{} <-> {}: {} / {}, net {}, round-trip ratio {:.3}, median lag {}, {} / {} of {} transfers returned within 1h / 24h{}",
+            pair.address_a,
+            pair.address_b,
+            Amount::Usd(pair.a_to_b_usd),
+            Amount::Usd(pair.b_to_a_usd),
+            Amount::Usd(pair.net_usd),
+            pair.round_trip_ratio,
+            pair.median_lag_seconds.map_or("-".to_string(), |lag| format!("{} s", lag)),
+            pair.returned_within_1h,
+            pair.returned_within_24h,
+            pair.transfers(),
+            if pair.is_pass_through() { ", possible pass-through" } else { "" }
+        );
+    }
+    println!("Found {} two-way pairs, saved as {}", pairs.len(), &file_pathname);
+    println!("{} pairs returned most of their transfers within 24h", pairs.iter().filter(|pair| pair.is_pass_through()).count());
+    if let Some(name) = &args.transfer_log {
+        let log_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, name);
+        write_atomically(&log_pathname, transfer_log(&graph, &pairs, args.dust_floor_usd)?.as_bytes())?;
+        println!("Saved the transfers of every pair as {}", &log_pathname);
+    }
+    Ok(())
+}
@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[cfg(feature = "crawl")]
+use {
+    crate::{atomic_file::{commit_csv, csv_writer}, RawTransaction, DATA_STORAGE_FOLDER, SATOSHI_TO_COIN_CONVERSION_FACTOR},
+    eyre::Result,
+    primitive_types::U256,
+    std::fmt::Write,
+    std::time::{SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "crawl")]
+pub const REJECTED_RECORDS_FILENAME: &str = "rejected_records.csv";
+// Rejected records kept with their values in the crawl state, beyond which only the counts grow.
+#[cfg(feature = "crawl")]
+const MAX_KEPT_REJECTIONS: usize = 1000;
+
+// Launch of the first EVM chain. Only genesis allocations, in block 0, may carry an earlier timestamp.
+#[cfg(feature = "crawl")]
+const EARLIEST_TIMESTAMP: u64 = 1_438_269_973;
+// Block times from the future beyond clock skew are not believed.
+#[cfg(feature = "crawl")]
+const MAX_CLOCK_SKEW_SECONDS: u64 = 86_400;
+#[cfg(feature = "crawl")]
+const MAX_BLOCK_NUMBER: u64 = 10_000_000_000;
+// A billion coins in one transfer is more than any chain the crawler reads has in circulation, and keeps the
+// wei sums of any realistic graph far from u128 overflow.
+#[cfg(feature = "crawl")]
+const MAX_PLAUSIBLE_WEI: u128 = 1_000_000_000 * 1_000_000_000_000_000_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionReason {
+    Missing,
+    // Anything other than plain ASCII digits (or hex digits for calldata amounts): signs, separators,
+    // exponents, whitespace.
+    Malformed,
+    Overflow,
+    OutOfRange,
+}
+
+#[cfg(feature = "crawl")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    pub field: String,
+    pub value: String,
+    pub reason: RejectionReason,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RejectedRecord {
+    pub address: String,
+    pub hash: String,
+    pub field: String,
+    pub value: String,
+    pub reason: RejectionReason,
+}
+
+// Records the crawl left out of the graph because a field failed checked parsing, counted per field and reason.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub counts: BTreeMap<String, usize>,
+    pub records: Vec<RejectedRecord>,
+}
+
+// The numeric fields of an Etherscan record, parsed and checked.
+#[cfg(feature = "crawl")]
+#[derive(Debug, Clone, Copy)]
+pub struct ParsedRecord {
+    pub timestamp: u64,
+    pub block_number: u64,
+    pub gas_price: u64,
+    pub gas_used: u64,
+    pub transaction_type: u8,
+    pub max_fee_per_gas: u64,
+    pub max_priority_fee_per_gas: u64,
+    pub transaction_index: u64,
+    pub nonce: u64,
+    pub value_wei: u128,
+}
+
+#[cfg(feature = "crawl")]
+fn rejection(field: &str, value: &str, reason: RejectionReason) -> Rejection {
+    Rejection { field: field.to_string(), value: value.to_string(), reason }
+}
+
+// Decimal digits only. str::parse alone accepts a leading +, and an unchecked unwrap_or_default turns every
+// failure into a zero that looks like data.
+#[cfg(feature = "crawl")]
+pub fn parse_decimal<T: std::str::FromStr>(field: &str, text: &str) -> Result<T, Rejection> {
+    if text.is_empty() {
+        return Err(rejection(field, text, RejectionReason::Missing));
+    }
+    if !text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(rejection(field, text, RejectionReason::Malformed));
+    }
+    // Digits only, so the parse can only fail by overflowing the target type.
+    text.parse().map_err(|_| rejection(field, text, RejectionReason::Overflow))
+}
+
+// Fields Etherscan leaves empty on some records, which then mean zero.
+#[cfg(feature = "crawl")]
+fn parse_optional_decimal<T: std::str::FromStr + Default>(field: &str, text: &str) -> Result<T, Rejection> {
+    if text.is_empty() {
+        return Ok(T::default());
+    }
+    parse_decimal(field, text)
+}
+
+// Etherscan gives the type as a decimal, the JSON-RPC proxy as a 0x quantity; legacy records leave it empty.
+#[cfg(feature = "crawl")]
+fn parse_transaction_type(text: &str) -> Result<u8, Rejection> {
+    match text.strip_prefix("0x") {
+        Some(hex) if !hex.is_empty() && hex.bytes().all(|byte| byte.is_ascii_hexdigit()) => {
+            u8::from_str_radix(hex, 16).map_err(|_| rejection("type", text, RejectionReason::Overflow))
+        }
+        Some(_) => Err(rejection("type", text, RejectionReason::Malformed)),
+        None => parse_optional_decimal("type", text),
+    }
+}
+
+#[cfg(feature = "crawl")]
+pub fn checked_wei(field: &str, text: &str) -> Result<u128, Rejection> {
+    let value: u128 = parse_decimal(field, text)?;
+    if value > MAX_PLAUSIBLE_WEI {
+        return Err(rejection(field, text, RejectionReason::OutOfRange));
+    }
+    Ok(value)
+}
+
+// Whole tokens of an 18-decimal token amount ABI-encoded as hex calldata, truncated as before.
+#[cfg(feature = "crawl")]
+pub fn checked_token_amount(field: &str, hex: &str) -> Result<f64, Rejection> {
+    let amount = U256::from_str_radix(hex, 16).map_err(|_| rejection(field, hex, RejectionReason::Malformed))?;
+    if amount > U256::from(MAX_PLAUSIBLE_WEI) {
+        return Err(rejection(field, hex, RejectionReason::OutOfRange));
+    }
+    Ok((amount / SATOSHI_TO_COIN_CONVERSION_FACTOR).as_u64() as f64)
+}
+
+#[cfg(feature = "crawl")]
+pub fn parse_record(transaction: &RawTransaction) -> Result<ParsedRecord, Rejection> {
+    let block_number: u64 = parse_decimal("blockNumber", &transaction.blockNumber)?;
+    if block_number > MAX_BLOCK_NUMBER {
+        return Err(rejection("blockNumber", &transaction.blockNumber, RejectionReason::OutOfRange));
+    }
+    let timestamp: u64 = parse_decimal("timeStamp", &transaction.timeStamp)?;
+    let latest = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + MAX_CLOCK_SKEW_SECONDS;
+    if timestamp > latest || (timestamp < EARLIEST_TIMESTAMP && block_number != 0) {
+        return Err(rejection("timeStamp", &transaction.timeStamp, RejectionReason::OutOfRange));
+    }
+    Ok(ParsedRecord {
+        timestamp,
+        block_number,
+        gas_price: parse_optional_decimal("gasPrice", &transaction.gasPrice)?,
+        gas_used: parse_optional_decimal("gasUsed", &transaction.gasUsed)?,
+        transaction_type: parse_transaction_type(&transaction.txType)?,
+        max_fee_per_gas: parse_optional_decimal("maxFeePerGas", &transaction.maxFeePerGas)?,
+        max_priority_fee_per_gas: parse_optional_decimal("maxPriorityFeePerGas", &transaction.maxPriorityFeePerGas)?,
+        transaction_index: parse_optional_decimal("transactionIndex", &transaction.transactionIndex)?,
+        nonce: parse_optional_decimal("nonce", &transaction.nonce)?,
+        value_wei: checked_wei("value", &transaction.value)?,
+    })
+}
+
+#[cfg(feature = "crawl")]
+impl ValidationReport {
+    pub fn reject(&mut self, address: &str, hash: &str, rejection: Rejection) {
+        *self.counts.entry(format!("{} {:?}", rejection.field, rejection.reason)).or_default() += 1;
+        if self.records.len() < MAX_KEPT_REJECTIONS {
+            self.records.push(RejectedRecord {
+                address: address.to_string(),
+                hash: hash.to_string(),
+                field: rejection.field,
+                value: rejection.value,
+                reason: rejection.reason,
+            });
+        }
+    }
+
+    pub fn add(&mut self, other: &ValidationReport) {
+        for (key, count) in other.counts.iter() {
+            *self.counts.entry(key.clone()).or_default() += count;
+        }
+        let room = MAX_KEPT_REJECTIONS.saturating_sub(self.records.len());
+        self.records.extend(other.records.iter().take(room).cloned());
+    }
+
+    pub fn rejected(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut description = format!("Rejected records: {}\n", self.rejected());
+        for (key, count) in self.counts.iter() {
+            writeln!(description, "  {}: {}", key, count).unwrap();
+        }
+        description
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, REJECTED_RECORDS_FILENAME);
+        let mut writer = csv_writer(&file_pathname)?;
+        for record in self.records.iter() {
+            writer.serialize(record)?;
+        }
+        commit_csv(writer)?;
+        if self.rejected() > 0 {
+            println!("Saved {} of {} rejected records as {}", self.records.len(), self.rejected(), file_pathname);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "crawl")]
+pub fn validate_history<'a>(address: &str, history: &'a [RawTransaction], report: &mut ValidationReport) -> Vec<(&'a RawTransaction, ParsedRecord)> {
+    history
+        .iter()
+        .filter_map(|transaction| match parse_record(transaction) {
+            Ok(parsed) => Some((transaction, parsed)),
+            Err(rejection) => {
+                println!("Rejected {} from the history of {}: {} {:?} ({:?})", transaction.hash, address, rejection.field, rejection.reason, rejection.value);
+                report.reject(address, &transaction.hash, rejection);
+                None
+            }
+        })
+        .collect()
+}
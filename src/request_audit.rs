@@ -0,0 +1,109 @@
+use eyre::Result;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs;
+
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::{RawTransaction, API_DAILY_REQUEST_QUOTA, DATA_STORAGE_FOLDER, TRANSACTIONS_TO_REQUEST};
+
+const REQUEST_AUDIT_FILENAME: &str = "request_audit.csv";
+const LEAST_COMPLETE_ADDRESSES_SHOWN: usize = 5;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AddressAudit {
+    pub address: String,
+    pub requests: usize,
+    pub fetched_transactions: usize,
+    pub new_edges: usize,
+    pub edges_per_request: f64,
+    // Nonces of transactions sent by the address that the fetched history does not contain, e.g. because
+    // the result window cut it short. Volume figures for addresses below 100% are lower bounds.
+    pub sent_transactions: usize,
+    pub missing_nonces: usize,
+    pub completeness_percent: f64,
+}
+
+impl AddressAudit {
+    pub fn new(address: &str, requests: usize, fetched_transactions: usize, new_edges: usize) -> AddressAudit {
+        AddressAudit {
+            address: address.to_string(),
+            requests,
+            fetched_transactions,
+            new_edges,
+            edges_per_request: if requests == 0 { 0.0 } else { new_edges as f64 / requests as f64 },
+            sent_transactions: 0,
+            missing_nonces: 0,
+            completeness_percent: 100.0,
+        }
+    }
+
+    // A history fetched from the first block must hold every nonce from 0 up to the highest one seen;
+    // a delta fetch only the range between its lowest and highest nonce.
+    pub fn with_nonce_audit(mut self, history: &[RawTransaction], from_first_block: bool) -> AddressAudit {
+        let nonces: BTreeSet<u64> = history.iter().filter(|t| t.from == self.address).filter_map(|t| t.nonce.parse().ok()).collect();
+        if let (Some(&lowest), Some(&highest)) = (nonces.first(), nonces.last()) {
+            let expected = highest - if from_first_block { 0 } else { lowest } + 1;
+            self.sent_transactions = nonces.len();
+            self.missing_nonces = expected as usize - nonces.len();
+            self.completeness_percent = 100.0 * nonces.len() as f64 / expected as f64;
+        }
+        self
+    }
+}
+
+pub fn request_audit_summary(audits: &[AddressAudit]) -> String {
+    let requests: usize = audits.iter().map(|a| a.requests).sum();
+    let new_edges: usize = audits.iter().map(|a| a.new_edges).sum();
+    let fetched: usize = audits.iter().map(|a| a.fetched_transactions).sum();
+    let full_pages = audits.iter().filter(|a| a.fetched_transactions >= TRANSACTIONS_TO_REQUEST).count();
+    let unproductive = audits.iter().filter(|a| a.new_edges == 0).count();
+    format!(
+        "Requests: {} for {} addresses ({:.1}% of the daily quota), {} transactions fetched, {} new edges ({:.1} per request)\n\
+         Addresses with at least one full page of {}: {}, addresses that added no edges: {}\n",
+        requests,
+        audits.len(),
+        100.0 * requests as f64 / API_DAILY_REQUEST_QUOTA as f64,
+        fetched,
+        new_edges,
+        if requests == 0 { 0.0 } else { new_edges as f64 / requests as f64 },
+        TRANSACTIONS_TO_REQUEST,
+        full_pages,
+        unproductive
+    ) + nonce_gap_summary(audits).as_str()
+}
+
+fn nonce_gap_summary(audits: &[AddressAudit]) -> String {
+    let mut incomplete: Vec<&AddressAudit> = audits.iter().filter(|a| a.missing_nonces > 0).collect();
+    incomplete.sort_by(|x, y| x.completeness_percent.partial_cmp(&y.completeness_percent).unwrap().then(x.address.cmp(&y.address)));
+    let sent: usize = audits.iter().map(|a| a.sent_transactions).sum();
+    let missing: usize = audits.iter().map(|a| a.missing_nonces).sum();
+    let mut summary = format!(
+        "Addresses with nonce gaps: {}, missing sent transactions: {} ({:.1}% of sent transactions fetched)\n",
+        incomplete.len(),
+        missing,
+        if sent + missing == 0 { 100.0 } else { 100.0 * sent as f64 / (sent + missing) as f64 }
+    );
+    for audit in incomplete.iter().take(LEAST_COMPLETE_ADDRESSES_SHOWN) {
+        summary.push_str(&format!(
+            "  {}: {:.1}% complete, {} of {} sent nonces missing\n",
+            audit.address,
+            audit.completeness_percent,
+            audit.missing_nonces,
+            audit.sent_transactions + audit.missing_nonces
+        ));
+    }
+    summary
+}
+
+pub fn save_request_audit(audits: &[AddressAudit]) -> Result<()> {
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, REQUEST_AUDIT_FILENAME);
+    let mut writer = csv_writer(&file_pathname)?;
+    for audit in audits {
+        writer.serialize(audit)?;
+    }
+    commit_csv(writer)?;
+    print!("{}", request_audit_summary(audits));
+    println!("Saved request audit for {} addresses as {}\n", audits.len(), &file_pathname);
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use eyre::Result;
+use serde::Serialize;
+use std::fs;
+
+use crate::atomic_file::{commit_csv, csv_writer, AtomicFile};
+use crate::DATA_STORAGE_FOLDER;
+
+pub const REQUEST_METRICS_FILENAME: &str = "request_metrics.csv";
+
+// One row per HTTP attempt of a crawl, retries included, for working out afterwards where the time went.
+#[derive(Serialize, Debug, Clone)]
+pub struct RequestMetric {
+    pub started_at_ms: u64,
+    pub address: String,
+    pub start_block: u64,
+    pub end_block: u64,
+    pub page: usize,
+    pub offset: usize,
+    pub sort: &'static str,
+    // 1 for the first try of a query, counting up with every retry.
+    pub attempt: usize,
+    pub outcome: &'static str,
+    pub latency_ms: f64,
+    pub bytes: usize,
+    pub results: usize,
+}
+
+pub struct RequestMetricsLog {
+    writer: csv::Writer<AtomicFile>,
+    pathname: String,
+    rows: usize,
+}
+
+impl RequestMetricsLog {
+    pub fn create() -> Result<RequestMetricsLog> {
+        fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+        let pathname = format!("{}/{}", DATA_STORAGE_FOLDER, REQUEST_METRICS_FILENAME);
+        Ok(RequestMetricsLog { writer: csv_writer(&pathname)?, pathname, rows: 0 })
+    }
+
+    pub fn append(&mut self, metric: &RequestMetric) -> Result<()> {
+        self.rows += 1;
+        Ok(self.writer.serialize(metric)?)
+    }
+
+    pub fn save(self) -> Result<String> {
+        commit_csv(self.writer)?;
+        Ok(format!("Saved {} request metrics as {}\n", self.rows, self.pathname))
+    }
+}
@@ -0,0 +1,216 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+
+use crate::address_poisoning::poisoning_candidates;
+use crate::annotations::Annotations;
+use crate::atomic_file::{commit_csv, csv_writer, write_atomically};
+use crate::cli::{EdgeWeight, RiskArgs};
+use crate::exposure::{exposure_scores, flagged_nodes, ExposureParameters};
+use crate::mixers::{KNOWN_MIXERS, MIXER_TAG, MIXER_USER_TAG};
+use crate::structuring::{detect_structuring, StructuringParameters};
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+// Annotation tags that are risky in themselves, with the score they give.
+const RISKY_TAGS: [(&str, f64); 5] = [("sanctioned", 1.0), (MIXER_TAG, 0.9), ("scam", 0.8), ("phishing", 0.8), (MIXER_USER_TAG, 0.5)];
+const POISONING_SCORE: f64 = 0.8;
+const STRUCTURING_SCORE: f64 = 0.5;
+
+// One scorer's finding about an address: a score from 0 to 1 and why.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RiskComponent {
+    pub scorer: String,
+    pub score: f64,
+    pub reason: String,
+}
+
+// A source of risk evidence. Scorers registered in a RiskScorerRegistry, built in or added through
+// PassRegistry::register_scorer, are combined by the risk command.
+pub trait RiskScorer {
+    fn name(&self) -> &str;
+    // Scores of the addresses the scorer found something about, from 0 to 1, with a reason each.
+    fn score(&self, graph: &G, annotations: &Annotations) -> Result<Vec<(NodeIndex, f64, String)>>;
+    // How much a full score of this scorer counts in the combined score, from 0 to 1.
+    fn weight(&self) -> f64 {
+        1.0
+    }
+}
+
+struct LabelScorer;
+
+impl RiskScorer for LabelScorer {
+    fn name(&self) -> &str {
+        "labels"
+    }
+
+    fn score(&self, graph: &G, annotations: &Annotations) -> Result<Vec<(NodeIndex, f64, String)>> {
+        Ok(graph
+            .node_indices()
+            .filter_map(|n| {
+                let annotation = annotations.get(&graph[n])?;
+                let (tag, score) = RISKY_TAGS.iter().filter(|(tag, _)| annotation.tags.contains(*tag)).max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())?;
+                Some((n, *score, format!("tagged {}", tag)))
+            })
+            .collect())
+    }
+}
+
+// Random-walk exposure to sanctioned addresses and mixers, relative to the most exposed address.
+struct MixerExposureScorer;
+
+impl RiskScorer for MixerExposureScorer {
+    fn name(&self) -> &str {
+        "mixer_exposure"
+    }
+
+    fn weight(&self) -> f64 {
+        0.7
+    }
+
+    fn score(&self, graph: &G, annotations: &Annotations) -> Result<Vec<(NodeIndex, f64, String)>> {
+        let known_mixers: Vec<String> = KNOWN_MIXERS.iter().map(|mixer| mixer.to_string()).collect();
+        let flagged = flagged_nodes(graph, annotations, &["sanctioned".to_string(), MIXER_TAG.to_string()], &known_mixers);
+        if flagged.is_empty() {
+            return Ok(vec![]);
+        }
+        let parameters = ExposureParameters { weight: EdgeWeight::UsdVolume, undirected: false, restart_probability: 0.15, iterations: 100, tolerance: 1e-10 };
+        let nodes: BTreeMap<&String, NodeIndex> = graph.node_indices().map(|n| (&graph[n], n)).collect();
+        Ok(exposure_scores(graph, &flagged, &parameters)
+            .into_iter()
+            .filter(|row| !row.flagged && row.relative_exposure > 0.0)
+            .map(|row| (nodes[&row.address], row.relative_exposure, format!("exposure {:.6}, {} hops from a flagged address", row.exposure, row.hops.unwrap())))
+            .collect())
+    }
+}
+
+// Lookalike senders of poisoning dust and senders of structured transfers.
+struct AnomalyScorer;
+
+impl RiskScorer for AnomalyScorer {
+    fn name(&self) -> &str {
+        "anomalies"
+    }
+
+    fn score(&self, graph: &G, _annotations: &Annotations) -> Result<Vec<(NodeIndex, f64, String)>> {
+        let mut findings: BTreeMap<NodeIndex, (f64, String)> = BTreeMap::new();
+        let parameters = StructuringParameters { window_seconds: 24 * 3_600, min_repeats: 3, round_unit: 1000.0, round_tolerance: 0.001 };
+        let nodes: BTreeMap<&String, NodeIndex> = graph.node_indices().map(|n| (&graph[n], n)).collect();
+        for pair in detect_structuring(graph, &parameters) {
+            findings.entry(nodes[&pair.sender]).or_insert((STRUCTURING_SCORE, format!("structured {} transfers to {}", pair.transfer_count, pair.recipient)));
+        }
+        for candidate in poisoning_candidates(graph) {
+            findings.insert(
+                candidate.lookalike,
+                (POISONING_SCORE, format!("lookalike of {} sending dust to {}", graph[candidate.imitated], graph[candidate.victim])),
+            );
+        }
+        Ok(findings.into_iter().map(|(n, (score, reason))| (n, score, reason)).collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressRisk {
+    pub address: String,
+    // 1 - product of (1 - weight * score) over the components, so independent evidence adds up without
+    // exceeding 1 and a single full-weight hit is enough for the maximum.
+    pub score: f64,
+    // Highest weighted contribution first.
+    pub components: Vec<RiskComponent>,
+}
+
+#[derive(Serialize)]
+struct RiskRow<'a> {
+    address: &'a str,
+    score: f64,
+    components: String,
+}
+
+#[derive(Default)]
+pub struct RiskScorerRegistry {
+    scorers: Vec<Box<dyn RiskScorer>>,
+}
+
+impl RiskScorerRegistry {
+    pub fn with_builtin_scorers() -> RiskScorerRegistry {
+        RiskScorerRegistry { scorers: vec![Box::new(LabelScorer), Box::new(MixerExposureScorer), Box::new(AnomalyScorer)] }
+    }
+
+    pub fn register(&mut self, scorer: Box<dyn RiskScorer>) -> Result<()> {
+        if self.scorers.iter().any(|s| s.name() == scorer.name()) {
+            return Err(eyre::eyre!("A risk scorer named {} is already registered", scorer.name()));
+        }
+        self.scorers.push(scorer);
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.scorers.iter().map(|s| s.name()).collect()
+    }
+
+    // Combines the selected scorers, all of them when none are selected, into the addresses with any risk,
+    // riskiest first.
+    pub fn score(&self, graph: &G, annotations: &Annotations, selected: &[String]) -> Result<Vec<AddressRisk>> {
+        if let Some(unknown) = selected.iter().find(|name| !self.names().contains(&name.as_str())) {
+            return Err(eyre::eyre!("Unknown risk scorer {}, registered scorers are {}", unknown, self.names().join(", ")));
+        }
+        let mut components: BTreeMap<NodeIndex, Vec<(f64, RiskComponent)>> = BTreeMap::new();
+        for scorer in self.scorers.iter().filter(|s| selected.is_empty() || selected.iter().any(|name| name == s.name())) {
+            println!("Running risk scorer {}", scorer.name());
+            let weight = scorer.weight().clamp(0.0, 1.0);
+            for (n, score, reason) in scorer.score(graph, annotations)? {
+                let score = score.clamp(0.0, 1.0);
+                components.entry(n).or_default().push((weight * score, RiskComponent { scorer: scorer.name().to_string(), score, reason }));
+            }
+        }
+        let mut risks: Vec<AddressRisk> = components
+            .into_iter()
+            .map(|(n, mut weighted)| {
+                weighted.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+                AddressRisk {
+                    address: graph[n].clone(),
+                    score: 1.0 - weighted.iter().fold(1.0, |remaining, (contribution, _)| remaining * (1.0 - contribution)),
+                    components: weighted.into_iter().map(|(_, component)| component).collect(),
+                }
+            })
+            .collect();
+        risks.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap().then(a.address.cmp(&b.address)));
+        Ok(risks)
+    }
+}
+
+fn component_list(risk: &AddressRisk) -> String {
+    risk.components.iter().map(|c| format!("{} {:.3}: {}", c.scorer, c.score, c.reason)).collect::<Vec<String>>().join("; ")
+}
+
+pub fn risk_report(risks: &[AddressRisk], annotations: &Annotations, show: usize) -> String {
+    let mut report = format!("Addresses with risk evidence: {}\n", risks.len());
+    for risk in risks.iter().take(show) {
+        writeln!(report, "{}: risk {:.3}", annotations.mention(&risk.address), risk.score).unwrap();
+        for component in risk.components.iter() {
+            writeln!(report, "  {} {:.3}: {}", component.scorer, component.score, component.reason).unwrap();
+        }
+    }
+    report
+}
+
+pub fn run_risk_command(args: &RiskArgs, scorers: &RiskScorerRegistry) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let annotations = Annotations::load()?;
+    let risks = scorers.score(&graph, &annotations, &args.scorers)?;
+    let report = risk_report(&risks, &annotations, args.show);
+    print!("{}", &report);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for risk in risks.iter() {
+        writer.serialize(RiskRow { address: &risk.address, score: risk.score, components: component_list(risk) })?;
+    }
+    commit_csv(writer)?;
+    write_atomically(&format!("{}/{}", DATA_STORAGE_FOLDER, args.report), report.as_bytes())?;
+    println!("Saved the risk scores of {} addresses as {}", risks.len(), &file_pathname);
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use eyre::Result;
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+
+use crate::money::Amount;
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::RollingFlowArgs;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FlowWindow {
+    pub window_start: u64,
+    pub window_end: u64,
+    pub inflow_usd: f64,
+    pub outflow_usd: f64,
+    pub net_flow_usd: f64,
+    pub transaction_count: usize,
+}
+
+// Transfers between two members of the set are internal and do not move funds in or out of it.
+fn boundary_flows(graph: &G, address_set: &HashSet<String>) -> Vec<(u64, f64)> {
+    let mut flows: Vec<(u64, f64)> = graph
+        .edge_references()
+        .filter(|e| e.weight().timestamp != 0)
+        .filter_map(|e| {
+            let data = e.weight().data.as_ref()?;
+            let source_inside = address_set.contains(&graph[e.source()].to_lowercase());
+            let target_inside = address_set.contains(&graph[e.target()].to_lowercase());
+            match (source_inside, target_inside) {
+                (false, true) => Some((e.weight().timestamp, data.usd_value)),
+                (true, false) => Some((e.weight().timestamp, -data.usd_value)),
+                _ => None,
+            }
+        })
+        .collect();
+    flows.sort_by_key(|&(timestamp, _)| timestamp);
+    flows
+}
+
+pub fn rolling_net_flow(graph: &G, address_set: &HashSet<String>, step_seconds: u64, window_seconds: u64) -> Vec<FlowWindow> {
+    assert!(step_seconds > 0 && window_seconds > 0);
+    let flows = boundary_flows(graph, address_set);
+    let (Some(&(first, _)), Some(&(last, _))) = (flows.first(), flows.last()) else {
+        return vec![];
+    };
+
+    let mut series = vec![];
+    let (mut low, mut high) = (0, 0); // flows[low..high] fall inside (window_end - window, window_end]
+    let mut window_end = first / step_seconds * step_seconds + step_seconds;
+    loop {
+        while high < flows.len() && flows[high].0 < window_end {
+            high += 1;
+        }
+        let window_start = window_end.saturating_sub(window_seconds);
+        while low < high && flows[low].0 < window_start {
+            low += 1;
+        }
+        let window_flows = &flows[low..high];
+        let inflow_usd = window_flows.iter().filter(|f| f.1 > 0.0).fold(0.0, |acc, f| acc + f.1);
+        let outflow_usd = window_flows.iter().filter(|f| f.1 < 0.0).fold(0.0, |acc, f| acc - f.1);
+        series.push(FlowWindow {
+            window_start,
+            window_end,
+            inflow_usd,
+            outflow_usd,
+            net_flow_usd: inflow_usd - outflow_usd,
+            transaction_count: window_flows.len(),
+        });
+        if window_end > last {
+            break;
+        }
+        window_end += step_seconds;
+    }
+    series
+}
+
+pub fn write_flow_series(series: &[FlowWindow], file_pathname: &str) -> Result<()> {
+    let mut writer = csv_writer(file_pathname)?;
+    for window in series {
+        writer.serialize(window)?;
+    }
+    commit_csv(writer)?;
+    Ok(())
+}
+
+pub fn run_rolling_flow_command(args: &RollingFlowArgs) -> Result<()> {
+    if args.step_hours == 0 || args.window_hours == 0 {
+        return Err(eyre::eyre!("--step-hours and --window-hours must be positive, got {} and {}", args.step_hours, args.window_hours));
+    }
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let address_set: HashSet<String> = args.addresses.iter().map(|a| a.to_lowercase()).collect();
+    let series = rolling_net_flow(&graph, &address_set, args.step_hours * 3_600, args.window_hours * 3_600);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    write_flow_series(&series, &file_pathname)?;
+    if let Some(latest) = series.last() {
+        println!(
+            "Latest {}h window for {} addresses: inflow {}, outflow {}, net {}",
+            args.window_hours,
+            address_set.len(),
+            Amount::Usd(latest.inflow_usd),
+            Amount::Usd(latest.outflow_usd),
+            Amount::Usd(latest.net_flow_usd)
+        );
+    }
+    println!("Saved {} rolling windows as {}", series.len(), &file_pathname);
+    Ok(())
+}
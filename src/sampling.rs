@@ -0,0 +1,189 @@
+use core::cmp::min;
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+#[cfg(not(target_arch = "wasm32"))]
+use plotters::prelude::*;
+use rand::seq::{index, SliceRandom};
+use rand::Rng;
+use std::collections::{HashSet, VecDeque};
+
+use crate::cli::{SampleArgs, SamplingMethod};
+use crate::seeded_rng::seeded_rng;
+use crate::{deserialize_graph, serialize_graph, FilteringResultInfo, G};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{plot_distribution_multicolor, workspace, DATA_STORAGE_FOLDER};
+
+fn sampling_info(graph: &G, sampled_graph: &G) -> FilteringResultInfo {
+    FilteringResultInfo {
+        node_count: sampled_graph.raw_nodes().len(),
+        edge_count: sampled_graph.raw_edges().len(),
+        node_count_before_filtering: graph.raw_nodes().len(),
+        edge_count_before_filtering: graph.raw_edges().len(),
+    }
+}
+
+fn induced_subgraph(graph: &G, kept_nodes: &HashSet<NodeIndex>) -> G {
+    graph.filter_map(
+        |node_index, node| kept_nodes.contains(&node_index).then_some(node.clone()),
+        |_edge_index, transaction| Some(transaction.clone()), // Edges with a removed endpoint are dropped by filter_map
+    )
+}
+
+pub fn uniform_edge_sample(graph: &G, target_edge_count: usize, rng: &mut impl Rng) -> (G, FilteringResultInfo) {
+    let edge_count = graph.edge_count();
+    let kept_edges: HashSet<usize> = index::sample(rng, edge_count, min(target_edge_count, edge_count))
+        .into_iter()
+        .collect();
+
+    let sampled_graph_stage1: G = graph.filter_map(
+        |_node_index, node| Some(node.clone()), // All nodes remain
+        |edge_index, transaction| kept_edges.contains(&edge_index.index()).then_some(transaction.clone()),
+    );
+    let sampled_graph_stage2 = sampled_graph_stage1.filter_map(
+        |node_index, node| (sampled_graph_stage1.neighbors_undirected(node_index).count() != 0).then_some(node.clone()),
+        |_edge_index, transaction| Some(transaction.clone()), // All transactions remain
+    );
+
+    let info = sampling_info(graph, &sampled_graph_stage2);
+    (sampled_graph_stage2, info)
+}
+
+pub fn random_walk_with_restart_sample(
+    graph: &G,
+    start: NodeIndex,
+    target_node_count: usize,
+    restart_probability: f64,
+    rng: &mut impl Rng,
+) -> (G, FilteringResultInfo) {
+    assert!((0.0..1.0).contains(&restart_probability));
+    let target_node_count = min(target_node_count, graph.node_count());
+    let max_steps = target_node_count.saturating_mul(100);
+
+    let mut visited: HashSet<NodeIndex> = HashSet::from([start]);
+    let mut current = start;
+    let mut steps = 0;
+    while visited.len() < target_node_count && steps < max_steps {
+        steps += 1;
+        if rng.gen_bool(restart_probability) {
+            current = start;
+            continue;
+        }
+        let neighbors: Vec<NodeIndex> = graph.neighbors_undirected(current).collect();
+        match neighbors.choose(rng) {
+            Some(&next) => {
+                visited.insert(next);
+                current = next;
+            }
+            None if current == start => break, // Isolated starting node, nothing to walk
+            None => current = start,
+        }
+    }
+
+    let sampled_graph = induced_subgraph(graph, &visited);
+    let info = sampling_info(graph, &sampled_graph);
+    (sampled_graph, info)
+}
+
+pub fn forest_fire_sample(
+    graph: &G,
+    target_node_count: usize,
+    forward_burning_probability: f64,
+    rng: &mut impl Rng,
+) -> (G, FilteringResultInfo) {
+    assert!((0.0..1.0).contains(&forward_burning_probability));
+    let target_node_count = min(target_node_count, graph.node_count());
+
+    let mut burned: HashSet<NodeIndex> = HashSet::new();
+    let mut unburned_nodes: Vec<NodeIndex> = graph.node_indices().collect();
+    unburned_nodes.shuffle(rng);
+
+    while burned.len() < target_node_count {
+        // A new fire starts from a random ambassador node each time the previous one dies out.
+        let Some(ambassador) = unburned_nodes.pop() else { break };
+        if !burned.insert(ambassador) {
+            continue;
+        }
+        let mut fire_front = VecDeque::from([ambassador]);
+
+        while let Some(node) = fire_front.pop_front() {
+            if burned.len() >= target_node_count {
+                break;
+            }
+            // Geometric number of links to burn, mean p / (1 - p).
+            let mut links_to_burn = 0;
+            while rng.gen_bool(forward_burning_probability) {
+                links_to_burn += 1;
+            }
+
+            let mut candidates: Vec<NodeIndex> = graph
+                .neighbors_undirected(node)
+                .filter(|n| !burned.contains(n))
+                .collect();
+            candidates.sort();
+            candidates.dedup();
+            candidates.shuffle(rng);
+
+            for next in candidates.into_iter().take(links_to_burn) {
+                if burned.len() >= target_node_count {
+                    break;
+                }
+                burned.insert(next);
+                fire_front.push_back(next);
+            }
+        }
+    }
+
+    let sampled_graph = induced_subgraph(graph, &burned);
+    let info = sampling_info(graph, &sampled_graph);
+    (sampled_graph, info)
+}
+
+fn find_start_node(graph: &G, start_address: &Option<String>) -> Result<NodeIndex> {
+    match start_address {
+        Some(address) => {
+            let address = address.to_lowercase();
+            graph
+                .node_indices()
+                .find(|&n| graph[n] == address)
+                .ok_or_else(|| eyre::eyre!("Address {} is not present in the graph", address))
+        }
+        None => graph
+            .node_indices()
+            .max_by_key(|&n| graph.neighbors_undirected(n).count())
+            .ok_or_else(|| eyre::eyre!("Cannot sample an empty graph")),
+    }
+}
+
+pub fn run_sample_command(args: &SampleArgs) -> Result<()> {
+    let (name, probability) = match args.method {
+        SamplingMethod::UniformEdge => ("", 0.0),
+        SamplingMethod::RandomWalk => ("--restart-probability", args.restart_probability),
+        SamplingMethod::ForestFire => ("--forward-burning-probability", args.forward_burning_probability),
+    };
+    if !(0.0..1.0).contains(&probability) {
+        return Err(eyre::eyre!("{} must be at least 0 and below 1, got {}", name, probability));
+    }
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let mut rng = seeded_rng(args.seed.seed);
+
+    let (sampled_graph, sampling_info) = match args.method {
+        SamplingMethod::UniformEdge => uniform_edge_sample(&graph, args.size, &mut rng),
+        SamplingMethod::RandomWalk => {
+            let start = find_start_node(&graph, &args.start)?;
+            random_walk_with_restart_sample(&graph, start, args.size, args.restart_probability, &mut rng)
+        }
+        SamplingMethod::ForestFire => forest_fire_sample(&graph, args.size, args.forward_burning_probability, &mut rng),
+    };
+    println!("Sampled {} with {:?}:\n{:#?}", &args.input, args.method, sampling_info);
+
+    serialize_graph(&sampled_graph, &sampling_info, &args.output)?;
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let plot_pathname = format!("{}/{}.png", DATA_STORAGE_FOLDER, args.output.trim_end_matches(".json"));
+        let mut sample_root = BitMapBackend::new(&plot_pathname, (720, 480)).into_drawing_area();
+        plot_distribution_multicolor(&sampled_graph, &mut sample_root, 0.0, &format!("Value distribution for {:?} sample", args.method));
+        workspace::record_artifact(&plot_pathname)?;
+        println!("Saved plot as {}", &plot_pathname);
+    }
+    Ok(())
+}
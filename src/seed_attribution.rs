@@ -0,0 +1,197 @@
+use eyre::Result;
+use petgraph::dot::{Config, Dot};
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+use std::fs;
+
+use crate::atomic_file::{commit_csv, csv_writer, write_atomically};
+use crate::cli::SeedMapArgs;
+use crate::delta_crawl::{load_crawl_state, CrawlState};
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+// One colour per seed in seed order, repeating beyond the palette. Addresses reached from several seeds
+// get the overlap colour where a node takes a single colour, and a wedge per seed in DOT.
+const SEED_COLORS: [&str; 8] = ["#1e88e5", "#43a047", "#fb8c00", "#8e24aa", "#00acc1", "#6d4c41", "#c0ca33", "#546e7a"];
+const OVERLAP_COLOR: &str = "#e53935";
+const UNREACHED_COLOR: &str = "#bdbdbd";
+
+fn seed_color(seed: usize) -> &'static str {
+    SEED_COLORS[seed % SEED_COLORS.len()]
+}
+
+// Seeds, by their position in the crawl's seed list, whose traversal reached each address. A traversal
+// reaches the counterparties of every address it expands, so an address was reached from a seed when a
+// path of expanded addresses leads there from the seed, whatever the direction of the transfers.
+pub fn seed_reach(graph: &G, crawl_state: &CrawlState) -> HashMap<NodeIndex, BTreeSet<usize>> {
+    let node_indices: HashMap<&str, NodeIndex> = graph.node_indices().map(|n| (graph[n].as_str(), n)).collect();
+    let expanded = |n: NodeIndex| crawl_state.last_fetched_blocks.contains_key(&graph[n]);
+    let mut reach: HashMap<NodeIndex, BTreeSet<usize>> = HashMap::new();
+    for (seed, address) in crawl_state.seeds.iter().enumerate() {
+        let Some(&start) = node_indices.get(address.to_lowercase().as_str()) else { continue };
+        let mut seen: HashSet<NodeIndex> = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            reach.entry(node).or_default().insert(seed);
+            if !expanded(node) {
+                continue;
+            }
+            for neighbor in graph.neighbors_undirected(node) {
+                if seen.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+    reach
+}
+
+fn node_color(seeds: Option<&BTreeSet<usize>>) -> &'static str {
+    match seeds {
+        Some(seeds) if seeds.len() > 1 => OVERLAP_COLOR,
+        Some(seeds) => seeds.first().map_or(UNREACHED_COLOR, |&seed| seed_color(seed)),
+        None => UNREACHED_COLOR,
+    }
+}
+
+fn seed_label(seeds: Option<&BTreeSet<usize>>, seed_addresses: &[String]) -> String {
+    seeds.map_or(String::new(), |seeds| seeds.iter().map(|&seed| seed_addresses[seed].as_str()).collect::<Vec<_>>().join(";"))
+}
+
+#[derive(Serialize, Debug)]
+pub struct SeedRow {
+    pub address: String,
+    // Seed addresses joined by semicolons.
+    pub seeds: String,
+    pub seed_count: usize,
+}
+
+pub fn seed_summary(reach: &HashMap<NodeIndex, BTreeSet<usize>>, seed_addresses: &[String], node_count: usize) -> String {
+    let mut summary = String::new();
+    for (seed, address) in seed_addresses.iter().enumerate() {
+        let reached = reach.values().filter(|seeds| seeds.contains(&seed)).count();
+        let only = reach.values().filter(|seeds| seeds.len() == 1 && seeds.contains(&seed)).count();
+        writeln!(summary, "Seed {} reached {} addresses, {} of them from no other seed", address, reached, only).unwrap();
+    }
+    let overlap = reach.values().filter(|seeds| seeds.len() > 1).count();
+    writeln!(summary, "{} of {} addresses were reached from more than one seed", overlap, node_count).unwrap();
+    summary
+}
+
+// Overlaps are drawn as wedges in the colours of the seeds that share the address.
+pub fn seed_dot(graph: &G, reach: &HashMap<NodeIndex, BTreeSet<usize>>) -> String {
+    let edge_attributes = |_, _| String::new();
+    let node_attributes = |_, (n, address): (NodeIndex, &String)| match reach.get(&n) {
+        Some(seeds) if seeds.len() > 1 => {
+            let colors: Vec<&str> = seeds.iter().map(|&seed| seed_color(seed)).collect();
+            format!("label = \"{}\" style = wedged fillcolor = \"{}\"", address, colors.join(":"))
+        }
+        seeds => format!("label = \"{}\" style = filled fillcolor = \"{}\"", address, node_color(seeds)),
+    };
+    format!("{:?}", Dot::with_attr_getters(graph, &[Config::NodeNoLabel, Config::EdgeNoLabel], &edge_attributes, &node_attributes))
+}
+
+// A self-contained page drawn with vis-network, nodes coloured by seed with overlaps in the overlap colour.
+pub fn seed_html(graph: &G, reach: &HashMap<NodeIndex, BTreeSet<usize>>, seed_addresses: &[String], summary: &str) -> Result<String> {
+    let nodes: Vec<_> = graph
+        .node_indices()
+        .map(|n| {
+            let seeds = reach.get(&n);
+            json!({"id": n.index(), "title": format!("{} (reached from {})", graph[n], seed_label(seeds, seed_addresses)), "color": node_color(seeds)})
+        })
+        .collect();
+    let edges: Vec<_> = graph.edge_references().map(|e| json!({"from": e.source().index(), "to": e.target().index()})).collect();
+    let mut legend: String = seed_addresses
+        .iter()
+        .enumerate()
+        .map(|(seed, address)| format!("<span style=\"background:{}\">&nbsp;&nbsp;&nbsp;</span> {} ", seed_color(seed), address))
+        .collect();
+    legend.push_str(&format!("<span style=\"background:{}\">&nbsp;&nbsp;&nbsp;</span> several seeds", OVERLAP_COLOR));
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Seed attribution</title>
+<script src="https://unpkg.com/vis-network/standalone/umd/vis-network.min.js"></script>
+</head>
+<body>
+<pre>{}</pre>
+<p>{}</p>
+<div id="graph" style="width: 100%; height: 90vh;"></div>
+<script>
+new vis.Network(document.getElementById("graph"), {{nodes: {}, edges: {}}}, {{nodes: {{shape: "dot", size: 8}}, edges: {{arrows: "to"}}, physics: {{stabilization: true}}}});
+</script>
+</body>
+</html>
+"#,
+        summary.trim_end(),
+        legend,
+        serde_json::to_string(&nodes)?,
+        serde_json::to_string(&edges)?
+    ))
+}
+
+// GEXF 1.3 for Gephi, with the seeds as a node attribute and the colour as viz:color.
+pub fn seed_gexf(graph: &G, reach: &HashMap<NodeIndex, BTreeSet<usize>>, seed_addresses: &[String]) -> String {
+    let mut gexf = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <gexf xmlns=\"http://gexf.net/1.3\" xmlns:viz=\"http://gexf.net/1.3/viz\" version=\"1.3\">\n\
+         <graph defaultedgetype=\"directed\">\n\
+         <attributes class=\"node\">\n\
+         <attribute id=\"seeds\" title=\"seeds\" type=\"string\"/>\n\
+         <attribute id=\"seed_count\" title=\"seed_count\" type=\"integer\"/>\n\
+         </attributes>\n<nodes>\n",
+    );
+    for n in graph.node_indices() {
+        let seeds = reach.get(&n);
+        let color = node_color(seeds);
+        let channel = |i: usize| u8::from_str_radix(&color[i..i + 2], 16).unwrap();
+        writeln!(
+            gexf,
+            "<node id=\"{}\" label=\"{}\"><attvalues><attvalue for=\"seeds\" value=\"{}\"/><attvalue for=\"seed_count\" value=\"{}\"/></attvalues><viz:color r=\"{}\" g=\"{}\" b=\"{}\"/></node>",
+            n.index(),
+            graph[n],
+            seed_label(seeds, seed_addresses),
+            seeds.map_or(0, |seeds| seeds.len()),
+            channel(1),
+            channel(3),
+            channel(5)
+        )
+        .unwrap();
+    }
+    gexf.push_str("</nodes>\n<edges>\n");
+    for e in graph.edge_references() {
+        writeln!(gexf, "<edge id=\"{}\" source=\"{}\" target=\"{}\"/>", e.id().index(), e.source().index(), e.target().index()).unwrap();
+    }
+    gexf.push_str("</edges>\n</graph>\n</gexf>\n");
+    gexf
+}
+
+pub fn run_seed_map_command(args: &SeedMapArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let crawl_state = load_crawl_state()?;
+    if crawl_state.seeds.is_empty() {
+        return Err(eyre::eyre!("The crawl state records no seeds, crawl with --seed first"));
+    }
+    let reach = seed_reach(&graph, &crawl_state);
+    let summary = seed_summary(&reach, &crawl_state.seeds, graph.node_count());
+    print!("{}", summary);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let prefix = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&format!("{}.csv", prefix))?;
+    for n in graph.node_indices() {
+        let seeds = reach.get(&n);
+        writer.serialize(SeedRow { address: graph[n].clone(), seeds: seed_label(seeds, &crawl_state.seeds), seed_count: seeds.map_or(0, |seeds| seeds.len()) })?;
+    }
+    commit_csv(writer)?;
+    write_atomically(&format!("{}.dot", prefix), seed_dot(&graph, &reach).as_bytes())?;
+    write_atomically(&format!("{}.html", prefix), seed_html(&graph, &reach, &crawl_state.seeds, &summary)?.as_bytes())?;
+    write_atomically(&format!("{}.gexf", prefix), seed_gexf(&graph, &reach, &crawl_state.seeds).as_bytes())?;
+    println!("Saved seed attribution as {}.csv, .dot, .html and .gexf", prefix);
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use eyre::Result;
+use serde::Deserialize;
+
+use crate::etherscan::{Etherscan, ProxyResponse};
+use crate::http_client;
+
+// The transaction a crawl is seeded from. Contract creations have no recipient.
+#[derive(Debug, Clone)]
+pub struct TransactionSeed {
+    pub hash: String,
+    pub from: String,
+    pub to: Option<String>,
+    pub block_number: u64,
+    pub timestamp: u64,
+}
+
+impl TransactionSeed {
+    pub fn endpoints(&self) -> Vec<String> {
+        let mut endpoints = vec![self.from.to_lowercase()];
+        endpoints.extend(self.to.as_ref().map(|to| to.to_lowercase()).filter(|to| *to != endpoints[0]));
+        endpoints
+    }
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct ProxyTransaction {
+    from: String,
+    to: Option<String>,
+    blockNumber: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProxyBlock {
+    timestamp: String,
+}
+
+fn parse_quantity(quantity: &str) -> Result<u64> {
+    let hex = quantity.strip_prefix("0x").ok_or_else(|| eyre::eyre!("Unexpected quantity {}", quantity))?;
+    Ok(u64::from_str_radix(hex, 16)?)
+}
+
+// Looks up the transaction and the timestamp of its block through the JSON-RPC proxy, two requests.
+pub async fn fetch_seed(hash: &str, api_key: &str) -> Result<TransactionSeed> {
+    let client = http_client::client();
+    let etherscan = Etherscan::new(&client, api_key);
+    let (transaction, _): (ProxyResponse<ProxyTransaction>, usize) = etherscan.proxy("eth_getTransactionByHash", &[("txhash", hash.to_string())]).await?;
+    let transaction = transaction
+        .result
+        .ok_or_else(|| eyre::eyre!("Transaction {} was not found", hash))?;
+    let block_number = parse_quantity(transaction.blockNumber.as_deref().ok_or_else(|| eyre::eyre!("Transaction {} is still pending", hash))?)?;
+    let parameters = [("tag", format!("{:#x}", block_number)), ("boolean", "false".to_string())];
+    let (block, _): (ProxyResponse<ProxyBlock>, usize) = etherscan.proxy("eth_getBlockByNumber", &parameters).await?;
+    let block = block
+        .result
+        .ok_or_else(|| eyre::eyre!("Block {} of transaction {} was not found", block_number, hash))?;
+    Ok(TransactionSeed {
+        hash: hash.to_lowercase(),
+        from: transaction.from,
+        to: transaction.to,
+        block_number,
+        timestamp: parse_quantity(&block.timestamp)?,
+    })
+}
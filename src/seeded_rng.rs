@@ -0,0 +1,16 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::workspace;
+
+// ChaCha8 from rand_chacha produces the same stream for a seed in every release, which StdRng does not promise.
+pub type SeededRng = ChaCha8Rng;
+
+// Every randomized command runs seeded, drawing a seed when none is given. The seed is printed and stored in
+// the catalog entries of the files the command writes, so any sample or embedding can be regenerated exactly.
+pub fn seeded_rng(seed: Option<u64>) -> SeededRng {
+    let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+    println!("Random seed is {}", seed);
+    workspace::record_seed(seed);
+    SeededRng::seed_from_u64(seed)
+}
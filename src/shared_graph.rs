@@ -0,0 +1,156 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::{Arc, RwLock};
+
+use crate::crawl_events::{CrawlEvent, Sink};
+use crate::{Transaction, G};
+
+// Edges held back while snapshots are out before the writer gives up waiting and copies the graph once.
+const MAX_DEFERRED_EDGES: usize = 100_000;
+
+// A consistent view of a shared graph, cheap to take and unaffected by later appends.
+#[derive(Clone)]
+pub struct GraphSnapshot {
+    graph: Arc<G>,
+    // Append batches the graph had seen, increasing with every batch and every replacement.
+    pub generation: u64,
+}
+
+impl Deref for GraphSnapshot {
+    type Target = G;
+
+    fn deref(&self) -> &G {
+        &self.graph
+    }
+}
+
+#[derive(Default)]
+struct SharedState {
+    graph: Arc<G>,
+    node_indices: HashMap<String, NodeIndex>,
+    generation: u64,
+    // Batches appended while a snapshot shared the graph, applied once the graph is no longer shared.
+    deferred: Vec<(String, String, Transaction)>,
+    deferred_generations: u64,
+}
+
+impl SharedState {
+    // Appends in place when no snapshot holds the graph. Otherwise the batch waits for the snapshots to
+    // be dropped, unless too much has piled up, in which case the graph is copied for the writer.
+    fn apply_deferred(&mut self) {
+        if self.deferred.is_empty() {
+            return;
+        }
+        if Arc::get_mut(&mut self.graph).is_none() && self.deferred.len() < MAX_DEFERRED_EDGES {
+            return;
+        }
+        let graph = Arc::make_mut(&mut self.graph);
+        graph.reserve_edges(self.deferred.len());
+        for (from, to, transaction) in self.deferred.drain(..) {
+            let mut node = |address: String| *self.node_indices.entry(address).or_insert_with_key(|address| graph.add_node(address.clone()));
+            let (source, target) = (node(from), node(to));
+            graph.add_edge(source, target, transaction);
+        }
+        self.generation += self.deferred_generations;
+        self.deferred_generations = 0;
+    }
+}
+
+// A graph that queries read while a running crawl appends to it. Readers either hold the lock for a
+// short query or take a snapshot, which shares the graph instead of copying it. Appends go straight into
+// the graph when nothing shares it and wait while snapshots do, so a long analysis on a snapshot holds
+// up only what the crawl adds meanwhile.
+#[derive(Default)]
+pub struct SharedGraph {
+    state: RwLock<SharedState>,
+}
+
+impl SharedGraph {
+    pub fn new(graph: G) -> SharedGraph {
+        let shared = SharedGraph::default();
+        shared.replace(graph);
+        shared
+    }
+
+    // Runs a query against the graph as of one moment. The crawler waits for as long as the query runs,
+    // so long analyses should run on a snapshot. Batches deferred for snapshots dropped since are applied
+    // first, which takes the write lock only while some are pending.
+    pub fn read<R>(&self, query: impl FnOnce(&G) -> R) -> R {
+        {
+            let state = self.state.read().unwrap();
+            if state.deferred.is_empty() {
+                return query(&state.graph);
+            }
+        }
+        let mut state = self.state.write().unwrap();
+        state.apply_deferred();
+        query(&state.graph)
+    }
+
+    pub fn snapshot(&self) -> GraphSnapshot {
+        let mut state = self.state.write().unwrap();
+        // A batch deferred for a snapshot that has been dropped since belongs in this one.
+        state.apply_deferred();
+        GraphSnapshot { graph: state.graph.clone(), generation: state.generation }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.read(|graph| graph.node_count())
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.read(|graph| graph.edge_count())
+    }
+
+    // Appends a batch as one generation, so readers never see part of an address's history.
+    pub fn append<S: AsRef<str>>(&self, edges: impl IntoIterator<Item = (S, S, Transaction)>) {
+        let mut state = self.state.write().unwrap();
+        state.deferred.extend(edges.into_iter().map(|(from, to, transaction)| (from.as_ref().to_string(), to.as_ref().to_string(), transaction)));
+        state.deferred_generations += 1;
+        state.apply_deferred();
+    }
+
+    // Swaps in a graph saved elsewhere, e.g. the one a crawl finished with. Snapshots keep the graph they had.
+    pub fn replace(&self, graph: G) {
+        let mut state = self.state.write().unwrap();
+        let generation = state.generation + state.deferred_generations + 1;
+        let node_indices = graph.node_indices().map(|node| (graph[node].clone(), node)).collect();
+        *state = SharedState { graph: Arc::new(graph), node_indices, generation, ..Default::default() };
+    }
+}
+
+// Appends the edges of a crawl to a shared graph as they arrive. Edges are buffered per address and
+// applied when the address is reported expanded, so queries see whole address histories.
+pub struct SharedGraphSink {
+    graph: Arc<SharedGraph>,
+    pending: Vec<(String, String, Transaction)>,
+}
+
+impl SharedGraphSink {
+    pub fn new(graph: Arc<SharedGraph>) -> SharedGraphSink {
+        SharedGraphSink { graph, pending: vec![] }
+    }
+
+    fn flush(&mut self) {
+        if !self.pending.is_empty() {
+            self.graph.append(self.pending.drain(..));
+        }
+    }
+}
+
+impl Sink for SharedGraphSink {
+    fn send(&mut self, event: &CrawlEvent) -> Result<()> {
+        match event {
+            CrawlEvent::EdgeAdded { from, to, transaction } => self.pending.push((from.clone(), to.clone(), (**transaction).clone())),
+            CrawlEvent::AddressExpanded { .. } | CrawlEvent::CheckpointWritten { .. } => self.flush(),
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.flush();
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[cfg(feature = "crawl")]
+use {
+    crate::{atomic_file::write_atomically, money::{wei_to_coin, Amount}, RawTransaction, CONTRACT_ADDRESSES, DATA_STORAGE_FOLDER},
+    eyre::Result,
+    std::fmt::Write,
+};
+
+#[cfg(feature = "crawl")]
+pub const SKIP_REPORT_FILENAME: &str = "skip_report.json";
+
+// Why a fetched record that passed validation became no edge. A failed contract creation counts as failed.
+// Genesis allocations are not skipped, they become edges that the special address report accounts for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    // Reverted on chain: isError is set, nothing but the fee moved.
+    Failed,
+    // Deploys a contract, so there is no recipient for an edge.
+    ContractCreation,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct SkippedRecord {
+    pub reason: SkipReason,
+    pub value_wei: u128,
+    pub stablecoin_call: bool,
+}
+
+#[cfg(feature = "crawl")]
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct SkipCount {
+    pub records: usize,
+    // Native value the records carried, which for failed ones never arrived.
+    pub value_wei: u128,
+    // Calls to a tracked stablecoin contract among them, whose token amounts are not in the graph either.
+    pub stablecoin_calls: usize,
+}
+
+// Records the ingest filter left out, keyed by transaction hash, so a transaction in the histories of both
+// its sender and recipient, or fetched again by a later run, counts once.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SkipReport {
+    pub records: BTreeMap<String, SkippedRecord>,
+}
+
+#[cfg(feature = "crawl")]
+impl SkipReport {
+    pub fn skip_reason(transaction: &RawTransaction) -> Option<SkipReason> {
+        if transaction.isError != "0" {
+            Some(SkipReason::Failed)
+        } else if !transaction.contractAddress.is_empty() {
+            Some(SkipReason::ContractCreation)
+        } else {
+            None
+        }
+    }
+
+    pub fn skip(&mut self, transaction: &RawTransaction, reason: SkipReason, value_wei: u128) {
+        let stablecoin_call = CONTRACT_ADDRESSES.lock().unwrap().values().any(|contract| *contract == transaction.to);
+        self.records.entry(transaction.hash.clone()).or_insert(SkippedRecord { reason, value_wei, stablecoin_call });
+    }
+
+    pub fn add(&mut self, other: &SkipReport) {
+        for (hash, record) in other.records.iter() {
+            self.records.entry(hash.clone()).or_insert(*record);
+        }
+    }
+
+    pub fn counts(&self) -> BTreeMap<SkipReason, SkipCount> {
+        let mut counts: BTreeMap<SkipReason, SkipCount> = BTreeMap::new();
+        for record in self.records.values() {
+            let count = counts.entry(record.reason).or_default();
+            count.records += 1;
+            count.value_wei += record.value_wei;
+            count.stablecoin_calls += record.stablecoin_call as usize;
+        }
+        counts
+    }
+
+    pub fn describe(&self) -> String {
+        let total_wei: u128 = self.records.values().map(|record| record.value_wei).sum();
+        let mut description = format!("Skipped records: {}, carrying {}\n", self.records.len(), Amount::Coin(wei_to_coin(total_wei)));
+        for (reason, count) in self.counts() {
+            writeln!(
+                description,
+                "  {:?}: {} records, {}, {} of them calls to tracked stablecoins",
+                reason,
+                count.records,
+                Amount::Coin(wei_to_coin(count.value_wei)),
+                count.stablecoin_calls
+            )
+            .unwrap();
+        }
+        description
+    }
+
+    // Per-reason counts over all runs; the records themselves stay in the crawl state.
+    pub fn save(&self) -> Result<()> {
+        let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, SKIP_REPORT_FILENAME);
+        let total_wei: u128 = self.records.values().map(|record| record.value_wei).sum();
+        let report = serde_json::json!({
+            "skipped_records": self.records.len(),
+            "skipped_value_wei": total_wei.to_string(),
+            "skipped_value": wei_to_coin(total_wei),
+            "reasons": self.counts().into_iter().map(|(reason, count)| (serde_json::to_value(reason).unwrap().as_str().unwrap().to_string(), count)).collect::<BTreeMap<String, SkipCount>>(),
+        });
+        write_atomically(&file_pathname, serde_json::to_string_pretty(&report)?.as_bytes())
+    }
+}
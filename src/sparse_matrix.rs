@@ -0,0 +1,95 @@
+use eyre::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+
+use crate::atomic_file::AtomicFile;
+use crate::cli::{EdgeWeight, ExportMatrixArgs};
+use crate::layers::select_layers;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+// Compressed sparse row matrix; row and column i correspond to node index i of the source graph.
+#[derive(Debug, Clone)]
+pub struct CsrMatrix {
+    pub dimension: usize,
+    pub row_offsets: Vec<usize>,
+    pub column_indices: Vec<usize>,
+    pub values: Vec<f64>,
+}
+
+impl CsrMatrix {
+    // Parallel edges are summed into one entry. A symmetric matrix also adds every edge in the reverse direction.
+    pub fn from_graph(graph: &G, weight: EdgeWeight, symmetric: bool) -> CsrMatrix {
+        let mut rows: Vec<BTreeMap<usize, f64>> = vec![BTreeMap::new(); graph.node_count()];
+        for edge in graph.raw_edges() {
+            let (source, target) = (edge.source().index(), edge.target().index());
+            let edge_weight = match weight {
+                EdgeWeight::Count => 1.0,
+                EdgeWeight::UsdVolume => edge.weight.data.as_ref().map_or(0.0, |data| data.usd_value),
+            };
+            if edge_weight <= 0.0 {
+                continue;
+            }
+            *rows[source].entry(target).or_insert(0.0) += edge_weight;
+            if symmetric && source != target {
+                *rows[target].entry(source).or_insert(0.0) += edge_weight;
+            }
+        }
+
+        let mut matrix = CsrMatrix { dimension: rows.len(), row_offsets: vec![0], column_indices: vec![], values: vec![] };
+        for row in rows {
+            for (column, value) in row {
+                matrix.column_indices.push(column);
+                matrix.values.push(value);
+            }
+            matrix.row_offsets.push(matrix.column_indices.len());
+        }
+        matrix
+    }
+
+    pub fn nonzero_count(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn row(&self, row: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let range = self.row_offsets[row]..self.row_offsets[row + 1];
+        self.column_indices[range.clone()].iter().copied().zip(self.values[range].iter().copied())
+    }
+
+    pub fn write_matrix_market(&self, file_pathname: &str) -> Result<()> {
+        let mut file = AtomicFile::create(file_pathname)?;
+        writeln!(file, "%%MatrixMarket matrix coordinate real general")?;
+        writeln!(file, "{} {} {}", self.dimension, self.dimension, self.nonzero_count())?;
+        for row in 0..self.dimension {
+            for (column, value) in self.row(row) {
+                writeln!(file, "{} {} {}", row + 1, column + 1, value)?; // Matrix Market indices are 1-based
+            }
+        }
+        file.commit()
+    }
+}
+
+pub fn run_export_matrix_command(args: &ExportMatrixArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let graph = select_layers(graph, &args.layer)?;
+    let matrix = CsrMatrix::from_graph(&graph, args.weight, args.symmetric);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    matrix.write_matrix_market(&file_pathname)?;
+    let addresses_pathname = format!("{}.addresses.txt", file_pathname.trim_end_matches(".mtx"));
+    let mut addresses_file = AtomicFile::create(&addresses_pathname)?;
+    for node in graph.node_indices() {
+        writeln!(addresses_file, "{}", graph[node])?;
+    }
+    addresses_file.commit()?;
+    println!(
+        "Saved {}x{} matrix with {} nonzero entries as {}, row addresses as {}",
+        matrix.dimension,
+        matrix.dimension,
+        matrix.nonzero_count(),
+        &file_pathname,
+        &addresses_pathname
+    );
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "crawl")]
+use std::fmt::Write;
+
+#[cfg(feature = "crawl")]
+use crate::{cli::CrawlArgs, money::{Amount, WEI_PER_COIN}, Transaction};
+
+// Sender Etherscan reports for the allocations of the genesis block, whose hashes are GENESIS_<address>.
+pub const GENESIS: &str = "GENESIS";
+pub const BURN_NODE: &str = "BURN";
+// The zero address and the conventional dead address, where value is sent to destroy it.
+pub const BURN_ADDRESSES: [&str; 2] = ["0x0000000000000000000000000000000000000000", "0x000000000000000000000000000000000000dead"];
+
+// What the crawl does with edges that start at the genesis pseudo-sender or end at a burn address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecialAddressPolicy {
+    /// Leave the edges out of the graph
+    Drop,
+    /// Keep the edges with a node per special address, or per allocation for genesis; these nodes are never crawled
+    Keep,
+    /// Keep the edges with one GENESIS or BURN node for all special addresses of the kind
+    Aggregate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpecialAddressPolicies {
+    pub genesis: SpecialAddressPolicy,
+    pub burn: SpecialAddressPolicy,
+}
+
+// Genesis allocations were always dropped, burn addresses always kept.
+impl Default for SpecialAddressPolicies {
+    fn default() -> SpecialAddressPolicies {
+        SpecialAddressPolicies { genesis: SpecialAddressPolicy::Drop, burn: SpecialAddressPolicy::Keep }
+    }
+}
+
+pub fn is_special(address: &str) -> bool {
+    address == GENESIS || address.starts_with("GENESIS_") || address == BURN_NODE || BURN_ADDRESSES.contains(&address)
+}
+
+// Edges and volume that touched special addresses, whatever the policy did with them.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy)]
+pub struct SpecialVolume {
+    pub edges: usize,
+    pub value_wei: u128,
+    pub usd_value: f64,
+}
+
+#[cfg(feature = "crawl")]
+impl SpecialVolume {
+    fn record(&mut self, transaction: &Transaction) {
+        self.edges += 1;
+        self.value_wei = self.value_wei.saturating_add(transaction.value_wei);
+        self.usd_value += transaction.data.as_ref().map_or(0.0, |data| data.usd_value);
+    }
+
+    fn add(&mut self, other: &SpecialVolume) {
+        self.edges += other.edges;
+        self.value_wei = self.value_wei.saturating_add(other.value_wei);
+        self.usd_value += other.usd_value;
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy)]
+pub struct SpecialAddressReport {
+    pub genesis: SpecialVolume,
+    pub burn: SpecialVolume,
+}
+
+#[cfg(feature = "crawl")]
+impl SpecialAddressReport {
+    pub fn add(&mut self, other: &SpecialAddressReport) {
+        self.genesis.add(&other.genesis);
+        self.burn.add(&other.burn);
+    }
+
+    pub fn describe(&self, policies: &SpecialAddressPolicies) -> String {
+        let mut description = String::from("Special addresses:\n");
+        for (name, policy, volume) in [("Genesis allocations", policies.genesis, &self.genesis), ("Burns", policies.burn, &self.burn)] {
+            writeln!(
+                description,
+                "{} ({:?}): {} edges, {} and {}",
+                name,
+                policy,
+                volume.edges,
+                Amount::Coin(volume.value_wei as f64 / WEI_PER_COIN),
+                Amount::Usd(volume.usd_value)
+            )
+            .unwrap();
+        }
+        description
+    }
+}
+
+#[cfg(feature = "crawl")]
+impl SpecialAddressPolicies {
+    pub fn from_args(args: &CrawlArgs) -> SpecialAddressPolicies {
+        let defaults = SpecialAddressPolicies::default();
+        SpecialAddressPolicies { genesis: args.genesis.unwrap_or(defaults.genesis), burn: args.burns.unwrap_or(defaults.burn) }
+    }
+
+    // The endpoints an edge gets under the policies, or None when it is dropped. The volume of edges with a
+    // special endpoint is recorded either way.
+    pub fn route(&self, from: &str, to: &str, transaction: &Transaction, report: &mut SpecialAddressReport) -> Option<(String, String)> {
+        let (mut from, mut to) = (from.to_string(), to.to_string());
+        let mut dropped = false;
+        if from == GENESIS {
+            report.genesis.record(transaction);
+            match self.genesis {
+                SpecialAddressPolicy::Drop => dropped = true,
+                SpecialAddressPolicy::Keep => from = transaction.hash.clone(),
+                SpecialAddressPolicy::Aggregate => {}
+            }
+        }
+        if BURN_ADDRESSES.contains(&to.as_str()) {
+            report.burn.record(transaction);
+            match self.burn {
+                SpecialAddressPolicy::Drop => dropped = true,
+                SpecialAddressPolicy::Keep => {}
+                SpecialAddressPolicy::Aggregate => to = BURN_NODE.to_string(),
+            }
+        }
+        (!dropped).then_some((from, to))
+    }
+}
@@ -0,0 +1,161 @@
+use eyre::Result;
+use nalgebra::{DMatrix, DVector, SymmetricEigen};
+use nalgebra_sparse::{CooMatrix, CsrMatrix as SparseMatrix};
+use rand::Rng;
+use std::collections::VecDeque;
+use std::fs;
+
+use crate::atomic_file::write_atomically;
+use crate::cli::SpectralArgs;
+use crate::seeded_rng::seeded_rng;
+use crate::sparse_matrix::CsrMatrix;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER};
+
+// Lanczos iteration with full (two-pass) reorthogonalization. Returns the Ritz values in descending order; the
+// extreme ones converge first. Vectors are kept orthogonal to `deflate` when it is given.
+fn lanczos_ritz_values(matrix: &SparseMatrix<f64>, deflate: Option<&DVector<f64>>, iterations: usize, rng: &mut impl Rng) -> Vec<f64> {
+    let dimension = matrix.nrows();
+    let orthogonalize = |vector: &mut DVector<f64>, basis: &[DVector<f64>]| {
+        for _ in 0..2 {
+            for b in deflate.into_iter().chain(basis.iter()) {
+                let projection = vector.dot(b);
+                vector.axpy(-projection, b, 1.0);
+            }
+        }
+    };
+    let steps = iterations.min(dimension - deflate.map_or(0, |_| 1));
+
+    let mut start = DVector::from_fn(dimension, |_, _| rng.gen::<f64>() - 0.5);
+    orthogonalize(&mut start, &[]);
+    if start.norm() < 1e-12 {
+        return vec![];
+    }
+    let mut basis = vec![start.normalize()];
+    let (mut alphas, mut betas) = (vec![], vec![]);
+    for step in 0..steps {
+        let mut next = matrix * &basis[step];
+        let scale = next.norm();
+        alphas.push(next.dot(&basis[step]));
+        orthogonalize(&mut next, &basis);
+        let beta = next.norm();
+        // The Krylov space is exhausted once the new direction is only rounding noise.
+        if beta <= 1e-10 * scale.max(1.0) || step + 1 == steps {
+            break;
+        }
+        betas.push(beta);
+        basis.push(next / beta);
+    }
+
+    let size = alphas.len();
+    let tridiagonal = DMatrix::from_fn(size, size, |i, j| match i.abs_diff(j) {
+        0 => alphas[i],
+        1 => betas[i.min(j)],
+        _ => 0.0,
+    });
+    let mut values: Vec<f64> = SymmetricEigen::new(tridiagonal).eigenvalues.iter().copied().collect();
+    values.sort_by(|a, b| b.partial_cmp(a).unwrap());
+    values
+}
+
+fn component_labels(matrix: &CsrMatrix) -> (Vec<usize>, usize) {
+    let mut labels = vec![usize::MAX; matrix.dimension];
+    let mut component_count = 0;
+    for start in 0..matrix.dimension {
+        if labels[start] != usize::MAX {
+            continue;
+        }
+        labels[start] = component_count;
+        let mut queue = VecDeque::from([start]);
+        while let Some(row) = queue.pop_front() {
+            for (column, _) in matrix.row(row) {
+                if labels[column] == usize::MAX {
+                    labels[column] = component_count;
+                    queue.push_back(column);
+                }
+            }
+        }
+        component_count += 1;
+    }
+    (labels, component_count)
+}
+
+pub struct SpectralSummary {
+    pub top_adjacency_eigenvalues: Vec<f64>,
+    pub component_count: usize,
+    pub largest_component_size: usize,
+    pub algebraic_connectivity: f64,
+}
+
+// The matrix must be symmetric. Algebraic connectivity is the second smallest Laplacian eigenvalue of
+// the largest connected component, because it is zero for any graph with more than one component.
+pub fn spectral_summary(matrix: &CsrMatrix, top: usize, iterations: usize, rng: &mut impl Rng) -> SpectralSummary {
+    let mut adjacency = CooMatrix::new(matrix.dimension, matrix.dimension);
+    for row in 0..matrix.dimension {
+        for (column, value) in matrix.row(row) {
+            adjacency.push(row, column, value);
+        }
+    }
+    let mut top_adjacency_eigenvalues = lanczos_ritz_values(&SparseMatrix::from(&adjacency), None, iterations, rng);
+    top_adjacency_eigenvalues.truncate(top);
+
+    let (labels, component_count) = component_labels(matrix);
+    let mut sizes = vec![0; component_count];
+    labels.iter().for_each(|&label| sizes[label] += 1);
+    let (largest, &largest_component_size) = sizes.iter().enumerate().max_by_key(|&(_, size)| *size).unwrap_or((0, &0));
+    let mut local_index = vec![usize::MAX; matrix.dimension];
+    for (local, node) in (0..matrix.dimension).filter(|&n| labels[n] == largest).enumerate() {
+        local_index[node] = local;
+    }
+
+    // Largest eigenvalues of shift * I - L are the smallest ones of L; the Gershgorin bound keeps the shift valid.
+    let mut laplacian_entries = vec![];
+    let mut degrees = vec![0.0; largest_component_size];
+    for row in (0..matrix.dimension).filter(|&n| labels[n] == largest) {
+        for (column, value) in matrix.row(row).filter(|&(column, _)| column != row) {
+            laplacian_entries.push((local_index[row], local_index[column], value));
+            degrees[local_index[row]] += value;
+        }
+    }
+    let shift = 2.0 * degrees.iter().copied().fold(0.0, f64::max);
+    let mut shifted_laplacian = CooMatrix::new(largest_component_size, largest_component_size);
+    for (row, column, value) in laplacian_entries {
+        shifted_laplacian.push(row, column, value);
+    }
+    for (node, degree) in degrees.iter().enumerate() {
+        shifted_laplacian.push(node, node, shift - degree);
+    }
+    let null_vector = DVector::from_element(largest_component_size, 1.0).normalize();
+    let algebraic_connectivity = if largest_component_size < 2 {
+        0.0
+    } else {
+        let ritz_values = lanczos_ritz_values(&SparseMatrix::from(&shifted_laplacian), Some(&null_vector), iterations, rng);
+        ritz_values.first().map_or(0.0, |&value| (shift - value).max(0.0))
+    };
+
+    SpectralSummary { top_adjacency_eigenvalues, component_count, largest_component_size, algebraic_connectivity }
+}
+
+pub fn run_spectral_command(args: &SpectralArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let matrix = CsrMatrix::from_graph(&graph, args.weight, true);
+    let summary = spectral_summary(&matrix, args.top, args.iterations, &mut seeded_rng(args.seed.seed));
+
+    let mut report = format!("Top {} adjacency eigenvalues:\n", summary.top_adjacency_eigenvalues.len());
+    for (rank, value) in summary.top_adjacency_eigenvalues.iter().enumerate() {
+        report.push_str(&format!("  {}: {:.6}\n", rank + 1, value));
+    }
+    report.push_str(&format!(
+        "Connected components: {}, largest has {} of {} addresses\nAlgebraic connectivity of the largest component: {:.6}\n",
+        summary.component_count,
+        summary.largest_component_size,
+        matrix.dimension,
+        summary.algebraic_connectivity
+    ));
+    print!("{}", report);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    write_atomically(&file_pathname, report.as_bytes())?;
+    println!("Saved spectral report as {}", &file_pathname);
+    Ok(())
+}
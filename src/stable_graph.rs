@@ -0,0 +1,41 @@
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::stable_graph::StableGraph;
+use petgraph::Directed;
+
+use crate::{Transaction, G};
+
+// Removing from a Graph swaps the last element into the freed slot, so indices held by a pruning pass
+// go stale. StableGraph leaves the slot vacant instead; compact renumbers nodes and edge endpoints
+// together, so the positional indices in the serialized format stay consistent.
+pub type StableG = StableGraph<String, Transaction, Directed>;
+
+pub fn to_stable(graph: &G) -> StableG {
+    StableG::from(graph.clone())
+}
+
+pub fn compact(graph: StableG) -> G {
+    G::from(graph)
+}
+
+// Removes matching nodes together with their edges, returning the number of removed nodes.
+pub fn remove_nodes_where(graph: &mut StableG, mut predicate: impl FnMut(&StableG, NodeIndex) -> bool) -> usize {
+    let node_count_before = graph.node_count();
+    graph.retain_nodes(|graph, node_index| !predicate(&graph, node_index));
+    node_count_before - graph.node_count()
+}
+
+// Keeps edges matching the predicate, returning the number of removed edges.
+pub fn retain_edges_where(graph: &mut StableG, mut predicate: impl FnMut(&StableG, EdgeIndex) -> bool) -> usize {
+    let edge_count_before = graph.edge_count();
+    graph.retain_edges(|graph, edge_index| predicate(&graph, edge_index));
+    edge_count_before - graph.edge_count()
+}
+
+// Drops nodes without any edge, which filters that remove edges leave behind, returning their number.
+// The remaining nodes keep their relative order.
+pub fn remove_isolated_nodes(graph: &mut G) -> usize {
+    let mut stable = StableG::from(std::mem::take(graph));
+    let removed = remove_nodes_where(&mut stable, |stable, node_index| stable.neighbors_undirected(node_index).next().is_none());
+    *graph = compact(stable);
+    removed
+}
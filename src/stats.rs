@@ -0,0 +1,138 @@
+use eyre::Result;
+use std::collections::HashSet;
+use std::fs;
+
+use crate::money::Amount;
+use crate::atomic_file::write_atomically;
+use crate::cli::{EdgeWeight, StatsArgs};
+use crate::annotations::Annotations;
+use crate::dust_filter::{save_removed_dust, REMOVED_DUST_FILENAME};
+use crate::edge_handling::apply_edge_handling;
+use crate::layers::select_layers;
+use crate::sparse_matrix::CsrMatrix;
+use crate::{calculate_total_usd_volume, deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+const DEGREE_QUANTILES: [f64; 5] = [0.5, 0.75, 0.9, 0.95, 0.99];
+const VOLUME_TOP_FRACTIONS: [f64; 3] = [0.01, 0.05, 0.1];
+
+// Undirected simple graph: parallel edges and directions collapsed, self-loops dropped.
+fn undirected_links(matrix: &CsrMatrix) -> Vec<(usize, usize)> {
+    (0..matrix.dimension)
+        .flat_map(|row| matrix.row(row).filter(move |&(column, _)| column > row).map(move |(column, _)| (row, column)))
+        .collect()
+}
+
+// Pearson correlation of the degrees at both ends of every link, counted in both orientations.
+pub fn degree_assortativity(degrees: &[usize], links: &[(usize, usize)]) -> Option<f64> {
+    let pairs: Vec<(f64, f64)> = links
+        .iter()
+        .flat_map(|&(a, b)| [(degrees[a] as f64, degrees[b] as f64), (degrees[b] as f64, degrees[a] as f64)])
+        .collect();
+    if pairs.is_empty() {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let mean = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+    let covariance = pairs.iter().map(|p| (p.0 - mean) * (p.1 - mean)).sum::<f64>() / n;
+    let variance = pairs.iter().map(|p| (p.0 - mean).powi(2)).sum::<f64>() / n;
+    (variance > 0.0).then(|| covariance / variance)
+}
+
+// Density of links among the given members: 2 E / (N (N - 1)).
+fn club_density(members: &HashSet<usize>, links: &[(usize, usize)]) -> Option<f64> {
+    let size = members.len();
+    if size < 2 {
+        return None;
+    }
+    let internal = links.iter().filter(|(a, b)| members.contains(a) && members.contains(b)).count();
+    Some(2.0 * internal as f64 / (size * (size - 1)) as f64)
+}
+
+pub fn rich_club_coefficient(degrees: &[usize], links: &[(usize, usize)], min_degree: usize) -> Option<f64> {
+    let members: HashSet<usize> = (0..degrees.len()).filter(|&n| degrees[n] > min_degree).collect();
+    club_density(&members, links)
+}
+
+pub fn graph_stats_report(graph: &G) -> String {
+    let matrix = CsrMatrix::from_graph(graph, EdgeWeight::Count, true);
+    let links = undirected_links(&matrix);
+    let mut degrees = vec![0; matrix.dimension];
+    for &(a, b) in links.iter() {
+        degrees[a] += 1;
+        degrees[b] += 1;
+    }
+    let (total_volume, mean_value) = calculate_total_usd_volume(graph);
+
+    let mut report = format!(
+        "Nodes: {}, Edges: {}, Undirected links: {}\nTotal volume: {}, Mean transaction value: {}\n",
+        graph.node_count(),
+        graph.edge_count(),
+        links.len(),
+        Amount::Usd(total_volume),
+        Amount::Usd(mean_value)
+    );
+    let overall_density = club_density(&(0..matrix.dimension).collect(), &links).unwrap_or(0.0);
+    report.push_str(&format!("Link density: {:.6}\n", overall_density));
+    match degree_assortativity(&degrees, &links) {
+        Some(r) => report.push_str(&format!("Degree assortativity: {:.4}\n", r)),
+        None => report.push_str("Degree assortativity: undefined\n"),
+    }
+
+    let mut sorted_degrees = degrees.clone();
+    sorted_degrees.sort_unstable();
+    report.push_str("\nRich-club coefficient by degree (density among addresses with degree > k):\n");
+    for quantile in DEGREE_QUANTILES {
+        let rank = (sorted_degrees.len() as f64 * quantile) as usize;
+        let Some(&min_degree) = sorted_degrees.get(rank.min(sorted_degrees.len().saturating_sub(1))) else {
+            break;
+        };
+        let members = degrees.iter().filter(|&&d| d > min_degree).count();
+        match rich_club_coefficient(&degrees, &links, min_degree) {
+            Some(phi) => report.push_str(&format!("  p{:.0} k > {}: {} addresses, phi = {:.4}\n", quantile * 100.0, min_degree, members, phi)),
+            None => report.push_str(&format!("  p{:.0} k > {}: {} addresses, undefined\n", quantile * 100.0, min_degree, members)),
+        }
+    }
+
+    // The same density for the addresses moving the most USD, compared against the whole graph.
+    let mut volumes = vec![0.0; matrix.dimension];
+    for edge in graph.raw_edges() {
+        if let Some(data) = &edge.weight.data {
+            volumes[edge.source().index()] += data.usd_value;
+            volumes[edge.target().index()] += data.usd_value;
+        }
+    }
+    let mut by_volume: Vec<usize> = (0..matrix.dimension).collect();
+    by_volume.sort_by(|&a, &b| volumes[b].partial_cmp(&volumes[a]).unwrap());
+    report.push_str("\nRich-club density by USD volume:\n");
+    for fraction in VOLUME_TOP_FRACTIONS {
+        let members: HashSet<usize> = by_volume.iter().take((by_volume.len() as f64 * fraction).ceil() as usize).copied().collect();
+        match club_density(&members, &links) {
+            Some(density) => report.push_str(&format!(
+                "  top {:.0}% ({} addresses): density {:.4}, {:.1}x the overall density\n",
+                fraction * 100.0,
+                members.len(),
+                density,
+                if overall_density > 0.0 { density / overall_density } else { 0.0 }
+            )),
+            None => report.push_str(&format!("  top {:.0}% ({} addresses): undefined\n", fraction * 100.0, members.len())),
+        }
+    }
+    report
+}
+
+pub fn run_stats_command(args: &StatsArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let graph = select_layers(graph, &args.layer)?;
+    let (graph, _, edge_handling_summary) = apply_edge_handling(&graph, &args.edge_handling, &Annotations::load()?);
+    let report = format!("{}{}", edge_handling_summary.describe(&args.edge_handling), graph_stats_report(&graph));
+    print!("{}", report);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    if args.edge_handling.drop_dust_below.is_some() {
+        save_removed_dust(&edge_handling_summary.dust, &format!("{}/{}", DATA_STORAGE_FOLDER, REMOVED_DUST_FILENAME))?;
+    }
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    write_atomically(&file_pathname, report.as_bytes())?;
+    println!("Saved graph statistics as {}", &file_pathname);
+    Ok(())
+}
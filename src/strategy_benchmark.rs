@@ -0,0 +1,203 @@
+use eyre::Result;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use priority_queue::PriorityQueue;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+
+use crate::annotations::Annotations;
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::{BenchmarkStrategiesArgs, TraversalStrategy};
+use crate::delta_crawl::{load_crawl_state, CrawlState};
+use crate::dry_run::requests_for_history;
+use crate::money::Amount;
+use crate::special_addresses::is_special;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+// The histories a recorded crawl fetched, taken back out of its graph: every edge touching a visited address.
+// A replayed strategy can only expand addresses the recording expanded, any other address it picks costs the
+// one request an empty history would and adds nothing, so its figures are lower bounds.
+struct Recording {
+    histories: HashMap<NodeIndex, Vec<EdgeIndex>>,
+    seeds: Vec<NodeIndex>,
+}
+
+impl Recording {
+    fn new(graph: &G, crawl_state: &CrawlState) -> Result<Recording> {
+        let nodes: HashMap<&String, NodeIndex> = graph.node_indices().map(|n| (&graph[n], n)).collect();
+        let histories: HashMap<NodeIndex, Vec<EdgeIndex>> = crawl_state
+            .visited
+            .iter()
+            .filter_map(|address| nodes.get(address))
+            .map(|&n| {
+                let mut edges: Vec<EdgeIndex> = graph.edges_directed(n, petgraph::Direction::Outgoing).map(|e| e.id()).collect();
+                edges.extend(graph.edges_directed(n, petgraph::Direction::Incoming).filter(|e| e.source() != n).map(|e| e.id()));
+                edges.sort();
+                (n, edges)
+            })
+            .collect();
+        let seed_addresses = if crawl_state.seeds.is_empty() { crawl_state.visited.iter().take(1).cloned().collect() } else { crawl_state.seeds.clone() };
+        let seeds: Vec<NodeIndex> = seed_addresses.iter().filter_map(|address| nodes.get(address).copied()).collect();
+        if histories.is_empty() || seeds.is_empty() {
+            return Err(eyre::eyre!("The crawl state records no visited addresses of the graph to replay"));
+        }
+        Ok(Recording { histories, seeds })
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StrategyResult {
+    pub strategy: String,
+    pub budget: usize,
+    pub visited: usize,
+    // Addresses the strategy expanded that the recording never fetched.
+    pub unrecorded: usize,
+    pub requests: usize,
+    pub edges: usize,
+    pub nodes: usize,
+    pub usd_volume: f64,
+    pub usd_per_request: f64,
+    pub targets: usize,
+    // Targets that appear in the replayed graph, and those among them whose own history was fetched.
+    pub targets_reached: usize,
+    pub targets_expanded: usize,
+    pub target_coverage_percent: f64,
+}
+
+fn strategy_name(strategy: TraversalStrategy) -> &'static str {
+    match strategy {
+        TraversalStrategy::Relevance => "relevance",
+        TraversalStrategy::BreadthFirst => "breadth-first",
+        TraversalStrategy::Volume => "volume",
+    }
+}
+
+fn usd_value(graph: &G, edge: EdgeIndex) -> f64 {
+    graph[edge].data.as_ref().map_or(0.0, |data| data.usd_value)
+}
+
+// Runs the crawl's traversal over the recorded histories until the transaction budget is reached or nothing
+// is left to expand. Relevance counts the transactions an address was seen in, as the crawl does; volume
+// weighs them by their USD value in cents; breadth-first expands by hops from the seeds. Ties go to the
+// lowest address, as in the crawl's queue.
+fn replay(graph: &G, recording: &Recording, strategy: TraversalStrategy, budget: usize, targets: &HashSet<NodeIndex>) -> StrategyResult {
+    let mut queue: PriorityQueue<NodeIndex, (i64, Reverse<String>)> = PriorityQueue::new();
+    let mut hops: HashMap<NodeIndex, i64> = HashMap::new();
+    for &seed in recording.seeds.iter() {
+        queue.push(seed, (0, Reverse(graph[seed].clone())));
+        hops.insert(seed, 0);
+    }
+    let (mut visited, mut collected, mut reached): (HashSet<NodeIndex>, BTreeSet<EdgeIndex>, HashSet<NodeIndex>) = Default::default();
+    let (mut unrecorded, mut requests) = (0, 0);
+    while collected.len() < budget {
+        let Some((address, _)) = queue.pop() else { break };
+        if !visited.insert(address) {
+            continue;
+        }
+        reached.insert(address);
+        let Some(history) = recording.histories.get(&address) else {
+            unrecorded += 1;
+            requests += 1;
+            continue;
+        };
+        requests += requests_for_history(history.len() as f64) as usize;
+        for &edge in history.iter().filter(|&&edge| collected.insert(edge)) {
+            let (source, target) = graph.edge_endpoints(edge).unwrap();
+            reached.extend([source, target]);
+            let hop = hops[&address] + 1;
+            for counterparty in [source, target] {
+                if visited.contains(&counterparty) || (counterparty == target && is_special(&graph[target])) {
+                    continue;
+                }
+                let increment = match strategy {
+                    TraversalStrategy::Relevance => 1,
+                    TraversalStrategy::Volume => (usd_value(graph, edge) * 100.0).round() as i64,
+                    TraversalStrategy::BreadthFirst => 0,
+                };
+                let known_hop = *hops.entry(counterparty).or_insert(hop);
+                if !queue.change_priority_by(&counterparty, |priority| priority.0 += increment) {
+                    let priority = if strategy == TraversalStrategy::BreadthFirst { -known_hop } else { increment };
+                    queue.push(counterparty, (priority, Reverse(graph[counterparty].clone())));
+                }
+            }
+        }
+    }
+    let usd_volume = collected.iter().fold(0.0, |total, &edge| total + usd_value(graph, edge));
+    let targets_reached = targets.iter().filter(|target| reached.contains(target)).count();
+    StrategyResult {
+        strategy: strategy_name(strategy).to_string(),
+        budget,
+        visited: visited.len(),
+        unrecorded,
+        requests,
+        edges: collected.len(),
+        nodes: reached.len(),
+        usd_volume,
+        usd_per_request: if requests == 0 { 0.0 } else { usd_volume / requests as f64 },
+        targets: targets.len(),
+        targets_reached,
+        targets_expanded: targets.iter().filter(|target| visited.contains(target) && recording.histories.contains_key(target)).count(),
+        target_coverage_percent: if targets.is_empty() { 0.0 } else { 100.0 * targets_reached as f64 / targets.len() as f64 },
+    }
+}
+
+pub fn benchmark_summary(results: &[StrategyResult]) -> String {
+    let mut summary = String::new();
+    for result in results {
+        summary.push_str(&format!(
+            "{:<13} budget {:>8}: {} addresses ({} unrecorded), {} requests, {} edges, {} ({} per request), {}/{} targets reached ({:.1}%)\n",
+            result.strategy,
+            result.budget,
+            result.visited,
+            result.unrecorded,
+            result.requests,
+            result.edges,
+            Amount::Usd(result.usd_volume),
+            Amount::Usd(result.usd_per_request),
+            result.targets_reached,
+            result.targets,
+            result.target_coverage_percent
+        ));
+    }
+    summary
+}
+
+pub fn run_benchmark_strategies_command(args: &BenchmarkStrategiesArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let crawl_state = load_crawl_state()?;
+    let recording = Recording::new(&graph, &crawl_state)?;
+    println!("Replaying {} recorded histories from {} seeds", recording.histories.len(), recording.seeds.len());
+
+    let nodes: HashMap<String, NodeIndex> = graph.node_indices().map(|n| (graph[n].to_lowercase(), n)).collect();
+    let target_addresses: Vec<String> = if args.targets.is_empty() { Annotations::load()?.addresses.into_keys().collect() } else { args.targets.clone() };
+    let targets: HashSet<NodeIndex> = target_addresses.iter().filter_map(|address| nodes.get(&address.to_lowercase()).copied()).collect();
+    if targets.len() < target_addresses.len() {
+        println!("{} of {} targets are not in the recorded graph and are left out", target_addresses.len() - targets.len(), target_addresses.len());
+    }
+
+    let strategies = if args.strategies.is_empty() {
+        vec![TraversalStrategy::Relevance, TraversalStrategy::BreadthFirst, TraversalStrategy::Volume]
+    } else {
+        args.strategies.clone()
+    };
+    let budgets = if args.budgets.is_empty() { vec![graph.edge_count()] } else { args.budgets.clone() };
+    let mut results = vec![];
+    for &budget in budgets.iter() {
+        for &strategy in strategies.iter() {
+            results.push(replay(&graph, &recording, strategy, budget, &targets));
+        }
+    }
+    print!("{}", benchmark_summary(&results));
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for result in results.iter() {
+        writer.serialize(result)?;
+    }
+    commit_csv(writer)?;
+    println!("Saved {} strategy results as {}", results.len(), &file_pathname);
+    Ok(())
+}
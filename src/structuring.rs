@@ -0,0 +1,150 @@
+use eyre::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::money::Amount;
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::StructuringArgs;
+use crate::{deserialize_graph, edges_by_pair, DATA_STORAGE_FOLDER, G};
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StructuringPair {
+    pub sender: String,
+    pub recipient: String,
+    pub self_transfer: bool,
+    pub transfer_count: usize,
+    pub max_identical_in_window: usize,
+    pub repeated_amount_usd: f64,
+    pub max_round_in_window: usize,
+    pub flagged_volume_usd: f64,
+    pub first_timestamp: u64,
+    pub last_timestamp: u64,
+}
+
+pub fn is_round_amount(usd_value: f64, round_unit: f64, relative_tolerance: f64) -> bool {
+    if usd_value < round_unit {
+        return false;
+    }
+    let nearest = (usd_value / round_unit).round() * round_unit;
+    (usd_value - nearest).abs() <= nearest * relative_tolerance
+}
+
+// Amounts equal to the cent are treated as identical.
+fn amount_key(usd_value: f64) -> i64 {
+    (usd_value * 100.0).round() as i64
+}
+
+pub struct StructuringParameters {
+    pub window_seconds: u64,
+    pub min_repeats: usize,
+    pub round_unit: f64,
+    pub round_tolerance: f64,
+}
+
+pub fn detect_structuring(graph: &G, parameters: &StructuringParameters) -> Vec<StructuringPair> {
+    let mut flagged = vec![];
+    for ((sender, recipient), edge_indices) in edges_by_pair(graph) {
+        let mut transfers: Vec<(u64, f64)> = edge_indices
+            .iter()
+            .filter_map(|&e| {
+                let transaction = &graph[e];
+                Some((transaction.timestamp, transaction.data.as_ref()?.usd_value))
+            })
+            .filter(|&(timestamp, usd_value)| timestamp != 0 && usd_value > 0.0)
+            .collect();
+        if transfers.len() < parameters.min_repeats {
+            continue;
+        }
+        transfers.sort_by_key(|&(timestamp, _)| timestamp);
+
+        // Windows starting at every transfer: identical amounts and round amounts are counted separately.
+        let mut max_identical_in_window = 0;
+        let mut repeated_amount_usd = 0.0;
+        let mut max_round_in_window = 0;
+        let mut flagged_transfers = vec![false; transfers.len()];
+        for start in 0..transfers.len() {
+            let window_end = transfers[start].0 + parameters.window_seconds;
+            let window: Vec<usize> = (start..transfers.len()).take_while(|&i| transfers[i].0 <= window_end).collect();
+
+            let mut by_amount: HashMap<i64, Vec<usize>> = HashMap::new();
+            for &i in window.iter() {
+                by_amount.entry(amount_key(transfers[i].1)).or_default().push(i);
+            }
+            for members in by_amount.values() {
+                if members.len() > max_identical_in_window {
+                    max_identical_in_window = members.len();
+                    repeated_amount_usd = transfers[members[0]].1;
+                }
+                if members.len() >= parameters.min_repeats {
+                    members.iter().for_each(|&i| flagged_transfers[i] = true);
+                }
+            }
+
+            let round: Vec<usize> = window
+                .into_iter()
+                .filter(|&i| is_round_amount(transfers[i].1, parameters.round_unit, parameters.round_tolerance))
+                .collect();
+            max_round_in_window = max_round_in_window.max(round.len());
+            if round.len() >= parameters.min_repeats {
+                round.iter().for_each(|&i| flagged_transfers[i] = true);
+            }
+        }
+
+        if max_identical_in_window < parameters.min_repeats && max_round_in_window < parameters.min_repeats {
+            continue;
+        }
+        flagged.push(StructuringPair {
+            sender: graph[sender].clone(),
+            recipient: graph[recipient].clone(),
+            self_transfer: sender == recipient,
+            transfer_count: transfers.len(),
+            max_identical_in_window,
+            repeated_amount_usd,
+            max_round_in_window,
+            flagged_volume_usd: transfers
+                .iter()
+                .zip(&flagged_transfers)
+                .filter(|(_, &is_flagged)| is_flagged)
+                .fold(0.0, |acc, (t, _)| acc + t.1),
+            first_timestamp: transfers.first().unwrap().0,
+            last_timestamp: transfers.last().unwrap().0,
+        });
+    }
+    flagged.sort_by(|a, b| b.flagged_volume_usd.partial_cmp(&a.flagged_volume_usd).unwrap());
+    flagged
+}
+
+pub fn run_structuring_command(args: &StructuringArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let parameters = StructuringParameters {
+        window_seconds: args.window_hours * 3_600,
+        min_repeats: args.min_repeats,
+        round_unit: args.round_unit_usd,
+        round_tolerance: args.round_tolerance,
+    };
+    let flagged = detect_structuring(&graph, &parameters);
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for pair in flagged.iter() {
+        writer.serialize(pair)?;
+    }
+    commit_csv(writer)?;
+    for pair in flagged.iter().take(10) {
+        println!(
+            "{} -> {}: {} transfers, up to {} identical ({:.2} USD) and {} round amounts within {}h, {} flagged",
+            pair.sender,
+            pair.recipient,
+            pair.transfer_count,
+            pair.max_identical_in_window,
+            pair.repeated_amount_usd,
+            pair.max_round_in_window,
+            args.window_hours,
+            Amount::Usd(pair.flagged_volume_usd)
+        );
+    }
+    println!("Flagged {} pairs with possible structuring, saved as {}", flagged.len(), &file_pathname);
+    Ok(())
+}
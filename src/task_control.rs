@@ -0,0 +1,103 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// A flag an embedder sets from any thread to stop a long-running call. Clones share the flag.
+#[derive(Clone, Default, Debug)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+// Error of calls that stopped on cancellation, so callers can tell it apart with downcast_ref.
+#[derive(Debug, Clone, Copy)]
+pub struct Cancelled {
+    pub stage: &'static str,
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} was cancelled", self.stage)
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+// Units of work done so far in a stage, e.g. transactions collected by a crawl or passes run by an analysis.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub stage: &'static str,
+    pub done: usize,
+    pub total: Option<usize>,
+}
+
+pub type ProgressCallback = Arc<dyn Fn(Progress) + Send + Sync>;
+
+// Cancellation and progress reporting for long-running library calls. The default is never cancelled
+// and reports nowhere. Calls check for cancellation between units of work, so a cancelled call returns
+// once the unit in flight, such as one address history, is done.
+#[derive(Clone, Default)]
+pub struct TaskControl {
+    cancellation: CancellationToken,
+    progress: Option<ProgressCallback>,
+    #[cfg(feature = "crawl")]
+    monitor: Option<Arc<crate::crawl_control::CrawlMonitor>>,
+}
+
+impl TaskControl {
+    pub fn new(cancellation: CancellationToken) -> TaskControl {
+        TaskControl { cancellation, ..Default::default() }
+    }
+
+    // The callback runs on the thread doing the work and should return quickly.
+    pub fn with_progress(mut self, progress: impl Fn(Progress) + Send + Sync + 'static) -> TaskControl {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    // Crawls publish their status to the monitor and take the commands sent to its control endpoint.
+    #[cfg(feature = "crawl")]
+    pub fn with_monitor(mut self, monitor: Arc<crate::crawl_control::CrawlMonitor>) -> TaskControl {
+        self.monitor = Some(monitor);
+        self
+    }
+
+    #[cfg(feature = "crawl")]
+    pub fn monitor(&self) -> Option<&crate::crawl_control::CrawlMonitor> {
+        self.monitor.as_deref()
+    }
+
+    // Whether the control endpoint asked the crawl to stop after the histories in flight.
+    #[cfg(feature = "crawl")]
+    pub fn stop_requested(&self) -> bool {
+        self.monitor().is_some_and(|monitor| monitor.stop_requested())
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    pub fn check(&self, stage: &'static str) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            return Err(Cancelled { stage });
+        }
+        Ok(())
+    }
+
+    pub fn report(&self, stage: &'static str, done: usize, total: Option<usize>) {
+        if let Some(progress) = &self.progress {
+            progress(Progress { stage, done, total });
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use crate::atomic_file::{commit_csv, csv_writer};
+use crate::cli::TemporalMotifsArgs;
+use crate::{deserialize_graph, DATA_STORAGE_FOLDER, G};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum TemporalMotif {
+    Chain,
+    PingPong,
+    Triangle,
+    FanOutBurst,
+    FanInBurst,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MotifParticipant {
+    pub motif: TemporalMotif,
+    pub address: String,
+    pub occurrences: usize,
+}
+
+pub struct MotifCounts {
+    pub counts: HashMap<TemporalMotif, usize>,
+    pub participation: HashMap<(TemporalMotif, usize), usize>,
+}
+
+impl MotifCounts {
+    fn record(&mut self, motif: TemporalMotif, participants: &[usize]) {
+        *self.counts.entry(motif).or_default() += 1;
+        for &node in participants.iter().collect::<HashSet<_>>() {
+            *self.participation.entry((motif, node)).or_default() += 1;
+        }
+    }
+}
+
+// Per node, (timestamp, counterparty) pairs sorted by time.
+fn timed_adjacency(graph: &G, outgoing: bool) -> Vec<Vec<(u64, usize)>> {
+    let mut adjacency = vec![vec![]; graph.node_count()];
+    for edge in graph.edge_references() {
+        let timestamp = edge.weight().timestamp;
+        if timestamp == 0 || edge.source() == edge.target() {
+            continue;
+        }
+        let (node, counterparty) = if outgoing { (edge.source(), edge.target()) } else { (edge.target(), edge.source()) };
+        adjacency[node.index()].push((timestamp, counterparty.index()));
+    }
+    adjacency.iter_mut().for_each(|events| events.sort_unstable());
+    adjacency
+}
+
+fn events_between(events: &[(u64, usize)], from: u64, to: u64) -> &[(u64, usize)] {
+    let start = events.partition_point(|&(t, _)| t < from);
+    let end = events.partition_point(|&(t, _)| t <= to);
+    &events[start..end.max(start)]
+}
+
+// Non-overlapping windows in which a node interacts with at least min_counterparties distinct counterparties.
+fn count_bursts(events: &[(u64, usize)], delta_seconds: u64, min_counterparties: usize) -> usize {
+    let mut bursts = 0;
+    let mut start = 0;
+    while start < events.len() {
+        let window = events_between(events, events[start].0, events[start].0 + delta_seconds);
+        let distinct: HashSet<usize> = window.iter().map(|&(_, c)| c).collect();
+        if distinct.len() >= min_counterparties {
+            bursts += 1;
+            start += window.len();
+        } else {
+            start += 1;
+        }
+    }
+    bursts
+}
+
+// Every motif must complete within delta_seconds of its first edge, with edges in non-decreasing time order.
+pub fn count_temporal_motifs(graph: &G, delta_seconds: u64, min_burst_counterparties: usize) -> MotifCounts {
+    let outgoing = timed_adjacency(graph, true);
+    let incoming = timed_adjacency(graph, false);
+    let mut motifs = MotifCounts { counts: HashMap::new(), participation: HashMap::new() };
+
+    for (a, a_events) in outgoing.iter().enumerate() {
+        for &(t1, b) in a_events {
+            let deadline = t1 + delta_seconds;
+            for &(t2, c) in events_between(&outgoing[b], t1, deadline) {
+                if c == a {
+                    motifs.record(TemporalMotif::PingPong, &[a, b]);
+                    continue;
+                }
+                motifs.record(TemporalMotif::Chain, &[a, b, c]);
+                for &(_, closing) in events_between(&outgoing[c], t2, deadline) {
+                    if closing == a {
+                        motifs.record(TemporalMotif::Triangle, &[a, b, c]);
+                    }
+                }
+            }
+        }
+    }
+
+    for node in 0..graph.node_count() {
+        for _ in 0..count_bursts(&outgoing[node], delta_seconds, min_burst_counterparties) {
+            motifs.record(TemporalMotif::FanOutBurst, &[node]);
+        }
+        for _ in 0..count_bursts(&incoming[node], delta_seconds, min_burst_counterparties) {
+            motifs.record(TemporalMotif::FanInBurst, &[node]);
+        }
+    }
+    motifs
+}
+
+pub fn top_participants(graph: &G, motifs: &MotifCounts, top: usize) -> Vec<MotifParticipant> {
+    let mut participants: Vec<MotifParticipant> = motifs
+        .participation
+        .iter()
+        .map(|(&(motif, node), &occurrences)| MotifParticipant { motif, address: graph[NodeIndex::new(node)].clone(), occurrences })
+        .collect();
+    participants.sort_by(|a, b| a.motif.cmp(&b.motif).then(b.occurrences.cmp(&a.occurrences)).then(a.address.cmp(&b.address)));
+    let mut per_motif: HashMap<TemporalMotif, usize> = HashMap::new();
+    participants.retain(|p| {
+        let taken = per_motif.entry(p.motif).or_default();
+        *taken += 1;
+        *taken <= top
+    });
+    participants
+}
+
+pub fn run_temporal_motifs_command(args: &TemporalMotifsArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let motifs = count_temporal_motifs(&graph, args.delta_minutes * 60, args.min_burst_counterparties);
+    let participants = top_participants(&graph, &motifs, args.top);
+
+    println!("Temporal motifs within {} minutes:", args.delta_minutes);
+    for motif in [TemporalMotif::Chain, TemporalMotif::PingPong, TemporalMotif::Triangle, TemporalMotif::FanOutBurst, TemporalMotif::FanInBurst] {
+        let top_address = participants.iter().find(|p| p.motif == motif);
+        println!(
+            "  {:?}: {}{}",
+            motif,
+            motifs.counts.get(&motif).copied().unwrap_or_default(),
+            top_address.map_or(String::new(), |p| format!(" (most frequent: {} in {})", p.address, p.occurrences))
+        );
+    }
+
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, args.output);
+    let mut writer = csv_writer(&file_pathname)?;
+    for participant in participants.iter() {
+        writer.serialize(participant)?;
+    }
+    commit_csv(writer)?;
+    println!("Saved top {} participants per motif as {}", args.top, &file_pathname);
+    Ok(())
+}
@@ -0,0 +1,306 @@
+use eyre::Result;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::exit_status::{self, FailureKind};
+use crate::request_metrics::{RequestMetric, RequestMetricsLog};
+use crate::API_REQUESTS_PER_SECOND;
+
+// Pacing starts at the free tier rate and probes upwards while responses stay fast and clean, so keys with a
+// higher limit are not held to it. Rate limit responses halve both the rate and the requests in flight.
+const MIN_REQUEST_RATE: f64 = 0.5;
+const MAX_REQUEST_RATE: f64 = API_REQUESTS_PER_SECOND * 4.0;
+const REQUEST_RATE_STEP: f64 = 0.5;
+// Consecutive clean responses before the throttle speeds up.
+const SPEEDUP_AFTER: usize = 10;
+// Smoothed latency above this multiple of the best seen means the API is queueing requests.
+const SLOW_LATENCY_FACTOR: f64 = 2.0;
+const LATENCY_SMOOTHING: f64 = 0.2;
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(1);
+const LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestOutcome {
+    Success,
+    RateLimited,
+    Failed,
+}
+
+impl RequestOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestOutcome::Success => "success",
+            RequestOutcome::RateLimited => "rate_limited",
+            RequestOutcome::Failed => "failed",
+        }
+    }
+}
+
+// When a request started, as the throttle handed out its slot.
+#[derive(Clone, Copy, Debug)]
+pub struct RequestStart {
+    instant: Instant,
+    unix_ms: u64,
+}
+
+// Etherscan answers over the limit with a 200 and "Max rate limit reached" as the result.
+pub fn is_rate_limit_error(error: &eyre::Report) -> bool {
+    error.to_string().to_lowercase().contains("rate limit")
+}
+
+struct ThrottleState {
+    request_rate: f64,
+    concurrency: usize,
+    max_concurrency: usize,
+    next_slot: Instant,
+    clean_streak: usize,
+    latency_ms: Option<f64>,
+    best_latency_ms: f64,
+    started: Instant,
+    requests: usize,
+    rate_limited: usize,
+    failed: usize,
+    window_started: Instant,
+    window_requests: usize,
+    window_rate_limited: usize,
+    // Requests per second actually completed over the last log window.
+    throughput: Option<f64>,
+}
+
+impl ThrottleState {
+    fn effective_rate(&self) -> f64 {
+        self.throughput.map_or(self.request_rate, |throughput| throughput.min(self.request_rate))
+    }
+
+    fn log_window(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.window_started).as_secs_f64();
+        let throughput = self.window_requests as f64 / elapsed;
+        self.throughput = Some(throughput);
+        println!(
+            "Throughput is {:.2} requests/s over the last {:.0} s, pacing at {:.2} requests/s with {} in flight, {:.0} ms latency, {} of {} requests rate limited",
+            throughput,
+            elapsed,
+            self.request_rate,
+            self.concurrency,
+            self.latency_ms.unwrap_or(0.0),
+            self.window_rate_limited,
+            self.window_requests
+        );
+        self.window_started = now;
+        self.window_requests = 0;
+        self.window_rate_limited = 0;
+    }
+}
+
+// Counts and pacing so far, as shown by the crawl control endpoint.
+#[derive(Serialize, Debug, Clone, Copy, Default)]
+pub struct RequestStats {
+    pub requests: usize,
+    pub rate_limited: usize,
+    pub failed: usize,
+    pub request_rate: f64,
+    pub concurrency: usize,
+    pub max_concurrency: usize,
+    pub latency_ms: Option<f64>,
+}
+
+// Shared by every request of a crawl: hands out request start times at the current rate and adjusts the
+// rate and the requests in flight from the latency and the rate limit responses it is told about.
+pub struct Throttle {
+    state: Mutex<ThrottleState>,
+    metrics: Mutex<Option<RequestMetricsLog>>,
+    aborted: AtomicBool,
+}
+
+impl Throttle {
+    pub fn new(max_concurrency: usize) -> Throttle {
+        let now = Instant::now();
+        let max_concurrency = max_concurrency.max(1);
+        Throttle {
+            state: Mutex::new(ThrottleState {
+                request_rate: API_REQUESTS_PER_SECOND,
+                concurrency: max_concurrency,
+                max_concurrency,
+                next_slot: now,
+                clean_streak: 0,
+                latency_ms: None,
+                best_latency_ms: f64::INFINITY,
+                started: now,
+                requests: 0,
+                rate_limited: 0,
+                failed: 0,
+                window_started: now,
+                window_requests: 0,
+                window_rate_limited: 0,
+                throughput: None,
+            }),
+            metrics: Mutex::new(None),
+            aborted: AtomicBool::new(false),
+        }
+    }
+
+    // Every recorded request is also written to the log, which `finish` saves.
+    pub fn with_metrics_log(self, log: RequestMetricsLog) -> Throttle {
+        *self.metrics.lock().unwrap() = Some(log);
+        self
+    }
+
+    // For crawls that keep going without the log when it cannot be created, rather than stop before the first request.
+    pub fn with_metrics_log_if_created(self) -> Throttle {
+        match RequestMetricsLog::create() {
+            Ok(log) => self.with_metrics_log(log),
+            Err(e) => {
+                eprintln!("Request metrics are not logged: {:#}", e);
+                self
+            }
+        }
+    }
+
+    // Requests allowed in flight at once right now.
+    pub fn concurrency(&self) -> usize {
+        self.state.lock().unwrap().concurrency
+    }
+
+    // Lowers the most requests in flight for the rest of the crawl; speeding up never goes above it again.
+    pub fn limit_concurrency(&self, max_concurrency: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.max_concurrency = state.max_concurrency.min(max_concurrency).max(1);
+        state.concurrency = state.concurrency.min(state.max_concurrency);
+    }
+
+    pub fn request_stats(&self) -> RequestStats {
+        let state = self.state.lock().unwrap();
+        RequestStats {
+            requests: state.requests,
+            rate_limited: state.rate_limited,
+            failed: state.failed,
+            request_rate: state.request_rate,
+            concurrency: state.concurrency,
+            max_concurrency: state.max_concurrency,
+            latency_ms: state.latency_ms,
+        }
+    }
+
+    // Waits for the next request slot and returns when the request starts, to be passed to `record`.
+    pub async fn acquire(&self) -> RequestStart {
+        let slot = {
+            let mut state = self.state.lock().unwrap();
+            let slot = state.next_slot.max(Instant::now());
+            state.next_slot = slot + Duration::from_secs_f64(1.0 / state.request_rate);
+            slot
+        };
+        tokio::time::sleep_until(slot.into()).await;
+        RequestStart { instant: Instant::now(), unix_ms: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64 }
+    }
+
+    // `metric` is filled in with the start time, latency and outcome.
+    pub fn record(&self, started: RequestStart, outcome: RequestOutcome, mut metric: RequestMetric) {
+        let now = Instant::now();
+        let latency = now.duration_since(started.instant);
+        {
+            let mut metrics = self.metrics.lock().unwrap();
+            if let Some(log) = metrics.as_mut() {
+                metric.started_at_ms = started.unix_ms;
+                metric.latency_ms = latency.as_secs_f64() * 1000.0;
+                metric.outcome = outcome.as_str();
+                // A log that cannot be written is dropped, the crawl goes on without it.
+                if let Err(e) = log.append(&metric) {
+                    eprintln!("Request metrics are no longer logged: {:#}", e);
+                    *metrics = None;
+                }
+            }
+        }
+        let mut state = self.state.lock().unwrap();
+        state.requests += 1;
+        state.window_requests += 1;
+        match outcome {
+            RequestOutcome::Success => {
+                let latency_ms = latency.as_secs_f64() * 1000.0;
+                let smoothed = state.latency_ms.map_or(latency_ms, |previous| previous + LATENCY_SMOOTHING * (latency_ms - previous));
+                state.latency_ms = Some(smoothed);
+                state.best_latency_ms = state.best_latency_ms.min(smoothed);
+                if smoothed > state.best_latency_ms * SLOW_LATENCY_FACTOR {
+                    state.concurrency = (state.concurrency - 1).max(1);
+                    state.clean_streak = 0;
+                } else {
+                    state.clean_streak += 1;
+                    if state.clean_streak >= SPEEDUP_AFTER {
+                        state.request_rate = (state.request_rate + REQUEST_RATE_STEP).min(MAX_REQUEST_RATE);
+                        state.concurrency = (state.concurrency + 1).min(state.max_concurrency);
+                        state.clean_streak = 0;
+                    }
+                }
+            }
+            RequestOutcome::RateLimited => {
+                state.rate_limited += 1;
+                state.window_rate_limited += 1;
+                state.request_rate = (state.request_rate / 2.0).max(MIN_REQUEST_RATE);
+                state.concurrency = (state.concurrency / 2).max(1);
+                state.clean_streak = 0;
+                state.next_slot = state.next_slot.max(now + RATE_LIMIT_BACKOFF);
+            }
+            RequestOutcome::Failed => {
+                state.failed += 1;
+                state.clean_streak = 0;
+            }
+        }
+        if now.duration_since(state.window_started) >= LOG_INTERVAL {
+            state.log_window(now);
+        }
+    }
+
+    // Stops the crawl after a failure no retry can fix, such as an invalid key or a spent daily quota: no
+    // further request is sent and the crawl saves what it has, as if cancelled.
+    pub fn abort(&self, kind: FailureKind, detail: String) {
+        println!("Stopping the crawl: {}", detail);
+        exit_status::record(kind, detail);
+        self.aborted.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::SeqCst)
+    }
+
+    // Time left for `total` units of work with `done` finished, assuming the remaining units cost as many
+    // requests each as the finished ones and run at the throughput the API currently allows.
+    pub fn eta(&self, done: usize, total: usize) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        if done == 0 || state.requests == 0 {
+            return None;
+        }
+        let remaining_requests = total.saturating_sub(done) as f64 * state.requests as f64 / done as f64;
+        Some(Duration::from_secs_f64(remaining_requests / state.effective_rate()))
+    }
+
+    // Saves the metrics log and returns the throughput summary of the crawl.
+    pub fn finish(&self) -> Result<String> {
+        let state = self.state.lock().unwrap();
+        let elapsed = state.started.elapsed().as_secs_f64();
+        let mut summary = format!(
+            "Made {} requests in {:.0} s, {:.2} requests/s on average; {} rate limited, {} failed; final pacing {:.2} requests/s with {} in flight\n",
+            state.requests,
+            elapsed,
+            state.requests as f64 / elapsed.max(f64::EPSILON),
+            state.rate_limited,
+            state.failed,
+            state.request_rate,
+            state.concurrency
+        );
+        if let Some(log) = self.metrics.lock().unwrap().take() {
+            summary.push_str(&log.save()?);
+        }
+        Ok(summary)
+    }
+}
+
+pub fn eta_label(eta: Option<Duration>) -> String {
+    match eta {
+        Some(eta) => {
+            let seconds = eta.as_secs();
+            format!("ETA {}h {:02}m {:02}s", seconds / 3600, seconds / 60 % 60, seconds % 60)
+        }
+        None => "ETA unknown".to_string(),
+    }
+}
@@ -0,0 +1,81 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Timelike};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use eyre::Result;
+use once_cell::sync::OnceCell;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum CalendarPeriod {
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+pub fn parse_timezone(name: &str) -> Result<Tz> {
+    name.parse::<Tz>().map_err(|e| eyre::eyre!("Unknown timezone {}: {}", name, e))
+}
+
+// Local midnight as a unix timestamp. On days where midnight falls into a DST gap the day starts at
+// the first valid local time; on ambiguous midnights the earlier instant is used.
+fn local_day_start(date: NaiveDate, tz: &Tz) -> u64 {
+    let mut local_time = date.and_time(NaiveTime::MIN);
+    loop {
+        if let Some(instant) = tz.from_local_datetime(&local_time).earliest() {
+            return instant.timestamp().max(0) as u64;
+        }
+        local_time += Duration::minutes(15);
+    }
+}
+
+// Timestamps past the dates chrono can represent, such as a corrupt field read as a huge number, are errors
+// for the caller to skip or reject.
+fn local_time(timestamp: u64, tz: &Tz) -> Result<DateTime<Tz>> {
+    i64::try_from(timestamp).ok().and_then(|seconds| tz.timestamp_opt(seconds, 0).earliest()).ok_or_else(|| eyre::eyre!("Timestamp {} is out of range", timestamp))
+}
+
+fn days_after(date: NaiveDate, days: i64) -> Result<NaiveDate> {
+    date.checked_add_signed(Duration::days(days)).ok_or_else(|| eyre::eyre!("No date {} days after {}", days, date))
+}
+
+pub fn bucket_start(timestamp: u64, period: CalendarPeriod, tz: &Tz) -> Result<u64> {
+    let local = local_time(timestamp, tz)?;
+    let date = local.date_naive();
+    Ok(match period {
+        CalendarPeriod::Hour => timestamp - (local.minute() as u64 * 60 + local.second() as u64),
+        CalendarPeriod::Day => local_day_start(date, tz),
+        CalendarPeriod::Week => local_day_start(days_after(date, -(date.weekday().num_days_from_monday() as i64))?, tz),
+        CalendarPeriod::Month => local_day_start(date.with_day(1).unwrap(), tz),
+    })
+}
+
+pub fn next_bucket_start(current_bucket_start: u64, period: CalendarPeriod, tz: &Tz) -> Result<u64> {
+    let date = local_time(current_bucket_start, tz)?.date_naive();
+    Ok(match period {
+        CalendarPeriod::Hour => current_bucket_start + 3_600,
+        CalendarPeriod::Day => local_day_start(days_after(date, 1)?, tz),
+        CalendarPeriod::Week => local_day_start(days_after(date, 7)?, tz),
+        CalendarPeriod::Month => {
+            let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+            local_day_start(NaiveDate::from_ymd_opt(year, month, 1).ok_or_else(|| eyre::eyre!("No month after {}", date))?, tz)
+        }
+    })
+}
+
+pub fn local_label(timestamp: u64, tz: &Tz) -> Result<String> {
+    Ok(local_time(timestamp, tz)?.format("%Y-%m-%d %H:%M %Z").to_string())
+}
+
+static DISPLAY_TIMEZONE: OnceCell<Tz> = OnceCell::new();
+
+// Set once at startup from the command line; times shown before that are in UTC.
+pub fn configure_display_timezone(tz: Tz) {
+    DISPLAY_TIMEZONE.set(tz).ok();
+}
+
+// ISO-8601 in the display timezone, e.g. 2024-03-01T12:00:00Z, for logs and reports of commands without a
+// timezone option of their own. Machine-readable outputs keep unix seconds.
+pub fn time_label(timestamp: u64) -> Result<String> {
+    let tz = DISPLAY_TIMEZONE.get().copied().unwrap_or(chrono_tz::UTC);
+    Ok(local_time(timestamp, &tz)?.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+}
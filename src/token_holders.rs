@@ -0,0 +1,100 @@
+use eyre::Result;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use strum::IntoEnumIterator;
+
+use crate::cli::TokenHoldersArgs;
+use crate::{
+    deserialize_graph, serialize_graph, DigestedData, FilteringResultInfo, OnchainFunction, Payload, Transaction, CONTRACT_ADDRESSES, G,
+};
+
+// Bipartite projection: holder -> token contract, one edge per pair carrying the total USD received.
+pub fn token_holder_projection(graph: &G) -> (G, FilteringResultInfo) {
+    let mut received: BTreeMap<(NodeIndex, String), (Payload, f64)> = BTreeMap::new();
+    for edge in graph.edge_references() {
+        if let Some(data) = &edge.weight().data {
+            let entry = received
+                .entry((edge.target(), format!("{:?}", data.payload)))
+                .or_insert((data.payload.clone(), 0.0));
+            entry.1 += data.usd_value;
+        }
+    }
+
+    let contract_addresses = CONTRACT_ADDRESSES.lock().unwrap();
+    let mut projection: G = G::new();
+    let mut node_indices: HashMap<String, NodeIndex> = HashMap::new();
+    for ((holder, _), (payload, usd_value)) in received {
+        let holder_address = graph[holder].clone();
+        let token_address = contract_addresses.get(&payload).unwrap().clone();
+        let holder_node = *node_indices
+            .entry(holder_address.clone())
+            .or_insert_with(|| projection.add_node(holder_address));
+        let token_node = *node_indices
+            .entry(token_address.clone())
+            .or_insert_with(|| projection.add_node(token_address));
+        projection.add_edge(
+            holder_node,
+            token_node,
+            Transaction {
+                data: Some(DigestedData { payload, usd_value, used_onchain_function: OnchainFunction::Transfer }),
+                ..Default::default()
+            },
+        );
+    }
+
+    let info = FilteringResultInfo {
+        node_count: projection.raw_nodes().len(),
+        edge_count: projection.raw_edges().len(),
+        node_count_before_filtering: graph.raw_nodes().len(),
+        edge_count_before_filtering: graph.raw_edges().len(),
+    };
+    (projection, info)
+}
+
+pub fn token_holder_metrics(projection: &G) -> String {
+    let mut holders_by_token: BTreeMap<String, HashSet<NodeIndex>> = BTreeMap::new();
+    let mut tokens_by_holder: HashMap<NodeIndex, usize> = HashMap::new();
+    for edge in projection.edge_references() {
+        let payload = &edge.weight().data.as_ref().unwrap().payload;
+        holders_by_token.entry(format!("{:?}", payload)).or_default().insert(edge.source());
+        *tokens_by_holder.entry(edge.source()).or_insert(0) += 1;
+    }
+
+    let mut log = String::from("Token-holder projection:\n");
+    for payload in Payload::iter() {
+        let name = format!("{:?}", payload);
+        log.push_str(&format!("{} holders: {}\n", name, holders_by_token.get(&name).map_or(0, |h| h.len())));
+    }
+
+    let mut tokens_per_address_histogram: BTreeMap<usize, usize> = BTreeMap::new();
+    for token_count in tokens_by_holder.values() {
+        *tokens_per_address_histogram.entry(*token_count).or_insert(0) += 1;
+    }
+    for (token_count, address_count) in tokens_per_address_histogram {
+        log.push_str(&format!("Addresses holding {} token(s): {}\n", token_count, address_count));
+    }
+
+    let tokens: Vec<(&String, &HashSet<NodeIndex>)> = holders_by_token.iter().collect();
+    for (i, (token_a, holders_a)) in tokens.iter().enumerate() {
+        for (token_b, holders_b) in tokens[i + 1..].iter() {
+            let shared = holders_a.intersection(holders_b).count();
+            let union = holders_a.union(holders_b).count();
+            log.push_str(&format!(
+                "Shared holders {} / {}: {}, Jaccard similarity: {:.4}\n",
+                token_a,
+                token_b,
+                shared,
+                shared as f64 / union as f64
+            ));
+        }
+    }
+    log
+}
+
+pub fn run_token_holders_command(args: &TokenHoldersArgs) -> Result<()> {
+    let (graph, _) = deserialize_graph(&args.input)?;
+    let (projection, info) = token_holder_projection(&graph);
+    println!("{}", token_holder_metrics(&projection));
+    serialize_graph(&projection, &info, &args.output)
+}
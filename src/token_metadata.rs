@@ -0,0 +1,180 @@
+use eyre::Result;
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::sync::Mutex;
+
+use crate::abi_decoding::{self, DecodedTransfer};
+use crate::atomic_file::write_atomically;
+use crate::etherscan::{Etherscan, ProxyResponse};
+use crate::pagination::AddressHistory;
+use crate::request_metrics::RequestMetric;
+use crate::throttle::{is_rate_limit_error, RequestOutcome, Throttle};
+use crate::{workspace, CONTRACT_ADDRESSES, DATA_STORAGE_FOLDER};
+
+pub const TOKEN_METADATA_FILENAME: &str = "token_metadata.json";
+const SYMBOL_SELECTOR: &str = "0x95d89b41";
+const DECIMALS_SELECTOR: &str = "0x313ce567";
+
+// None when the contract does not answer the call, e.g. tokens predating the optional ERC-20 metadata.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TokenMetadata {
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    // Earliest transfer timestamp the crawls saw for the token.
+    pub first_seen: u64,
+}
+
+// Keyed by lowercase contract address. Kept in the workspace across crawls, so each token is asked about once.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct TokenMetadataCache {
+    tokens: BTreeMap<String, TokenMetadata>,
+    #[serde(skip)]
+    changed: bool,
+}
+
+fn cache_pathname() -> String {
+    format!("{}/{}", DATA_STORAGE_FOLDER, TOKEN_METADATA_FILENAME)
+}
+
+static CACHE: Lazy<Mutex<TokenMetadataCache>> = Lazy::new(|| {
+    let cache = File::open(cache_pathname()).ok().and_then(|file| serde_json::from_reader(std::io::BufReader::new(file)).ok());
+    Mutex::new(cache.unwrap_or_default())
+});
+
+// Ok(None) when the call reverted or returned nothing; errors are left for a later crawl to retry.
+async fn eth_call(token: &str, selector: &str, client: &Client, api_key: &str, throttle: &Throttle) -> Result<Option<String>> {
+    let started = throttle.acquire().await;
+    let metric = RequestMetric {
+        started_at_ms: 0,
+        address: token.to_string(),
+        start_block: 0,
+        end_block: 0,
+        page: 0,
+        offset: 0,
+        sort: "eth_call",
+        attempt: 1,
+        outcome: "",
+        latency_ms: 0.0,
+        bytes: 0,
+        results: 0,
+    };
+    let parameters = [("to", token.to_string()), ("data", selector.to_string()), ("tag", "latest".to_string())];
+    let result = async {
+        let (response, _): (ProxyResponse<String>, usize) = Etherscan::new(client, api_key).proxy("eth_call", &parameters).await?;
+        match response.result {
+            Some(result) if result.starts_with("0x") => Ok(Some(result[2..].to_string()).filter(|hex| !hex.is_empty())),
+            Some(message) => Err(eyre::eyre!("Unexpected eth_call result {}", message)),
+            None => Ok(None),
+        }
+    }
+    .await;
+    match &result {
+        Ok(_) => throttle.record(started, RequestOutcome::Success, metric),
+        Err(e) => throttle.record(started, if is_rate_limit_error(e) { RequestOutcome::RateLimited } else { RequestOutcome::Failed }, metric),
+    }
+    result
+}
+
+fn decode_decimals(hex: &str) -> Option<u8> {
+    let word = hex.get(..64)?;
+    u8::from_str_radix(word.get(62..)?, 16).ok().filter(|_| word[..62].bytes().all(|digit| digit == b'0'))
+}
+
+// ABI-encoded strings, or the bytes32 symbols of tokens such as MKR.
+fn decode_symbol(hex: &str) -> Option<String> {
+    let bytes: Vec<u8> = (0..hex.len() / 2).map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)).collect::<Result<_, _>>().ok()?;
+    let text = if bytes.len() >= 64 {
+        let length = usize::try_from(primitive_types::U256::from_big_endian(&bytes[32..64])).ok()?;
+        bytes.get(64..64 + length)?.to_vec()
+    } else {
+        bytes.into_iter().take_while(|&byte| byte != 0).collect()
+    };
+    String::from_utf8(text).ok().map(|symbol| symbol.trim().to_string()).filter(|symbol| !symbol.is_empty() && symbol.chars().all(|c| !c.is_control()))
+}
+
+// Contracts of the token transfers decoded from a history, other than the tracked stablecoins, with the
+// earliest timestamp each appears at.
+fn history_tokens(history: &AddressHistory) -> BTreeMap<String, u64> {
+    let stablecoins: Vec<String> = CONTRACT_ADDRESSES.lock().unwrap().values().cloned().collect();
+    let mut tokens: BTreeMap<String, u64> = BTreeMap::new();
+    for transaction in history.transactions.iter() {
+        let timestamp = transaction.timeStamp.parse().unwrap_or(u64::MAX);
+        let mut calls = vec![(transaction.from.clone(), transaction.to.clone(), transaction.input.clone())];
+        if abi_decoding::ENTRY_POINTS.contains(&transaction.to.as_str()) {
+            for operation in abi_decoding::decode_handle_ops(&transaction.input).unwrap_or_default() {
+                calls.extend(operation.calls.iter().map(|call| (operation.sender.clone(), call.target.clone(), call.data.clone())));
+            }
+        }
+        for (sender, target, input) in calls.into_iter().filter(|(_, target, _)| !stablecoins.contains(target)) {
+            if let Some(transfer) = abi_decoding::decode_input(&input).and_then(|call| abi_decoding::implied_transfer(&call, &sender, &target)) {
+                let first_seen = tokens.entry(transfer.token.to_lowercase()).or_insert(timestamp);
+                *first_seen = (*first_seen).min(timestamp);
+            }
+        }
+    }
+    tokens
+}
+
+// Asks for the symbol and decimals of the tokens in the history that the cache does not know yet, before the
+// history is ingested, so its decoded transfers are normalized.
+pub async fn resolve_history_tokens(history: &AddressHistory, client: &Client, api_key: &str, throttle: &Throttle) {
+    let mut unknown = vec![];
+    {
+        let mut cache = CACHE.lock().unwrap();
+        for (token, timestamp) in history_tokens(history) {
+            match cache.tokens.get_mut(&token) {
+                Some(metadata) if metadata.first_seen > timestamp => {
+                    metadata.first_seen = timestamp;
+                    cache.changed = true;
+                }
+                Some(_) => {}
+                None => unknown.push((token, timestamp)),
+            }
+        }
+    }
+    for (token, first_seen) in unknown {
+        if throttle.is_aborted() {
+            return;
+        }
+        let (symbol, decimals) = match (
+            eth_call(&token, SYMBOL_SELECTOR, client, api_key, throttle).await,
+            eth_call(&token, DECIMALS_SELECTOR, client, api_key, throttle).await,
+        ) {
+            (Ok(symbol), Ok(decimals)) => (symbol.as_deref().and_then(decode_symbol), decimals.as_deref().and_then(decode_decimals)),
+            (Err(e), _) | (_, Err(e)) => {
+                println!("Could not resolve the metadata of token {}: {}", token, e);
+                continue;
+            }
+        };
+        println!("Token {} is {} with {} decimals", token, symbol.as_deref().unwrap_or("unnamed"), decimals.map_or("unknown".to_string(), |d| d.to_string()));
+        let mut cache = CACHE.lock().unwrap();
+        cache.tokens.insert(token, TokenMetadata { symbol, decimals, first_seen });
+        cache.changed = true;
+    }
+}
+
+// Stamps the symbol and decimals of a resolved token on a decoded transfer.
+pub fn with_metadata(mut transfer: DecodedTransfer) -> DecodedTransfer {
+    if let Some(metadata) = CACHE.lock().unwrap().tokens.get(&transfer.token.to_lowercase()) {
+        transfer.symbol = metadata.symbol.clone();
+        transfer.decimals = metadata.decimals;
+    }
+    transfer
+}
+
+// Writes the cache when tokens were resolved since it was loaded.
+pub fn save_token_metadata() -> Result<()> {
+    let mut cache = CACHE.lock().unwrap();
+    if !cache.changed {
+        return Ok(());
+    }
+    fs::create_dir_all(DATA_STORAGE_FOLDER)?;
+    write_atomically(&cache_pathname(), serde_json::to_string_pretty(&*cache)?.as_bytes())?;
+    workspace::record_artifact(&cache_pathname())?;
+    println!("Saved metadata of {} tokens as {}", cache.tokens.len(), cache_pathname());
+    cache.changed = false;
+    Ok(())
+}
@@ -0,0 +1,188 @@
+use priority_queue::PriorityQueue;
+use reqwest::Client;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::Arc;
+
+use crate::cli::CrawlArgs;
+use crate::crawl_events::CrawlSinks;
+use crate::delta_crawl::{save_crawl, CrawlState};
+use crate::graph_builder::GraphBuilder;
+use crate::history_window::HistoryWindow;
+use crate::http_client;
+use crate::pagination::{get_address_history, get_address_sample, AddressHistory};
+use crate::special_addresses::is_special;
+use crate::task_control::TaskControl;
+use crate::throttle::{eta_label, Throttle};
+use crate::token_metadata::resolve_history_tokens;
+use crate::{insert_address_history, AddressPriority, G, MAX_TRANSACTIONS_TO_PARSE};
+
+// Runs fetches on the tokio worker threads with at most as many in flight as the throttle allows, handing the results to
+// `handle` in the order of `addresses` so the built graph does not depend on response timing. Stops early
+// when `handle` returns false and returns the addresses whose results were not handled.
+async fn fetch_in_order<Fetch, Fut>(
+    addresses: Vec<String>,
+    throttle: &Throttle,
+    fetch: Fetch,
+    mut handle: impl FnMut(String, AddressHistory) -> bool,
+) -> Vec<String>
+where
+    Fetch: Fn(String) -> Fut,
+    Fut: Future<Output = AddressHistory> + Send + 'static,
+{
+    let mut pending = addresses.into_iter();
+    let mut in_flight = VecDeque::new();
+    loop {
+        while in_flight.len() < throttle.concurrency() {
+            let Some(address) = pending.next() else { break };
+            in_flight.push_back((address.clone(), tokio::spawn(fetch(address))));
+        }
+        let Some((address, task)) = in_flight.pop_front() else { return vec![] };
+        if !handle(address, task.await.unwrap()) {
+            for (_, task) in in_flight.iter() {
+                task.abort();
+            }
+            return in_flight.into_iter().map(|(address, _)| address).chain(pending).collect();
+        }
+    }
+}
+
+// Discovery samples the first records in the history window of every address up to the given depth and
+// counts how often each counterparty appears; only addresses closer than the depth are sampled, so the
+// deepest level is known but unsampled. Returns the relevance of every discovered address and the number of requests spent.
+async fn discover(
+    roots: &[String],
+    window: &HistoryWindow,
+    args: &CrawlArgs,
+    client: &Client,
+    api_key: &str,
+    throttle: &Arc<Throttle>,
+    control: &TaskControl,
+) -> (HashMap<String, i32>, usize) {
+    let mut relevance: HashMap<String, i32> = roots.iter().map(|root| (root.clone(), 1)).collect();
+    let mut level = roots.to_vec();
+    let mut requests = 0;
+    for depth in 0..args.discovery_depth {
+        if control.is_cancelled() || throttle.is_aborted() || control.stop_requested() {
+            break;
+        }
+        let mut next_level = vec![];
+        let fetch = |address: String| {
+            let (client, api_key, window, offset, throttle) = (client.clone(), api_key.to_string(), *window, args.discovery_offset, throttle.clone());
+            async move { get_address_sample(&address, &window, offset, &client, &api_key, &throttle).await }
+        };
+        fetch_in_order(level, throttle, fetch, |address, sample| {
+            requests += sample.requests;
+            for transaction in sample.transactions.iter().filter(|t| t.isError == "0" && t.from != "GENESIS") {
+                for counterparty in [&transaction.from, &transaction.to] {
+                    if counterparty.is_empty() || *counterparty == address || is_special(counterparty) {
+                        continue;
+                    }
+                    let counter = relevance.entry(counterparty.clone()).or_insert_with(|| {
+                        next_level.push(counterparty.clone());
+                        0
+                    });
+                    *counter += 1;
+                }
+            }
+            if let Some(monitor) = control.monitor() {
+                monitor.publish("discovery", next_level.len(), relevance.len(), 0, throttle);
+            }
+            !control.is_cancelled() && !throttle.is_aborted() && !control.stop_requested()
+        })
+        .await;
+        next_level.sort();
+        println!("Discovery depth {} found {} new addresses, {} in total", depth + 1, next_level.len(), relevance.len());
+        level = next_level;
+    }
+    (relevance, requests)
+}
+
+// Pass one discovers the address set cheaply, pass two fetches the full histories of the most relevant
+// addresses concurrently. Addresses left out of the backfill form the frontier a resumed crawl continues from.
+pub async fn parse_blockchain_two_pass(
+    starting_addresses: &[String],
+    api_key: &str,
+    mut crawl_state: CrawlState,
+    args: &CrawlArgs,
+    sinks: &mut CrawlSinks,
+    control: &TaskControl,
+) -> (G, CrawlState) {
+    let client = http_client::client();
+    let mut roots: Vec<String> = starting_addresses.iter().map(|address| address.to_lowercase()).collect();
+    roots.sort();
+    roots.dedup();
+    let window = crawl_state.history_window;
+    // One throttle for both passes, so the backfill starts at the pace discovery settled on.
+    let throttle = Arc::new(Throttle::new(args.concurrency).with_metrics_log_if_created());
+    let (relevance, discovery_requests) = discover(&roots, &window, args, &client, api_key, &throttle, control).await;
+
+    let mut ranked: Vec<(String, i32)> = relevance.into_iter().collect();
+    // The roots always come first, the rest by relevance and then address.
+    ranked.sort_by(|x, y| roots.contains(&y.0).cmp(&roots.contains(&x.0)).then(y.1.cmp(&x.1)).then(x.0.cmp(&y.0)));
+    let rest = ranked.split_off(args.backfill_addresses.min(ranked.len()));
+    println!(
+        "Discovery spent {} requests, backfilling {} of {} discovered addresses",
+        discovery_requests,
+        ranked.len(),
+        ranked.len() + rest.len()
+    );
+
+    let mut graph_builder = GraphBuilder::with_capacity(0, 0);
+    // Counterparties found during the backfill only feed the frontier, the backfilled set is fixed.
+    let mut priority_pq: PriorityQueue<String, AddressPriority> = PriorityQueue::new();
+    let selected: Vec<String> = ranked.iter().map(|(address, _)| address.clone()).collect();
+    let fetch = |address: String| {
+        let (client, api_key, throttle) = (client.clone(), api_key.to_string(), throttle.clone());
+        async move {
+            let history = get_address_history(&address, &window, &client, &api_key, &throttle).await;
+            resolve_history_tokens(&history, &client, &api_key, &throttle).await;
+            history
+        }
+    };
+    let skipped = fetch_in_order(selected.clone(), &throttle, fetch, |address, history| {
+        crawl_state.visited.push(address.clone());
+        let outcome = insert_address_history(&mut priority_pq, &mut graph_builder, address.clone(), &history, window.start_block, crawl_state.ingest_options(), sinks);
+        crawl_state.record_fetch(&address, outcome);
+        let edge_count = graph_builder.graph().edge_count();
+        println!(
+            "Backfill progress is {} / {} addresses, {} transactions, {}",
+            crawl_state.visited.len(),
+            selected.len(),
+            edge_count,
+            eta_label(throttle.eta(crawl_state.visited.len(), selected.len()))
+        );
+        control.report("backfill", crawl_state.visited.len(), Some(selected.len()));
+        if let Some(monitor) = control.monitor() {
+            let unfetched = selected.len() - crawl_state.visited.len();
+            monitor.publish("backfill", unfetched + priority_pq.len(), crawl_state.visited.len(), edge_count, &throttle);
+            if monitor.take_checkpoint_request() {
+                // The checkpoint's frontier holds the backfill addresses not fetched yet, ahead of those found on the way.
+                let backfill_frontier = ranked.iter().filter(|(address, _)| !crawl_state.last_fetched_blocks.contains_key(address)).cloned();
+                let found_frontier = priority_pq.clone().into_sorted_iter().map(|(address, (relevance, _))| (address, relevance));
+                crawl_state.frontier = backfill_frontier.chain(found_frontier).collect();
+                if let Err(e) = save_crawl(graph_builder.graph(), &crawl_state) {
+                    eprintln!("Could not save the checkpoint: {:#}", e);
+                }
+            }
+        }
+        edge_count < MAX_TRANSACTIONS_TO_PARSE && !control.is_cancelled() && !throttle.is_aborted() && !control.stop_requested()
+    })
+    .await;
+    match throttle.finish() {
+        Ok(summary) => print!("{}", summary),
+        Err(e) => eprintln!("Could not save the request metrics: {:#}", e),
+    }
+
+    for (address, relevance) in rest.into_iter().chain(ranked.into_iter().filter(|(address, _)| skipped.contains(address))) {
+        if !priority_pq.change_priority_by(&address, |x: &mut AddressPriority| x.0 += relevance) {
+            priority_pq.push(address.clone(), (relevance, std::cmp::Reverse(address)));
+        }
+    }
+    crawl_state.frontier = priority_pq
+        .into_sorted_iter()
+        .filter(|(address, _)| !crawl_state.last_fetched_blocks.contains_key(address))
+        .map(|(address, (relevance, _))| (address, relevance))
+        .collect();
+    (graph_builder.into_graph(), crawl_state)
+}
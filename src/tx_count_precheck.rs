@@ -0,0 +1,80 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "crawl")]
+use {
+    crate::etherscan::{Etherscan, ProxyResponse},
+    crate::request_metrics::RequestMetric,
+    crate::throttle::{is_rate_limit_error, RequestOutcome, Throttle},
+    eyre::Result,
+    reqwest::Client,
+    std::time::Duration,
+};
+
+// What the traversal does with an address that sent more transactions than the limit, typically an
+// exchange hot wallet or a router whose history alone would use up the request budget.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OversizedAction {
+    /// Leave the history unfetched, the address stays a leaf of the graph
+    #[default]
+    Skip,
+    /// Fetch the history anyway and only record the address as oversized
+    Flag,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrecheckPolicy {
+    pub max_sent_transactions: u64,
+    pub action: OversizedAction,
+}
+
+// Transactions sent by the address, i.e. its nonce, from the eth_getTransactionCount proxy. A single cheap
+// request, unlike paging through a history to learn its length.
+#[cfg(feature = "crawl")]
+async fn get_transaction_count(address: &str, client: &Client, api_key: &str) -> Result<u64> {
+    let parameters = [("address", address.to_string()), ("tag", "latest".to_string())];
+    let (response, _): (ProxyResponse<String>, usize) = Etherscan::new(client, api_key).proxy("eth_getTransactionCount", &parameters).await?;
+    let result = response.result.unwrap_or_default();
+    // Errors such as rate limiting come back as a message in place of the hex quantity.
+    let hex = result.strip_prefix("0x").ok_or_else(|| eyre::eyre!("Unexpected transaction count {}", result))?;
+    Ok(u64::from_str_radix(hex, 16)?)
+}
+
+// None when the count could not be fetched, in which case the address is crawled as usual. Every attempt is paced
+// and logged by the crawl's throttle, and a failed one waits a little longer before the next.
+#[cfg(feature = "crawl")]
+pub async fn get_transaction_count_with_retry(address: &str, client: &Client, api_key: &str, throttle: &Throttle) -> Option<u64> {
+    const ATTEMPTS: usize = 3;
+    const RETRY_DELAY: Duration = Duration::from_secs(1);
+    for attempt in 1..=ATTEMPTS {
+        let started = throttle.acquire().await;
+        let metric = RequestMetric {
+            started_at_ms: 0,
+            address: address.to_string(),
+            start_block: 0,
+            end_block: 0,
+            page: 0,
+            offset: 0,
+            sort: "eth_getTransactionCount",
+            attempt,
+            outcome: "",
+            latency_ms: 0.0,
+            bytes: 0,
+            results: 0,
+        };
+        match get_transaction_count(address, client, api_key).await {
+            Ok(count) => {
+                throttle.record(started, RequestOutcome::Success, metric);
+                return Some(count);
+            }
+            Err(e) => {
+                throttle.record(started, if is_rate_limit_error(&e) { RequestOutcome::RateLimited } else { RequestOutcome::Failed }, metric);
+                println!("Incorrect transaction count response for {}:\n{}", address, e);
+                if attempt < ATTEMPTS {
+                    tokio::time::sleep(RETRY_DELAY * attempt as u32).await;
+                }
+            }
+        }
+    }
+    None
+}
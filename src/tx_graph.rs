@@ -0,0 +1,271 @@
+use eyre::Result;
+use petgraph::graph::{EdgeReference, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+
+use crate::edge_payload::{legacy_format_version, EdgeSchema, GRAPH_FORMAT_VERSION};
+use crate::{FilteringResultInfo, Transaction, DATA_STORAGE_FOLDER, G};
+
+// Below this many edges per thread, spawning costs more than it saves.
+const MIN_EDGES_PER_THREAD: usize = 50_000;
+
+// The layout of SerializableGraph with the edge list left as unparsed text borrowed from the input.
+#[derive(Deserialize)]
+struct GraphHeader<'a> {
+    #[serde(default = "legacy_format_version")]
+    format_version: u32,
+    #[serde(default)]
+    edge_schema: EdgeSchema,
+    info: FilteringResultInfo,
+    nodes: Vec<String>,
+    #[serde(borrow)]
+    edges: &'a RawValue,
+}
+
+type SerializedEdge = (usize, usize, Transaction);
+
+// Splits the text of a JSON array into the text of its elements, tracking nesting outside of strings.
+// The array was already validated by the header parse, so strings and brackets are balanced.
+fn array_elements(array: &str) -> Vec<&str> {
+    let mut elements = Vec::new();
+    let (mut depth, mut in_string, mut escaped, mut start) = (0usize, false, false, 0);
+    for (i, byte) in array.bytes().enumerate() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                if depth == 1 {
+                    start = i + 1;
+                }
+            }
+            b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 && !array[start..i].trim().is_empty() {
+                    elements.push(array[start..i].trim());
+                }
+            }
+            b',' if depth == 1 => {
+                elements.push(array[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    elements
+}
+
+fn parse_edges(elements: &[&str]) -> Result<Vec<SerializedEdge>> {
+    elements.iter().map(|element| Ok(serde_json::from_str(element)?)).collect()
+}
+
+// Edges are parsed in contiguous chunks on scoped threads and concatenated in order, so edge indices
+// match the file. Without available parallelism, e.g. on wasm32, everything runs on the calling thread.
+fn parse_edges_in_parallel(elements: &[&str]) -> Result<Vec<SerializedEdge>> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = elements.len().div_ceil(threads).max(MIN_EDGES_PER_THREAD);
+    if elements.len() <= chunk_size {
+        return parse_edges(elements);
+    }
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = elements.chunks(chunk_size).map(|chunk| scope.spawn(move || parse_edges(chunk))).collect();
+        let mut edges = Vec::with_capacity(elements.len());
+        for handle in handles {
+            edges.extend(handle.join().unwrap()?);
+        }
+        Ok(edges)
+    })
+}
+
+// Position of an address in the serialized node list. Unlike NodeIndex it carries no petgraph version.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct AddressId(pub usize);
+
+// One transaction with its endpoints resolved to addresses. The USD value is the one stamped by enrich-usd, else
+// the stablecoin amount of a parsed transfer, and None for edges nobody priced.
+#[derive(Debug, Clone, Copy)]
+pub struct TransactionRecord<'a> {
+    pub id: usize,
+    pub from: &'a str,
+    pub to: &'a str,
+    pub hash: &'a str,
+    pub timestamp: u64,
+    pub value_wei: u128,
+    pub usd_value: Option<f64>,
+    pub fee_usd: Option<f64>,
+    pub transaction: &'a Transaction,
+}
+
+// All transactions from one address to another, in edge order.
+#[derive(Debug, Clone)]
+pub struct PairRecord<'a> {
+    pub from: &'a str,
+    pub to: &'a str,
+    pub transactions: Vec<TransactionRecord<'a>>,
+}
+
+impl PairRecord<'_> {
+    // Sum over the priced transactions, None when none of them has a USD value.
+    pub fn usd_value(&self) -> Option<f64> {
+        self.transactions.iter().filter_map(|record| record.usd_value).fold(None, |total, usd| Some(total.unwrap_or(0.0) + usd))
+    }
+}
+
+// Crate-owned transaction graph. Petgraph stays an implementation detail behind as_petgraph for
+// callers that want its algorithms.
+pub struct TxGraph {
+    graph: G,
+}
+
+impl TxGraph {
+    pub fn with_capacity(addresses: usize, transactions: usize) -> TxGraph {
+        TxGraph { graph: G::with_capacity(addresses, transactions) }
+    }
+
+    pub fn add_address(&mut self, address: String) -> AddressId {
+        AddressId(self.graph.add_node(address).index())
+    }
+
+    pub fn add_transaction(&mut self, from: AddressId, to: AddressId, transaction: Transaction) -> Result<()> {
+        if from.0 >= self.address_count() || to.0 >= self.address_count() {
+            return Err(eyre::eyre!("Transaction {} -> {} references an unknown address", from.0, to.0));
+        }
+        self.graph.add_edge(NodeIndex::new(from.0), NodeIndex::new(to.0), transaction);
+        Ok(())
+    }
+
+    // Bulk insertion reserving for the batch size the iterator reports, failing on the first unknown address.
+    pub fn extend_transactions(&mut self, transactions: impl IntoIterator<Item = (AddressId, AddressId, Transaction)>) -> Result<()> {
+        let transactions = transactions.into_iter();
+        self.graph.reserve_edges(transactions.size_hint().0);
+        for (from, to, transaction) in transactions {
+            self.add_transaction(from, to, transaction)?;
+        }
+        Ok(())
+    }
+
+    pub fn address_count(&self) -> usize {
+        self.as_petgraph().node_count()
+    }
+
+    pub fn transaction_count(&self) -> usize {
+        self.as_petgraph().edge_count()
+    }
+
+    pub fn address(&self, id: AddressId) -> Option<&str> {
+        self.graph.node_weight(NodeIndex::new(id.0)).map(String::as_str)
+    }
+
+    pub fn address_id(&self, address: &str) -> Option<AddressId> {
+        self.graph.node_indices().find(|&node| self.graph[node].eq_ignore_ascii_case(address)).map(|node| AddressId(node.index()))
+    }
+
+    fn record<'a>(&'a self, edge: EdgeReference<'a, Transaction>) -> TransactionRecord<'a> {
+        let transaction = edge.weight();
+        TransactionRecord {
+            id: edge.id().index(),
+            from: &self.graph[edge.source()],
+            to: &self.graph[edge.target()],
+            hash: &transaction.hash,
+            timestamp: transaction.timestamp,
+            value_wei: transaction.value_wei,
+            usd_value: transaction.usd_value(),
+            fee_usd: transaction.fee_usd,
+            transaction,
+        }
+    }
+
+    // Every transaction in edge order, which is the order of the saved file.
+    pub fn iter_transactions(&self) -> impl Iterator<Item = TransactionRecord<'_>> + '_ {
+        self.graph.edge_references().map(|edge| self.record(edge))
+    }
+
+    // Every ordered address pair with at least one transaction between them, by the positions of the
+    // addresses. Transactions both ways between two addresses make two pairs.
+    pub fn iter_pairs(&self) -> impl Iterator<Item = PairRecord<'_>> + '_ {
+        let mut pairs: BTreeMap<(usize, usize), Vec<TransactionRecord<'_>>> = BTreeMap::new();
+        for edge in self.graph.edge_references() {
+            pairs.entry((edge.source().index(), edge.target().index())).or_default().push(self.record(edge));
+        }
+        pairs.into_iter().map(|((from, to), transactions)| PairRecord {
+            from: &self.graph[NodeIndex::new(from)],
+            to: &self.graph[NodeIndex::new(to)],
+            transactions,
+        })
+    }
+
+    // Transactions sent or received by the address in edge order, a self-transfer once. Unknown addresses,
+    // matched without regard to case, have none.
+    pub fn iter_address_transactions<'a>(&'a self, address: &str) -> impl Iterator<Item = TransactionRecord<'a>> + 'a {
+        let mut records: Vec<TransactionRecord<'a>> = match self.address_id(address) {
+            Some(id) => {
+                let node = NodeIndex::new(id.0);
+                let outgoing = self.graph.edges_directed(node, Direction::Outgoing);
+                let incoming = self.graph.edges_directed(node, Direction::Incoming).filter(|edge| edge.source() != edge.target());
+                outgoing.chain(incoming).map(|edge| self.record(edge)).collect()
+            }
+            None => vec![],
+        };
+        records.sort_by_key(|record| record.id);
+        records.into_iter()
+    }
+
+    pub fn as_petgraph(&self) -> &G {
+        &self.graph
+    }
+
+    pub fn into_petgraph(self) -> G {
+        self.graph
+    }
+
+    pub fn load(pathname: &str) -> Result<(TxGraph, FilteringResultInfo)> {
+        let file_pathname = format!("{}/{}", DATA_STORAGE_FOLDER, pathname);
+        let file = File::open(&file_pathname)?;
+        let (graph, info) = TxGraph::from_reader(std::io::BufReader::new(file))?;
+        crate::workspace::record_input(&file_pathname);
+        println!("Loaded graph from {} with {} nodes and {} edges", &file_pathname, graph.address_count(), graph.transaction_count());
+        Ok((graph, info))
+    }
+
+    // Malformed input, including edges pointing past the node list, is an error rather than a panic.
+    pub fn from_reader(mut reader: impl Read) -> Result<(TxGraph, FilteringResultInfo)> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        TxGraph::from_slice(&bytes)
+    }
+
+    // Parsing from memory avoids the per-byte overhead of reading through serde_json's reader adapter.
+    pub fn from_slice(bytes: &[u8]) -> Result<(TxGraph, FilteringResultInfo)> {
+        let serializable_graph: GraphHeader = serde_json::from_slice(bytes)?;
+        if serializable_graph.format_version > GRAPH_FORMAT_VERSION {
+            return Err(eyre::eyre!(
+                "Graph format version {} is newer than the supported version {}, update ethparser",
+                serializable_graph.format_version,
+                GRAPH_FORMAT_VERSION
+            ));
+        }
+        let edges = parse_edges_in_parallel(&array_elements(serializable_graph.edges.get()))?;
+        let mut graph = TxGraph::with_capacity(serializable_graph.nodes.len(), edges.len());
+        for address in serializable_graph.nodes {
+            graph.add_address(address);
+        }
+        graph.extend_transactions(edges.into_iter().map(|(source, target, transaction)| (AddressId(source), AddressId(target), transaction)))?;
+        if serializable_graph.edge_schema == EdgeSchema::Lean {
+            println!("Graph edges use the lean schema without block positions, nonces, gas or decoded calls");
+        }
+        Ok((graph, serializable_graph.info))
+    }
+}
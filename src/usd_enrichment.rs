@@ -0,0 +1,69 @@
+use eyre::Result;
+
+use crate::cli::EnrichUsdArgs;
+use crate::fees::effective_fee_wei;
+use crate::money::wei_to_coin;
+use crate::pricing::{PriceCache, PriceSeries};
+use crate::{deserialize_graph, serialize_graph, DATA_STORAGE_FOLDER, G};
+
+#[derive(Default, Debug)]
+pub struct EnrichmentSummary {
+    pub priced_values: usize,
+    pub unpriced_values: usize,
+    pub priced_native_values: usize,
+    pub unpriced_native_values: usize,
+    pub priced_fees: usize,
+    pub unpriced_fees: usize,
+}
+
+// Token amounts are priced with the payload symbol, and native coin sent with a call and fees with fee_symbol, all
+// at the hour of the transfer.
+// Previously stamped values are overwritten, so a graph can be re-enriched with a better price source.
+pub fn enrich_usd_values(graph: &mut G, prices: &mut PriceCache, fee_symbol: &str) -> EnrichmentSummary {
+    let mut summary = EnrichmentSummary::default();
+    for transaction in graph.edge_weights_mut() {
+        transaction.value_usd = transaction
+            .data
+            .as_ref()
+            .and_then(|data| prices.usd_price(&format!("{:?}", data.payload), transaction.timestamp).map(|price| price * data.usd_value));
+        if transaction.data.is_some() {
+            match transaction.value_usd {
+                Some(_) => summary.priced_values += 1,
+                None => summary.unpriced_values += 1,
+            }
+        } else if transaction.value_wei != 0 {
+            transaction.value_usd = prices.usd_price(fee_symbol, transaction.timestamp).map(|price| price * wei_to_coin(transaction.value_wei));
+            match transaction.value_usd {
+                Some(_) => summary.priced_native_values += 1,
+                None => summary.unpriced_native_values += 1,
+            }
+        }
+
+        let fee_wei = effective_fee_wei(transaction);
+        transaction.fee_usd = fee_wei.and_then(|fee_wei| prices.usd_price(fee_symbol, transaction.timestamp).map(|price| price * wei_to_coin(fee_wei)));
+        match transaction.fee_usd {
+            Some(_) => summary.priced_fees += 1,
+            None if fee_wei.is_some() => summary.unpriced_fees += 1,
+            None => {}
+        }
+    }
+    summary
+}
+
+pub fn run_enrich_usd_command(args: &EnrichUsdArgs) -> Result<()> {
+    let (mut graph, info) = deserialize_graph(&args.input)?;
+    let price_series = PriceSeries::from_csv(&format!("{}/{}", DATA_STORAGE_FOLDER, args.prices))?;
+    let mut price_cache = PriceCache::new(&price_series);
+    let summary = enrich_usd_values(&mut graph, &mut price_cache, &args.fee_symbol);
+    println!(
+        "Priced {} token values ({} without a price), {} native values ({} without a price) and {} fees ({} without a price) from {} symbol-hours",
+        summary.priced_values,
+        summary.unpriced_values,
+        summary.priced_native_values,
+        summary.unpriced_native_values,
+        summary.priced_fees,
+        summary.unpriced_fees,
+        price_cache.resolved_hours()
+    );
+    serialize_graph(&graph, &info, &args.output)
+}
@@ -0,0 +1,254 @@
+use eyre::Result;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::atomic_file::write_atomically;
+use crate::cli::{DescribeArgs, GcArgs};
+use crate::time_buckets::time_label;
+use crate::DATA_STORAGE_FOLDER;
+
+// The data storage folder is the workspace. Its catalog records every graph and derived file the binary
+// writes there, with the command that produced it and the graphs that command loaded.
+const CATALOG_FILENAME: &str = "catalog.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum EntryKind {
+    Graph,
+    Artifact,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CatalogEntry {
+    pub kind: EntryKind,
+    pub saved_at: u64,
+    pub command: String,
+    // Names of the graphs loaded by the command before it wrote this entry.
+    pub inputs: Vec<String>,
+    pub node_count: Option<usize>,
+    pub edge_count: Option<usize>,
+    // Seed of the random number generator used by the command, if it used one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Catalog {
+    // Keyed by the path relative to the workspace, e.g. filtered_transactions_polygon.json.
+    pub entries: BTreeMap<String, CatalogEntry>,
+}
+
+struct Session {
+    command: String,
+    inputs: Vec<String>,
+    seed: Option<u64>,
+    outputs: Vec<String>,
+}
+
+// Cataloging is only on inside the binary; library users such as the FFI and wasm builds write no catalog.
+static SESSION: Lazy<Mutex<Option<Session>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn start_session(command: String) {
+    *SESSION.lock().unwrap() = Some(Session { command, inputs: Vec::new(), seed: None, outputs: Vec::new() });
+}
+
+fn catalog_pathname() -> String {
+    format!("{}/{}", DATA_STORAGE_FOLDER, CATALOG_FILENAME)
+}
+
+// Name of a file inside the workspace, or None for files elsewhere.
+fn workspace_name(pathname: &str) -> Option<String> {
+    let name = Path::new(pathname).strip_prefix(DATA_STORAGE_FOLDER).ok()?.to_str()?.to_string();
+    (!name.is_empty() && name != CATALOG_FILENAME).then_some(name)
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+impl Catalog {
+    pub fn load() -> Result<Catalog> {
+        match File::open(catalog_pathname()) {
+            Ok(file) => Ok(serde_json::from_reader(std::io::BufReader::new(file))?),
+            Err(_) => Ok(Catalog::default()),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        write_atomically(&catalog_pathname(), serde_json::to_string_pretty(self)?.as_bytes())
+    }
+
+    // Entries whose inputs include the named graph.
+    pub fn derived_from(&self, name: &str) -> Vec<&String> {
+        self.entries.iter().filter(|(_, entry)| entry.inputs.iter().any(|input| input == name)).map(|(n, _)| n).collect()
+    }
+
+    // Accepts a name with or without the .json extension.
+    pub fn resolve(&self, name: &str) -> Option<(&String, &CatalogEntry)> {
+        self.entries.get_key_value(name).or_else(|| self.entries.get_key_value(&format!("{}.json", name)))
+    }
+}
+
+fn record(pathname: &str, kind: EntryKind, counts: Option<(usize, usize)>) -> Result<()> {
+    let Some(name) = workspace_name(pathname) else { return Ok(()) };
+    let (command, inputs, seed) = match SESSION.lock().unwrap().as_mut() {
+        Some(session) => {
+            if !session.outputs.contains(&name) {
+                session.outputs.push(name.clone());
+            }
+            (session.command.clone(), session.inputs.clone(), session.seed)
+        }
+        None => return Ok(()),
+    };
+    let mut catalog = Catalog::load()?;
+    let entry = CatalogEntry {
+        kind,
+        saved_at: now(),
+        command,
+        inputs: inputs.into_iter().filter(|input| *input != name).collect(),
+        node_count: counts.map(|(nodes, _)| nodes),
+        edge_count: counts.map(|(_, edges)| edges),
+        seed,
+    };
+    catalog.entries.insert(name, entry);
+    catalog.save()
+}
+
+// Workspace files this session has written, in the order they were first written.
+pub fn session_outputs() -> Vec<String> {
+    SESSION.lock().unwrap().as_ref().map_or(vec![], |session| session.outputs.clone())
+}
+
+pub fn record_input(pathname: &str) {
+    if let (Some(session), Some(name)) = (SESSION.lock().unwrap().as_mut(), workspace_name(pathname)) {
+        if !session.inputs.contains(&name) {
+            session.inputs.push(name);
+        }
+    }
+}
+
+pub fn record_seed(seed: u64) {
+    if let Some(session) = SESSION.lock().unwrap().as_mut() {
+        session.seed = Some(seed);
+    }
+}
+
+pub fn record_artifact(pathname: &str) -> Result<()> {
+    record(pathname, EntryKind::Artifact, None)
+}
+
+pub fn record_graph(pathname: &str, node_count: usize, edge_count: usize) -> Result<()> {
+    record(pathname, EntryKind::Graph, Some((node_count, edge_count)))
+}
+
+fn workspace_files() -> Result<BTreeSet<String>> {
+    let mut files = BTreeSet::new();
+    if !Path::new(DATA_STORAGE_FOLDER).exists() {
+        return Ok(files);
+    }
+    for entry in fs::read_dir(DATA_STORAGE_FOLDER)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name != CATALOG_FILENAME {
+                files.insert(name);
+            }
+        }
+    }
+    Ok(files)
+}
+
+pub fn run_list_command() -> Result<()> {
+    let catalog = Catalog::load()?;
+    let files = workspace_files()?;
+    println!("{:<44} {:>8} {:>10} {:>9}  command", "graph", "nodes", "edges", "derived");
+    for (name, entry) in catalog.entries.iter().filter(|(_, e)| e.kind == EntryKind::Graph) {
+        println!(
+            "{:<44} {:>8} {:>10} {:>9}  {}",
+            name,
+            entry.node_count.unwrap_or(0),
+            entry.edge_count.unwrap_or(0),
+            catalog.derived_from(name).len(),
+            entry.command
+        );
+    }
+    let artifacts = catalog.entries.values().filter(|e| e.kind == EntryKind::Artifact).count();
+    let untracked: Vec<&String> = files.iter().filter(|name| !catalog.entries.contains_key(*name)).collect();
+    println!("\n{} derived artifacts, {} untracked files in {}", artifacts, untracked.len(), DATA_STORAGE_FOLDER);
+    for name in untracked {
+        println!("  untracked: {}", name);
+    }
+    Ok(())
+}
+
+pub fn run_describe_command(args: &DescribeArgs) -> Result<()> {
+    let catalog = Catalog::load()?;
+    let (name, entry) = catalog.resolve(&args.name).ok_or_else(|| eyre::eyre!("{} is not in the workspace catalog", args.name))?;
+    let pathname = format!("{}/{}", DATA_STORAGE_FOLDER, name);
+    println!("{} ({:?})", name, entry.kind);
+    match fs::metadata(&pathname) {
+        Ok(metadata) => println!("File: {}, {} bytes", &pathname, metadata.len()),
+        Err(_) => println!("File: {} is missing, run gc to drop the entry", &pathname),
+    }
+    if let (Some(nodes), Some(edges)) = (entry.node_count, entry.edge_count) {
+        println!("Nodes: {}, Edges: {}", nodes, edges);
+    }
+    println!("Saved at: {} ({} unix seconds)", time_label(entry.saved_at)?, entry.saved_at);
+    println!("Command: ethparser {}", entry.command);
+    if let Some(seed) = entry.seed {
+        println!("Seed: {} (rerun with --seed {} to reproduce)", seed, seed);
+    }
+    println!("Inputs: {}", if entry.inputs.is_empty() { "none".to_string() } else { entry.inputs.join(", ") });
+    let derived = catalog.derived_from(name);
+    println!("Derived: {}", if derived.is_empty() { "none".to_string() } else { derived.iter().map(|n| n.as_str()).collect::<Vec<&str>>().join(", ") });
+    Ok(())
+}
+
+// Drops entries whose files are gone, deletes artifacts whose input graphs were all deleted, and deletes
+// temporary files left by interrupted writes. Only prints the plan unless apply is set.
+pub fn run_gc_command(args: &GcArgs) -> Result<()> {
+    let mut catalog = Catalog::load()?;
+    let files = workspace_files()?;
+
+    let missing: Vec<String> = catalog.entries.keys().filter(|name| !files.contains(*name)).cloned().collect();
+    for name in missing.iter() {
+        println!("Missing file, dropping entry: {}", name);
+        catalog.entries.remove(name);
+    }
+    let orphaned: Vec<String> = catalog
+        .entries
+        .iter()
+        .filter(|(_, entry)| {
+            entry.kind == EntryKind::Artifact
+                && !entry.inputs.is_empty()
+                && entry.inputs.iter().all(|input| !files.contains(input))
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    let temporary: Vec<&String> = files.iter().filter(|name| name.ends_with(".tmp")).collect();
+    for name in orphaned.iter() {
+        println!("Orphaned artifact, deleting: {}", name);
+    }
+    for name in temporary.iter() {
+        println!("Leftover temporary file, deleting: {}", name);
+    }
+
+    if !args.apply {
+        println!("\nDry run, pass --apply to drop {} entries and delete {} files", missing.len(), orphaned.len() + temporary.len());
+        return Ok(());
+    }
+    for name in orphaned.iter() {
+        fs::remove_file(format!("{}/{}", DATA_STORAGE_FOLDER, name))?;
+        catalog.entries.remove(name);
+    }
+    for name in temporary.iter() {
+        fs::remove_file(format!("{}/{}", DATA_STORAGE_FOLDER, name))?;
+    }
+    catalog.save()?;
+    println!("\nDropped {} entries and deleted {} files", missing.len() + orphaned.len(), orphaned.len() + temporary.len());
+    Ok(())
+}
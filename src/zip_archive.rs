@@ -0,0 +1,78 @@
+use chrono::{Datelike, Timelike, Utc};
+use eyre::Result;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+// Deflated entries with UTF-8 names, enough for any unzip tool. Entry times are the time the archive was made.
+pub struct ZipArchive<W: Write> {
+    writer: W,
+    offset: u32,
+    central_directory: Vec<u8>,
+    entries: u16,
+    time: u16,
+    date: u16,
+}
+
+const UTF8_NAMES: u16 = 1 << 11;
+const DEFLATED: u16 = 8;
+const VERSION: u16 = 20;
+
+impl<W: Write> ZipArchive<W> {
+    pub fn new(writer: W) -> ZipArchive<W> {
+        let now = Utc::now();
+        let time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+        let date = (((now.year() - 1980).max(0) as u32) << 9 | now.month() << 5 | now.day()) as u16;
+        ZipArchive { writer, offset: 0, central_directory: vec![], entries: 0, time, date }
+    }
+
+    pub fn add(&mut self, name: &str, contents: &[u8]) -> Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(contents)?;
+        let compressed = encoder.finish()?;
+        let (crc, compressed_size, size) = (crc32fast::hash(contents), u32::try_from(compressed.len())?, u32::try_from(contents.len())?);
+        let name_length = u16::try_from(name.len())?;
+
+        // Fields shared by the local header and the central directory record, from version needed on.
+        let mut common = vec![];
+        for half_word in [VERSION, UTF8_NAMES, DEFLATED, self.time, self.date] {
+            common.extend(half_word.to_le_bytes());
+        }
+        for word in [crc, compressed_size, size] {
+            common.extend(word.to_le_bytes());
+        }
+        common.extend(name_length.to_le_bytes());
+        common.extend(0u16.to_le_bytes()); // Extra field length
+
+        let mut local_header = 0x04034b50u32.to_le_bytes().to_vec();
+        local_header.extend(&common);
+        local_header.extend(name.as_bytes());
+        self.writer.write_all(&local_header)?;
+        self.writer.write_all(&compressed)?;
+
+        self.central_directory.extend(0x02014b50u32.to_le_bytes());
+        self.central_directory.extend(VERSION.to_le_bytes()); // Version made by
+        self.central_directory.extend(&common);
+        // Comment length, disk number, internal and external attributes.
+        self.central_directory.extend([0u8; 10]);
+        self.central_directory.extend(self.offset.to_le_bytes());
+        self.central_directory.extend(name.as_bytes());
+
+        self.offset = self.offset.checked_add(u32::try_from(local_header.len() + compressed.len())?).ok_or_else(|| eyre::eyre!("Archive exceeds 4 GiB"))?;
+        self.entries = self.entries.checked_add(1).ok_or_else(|| eyre::eyre!("Archive exceeds 65535 entries"))?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.write_all(&self.central_directory)?;
+        let mut end = 0x06054b50u32.to_le_bytes().to_vec();
+        end.extend([0u8; 4]); // Disk numbers
+        end.extend(self.entries.to_le_bytes());
+        end.extend(self.entries.to_le_bytes());
+        end.extend(u32::try_from(self.central_directory.len())?.to_le_bytes());
+        end.extend(self.offset.to_le_bytes());
+        end.extend(0u16.to_le_bytes()); // Comment length
+        self.writer.write_all(&end)?;
+        Ok(self.writer)
+    }
+}
@@ -0,0 +1,161 @@
+// Golden numbers over the bundled anonymized graph in tests/data, produced with
+//   ethparser gen-fixture --pattern random:300:2500 --pattern cycle:6@5000 --pattern two-way:40 --pattern star:25@250
+//     --pattern fan-in:25@40 --pattern chain:8@12000 --pattern pair:12@9999 --value-spread 0.5 --mixed-tokens
+//     --gas-price-gwei 5 --interval 600 --seed 1214 --output golden_transactions.json --prices-output golden_prices.csv
+// Each test runs the CLI in a scratch workspace and compares what it saved with tests/golden. Numbers are
+// compared with a relative tolerance, since summation order may change without changing results. After an
+// intended change of results, rerun with UPDATE_GOLDEN=1 and review the diff of tests/golden.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const GRAPH: &str = "golden_transactions.json";
+const PRICES: &str = "golden_prices.csv";
+const TOLERANCE: f64 = 1e-9;
+const TOP_K: usize = 10;
+
+fn workspace(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ethparser-golden-{}-{}", std::process::id(), test));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("json")).unwrap();
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    for file in [GRAPH, PRICES] {
+        fs::copy(data.join(file), dir.join("json").join(file)).unwrap();
+    }
+    dir
+}
+
+fn ethparser(dir: &Path, args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_ethparser")).args(args).current_dir(dir).output().unwrap();
+    assert!(output.status.success(), "ethparser {} failed:\n{}", args.join(" "), String::from_utf8_lossy(&output.stderr));
+}
+
+fn saved(dir: &Path, name: &str) -> String {
+    fs::read_to_string(dir.join("json").join(name)).unwrap()
+}
+
+fn tokens(text: &str) -> Vec<&str> {
+    text.split(|c: char| c.is_whitespace() || c == ',').filter(|token| !token.is_empty()).collect()
+}
+
+fn same_token(expected: &str, actual: &str) -> bool {
+    match (expected.parse::<f64>(), actual.parse::<f64>()) {
+        (Ok(expected), Ok(actual)) => (expected - actual).abs() <= TOLERANCE * expected.abs().max(actual.abs()).max(1.0),
+        _ => expected == actual,
+    }
+}
+
+fn assert_golden(name: &str, actual: &str) {
+    let pathname = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(pathname.parent().unwrap()).unwrap();
+        fs::write(&pathname, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&pathname).unwrap_or_else(|_| panic!("Missing {}, run with UPDATE_GOLDEN=1 to create it", pathname.display()));
+    let (expected_tokens, actual_tokens) = (tokens(&expected), tokens(actual));
+    let mismatch = expected_tokens.iter().zip(actual_tokens.iter()).position(|(e, a)| !same_token(e, a));
+    assert!(
+        mismatch.is_none() && expected_tokens.len() == actual_tokens.len(),
+        "{} differs from the golden file at token {}:\n--- expected\n{}\n--- actual\n{}",
+        name,
+        mismatch.unwrap_or(expected_tokens.len().min(actual_tokens.len())),
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn stats_report() {
+    let dir = workspace("stats");
+    ethparser(&dir, &["stats", "--input", GRAPH]);
+    assert_golden("stats_report.txt", &saved(&dir, "stats_report.txt"));
+}
+
+#[test]
+fn analysis_pass_metrics() {
+    let dir = workspace("analyze");
+    ethparser(&dir, &["analyze", "--input", GRAPH, "--prices", PRICES, "--no-cache"]);
+    let manifest: serde_json::Value = serde_json::from_str(&saved(&dir, "analysis_manifest.json")).unwrap();
+    let mut lines = String::new();
+    for pass in manifest["passes"].as_array().unwrap() {
+        assert!(pass["error"].is_null(), "Pass {} failed: {}", pass["pass"], pass["error"]);
+        let metrics: BTreeMap<String, f64> = serde_json::from_value(pass["metrics"].clone()).unwrap();
+        for (metric, value) in metrics {
+            lines.push_str(&format!("{} {} {}\n", pass["pass"].as_str().unwrap(), metric, value));
+        }
+    }
+    assert_golden("analysis_metrics.txt", &lines);
+}
+
+#[test]
+fn fee_percentiles() {
+    let dir = workspace("fees");
+    ethparser(&dir, &["fees", "--input", GRAPH, "--prices", PRICES]);
+    assert_golden("fee_percentiles.csv", &saved(&dir, "fee_percentiles.csv"));
+}
+
+#[test]
+fn dynamic_fee_percentiles() {
+    let dir = workspace("dynamic-fees");
+    ethparser(&dir, &[
+        "gen-fixture", "--pattern", "random:40:200", "--base-fee-gwei", "20", "--priority-fee-gwei", "2", "--seed", "1244",
+        "--output", "dynamic_fees.json", "--prices-output", "dynamic_fee_prices.csv",
+    ]);
+    ethparser(&dir, &["fees", "--input", "dynamic_fees.json", "--prices", "dynamic_fee_prices.csv"]);
+    assert_golden("dynamic_fee_percentiles.csv", &saved(&dir, "fee_percentiles.csv"));
+}
+
+#[test]
+fn two_way_flows() {
+    let dir = workspace("reciprocity");
+    ethparser(&dir, &["reciprocity", "--input", GRAPH]);
+    assert_golden("reciprocity.csv", &saved(&dir, "reciprocity.csv"));
+}
+
+#[test]
+fn rolling_net_flow() {
+    let dir = workspace("rolling-flow");
+    let addresses = "0x0000000000000000000000000000000000000001,0x0000000000000000000000000000000000000002,0x000000000000000000000000000000000000012d";
+    ethparser(&dir, &["rolling-flow", "--input", GRAPH, "--addresses", addresses]);
+    assert_golden("rolling_flow.csv", &saved(&dir, "rolling_flow.csv"));
+}
+
+#[test]
+fn temporal_motifs() {
+    let dir = workspace("temporal-motifs");
+    ethparser(&dir, &["temporal-motifs", "--input", GRAPH]);
+    assert_golden("temporal_motifs.csv", &saved(&dir, "temporal_motifs.csv"));
+}
+
+// Degree centrality and USD volume rankings from the feature table, ties broken by address.
+#[test]
+fn centrality_top_k() {
+    let dir = workspace("features");
+    ethparser(&dir, &["features", "--input", GRAPH]);
+    let mut reader = csv::Reader::from_path(dir.join("json/node_features.csv")).unwrap();
+    let headers = reader.headers().unwrap().clone();
+    let column = |name: &str| headers.iter().position(|header| header == name).unwrap();
+    let (address, in_degree, out_degree, in_usd, out_usd) = (column("address"), column("in_degree"), column("out_degree"), column("total_in_usd"), column("total_out_usd"));
+    let number = |row: &csv::StringRecord, i: usize| row[i].parse::<f64>().unwrap();
+    let scores: Vec<(String, [f64; 3])> = reader
+        .records()
+        .map(|record| {
+            let row = record.unwrap();
+            let scores = [number(&row, in_degree) + number(&row, out_degree), number(&row, in_degree), number(&row, in_usd) + number(&row, out_usd)];
+            (row[address].to_string(), scores)
+        })
+        .collect();
+
+    let mut text = String::new();
+    for (i, ranking) in ["degree", "in_degree", "usd_volume"].iter().enumerate() {
+        let mut ranked: Vec<(f64, &str)> = scores.iter().map(|(address, scores)| (scores[i], address.as_str())).collect();
+        ranked.sort_by(|x, y| y.0.partial_cmp(&x.0).unwrap().then(x.1.cmp(y.1)));
+        for (rank, (score, address)) in ranked.iter().take(TOP_K).enumerate() {
+            text.push_str(&format!("{} {} {} {}\n", ranking, rank + 1, address, score));
+        }
+    }
+    assert_golden("centrality_top_k.txt", &text);
+}
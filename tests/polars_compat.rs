@@ -0,0 +1,44 @@
+// Parquet exports from before a column was added still load, with the missing fields left at their defaults.
+#![cfg(feature = "polars")]
+
+use ethparser::polars_integration::{graph_from_polars, ToPolars};
+use polars::prelude::*;
+
+#[test]
+fn loads_edges_with_only_the_first_export_columns() {
+    let nodes = df!(
+        "node_id" => [0u32, 1, 2],
+        "address" => ["0x0000000000000000000000000000000000000001", "0x0000000000000000000000000000000000000002", "0x0000000000000000000000000000000000000003"],
+    )
+    .unwrap();
+    let edges = df!(
+        "source" => [0u32, 1],
+        "target" => [1u32, 2],
+        "hash" => ["0x01", "0x02"],
+        "timestamp" => [1_700_000_000u64, 1_700_000_600],
+        "payload" => [Some("BSCUSD"), None],
+        "usd_value" => [Some(125.5), None],
+        "onchain_function" => [Some("Transfer"), None],
+    )
+    .unwrap();
+
+    let graph = graph_from_polars(&nodes, &edges).unwrap();
+    assert_eq!((graph.node_count(), graph.edge_count()), (3, 2));
+
+    let (_, exported) = graph.to_polars().unwrap();
+    let u64_column = |name: &str| exported.column(name).unwrap().as_materialized_series().u64().unwrap().into_iter().collect::<Vec<_>>();
+    for name in ["gas_price", "gas_used", "block_number", "transaction_index", "nonce", "max_fee_per_gas"] {
+        assert_eq!(u64_column(name), vec![Some(0), Some(0)], "{}", name);
+    }
+    let kinds: Vec<Option<&str>> = exported.column("kind").unwrap().as_materialized_series().str().unwrap().into_iter().collect();
+    assert_eq!(kinds, vec![Some("Call"), Some("Call")]);
+    let usd: Vec<Option<f64>> = exported.column("usd_value").unwrap().as_materialized_series().f64().unwrap().into_iter().collect();
+    assert_eq!(usd, vec![Some(125.5), None]);
+}
+
+#[test]
+fn rejects_edges_without_a_first_export_column() {
+    let nodes = df!("node_id" => [0u32], "address" => ["0x0000000000000000000000000000000000000001"]).unwrap();
+    let edges = df!("source" => [0u32], "target" => [0u32], "hash" => ["0x01"]).unwrap();
+    assert!(graph_from_polars(&nodes, &edges).is_err());
+}
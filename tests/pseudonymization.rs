@@ -0,0 +1,96 @@
+// Pseudonymizes a small full-schema graph whose edges carry decoded calls, decoded transfers and raw calldata, and
+// checks that nothing of the original addresses or transaction identity survives in the saved file.
+
+use serde_json::json;
+use std::fs;
+use std::process::Command;
+
+const SENDER: &str = "0x5a0b54d5dc17e0aadc383d2db43b0a0d3e029c4c";
+const RECIPIENT: &str = "0x9f8f72aa9304c8b593d555f12ef6589cc3a579a2";
+const SPENDER: &str = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d";
+const TOKEN: &str = "0xdac17f958d2ee523a2206206994597c13d831ec7";
+const SWAP_OUTPUT_TOKEN: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+const BENEFICIARY: &str = "0x28c6c06298d514db089934071355e5743bf21d60";
+const TRANSACTION_HASH: &str = "0x3b5ad1a09271f5d30bd19b3e04d7bd91ba92c2e6cc8e12d6905d2af2d7c6f0e1";
+const BLOCK_HASH: &str = "0x8e38b4dbf6b11fcc3b9dee84fb7986e29ca0a02cecd8977c161ff7333329681e";
+
+fn graph() -> serde_json::Value {
+    let full = json!({
+        "block_hash": BLOCK_HASH,
+        "value": "0",
+        "gas_limit": 90000,
+        "cumulative_gas_used": 1234567,
+        "input": format!("0xa9059cbb000000000000000000000000{}0000000000000000000000000000000000000000000000000000000005f5e100", &RECIPIENT[2..]),
+        "method_id": "0xa9059cbb",
+        "function_name": "transfer(address _to, uint256 _value)"
+    });
+    let edge = |hash: &str, decoded_call: serde_json::Value, decoded_transfer: serde_json::Value| {
+        json!({
+            "hash": hash,
+            "timestamp": 1_700_000_000u64,
+            "block_number": 18_573_210u64,
+            "transaction_index": 57u64,
+            "nonce": 4312u64,
+            "contract_call": true,
+            "data": null,
+            "decoded_call": decoded_call,
+            "decoded_transfer": decoded_transfer,
+            "full": full
+        })
+    };
+    json!({
+        "format_version": 3,
+        "edge_schema": "full",
+        "info": { "node_count_before_filtering": 3, "edge_count_before_filtering": 3, "node_count": 3, "edge_count": 3 },
+        "nodes": [SENDER, TOKEN, SPENDER],
+        "edges": [
+            [0, 1, edge(TRANSACTION_HASH, json!({ "Transfer": { "to": RECIPIENT, "amount": "100000000" } }), json!({
+                "from": SENDER, "to": RECIPIENT, "token": TOKEN, "amount": "100000000", "amount_is_minimum": false
+            }))],
+            [0, 2, edge("0x01", json!({ "Approve": { "spender": SPENDER.to_uppercase().replace("0X", "0x"), "amount": "1" } }), json!(null))],
+            [0, 2, edge("0x02", json!({ "Swap": {
+                "method": "SwapExactTokensForTokens", "amount_in": "5", "amount_out": "4",
+                "path": [TOKEN, SWAP_OUTPUT_TOKEN], "to": BENEFICIARY
+            } }), json!(null))]
+        ]
+    })
+}
+
+fn pseudonymize(test: &str, scheme: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("ethparser-pseudonymization-{}-{}", std::process::id(), test));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("json")).unwrap();
+    fs::write(dir.join("json/input.json"), graph().to_string()).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_ethparser"))
+        .args(["pseudonymize", "--input", "input.json", "--output", "output.json", "--scheme", scheme, "--salt", "fixed"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "pseudonymize failed:\n{}", String::from_utf8_lossy(&output.stderr));
+    fs::read_to_string(dir.join("json/output.json")).unwrap().to_lowercase()
+}
+
+fn assert_nothing_identifying_survives(saved: &str) {
+    for address in [SENDER, RECIPIENT, SPENDER, TOKEN, SWAP_OUTPUT_TOKEN, BENEFICIARY] {
+        assert!(!saved.contains(&address[2..]), "{} survived pseudonymization:\n{}", address, saved);
+    }
+    for identifier in [TRANSACTION_HASH, BLOCK_HASH, "18573210", "4312"] {
+        assert!(!saved.contains(&identifier.to_lowercase()), "{} survived pseudonymization:\n{}", identifier, saved);
+    }
+}
+
+#[test]
+fn salted_hash_leaves_no_original_address() {
+    let saved = pseudonymize("salted-hash", "salted-hash");
+    assert_nothing_identifying_survives(&saved);
+    let graph: serde_json::Value = serde_json::from_str(&saved).unwrap();
+    let edges = graph["edges"].as_array().unwrap();
+    // The decoded spender is the node it was spelled differently from, so both get one pseudonym.
+    assert_eq!(edges[1][2]["decoded_call"]["approve"]["spender"], graph["nodes"][2]);
+    assert_eq!(edges[0][2]["decoded_transfer"]["token"], graph["nodes"][1]);
+}
+
+#[test]
+fn sequential_leaves_no_original_address() {
+    assert_nothing_identifying_survives(&pseudonymize("sequential", "sequential"));
+}
@@ -0,0 +1,145 @@
+// Snapshots of rendered reports and exports over the handcrafted graph in tests/data/fixture_graph.json: a
+// two-way pair with a late top-up, a three-address cycle, a dust transfer, a stablecoin transfer logged next to
+// its outer call, and a plain native call. Each test runs the CLI in a scratch workspace and snapshots what it
+// saved. After an intended change of rendering, review and accept the new snapshots with cargo insta review.
+
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const GRAPH: &str = "fixture_graph.json";
+
+fn workspace(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ethparser-snapshot-{}-{}", std::process::id(), test));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("json")).unwrap();
+    fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data").join(GRAPH), dir.join("json").join(GRAPH)).unwrap();
+    dir
+}
+
+fn address(suffix: &str) -> String {
+    format!("0x{:0>40}", suffix)
+}
+
+// A crawl seeded from the two-way pair, stopped with two addresses still queued and one fetched only in part.
+fn write_crawl_state(dir: &Path) {
+    let fetched = ["a11ce", "b0b", "ca201", "da7e", "e217"].map(address);
+    let crawl_state = json!({
+        "last_fetched_blocks": fetched.iter().enumerate().map(|(i, a)| (a.clone(), 33_060_000 + i as u64)).collect::<std::collections::BTreeMap<_, _>>(),
+        "seeds": [address("a11ce"), address("b0b")],
+        "frontier": [[address("f2a2c"), 2], [address("70c3e2"), 1]],
+        "visited": fetched,
+        "partially_fetched": [address("e217")],
+        "transaction_counts": { address("a11ce"): 4, address("70c3e2"): 12_000 },
+        "oversized_addresses": [address("70c3e2")]
+    });
+    fs::write(dir.join("json/crawl_state.json"), crawl_state.to_string()).unwrap();
+}
+
+fn ethparser(dir: &Path, args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_ethparser")).args(args).current_dir(dir).output().unwrap();
+    assert!(output.status.success(), "ethparser {} failed:\n{}", args.join(" "), String::from_utf8_lossy(&output.stderr));
+}
+
+fn saved(dir: &Path, name: &str) -> String {
+    fs::read_to_string(dir.join("json").join(name)).unwrap()
+}
+
+// A delta run over a saved crawl that knows no fetched addresses makes no requests, so it renders result.txt
+// from the saved graph alone.
+#[cfg(feature = "crawl")]
+#[test]
+fn crawl_result() {
+    let dir = workspace("crawl");
+    fs::copy(dir.join("json").join(GRAPH), dir.join("json/parsed_transactions.json")).unwrap();
+    fs::write(dir.join("json/crawl_state.json"), r#"{"last_fetched_blocks": {}}"#).unwrap();
+    fs::write(dir.join("api_key.txt"), "unused").unwrap();
+    ethparser(&dir, &["crawl", "--since-last-run"]);
+    insta::assert_snapshot!("result", saved(&dir, "result.txt"));
+}
+
+#[test]
+fn two_way_report() {
+    let dir = workspace("reciprocity");
+    ethparser(&dir, &["reciprocity", "--input", GRAPH, "--transfer-log", "reciprocity_transfers.txt", "--dust-floor-usd", "50"]);
+    insta::assert_snapshot!("reciprocity_csv", saved(&dir, "reciprocity.csv"));
+    insta::assert_snapshot!("reciprocity_transfers", saved(&dir, "reciprocity_transfers.txt"));
+}
+
+#[test]
+fn features_csv() {
+    let dir = workspace("features");
+    ethparser(&dir, &["features", "--input", GRAPH]);
+    insta::assert_snapshot!("node_features_csv", saved(&dir, "node_features.csv"));
+}
+
+// The Parquet export holds the same rows and columns as the CSV.
+#[cfg(feature = "polars")]
+#[test]
+fn features_parquet() {
+    use polars::prelude::*;
+    let dir = workspace("features-parquet");
+    ethparser(&dir, &["features", "--input", GRAPH, "--parquet", "node_features.parquet"]);
+    let features = ParquetReader::new(fs::File::open(dir.join("json/node_features.parquet")).unwrap()).finish().unwrap();
+    let csv = saved(&dir, "node_features.csv");
+    let header: Vec<&str> = csv.lines().next().unwrap().split(',').collect();
+    assert_eq!(features.get_column_names_str(), header);
+    assert_eq!(features.height(), csv.lines().count() - 1);
+}
+
+// The stablecoin transfer shares its hash and gas with the outer call, so its day counts one transaction and one fee.
+#[test]
+fn fee_percentiles_csv() {
+    let dir = workspace("fees");
+    ethparser(&dir, &["fees", "--input", GRAPH]);
+    insta::assert_snapshot!("fee_percentiles_csv", saved(&dir, "fee_percentiles.csv"));
+}
+
+// A timestamp past the dates chrono can represent leaves its edge out of the fee periods.
+#[test]
+fn fees_skip_out_of_range_timestamps() {
+    let dir = workspace("fees-out-of-range");
+    let mut graph: serde_json::Value = serde_json::from_str(&saved(&dir, GRAPH)).unwrap();
+    graph["edges"][0][2]["timestamp"] = json!(u64::MAX);
+    fs::write(dir.join("json").join(GRAPH), graph.to_string()).unwrap();
+    ethparser(&dir, &["fees", "--input", GRAPH]);
+    let counted: usize = saved(&dir, "fee_percentiles.csv").lines().skip(1).map(|line| line.split(',').nth(3).unwrap().parse::<usize>().unwrap()).sum();
+    assert_eq!(counted, 13);
+}
+
+#[test]
+fn matrix_market_export() {
+    let dir = workspace("export-matrix");
+    ethparser(&dir, &["export-matrix", "--input", GRAPH]);
+    insta::assert_snapshot!("adjacency_mtx", saved(&dir, "adjacency.mtx"));
+    insta::assert_snapshot!("adjacency_addresses", saved(&dir, "adjacency.addresses.txt"));
+}
+
+#[test]
+fn coverage_exports() {
+    let dir = workspace("coverage");
+    write_crawl_state(&dir);
+    ethparser(&dir, &["coverage", "--input", GRAPH]);
+    insta::assert_snapshot!("coverage_csv", saved(&dir, "coverage.csv"));
+    insta::assert_snapshot!("coverage_dot", saved(&dir, "coverage.dot"));
+}
+
+#[test]
+fn seed_map_exports() {
+    let dir = workspace("seed-map");
+    write_crawl_state(&dir);
+    ethparser(&dir, &["seed-map", "--input", GRAPH]);
+    insta::assert_snapshot!("seed_map_csv", saved(&dir, "seed_map.csv"));
+    insta::assert_snapshot!("seed_map_dot", saved(&dir, "seed_map.dot"));
+    insta::assert_snapshot!("seed_map_gexf", saved(&dir, "seed_map.gexf"));
+}
+
+#[test]
+fn pipeline_graphml_export() {
+    let dir = workspace("pipeline");
+    let pipeline = format!("input = \"{}\"\n\n[[step]]\nkind = \"two-way\"\n\n[[step]]\nkind = \"export\"\nformat = \"graphml\"\noutput = \"two_way.graphml\"\n", GRAPH);
+    fs::write(dir.join("pipeline.toml"), pipeline).unwrap();
+    ethparser(&dir, &["analyze", "--pipeline", "pipeline.toml", "--no-cache"]);
+    insta::assert_snapshot!("two_way_graphml", saved(&dir, "two_way.graphml"));
+}
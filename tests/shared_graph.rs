@@ -0,0 +1,18 @@
+// Appends made while a snapshot shares the graph show up in reads once the snapshot is dropped.
+#![cfg(feature = "grpc")]
+
+use ethparser::shared_graph::SharedGraph;
+use ethparser::{Transaction, G};
+
+#[test]
+fn read_after_deferred_append() {
+    let shared = SharedGraph::new(G::new());
+    shared.append([("0xa", "0xb", Transaction::default())]);
+    let snapshot = shared.snapshot();
+    shared.append([("0xb", "0xc", Transaction::default()), ("0xc", "0xa", Transaction::default())]);
+    assert_eq!(shared.edge_count(), 1);
+    drop(snapshot);
+    assert_eq!(shared.read(|graph| graph.edge_count()), 3);
+    assert_eq!(shared.node_count(), 3);
+    assert_eq!(shared.snapshot().generation, 3);
+}